@@ -8,6 +8,8 @@ pub enum Action {
     Bottom,
     MaxLeft,
     MaxRight,
+    WordLeft,
+    WordRight,
     
     // Movement with selection (Shift+arrows)
     SelectLeft,
@@ -18,7 +20,23 @@ pub enum Action {
     SelectBottom,
     SelectMaxLeft,
     SelectMaxRight,
+    SelectWordLeft,
+    SelectWordRight,
     SelectAll,
+
+    // Multiple cursors
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextOccurrence,
+    SelectAllOccurrences,
+    SplitSelectionIntoLines,
+    CollapseCursors,
+
+    // Block selection (Alt+Shift+arrows)
+    BlockSelectLeft,
+    BlockSelectRight,
+    BlockSelectUp,
+    BlockSelectDown,
     
     // Mouse
     MouseDown(u16, u16),
@@ -31,6 +49,19 @@ pub enum Action {
     NextLine,
     Backspace,
     Delete,
+    DeleteWordLeft,
+    DeleteWordRight,
+
+    // Line commands
+    DuplicateLine,
+    MoveLineUp,
+    MoveLineDown,
+    DeleteLine,
+    JoinLines,
+    SortLines,
+    ReverseLines,
+    UniqueLines,
+    ShuffleLines,
     Copy,
     Cut,
     Paste,
@@ -42,9 +73,54 @@ pub enum Action {
     Undo,
     Redo,
     Search,
+    GoToLine,
+    JumpToBracket,
+    SelectToBracket,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    ToggleBookmark,
+    NameBookmark,
+    NextBookmark,
+    PrevBookmark,
+    ListBookmarks,
+    Complete,
+    ToggleSoftWrap,
+
+    // Language server
+    Hover,
+    GoToDefinition,
+    Rename,
+    ListDiagnostics,
+
+    // External commands
+    FormatDocument,
+    FilterSelection,
+
+    // JSON / TOML / YAML
+    ValidateData,
+    PrettyPrintJson,
+    MinifyJson,
+    SortJsonKeys,
+    ConvertData,
 
     // Tab operations
     SwitchTab(usize),
+
+    // Split panes
+    SplitSideBySide,
+    SplitStacked,
+    FocusOtherPane,
+    CloseSplit,
+
+    // Diff view
+    CompareWithSaved,
+    CompareTabs,
+
+    // Git
+    ShowHunkDiff,
+    RevertHunk,
     
     // Updateing 
     CheckUpdate,
@@ -0,0 +1,115 @@
+// autopair module - which brackets and quotes close themselves, per file type
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Pairs for code in general, and for text where ' is mostly an apostrophe
+// (or, in Rust, the start of a lifetime)
+const CODE_PAIRS: &str = "()[]{}\"\"''";
+const NO_APOSTROPHE_PAIRS: &str = "()[]{}\"\"";
+const MARKDOWN_PAIRS: &str = "()[]{}\"\"``";
+
+// The "auto_close" block of settings.json, e.g.
+//   "auto_close": { "enabled": true, "pairs": { "Rust": "()[]{}\"\"", "Text File": "" } }
+// File types use the names shown in the footer; a pair string lists opening and closing
+// characters alternately.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AutoCloseSettings {
+    pub enabled: bool,
+    pub pairs: HashMap<String, String>,
+}
+
+impl Default for AutoCloseSettings {
+    fn default() -> Self {
+        Self { enabled: true, pairs: HashMap::new() }
+    }
+}
+
+impl AutoCloseSettings {
+    pub fn for_filetype(&self, filetype: Option<&str>) -> AutoPairs {
+        if !self.enabled {
+            return AutoPairs::default();
+        }
+        let name = filetype.unwrap_or("").trim();
+        let configured = self
+            .pairs
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, spec)| spec.as_str());
+
+        let default = match name.to_lowercase().as_str() {
+            "rust" => NO_APOSTROPHE_PAIRS,
+            "markdown" => MARKDOWN_PAIRS,
+            "" | "text file" | "log file" | "latex" => NO_APOSTROPHE_PAIRS,
+            _ => CODE_PAIRS,
+        };
+        AutoPairs::parse(configured.unwrap_or(default))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AutoPairs {
+    pairs: Vec<(char, char)>,
+}
+
+impl AutoPairs {
+    // "()[]" -> ('(', ')'), ('[', ']'); a trailing unpaired character is ignored
+    pub fn parse(spec: &str) -> Self {
+        let chars: Vec<char> = spec.chars().collect();
+        Self { pairs: chars.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect() }
+    }
+
+    pub fn closing(&self, open: char) -> Option<char> {
+        self.pairs.iter().find(|&&(o, _)| o == open).map(|&(_, close)| close)
+    }
+
+    pub fn is_closing(&self, ch: char) -> bool {
+        self.pairs.iter().any(|&(_, close)| close == ch)
+    }
+
+    // Whether typing `open` between `before` and `after` should also insert its closer:
+    // only in front of whitespace, a closer or the line end, and a quote not right after
+    // a word (an apostrophe) or another quote
+    pub fn should_close(&self, open: char, before: Option<char>, after: Option<char>) -> bool {
+        let Some(close) = self.closing(open) else {
+            return false;
+        };
+        let room_after = match after {
+            None => true,
+            Some(c) => c.is_whitespace() || self.is_closing(c) || matches!(c, ',' | ';' | ':' | '.'),
+        };
+        let is_quote = open == close;
+        let quote_ok = !is_quote || !before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == open);
+        room_after && quote_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_filetype_pairs_and_overrides() {
+        let mut settings = AutoCloseSettings::default();
+        assert_eq!(settings.for_filetype(Some("Python")).closing('\''), Some('\''));
+        assert_eq!(settings.for_filetype(Some("Rust")).closing('\''), None);
+        assert_eq!(settings.for_filetype(Some("Markdown")).closing('`'), Some('`'));
+
+        settings.pairs.insert("rust".to_string(), "()<>".to_string());
+        let rust = settings.for_filetype(Some("Rust"));
+        assert_eq!(rust.closing('<'), Some('>'));
+        assert_eq!(rust.closing('['), None);
+
+        settings.enabled = false;
+        assert_eq!(settings.for_filetype(Some("Python")).closing('('), None);
+    }
+
+    #[test]
+    fn test_quotes_do_not_close_after_words() {
+        let pairs = AutoPairs::parse(CODE_PAIRS);
+        assert!(pairs.should_close('\'', Some(' '), None));
+        assert!(!pairs.should_close('\'', Some('n'), Some(' ')));
+        assert!(pairs.should_close('(', Some('f'), Some(')')));
+        assert!(!pairs.should_close('(', None, Some('x')));
+    }
+}
@@ -0,0 +1,144 @@
+// bookmarks module - named or numbered line marks that follow the text as it is edited
+use crate::core::edit_history::line_after_edit;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub line: usize,
+    pub name: String,
+}
+
+// The bookmarks of one document, at most one per line, kept in line order
+#[derive(Clone, Debug, Default)]
+pub struct Bookmarks {
+    marks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    // Restore saved bookmarks, dropping any past the end of a file that shrank on disk
+    pub fn restore(marks: Vec<Bookmark>, line_count: usize) -> Self {
+        let mut bookmarks = Self::default();
+        for mark in marks.into_iter().filter(|mark| mark.line < line_count) {
+            bookmarks.set(mark.line, &mark.name);
+        }
+        bookmarks
+    }
+
+    pub fn to_vec(&self) -> Vec<Bookmark> {
+        self.marks.clone()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.marks.iter()
+    }
+
+    pub fn at(&self, line: usize) -> Option<&Bookmark> {
+        self.marks.iter().find(|mark| mark.line == line)
+    }
+
+    // Line of the bookmark called `name`
+    pub fn line_of(&self, name: &str) -> Option<usize> {
+        self.marks.iter().find(|mark| mark.name == name).map(|mark| mark.line)
+    }
+
+    // Remove the bookmark on `line`, or add one numbered with the lowest free number
+    pub fn toggle(&mut self, line: usize) -> bool {
+        if self.at(line).is_some() {
+            self.marks.retain(|mark| mark.line != line);
+            return false;
+        }
+        let number = (1..)
+            .map(|n: usize| n.to_string())
+            .find(|name| self.line_of(name).is_none())
+            .unwrap_or_default();
+        self.set(line, &number);
+        true
+    }
+
+    // Put the bookmark `name` on `line`, moving it if it was elsewhere
+    pub fn set(&mut self, line: usize, name: &str) {
+        self.marks.retain(|mark| mark.line != line && mark.name != name);
+        let idx = self.marks.partition_point(|mark| mark.line < line);
+        self.marks.insert(idx, Bookmark { line, name: name.to_string() });
+    }
+
+    // The first bookmark below `line`, wrapping round to the top
+    pub fn next_after(&self, line: usize) -> Option<usize> {
+        let lines = self.marks.iter().map(|mark| mark.line);
+        lines.clone().find(|&l| l > line).or_else(|| lines.min())
+    }
+
+    // The first bookmark above `line`, wrapping round to the bottom
+    pub fn prev_before(&self, line: usize) -> Option<usize> {
+        let lines = self.marks.iter().map(|mark| mark.line);
+        lines.clone().rev().find(|&l| l < line).or_else(|| lines.max())
+    }
+
+    // Follow an edit that replaced lines first..=old_last with first..=new_last: marks below
+    // it move, marks on deleted lines land on the last line left, where a mark already there wins
+    pub fn on_edit(&mut self, first: usize, old_last: usize, new_last: usize) {
+        let mut kept: Vec<Bookmark> = Vec::with_capacity(self.marks.len());
+        for mut mark in self.marks.drain(..) {
+            mark.line = line_after_edit(mark.line, first, old_last, new_last);
+            if kept.last().is_none_or(|last| last.line != mark.line) {
+                kept.push(mark);
+            }
+        }
+        self.marks = kept;
+    }
+
+    // Move the mark on one line to another, free one
+    pub fn move_mark(&mut self, from: usize, to: usize) {
+        if self.at(to).is_some() {
+            return;
+        }
+        if let Some(mark) = self.marks.iter_mut().find(|mark| mark.line == from) {
+            mark.line = to;
+            self.marks.sort_by_key(|mark| mark.line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_numbers_and_navigation_wraps() {
+        let mut marks = Bookmarks::default();
+        assert!(marks.toggle(10));
+        assert!(marks.toggle(2));
+        marks.set(6, "todo");
+        assert_eq!(marks.at(10).map(|m| m.name.as_str()), Some("1"));
+        assert_eq!(marks.at(2).map(|m| m.name.as_str()), Some("2"));
+
+        // A removed number is reused
+        assert!(!marks.toggle(10));
+        assert!(marks.toggle(8));
+        assert_eq!(marks.line_of("1"), Some(8));
+
+        assert_eq!(marks.next_after(2), Some(6));
+        assert_eq!(marks.next_after(8), Some(2));
+        assert_eq!(marks.prev_before(2), Some(8));
+        assert_eq!(marks.line_of("todo"), Some(6));
+    }
+
+    #[test]
+    fn test_marks_follow_inserted_and_deleted_lines() {
+        let mut marks = Bookmarks::default();
+        marks.set(1, "a");
+        marks.set(5, "b");
+        marks.set(7, "c");
+
+        // Two lines inserted after line 2
+        marks.on_edit(2, 2, 4);
+        assert_eq!(marks.line_of("b"), Some(7));
+        assert_eq!(marks.line_of("c"), Some(9));
+
+        // Lines 6..=9 joined into line 6: both marks collapse onto it and the first one wins
+        marks.on_edit(6, 9, 6);
+        assert_eq!(marks.line_of("b"), Some(6));
+        assert_eq!(marks.line_of("c"), None);
+        assert_eq!(marks.line_of("a"), Some(1));
+    }
+}
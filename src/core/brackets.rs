@@ -0,0 +1,158 @@
+// brackets module - finds the partner of a bracket, skipping brackets in strings and comments
+use crate::core::selection::TextPosition;
+use crate::core::syntax::{SyntaxHighlighter, TokenType};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Lines searched in each direction, so redraws stay cheap on huge files
+const MAX_SCAN_LINES: usize = 2000;
+
+const CODE_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+const MARKUP_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+pub struct BracketMatcher {
+    highlighter: SyntaxHighlighter,
+    pairs: &'static [(char, char)],
+}
+
+impl BracketMatcher {
+    pub fn new(file_type: Option<String>) -> Self {
+        // Angle brackets only pair up in markup; in code they are mostly operators
+        let markup = file_type.as_deref().is_some_and(|ft| {
+            let ft = ft.trim().to_lowercase();
+            ft.contains("html") || ft.contains("xml") || ft == "vue"
+        });
+        Self {
+            highlighter: SyntaxHighlighter::new(file_type),
+            pairs: if markup { MARKUP_PAIRS } else { CODE_PAIRS },
+        }
+    }
+
+    // The bracket at or just before `pos` and its partner
+    pub fn matching(&self, lines: &[String], pos: TextPosition) -> Option<(TextPosition, TextPosition)> {
+        let brackets = self.brackets_in(lines.get(pos.line)?);
+        let candidates = [Some(pos.column), pos.column.checked_sub(1)];
+        candidates.into_iter().flatten().find_map(|column| {
+            let &(_, ch) = brackets.iter().find(|(col, _)| *col == column)?;
+            let here = TextPosition { line: pos.line, column };
+            self.partner(lines, here, ch).map(|partner| (here, partner))
+        })
+    }
+
+    // The innermost bracket pair around `pos`, as (opening, closing)
+    pub fn enclosing(&self, lines: &[String], pos: TextPosition) -> Option<(TextPosition, TextPosition)> {
+        // Closers seen while walking back, per pair, that still need their opener
+        let mut pending = vec![0usize; self.pairs.len()];
+        let first = pos.line.saturating_sub(MAX_SCAN_LINES);
+
+        for line in (first..=pos.line.min(lines.len().saturating_sub(1))).rev() {
+            for (column, ch) in self.brackets_in(&lines[line]).into_iter().rev() {
+                if line == pos.line && column >= pos.column {
+                    continue;
+                }
+                let Some(kind) = self.pairs.iter().position(|&(open, close)| ch == open || ch == close) else {
+                    continue;
+                };
+                if ch == self.pairs[kind].1 {
+                    pending[kind] += 1;
+                } else if pending[kind] > 0 {
+                    pending[kind] -= 1;
+                } else {
+                    let open = TextPosition { line, column };
+                    return self.partner(lines, open, ch).map(|close| (open, close));
+                }
+            }
+        }
+        None
+    }
+
+    // Scan away from the bracket `ch` at `pos` until its depth drops back to zero
+    fn partner(&self, lines: &[String], pos: TextPosition, ch: char) -> Option<TextPosition> {
+        let &(open, close) = self.pairs.iter().find(|&&(open, close)| ch == open || ch == close)?;
+        let forward = ch == open;
+        let mut depth = 0usize;
+
+        let line_range: Box<dyn Iterator<Item = usize>> = if forward {
+            Box::new(pos.line..lines.len().min(pos.line + MAX_SCAN_LINES))
+        } else {
+            Box::new((pos.line.saturating_sub(MAX_SCAN_LINES)..=pos.line).rev())
+        };
+
+        for line in line_range {
+            let mut brackets = self.brackets_in(&lines[line]);
+            if !forward {
+                brackets.reverse();
+            }
+            for (column, found) in brackets {
+                if line == pos.line && (if forward { column < pos.column } else { column > pos.column }) {
+                    continue;
+                }
+                if found == ch {
+                    depth += 1;
+                } else if found == if forward { close } else { open } {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(TextPosition { line, column });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_opening(&self, ch: char) -> bool {
+        self.pairs.iter().any(|&(open, _)| open == ch)
+    }
+
+    // Grapheme columns of the brackets in `line` that are code, not string or comment text
+    pub fn brackets_in(&self, line: &str) -> Vec<(usize, char)> {
+        let mut brackets = Vec::new();
+        let mut column = 0;
+        for token in self.highlighter.highlight_line(line) {
+            let is_code = !matches!(token.token_type, TokenType::String | TokenType::Comment);
+            for grapheme in token.text.graphemes(true) {
+                let mut chars = grapheme.chars();
+                if let (Some(ch), None, true) = (chars.next(), chars.next(), is_code) {
+                    if self.pairs.iter().any(|&(open, close)| ch == open || ch == close) {
+                        brackets.push((column, ch));
+                    }
+                }
+                column += 1;
+            }
+        }
+        brackets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn pos(line: usize, column: usize) -> TextPosition {
+        TextPosition { line, column }
+    }
+
+    #[test]
+    fn test_matching_skips_brackets_in_strings_and_comments() {
+        let matcher = BracketMatcher::new(Some("Rust".to_string()));
+        let buf = lines(&["fn f() {", "    let s = \"}\"; // }", "}"]);
+
+        assert_eq!(matcher.matching(&buf, pos(0, 7)), Some((pos(0, 7), pos(2, 0))));
+        // Just after a bracket counts too, and the match works backwards
+        assert_eq!(matcher.matching(&buf, pos(2, 1)), Some((pos(2, 0), pos(0, 7))));
+        assert_eq!(matcher.matching(&buf, pos(1, 2)), None);
+        assert_eq!(matcher.enclosing(&buf, pos(1, 4)), Some((pos(0, 7), pos(2, 0))));
+    }
+
+    #[test]
+    fn test_angle_brackets_only_pair_in_markup() {
+        let buf = lines(&["<div><b>x</b></div>"]);
+        let html = BracketMatcher::new(Some("HTML".to_string()));
+        assert_eq!(html.matching(&buf, pos(0, 0)), Some((pos(0, 0), pos(0, 4))));
+        let rust = BracketMatcher::new(Some("Rust".to_string()));
+        assert_eq!(rust.matching(&buf, pos(0, 0)), None);
+    }
+}
@@ -0,0 +1,245 @@
+// compare module - two texts side by side: their line diff lined up into rows, what
+// changed within changed lines, and copying a hunk from one side over the other
+use crate::core::diff::{diff_lines, Hunk};
+use crate::core::tabs::Tab;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+// What a side shows: an open tab (which hunks can be copied into), or fixed text such as
+// the saved version of a file
+#[derive(Clone, Debug)]
+pub enum Source {
+    Tab(u64),
+    Text { title: String, text: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+// One row of the side-by-side view: a line of each side, or a gap where a side has none
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    // The hunk the row belongs to, None for lines both sides share
+    pub hunk: Option<usize>,
+}
+
+pub struct Comparison {
+    pub left: Source,
+    pub right: Source,
+    pub titles: (String, String),
+    pub lines: (Vec<String>, Vec<String>),
+    pub hunks: Vec<Hunk>,
+    pub rows: Vec<Row>,
+    // The hunk last jumped to or copied
+    pub current: Option<usize>,
+}
+
+impl Comparison {
+    pub fn new(left: Source, right: Source, tabs: &[Tab]) -> Result<Self, String> {
+        let mut comparison = Self {
+            left,
+            right,
+            titles: (String::new(), String::new()),
+            lines: (Vec::new(), Vec::new()),
+            hunks: Vec::new(),
+            rows: Vec::new(),
+            current: None,
+        };
+        comparison.refresh(tabs)?;
+        comparison.current = (!comparison.hunks.is_empty()).then_some(0);
+        Ok(comparison)
+    }
+
+    // Compare "the saved version" with the tab holding it
+    pub fn with_saved(tabs: &[Tab], index: usize) -> Result<Self, String> {
+        let tab = &tabs[index];
+        let Some(path) = tab.filepath.as_deref() else {
+            return Err("Not saved yet - nothing to compare with".to_string());
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let title = format!("{} (saved)", tab.filename.as_deref().unwrap_or(path));
+        Self::new(Source::Text { title, text }, Source::Tab(tab.id), tabs)
+    }
+
+    // Read both sides again, after one of them changed
+    pub fn refresh(&mut self, tabs: &[Tab]) -> Result<(), String> {
+        let (left_title, left) = read(&self.left, tabs)?;
+        let (right_title, right) = read(&self.right, tabs)?;
+        self.hunks = diff_lines(&left, &right);
+        self.rows = align(left.len(), right.len(), &self.hunks);
+        self.titles = (left_title, right_title);
+        self.lines = (left, right);
+        self.current = self.current.map(|hunk| hunk.min(self.hunks.len().saturating_sub(1))).filter(|_| !self.hunks.is_empty());
+        Ok(())
+    }
+
+    // Whether a compared tab was edited since the sides were last read
+    pub fn is_stale(&self, tabs: &[Tab]) -> bool {
+        [(&self.left, &self.lines.0), (&self.right, &self.lines.1)].into_iter().any(|(source, lines)| match source {
+            Source::Tab(id) => tabs.iter().find(|tab| tab.id == *id).is_none_or(|tab| {
+                let editor = &tab.editor;
+                editor.buffer.lines[..=editor.last_content_line()] != lines[..]
+            }),
+            Source::Text { .. } => false,
+        })
+    }
+
+    // The first row of a hunk
+    pub fn hunk_row(&self, hunk: usize) -> usize {
+        self.rows.iter().position(|row| row.hunk == Some(hunk)).unwrap_or(0)
+    }
+
+    // Move to the next (or previous) hunk after the current one, wrapping around; returns its row
+    pub fn step(&mut self, forward: bool) -> Option<usize> {
+        let count = self.hunks.len();
+        if count == 0 {
+            return None;
+        }
+        let hunk = match self.current {
+            Some(current) if forward => (current + 1) % count,
+            Some(current) => (current + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        };
+        self.current = Some(hunk);
+        Some(self.hunk_row(hunk))
+    }
+
+    // Replace the current hunk on side `to` with the other side's version of it
+    pub fn copy_hunk(&mut self, to: Side, tabs: &mut [Tab]) -> Result<(), String> {
+        let Some(hunk) = self.current.and_then(|current| self.hunks.get(current)).cloned() else {
+            return Err("No differences".to_string());
+        };
+        let (target, from, into, range) = match to {
+            Side::Left => (&self.left, &self.lines.1[hunk.new.clone()], &self.lines.0, hunk.old),
+            Side::Right => (&self.right, &self.lines.0[hunk.old.clone()], &self.lines.1, hunk.new),
+        };
+        let Source::Tab(id) = target else {
+            return Err("That side is read-only".to_string());
+        };
+        let Some(tab) = tabs.iter_mut().find(|tab| tab.id == *id) else {
+            return Err("The tab was closed".to_string());
+        };
+        let mut lines = into.clone();
+        lines.splice(range, from.iter().cloned());
+        if tab.editor.replace_all(&lines.join("\n")) {
+            tab.has_unsaved_changes = true;
+        }
+        self.refresh(tabs)
+    }
+}
+
+fn read(source: &Source, tabs: &[Tab]) -> Result<(String, Vec<String>), String> {
+    match source {
+        Source::Tab(id) => {
+            let tab = tabs.iter().find(|tab| tab.id == *id).ok_or("A compared tab was closed")?;
+            let title = tab.filename.clone().unwrap_or_else(|| "untitled".to_string());
+            Ok((title, tab.editor.content().split('\n').map(String::from).collect()))
+        }
+        // Read the way a tab reads a file, so line endings don't count as changes
+        Source::Text { title, text } => {
+            let mut lines: Vec<String> = text.lines().map(String::from).collect();
+            if lines.is_empty() {
+                lines.push(String::new());
+            }
+            Ok((title.clone(), lines))
+        }
+    }
+}
+
+// Rows for both texts: shared lines side by side, and each hunk's lines paired up, the
+// shorter side padded with gaps
+fn align(left_len: usize, right_len: usize, hunks: &[Hunk]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let (mut left, mut right) = (0, 0);
+    for (index, hunk) in hunks.iter().enumerate() {
+        while left < hunk.old.start {
+            rows.push(Row { left: Some(left), right: Some(right), hunk: None });
+            left += 1;
+            right += 1;
+        }
+        for i in 0..hunk.old.len().max(hunk.new.len()) {
+            let left = (i < hunk.old.len()).then(|| hunk.old.start + i);
+            let right = (i < hunk.new.len()).then(|| hunk.new.start + i);
+            rows.push(Row { left, right, hunk: Some(index) });
+        }
+        (left, right) = (hunk.old.end, hunk.new.end);
+    }
+    while left < left_len && right < right_len {
+        rows.push(Row { left: Some(left), right: Some(right), hunk: None });
+        left += 1;
+        right += 1;
+    }
+    rows
+}
+
+// Grapheme columns that differ between two paired lines, word by word: (in old, in new)
+pub fn intra_line(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let a: Vec<String> = old.split_word_bounds().map(String::from).collect();
+    let b: Vec<String> = new.split_word_bounds().map(String::from).collect();
+    let columns = |words: &[String], range: Range<usize>| {
+        let start: usize = words[..range.start].iter().map(|w| w.graphemes(true).count()).sum();
+        let len: usize = words[range].iter().map(|w| w.graphemes(true).count()).sum();
+        start..start + len
+    };
+    let mut changes = (Vec::new(), Vec::new());
+    for hunk in diff_lines(&a, &b) {
+        let (old, new) = (columns(&a, hunk.old), columns(&b, hunk.new));
+        if !old.is_empty() {
+            changes.0.push(old);
+        }
+        if !new.is_empty() {
+            changes.1.push(new);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer::Buffer;
+
+    fn text(title: &str, text: &str) -> Source {
+        Source::Text { title: title.to_string(), text: text.to_string() }
+    }
+
+    #[test]
+    fn test_rows_line_up_both_sides() {
+        let comparison = Comparison::new(text("a", "one\ntwo\nthree\nfour"), text("b", "one\n2\nthree\nfour\nfive"), &[]).unwrap();
+        let rows: Vec<_> = comparison.rows.iter().map(|row| (row.left, row.right, row.hunk)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some(0), Some(0), None),
+                (Some(1), Some(1), Some(0)),
+                (Some(2), Some(2), None),
+                (Some(3), Some(3), None),
+                (None, Some(4), Some(1)),
+            ]
+        );
+        assert_eq!(intra_line("a = b + c", "x = b + cd"), (vec![0..1, 8..9], vec![0..1, 8..10]));
+    }
+
+    #[test]
+    fn test_copying_a_hunk_into_a_tab() {
+        let mut tabs = vec![Tab::new(Buffer::from_string("a\nB\nc".to_string()), None, None, None)];
+        let id = tabs[0].id;
+        let mut comparison = Comparison::new(text("saved", "a\nb\nc\nd"), Source::Tab(id), &tabs).unwrap();
+        assert_eq!(comparison.hunks.len(), 2);
+        assert!(comparison.copy_hunk(Side::Left, &mut tabs).is_err());
+
+        comparison.copy_hunk(Side::Right, &mut tabs).unwrap();
+        assert_eq!(tabs[0].editor.content(), "a\nb\nc");
+        assert!(tabs[0].has_unsaved_changes);
+        assert!(!comparison.is_stale(&tabs));
+        assert_eq!(comparison.step(true), Some(3));
+        comparison.copy_hunk(Side::Right, &mut tabs).unwrap();
+        assert!(comparison.hunks.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+// completion module - word completion from open buffers and language keywords
+use crate::core::graphemes::{grapheme_slice, is_word_grapheme};
+use crate::core::selection::TextPosition;
+use crate::core::syntax::SyntaxHighlighter;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Entries shown in the popup, shortest words gathered, and accepted words remembered
+const MAX_ITEMS: usize = 10;
+const MIN_WORD_LEN: usize = 3;
+const RECENT_LIMIT: usize = 50;
+
+// The "completion" block of settings.json, e.g.
+//   "completion": { "auto": true, "min_prefix": 2 }
+// With `auto` the popup opens while typing once the word reaches `min_prefix` characters;
+// Ctrl+Space opens it at any time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CompletionSettings {
+    pub auto: bool,
+    pub min_prefix: usize,
+}
+
+impl Default for CompletionSettings {
+    fn default() -> Self {
+        Self { auto: true, min_prefix: 2 }
+    }
+}
+
+// The word characters just before `column`: their start column and text
+pub fn prefix_at(line: &str, column: usize) -> (usize, String) {
+    let graphemes: Vec<&str> = line.graphemes(true).take(column).collect();
+    let start = graphemes.iter().rposition(|g| !is_word_grapheme(g)).map_or(0, |idx| idx + 1);
+    (start, grapheme_slice(line, start, graphemes.len()))
+}
+
+// Distinct words in `lines` long enough to be worth completing
+pub fn collect_words<'a>(lines: impl IntoIterator<Item = &'a String>, words: &mut HashSet<String>) {
+    for line in lines {
+        let parts = line.split(|c: char| !(c.is_alphanumeric() || c == '_'));
+        for word in parts.filter(|w| w.chars().count() >= MIN_WORD_LEN) {
+            if !word.starts_with(|c: char| c.is_ascii_digit()) && !words.contains(word) {
+                words.insert(word.to_string());
+            }
+        }
+    }
+}
+
+// How well `word` completes `prefix`, lower is better: a prefix in the same case, a prefix in
+// any case, then the prefix's letters in order, with fewer gaps between them ranked first
+fn match_rank(prefix: &str, word: &str) -> Option<(u8, usize)> {
+    if word.starts_with(prefix) {
+        return Some((0, 0));
+    }
+    let (prefix, word) = (prefix.to_lowercase(), word.to_lowercase());
+    if word.starts_with(&prefix) {
+        return Some((1, 0));
+    }
+    // Fuzzy matches still have to start with the same letter
+    if prefix.chars().next() != word.chars().next() {
+        return None;
+    }
+    let mut wanted = prefix.chars().peekable();
+    let (mut gaps, mut in_run) = (0, true);
+    for c in word.chars() {
+        match wanted.peek() {
+            Some(&w) if w == c => {
+                wanted.next();
+                in_run = true;
+            }
+            Some(_) => {
+                gaps += usize::from(in_run);
+                in_run = false;
+            }
+            None => break,
+        }
+    }
+    wanted.peek().is_none().then_some((2, gaps))
+}
+
+// Ranks candidates, remembering the words accepted lately so they come first next time
+#[derive(Debug, Default)]
+pub struct Completer {
+    recent: VecDeque<String>,
+}
+
+impl Completer {
+    pub fn accepted(&mut self, word: &str) {
+        self.recent.retain(|w| w != word);
+        self.recent.push_front(word.to_string());
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    // Popup for the word before `cursor` in `lines` once it has `min_prefix` characters,
+    // offering words from it and `other_buffers` plus the keywords of `filetype`
+    pub fn complete<'a>(
+        &self,
+        lines: &[String],
+        cursor: TextPosition,
+        other_buffers: impl IntoIterator<Item = &'a Vec<String>>,
+        filetype: Option<String>,
+        min_prefix: usize,
+    ) -> Option<Completion> {
+        let (start, prefix) = prefix_at(lines.get(cursor.line)?, cursor.column);
+        if prefix.chars().count() < min_prefix.max(1) {
+            return None;
+        }
+        let mut words = HashSet::new();
+        collect_words(lines, &mut words);
+        for buffer in other_buffers {
+            collect_words(buffer, &mut words);
+        }
+        words.extend(SyntaxHighlighter::new(filetype).keywords().into_iter().map(String::from));
+        Completion::new(TextPosition { line: cursor.line, column: start }, self.candidates(&prefix, words))
+    }
+
+    // Put a language server's suggestions for the word before `cursor` ahead of the buffer
+    // words in `completion`. After a `.` or `::` the prefix is empty and every suggestion is
+    // offered in the server's order.
+    pub fn with_server_items(
+        &self,
+        lines: &[String],
+        cursor: TextPosition,
+        completion: Option<Completion>,
+        items: Vec<String>,
+    ) -> Option<Completion> {
+        let (start, prefix) = prefix_at(lines.get(cursor.line)?, cursor.column);
+        let mut merged = if prefix.is_empty() { items } else { self.candidates(&prefix, items) };
+        for word in completion.map(|c| c.items).unwrap_or_default() {
+            if !merged.contains(&word) {
+                merged.push(word);
+            }
+        }
+        merged.dedup();
+        merged.truncate(MAX_ITEMS);
+        Completion::new(TextPosition { line: cursor.line, column: start }, merged)
+    }
+
+    // The best completions of `prefix` among `words`, leaving out the prefix itself
+    pub fn candidates(&self, prefix: &str, words: impl IntoIterator<Item = String>) -> Vec<String> {
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let mut ranked: Vec<_> = words
+            .into_iter()
+            .filter(|word| word != prefix)
+            .filter_map(|word| {
+                let (tier, gaps) = match_rank(prefix, &word)?;
+                let recency = self.recent.iter().position(|w| *w == word).unwrap_or(usize::MAX);
+                Some(((tier, recency, gaps, word.chars().count()), word))
+            })
+            .collect();
+        ranked.sort();
+        ranked.dedup_by(|a, b| a.1 == b.1);
+        ranked.into_iter().take(MAX_ITEMS).map(|(_, word)| word).collect()
+    }
+}
+
+// An open completion popup: the words offered for the prefix starting at `start`
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub start: TextPosition,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+impl Completion {
+    pub fn new(start: TextPosition, items: Vec<String>) -> Option<Self> {
+        (!items.is_empty()).then_some(Self { start, items, selected: 0 })
+    }
+
+    // Move the highlight, wrapping at either end
+    pub fn select_next(&mut self, down: bool) {
+        let count = self.items.len();
+        self.selected = if down { (self.selected + 1) % count } else { (self.selected + count - 1) % count };
+    }
+
+    pub fn current(&self) -> &str {
+        &self.items[self.selected]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_and_word_collection() {
+        assert_eq!(prefix_at("let foo_ba = 1", 10), (4, "foo_ba".to_string()));
+        assert_eq!(prefix_at("a.bc", 4), (2, "bc".to_string()));
+        assert_eq!(prefix_at("x ", 2), (2, String::new()));
+
+        let mut words = HashSet::new();
+        collect_words(&["fn do_it(x: 1st) -> Result".to_string()], &mut words);
+        let mut words: Vec<_> = words.into_iter().collect();
+        words.sort();
+        assert_eq!(words, vec!["Result", "do_it"]);
+    }
+
+    #[test]
+    fn test_ranking_prefers_case_then_fuzzy_then_recent() {
+        let words = || ["format", "Formatter", "foo_rm", "for_each_mat", "float", "form"].map(String::from);
+        let mut completer = Completer::default();
+        assert_eq!(
+            completer.candidates("form", words()),
+            vec!["format", "Formatter", "foo_rm", "for_each_mat"]
+        );
+        assert_eq!(completer.candidates("fo", words())[..2], ["form", "foo_rm"]);
+
+        // A word accepted lately moves ahead within its tier
+        completer.accepted("for_each_mat");
+        assert_eq!(completer.candidates("fo", words())[0], "for_each_mat");
+        assert_eq!(completer.candidates("Form", words())[0], "Formatter");
+    }
+
+    #[test]
+    fn test_server_items_come_first_and_an_empty_prefix_takes_them_all() {
+        let completer = Completer::default();
+        let lines = vec!["let v = items.le".to_string(), "x.".to_string()];
+        let buffer_words = Completion::new(TextPosition { line: 0, column: 14 }, vec!["length".to_string()]);
+        let server = vec!["len".to_string(), "is_empty".to_string(), "length".to_string()];
+        let merged = completer.with_server_items(&lines, TextPosition { line: 0, column: 16 }, buffer_words, server.clone());
+        assert_eq!(merged.unwrap().items, vec!["len", "length"]);
+
+        let after_dot = completer.with_server_items(&lines, TextPosition { line: 1, column: 2 }, None, server).unwrap();
+        assert_eq!(after_dot.start, TextPosition { line: 1, column: 2 });
+        assert_eq!(after_dot.items, vec!["len", "is_empty", "length"]);
+    }
+}
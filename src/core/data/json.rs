@@ -0,0 +1,140 @@
+// json module - JSON re-laid-out as written: strings and numbers keep their exact text, so
+// pretty-printing, minifying and sorting keys never change the data (1.50 stays 1.50)
+
+pub enum Node {
+    // Keys as written, quotes and escapes included
+    Object(Vec<(String, Node)>),
+    Array(Vec<Node>),
+    // A string, number, true, false or null
+    Scalar(String),
+}
+
+// Only for text serde_json has accepted, so every token is well-formed
+pub fn read(text: &str) -> Node {
+    let mut reader = Reader { text, pos: 0 };
+    reader.node()
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&mut self) -> u8 {
+        while self.text.as_bytes().get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        self.text.as_bytes().get(self.pos).copied().unwrap_or(b']')
+    }
+
+    fn node(&mut self) -> Node {
+        match self.peek() {
+            b'{' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                while self.peek() != b'}' {
+                    let key = self.token();
+                    self.peek();
+                    self.pos += 1; // :
+                    entries.push((key, self.node()));
+                    if self.peek() == b',' {
+                        self.pos += 1;
+                    }
+                }
+                self.pos += 1;
+                Node::Object(entries)
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while self.peek() != b']' {
+                    items.push(self.node());
+                    if self.peek() == b',' {
+                        self.pos += 1;
+                    }
+                }
+                self.pos += 1;
+                Node::Array(items)
+            }
+            _ => Node::Scalar(self.token()),
+        }
+    }
+
+    // A string with its quotes, or a number or keyword; structural characters are ASCII,
+    // so bytes inside multi-byte characters never match them
+    fn token(&mut self) -> String {
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        if bytes[self.pos] == b'"' {
+            self.pos += 1;
+            while bytes[self.pos] != b'"' {
+                self.pos += if bytes[self.pos] == b'\\' { 2 } else { 1 };
+            }
+            self.pos += 1;
+        } else {
+            while self.pos < bytes.len() && !bytes[self.pos].is_ascii_whitespace() && !b",]}:".contains(&bytes[self.pos]) {
+                self.pos += 1;
+            }
+        }
+        self.text[start..self.pos].to_string()
+    }
+}
+
+// Every object's keys in order, compared as the strings they stand for
+pub fn sort_keys(node: &mut Node) {
+    match node {
+        Node::Object(entries) => {
+            entries.sort_by_cached_key(|(key, _)| serde_json::from_str::<String>(key).unwrap_or_default());
+            entries.iter_mut().for_each(|(_, value)| sort_keys(value));
+        }
+        Node::Array(items) => items.iter_mut().for_each(sort_keys),
+        Node::Scalar(_) => {}
+    }
+}
+
+// Two spaces per level, as serde_json pretty-prints
+pub fn pretty(node: &Node) -> String {
+    let mut out = String::new();
+    write(&mut out, node, Some(0));
+    out
+}
+
+pub fn minified(node: &Node) -> String {
+    let mut out = String::new();
+    write(&mut out, node, None);
+    out
+}
+
+fn write(out: &mut String, node: &Node, indent: Option<usize>) {
+    let inner = indent.map(|indent| indent + 2);
+    let (open, close, count) = match node {
+        Node::Scalar(text) => return out.push_str(text),
+        Node::Object(entries) => ('{', '}', entries.len()),
+        Node::Array(items) => ('[', ']', items.len()),
+    };
+    out.push(open);
+    for idx in 0..count {
+        if idx > 0 {
+            out.push(',');
+        }
+        if let Some(inner) = inner {
+            out.push('\n');
+            out.push_str(&" ".repeat(inner));
+        }
+        match node {
+            Node::Object(entries) => {
+                out.push_str(&entries[idx].0);
+                out.push_str(if inner.is_some() { ": " } else { ":" });
+                write(out, &entries[idx].1, inner);
+            }
+            Node::Array(items) => write(out, &items[idx], inner),
+            Node::Scalar(_) => {}
+        }
+    }
+    if let (Some(indent), true) = (indent, count > 0) {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent));
+    }
+    out.push(close);
+}
@@ -0,0 +1,325 @@
+// data module - structured files (JSON, TOML, YAML): validation with the error's position,
+// JSON pretty-printing, minifying and key sorting, and conversion between the formats
+mod json;
+mod toml;
+mod yaml;
+
+use crate::core::lsp::{Diagnostic, Severity};
+use crate::core::selection::TextPosition;
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+// Where and why a document doesn't parse; line and column count from 0
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    // At a byte offset into `text`, which parsers count in
+    fn at_offset(text: &str, offset: usize, message: &str) -> Self {
+        let mut offset = offset.min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        ParseError {
+            line: before.matches('\n').count(),
+            column: before[line_start..].chars().count(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn position(&self) -> TextPosition {
+        TextPosition { line: self.line, column: self.column }
+    }
+
+    // Marked like a language server's error, over the rest of the line
+    pub fn diagnostic(&self, format: Format) -> Diagnostic {
+        Diagnostic {
+            start: self.position(),
+            end: TextPosition { line: self.line, column: usize::MAX },
+            severity: Severity::Error,
+            message: format!("{}: {}", format.name(), self.message),
+        }
+    }
+
+    pub fn summary(&self, format: Format) -> String {
+        format!("Invalid {} at {}:{} - {}", format.name(), self.line + 1, self.column + 1, self.message)
+    }
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Toml, Format::Yaml];
+
+    // From the file type shown in the footer
+    pub fn from_filetype(filetype: Option<&str>) -> Option<Format> {
+        match filetype? {
+            "JSON" => Some(Format::Json),
+            "TOML" => Some(Format::Toml),
+            "YAML" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    // From what the user typed: a name or an extension
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.trim().trim_start_matches('.').to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Toml => "TOML",
+            Format::Yaml => "YAML",
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Value, ParseError> {
+        match self {
+            Format::Json => serde_json::from_str(text).map_err(|e| ParseError {
+                line: e.line().saturating_sub(1),
+                column: e.column().saturating_sub(1),
+                message: strip_position(&e.to_string()),
+            }),
+            Format::Toml => toml::parse(text),
+            Format::Yaml => yaml::parse(text),
+        }
+    }
+
+    // The value written out in this format, without a trailing newline
+    pub fn write(self, value: &Value) -> Result<String, String> {
+        let text = match self {
+            Format::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string())?,
+            Format::Toml => toml::write(value)?,
+            Format::Yaml => yaml::write(value),
+        };
+        Ok(text.trim_end_matches('\n').to_string())
+    }
+}
+
+// serde_json ends its messages with " at line 3 column 7", which the footer already says
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(idx) => message[..idx].to_string(),
+        None => message.to_string(),
+    }
+}
+
+// These check the text with serde_json, then lay out its tokens as written
+pub fn pretty_json(text: &str) -> Result<String, ParseError> {
+    Format::Json.parse(text)?;
+    Ok(json::pretty(&json::read(text)))
+}
+
+pub fn minify_json(text: &str) -> Result<String, ParseError> {
+    Format::Json.parse(text)?;
+    Ok(json::minified(&json::read(text)))
+}
+
+// Pretty-printed, with every object's keys in order
+pub fn sort_json_keys(text: &str) -> Result<String, ParseError> {
+    Format::Json.parse(text)?;
+    let mut node = json::read(text);
+    json::sort_keys(&mut node);
+    Ok(json::pretty(&node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"# settings
+title = "Quick"
+tags = ["a", "b"]
+
+[owner]
+name = 'Filip'
+born = 1979-05-27
+
+[[servers]]
+ip = "10.0.0.1"
+ports = [80, 443]
+
+[[servers]]
+ip = "10.0.0.2"
+opts = { fast = true, ratio = 0.5 }
+"#;
+
+    const YAML: &str = "# settings
+title: Quick
+tags:
+- a
+- b
+owner:
+  name: 'Filip'
+  born: 1979-05-27
+servers:
+  - ip: 10.0.0.1
+    ports: [80, 443]
+  - ip: \"10.0.0.2\"
+    opts: {fast: true, ratio: 0.5}
+notes: |
+  line one
+  line two
+";
+
+    #[test]
+    fn test_toml_and_yaml_read_the_same_document_and_round_trip() {
+        let toml_value = Format::Toml.parse(TOML).unwrap();
+        let mut yaml_value = Format::Yaml.parse(YAML).unwrap();
+        assert_eq!(yaml_value["notes"], "line one\nline two\n");
+        yaml_value.as_object_mut().unwrap().remove("notes");
+        assert_eq!(toml_value, yaml_value);
+        assert_eq!(toml_value["servers"][1]["opts"]["ratio"], 0.5);
+
+        for format in Format::ALL {
+            let written = format.write(&toml_value).unwrap();
+            assert_eq!(format.parse(&written).unwrap(), toml_value, "{}", written);
+        }
+        let yaml = Format::Yaml.write(&Format::Yaml.parse(YAML).unwrap()).unwrap();
+        assert!(yaml.contains("notes: |\n  line one\n  line two"));
+    }
+
+    #[test]
+    fn test_errors_point_at_the_line_and_column() {
+        let error = Format::Json.parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 6));
+        let error = Format::Toml.parse("a = 1\nb = tru\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 4));
+        let error = Format::Yaml.parse("a:\n  b: 1\n   c: 2\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(Format::Toml.write(&serde_json::json!({ "a": null })), Err("TOML has no null (at `a`)".to_string()));
+    }
+
+    #[test]
+    fn test_yaml_anchors_aliases_tags_and_several_documents_are_valid() {
+        let yaml = "%YAML 1.2
+---
+base: &base {host: localhost, port: 80}
+copy: *base
+list: &items [1, 2]
+again: *items
+tagged: !!str 42
+custom: !thing plain
+inf: .inf
+? [complex, key]
+: 1
+...
+";
+        let value = Format::Yaml.parse(yaml).unwrap();
+        assert_eq!(value["copy"], value["base"]);
+        assert_eq!(value["again"], serde_json::json!([1, 2]));
+        assert_eq!(value["tagged"], "42");
+        assert_eq!(value["custom"], "plain");
+        assert_eq!(value["inf"], ".inf");
+        assert_eq!(value["[\"complex\",\"key\"]"], 1);
+
+        let documents = Format::Yaml.parse("---\na: 1\n---\n- b\n---\n").unwrap();
+        assert_eq!(documents, serde_json::json!([{ "a": 1 }, ["b"], null]));
+        assert_eq!(Format::Yaml.parse("# nothing\n").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_toml_reads_the_whole_spec() {
+        let toml = r#"
+"quoted key" = 1
+site."google.com" = true
+hex = 0xDEAD_BEEF
+oct = 0o755
+bin = 0b1101
+big = 1_000_000
+inf = -inf
+nan = nan
+local = 07:32:00
+when = 1979-05-27T07:32:00.999-07:00
+lines = """
+Roses are red \
+  Violets are blue"""
+regex = '''I [dw]on't need \d{2}'''
+
+[a.b.c]
+x = 1
+
+[a]
+y = 2
+
+[[fruit]]
+name = "apple"
+
+[[fruit.variety]]
+name = "red"
+
+[[fruit]]
+name = "banana"
+"#;
+        let value = Format::Toml.parse(toml).unwrap();
+        assert_eq!(value["quoted key"], 1);
+        assert_eq!(value["site"]["google.com"], true);
+        assert_eq!([&value["hex"], &value["oct"], &value["bin"], &value["big"]], [0xDEAD_BEEF_i64, 0o755, 13, 1_000_000]);
+        assert_eq!([&value["inf"], &value["nan"]], ["-inf", "nan"]);
+        assert_eq!([&value["local"], &value["when"]], ["07:32:00", "1979-05-27T07:32:00.999-07:00"]);
+        assert_eq!(value["lines"], "Roses are red Violets are blue");
+        assert_eq!(value["regex"], r"I [dw]on't need \d{2}");
+        assert_eq!([&value["a"]["b"]["c"]["x"], &value["a"]["y"]], [1, 2]);
+        assert_eq!(value["fruit"][0]["variety"][0]["name"], "red");
+        assert_eq!(value["fruit"][1]["name"], "banana");
+    }
+
+    #[test]
+    fn test_invalid_documents_are_refused_where_they_go_wrong() {
+        assert_eq!(Format::Toml.parse("a = 1\na = 2\n").unwrap_err().line, 1);
+        assert_eq!(Format::Toml.parse("[t]\nx = 1\n[t]\n").unwrap_err().line, 2);
+        let error = Format::Toml.parse("k = \"éé\nx = 1\n").unwrap_err();
+        assert_eq!(error.line, 0);
+        assert!(error.column >= 4, "{:?}", error);
+        let error = Format::Yaml.parse("a: *missing\n").unwrap_err();
+        assert_eq!((error.line, error.column), (0, 3));
+        assert!(!error.message.contains(" at line "), "{}", error.message);
+        assert_eq!(Format::Yaml.parse("a: [1, 2\nb: 3\n").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn test_yaml_writes_strings_that_look_like_other_values_quoted() {
+        let value = serde_json::json!({ "a": "yes", "b": "null", "c": "1.5", "d": "0x10", "e": "- x", "f": "plain text" });
+        let written = Format::Yaml.write(&value).unwrap();
+        assert!(written.ends_with("\nf: plain text"), "{}", written);
+        assert!(written.contains("a: \"yes\"\n"), "{}", written);
+        assert_eq!(Format::Yaml.parse(&written).unwrap(), value);
+    }
+
+    #[test]
+    fn test_json_pretty_minify_and_sorted_keys() {
+        assert_eq!(minify_json("{ \"b\": [1, 2], \"a\": {} }").unwrap(), r#"{"b":[1,2],"a":{}}"#);
+        assert_eq!(pretty_json(r#"{"b":1}"#).unwrap(), "{\n  \"b\": 1\n}");
+        assert_eq!(sort_json_keys(r#"{"b":{"d":1,"c":2},"a":0}"#).unwrap(), "{\n  \"a\": 0,\n  \"b\": {\n    \"c\": 2,\n    \"d\": 1\n  }\n}");
+
+        // Numbers keep their digits: trailing zeros, exponents and integers past 64 bits
+        let numbers = r#"[1.50,1e3,-2.5E-7,123456789012345678901234567890,0.1]"#;
+        assert_eq!(minify_json(numbers).unwrap(), numbers);
+        assert_eq!(pretty_json(numbers).unwrap().replace([' ', '\n'], ""), numbers);
+        assert_eq!(sort_json_keys(r#"{"b":1.50,"a":1e3}"#).unwrap(), "{\n  \"a\": 1e3,\n  \"b\": 1.50\n}");
+        let strings = r#"{"k\"": "é,]} \\", "e": [ ], "o": {}}"#;
+        assert_eq!(pretty_json(strings).unwrap(), "{\n  \"k\\\"\": \"é,]} \\\\\",\n  \"e\": [],\n  \"o\": {}\n}");
+
+        // Converting keeps every digit; exponents come out in one spelling
+        let value = Format::Json.parse(numbers).unwrap();
+        let written = Format::Json.write(&value).unwrap().replace([' ', '\n'], "");
+        assert_eq!(written, "[1.50,1e+3,-2.5e-7,123456789012345678901234567890,0.1]");
+        assert!(Format::Yaml.write(&value).unwrap().contains("- 123456789012345678901234567890"));
+    }
+}
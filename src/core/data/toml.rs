@@ -0,0 +1,128 @@
+// toml module - TOML read into and written from JSON values. Dates and times, and inf and
+// nan, are kept as strings, since JSON has no type for them.
+use super::ParseError;
+use serde_json::{Map, Number, Value};
+use toml_edit::{Document, Item, Table};
+
+pub fn parse(text: &str) -> Result<Value, ParseError> {
+    let document = Document::parse(text).map_err(|e| {
+        let offset = e.span().map_or(0, |span| span.start);
+        ParseError::at_offset(text, offset, e.message().trim_end())
+    })?;
+    Ok(table(document.as_table()))
+}
+
+fn table(table: &Table) -> Value {
+    let entries = table.iter().filter_map(|(key, item)| Some((key.to_string(), self::item(item)?)));
+    Value::Object(entries.collect())
+}
+
+fn item(item: &Item) -> Option<Value> {
+    Some(match item {
+        Item::None => return None,
+        Item::Value(value) => self::value(value),
+        Item::Table(inner) => table(inner),
+        Item::ArrayOfTables(tables) => Value::Array(tables.iter().map(table).collect()),
+    })
+}
+
+fn value(value: &toml_edit::Value) -> Value {
+    use toml_edit::Value as Toml;
+    match value {
+        Toml::String(s) => Value::String(s.value().clone()),
+        Toml::Integer(i) => Value::from(*i.value()),
+        Toml::Float(f) => match Number::from_f64(*f.value()) {
+            Some(number) => Value::Number(number),
+            None if f.value().is_nan() => Value::String("nan".to_string()),
+            None => Value::String(f.value().to_string()),
+        },
+        Toml::Boolean(b) => Value::Bool(*b.value()),
+        Toml::Datetime(d) => Value::String(d.value().to_string()),
+        Toml::Array(items) => Value::Array(items.iter().map(self::value).collect()),
+        Toml::InlineTable(inline) => {
+            let entries = inline.iter().map(|(key, value)| (key.to_string(), self::value(value)));
+            Value::Object(entries.collect::<Map<_, _>>())
+        }
+    }
+}
+
+pub fn write(value: &Value) -> Result<String, String> {
+    let Value::Object(root) = value else {
+        return Err("TOML needs a table at the top level".to_string());
+    };
+    let mut out = String::new();
+    write_table(&mut out, &[], root)?;
+    Ok(out.trim_start_matches('\n').to_string())
+}
+
+// Plain values first, then sub-tables and arrays of tables under their headers
+fn write_table(out: &mut String, path: &[String], table: &Map<String, Value>) -> Result<(), String> {
+    let is_table_array = |value: &Value| matches!(value, Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object));
+    for (key, value) in table {
+        if !value.is_object() && !is_table_array(value) {
+            out.push_str(&format!("{} = {}\n", write_key(key), inline(value, &path_name(path, key))?));
+        }
+    }
+    for (key, value) in table {
+        let mut child = path.to_vec();
+        child.push(key.clone());
+        let header: Vec<String> = child.iter().map(|key| write_key(key)).collect();
+        match value {
+            Value::Object(map) => {
+                out.push_str(&format!("\n[{}]\n", header.join(".")));
+                write_table(out, &child, map)?;
+            }
+            Value::Array(items) if is_table_array(value) => {
+                for item in items {
+                    out.push_str(&format!("\n[[{}]]\n", header.join(".")));
+                    if let Value::Object(map) = item {
+                        write_table(out, &child, map)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn path_name(path: &[String], key: &str) -> String {
+    path.iter().map(String::as_str).chain([key]).collect::<Vec<_>>().join(".")
+}
+
+fn write_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return key.to_string();
+    }
+    quote(key)
+}
+
+fn quote(text: &str) -> String {
+    // JSON string escapes are valid in TOML basic strings
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn inline(value: &Value, name: &str) -> Result<String, String> {
+    Ok(match value {
+        Value::Null => return Err(format!("TOML has no null (at `{}`)", name)),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => quote(s),
+        Value::Array(items) => {
+            let items: Result<Vec<_>, _> = items.iter().map(|item| inline(item, name)).collect();
+            format!("[{}]", items?.join(", "))
+        }
+        Value::Object(map) => {
+            let entries: Result<Vec<_>, String> = map
+                .iter()
+                .map(|(key, value)| Ok(format!("{} = {}", write_key(key), inline(value, &path_name(&[name.to_string()], key))?)))
+                .collect();
+            let entries = entries?;
+            if entries.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", entries.join(", "))
+            }
+        }
+    })
+}
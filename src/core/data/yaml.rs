@@ -0,0 +1,139 @@
+// yaml module - YAML read into and written from JSON values. Aliases read as a copy of what
+// their anchor marks, and a stream of several documents as a sequence of them.
+use super::ParseError;
+use serde_json::{Map, Number, Value};
+use yaml_rust2::{Yaml, YamlLoader};
+
+pub fn parse(text: &str) -> Result<Value, ParseError> {
+    let mut documents = YamlLoader::load_from_str(text).map_err(|e| ParseError {
+        line: e.marker().line().saturating_sub(1),
+        column: e.marker().col(),
+        message: super::strip_position(&e.to_string()),
+    })?;
+    Ok(match documents.len() {
+        0 => Value::Null,
+        1 => value(&documents.remove(0)),
+        _ => Value::Array(documents.iter().map(value).collect()),
+    })
+}
+
+fn value(yaml: &Yaml) -> Value {
+    match yaml {
+        // As written when JSON can take it, so 1.50 stays 1.50
+        Yaml::Real(text) => match text.parse::<Number>().ok().or_else(|| yaml.as_f64().and_then(Number::from_f64)) {
+            Some(number) => Value::Number(number),
+            // .inf and .nan
+            None => Value::String(text.clone()),
+        },
+        Yaml::Integer(i) => Value::from(*i),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(items) => Value::Array(items.iter().map(value).collect()),
+        Yaml::Hash(map) => Value::Object(map.iter().map(|(k, v)| (key(k), value(v))).collect()),
+        Yaml::Null | Yaml::Alias(_) | Yaml::BadValue => Value::Null,
+    }
+}
+
+// JSON keys are strings; a sequence or mapping as a key is written as JSON
+fn key(yaml: &Yaml) -> String {
+    match value(yaml) {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+pub fn write(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => write_mapping(&mut out, map, 0),
+        Value::Array(items) if !items.is_empty() => write_sequence(&mut out, items, 0),
+        _ => {
+            out.push_str(&inline(value, 2));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_mapping(out: &mut String, map: &Map<String, Value>, indent: usize) {
+    let pad = " ".repeat(indent);
+    for (key, value) in map {
+        // Keys stay on one line
+        let key = if key.contains('\n') { serde_json::to_string(key).unwrap_or_default() } else { string(key, indent) };
+        match value {
+            Value::Object(child) if !child.is_empty() => {
+                out.push_str(&format!("{}{}:\n", pad, key));
+                write_mapping(out, child, indent + 2);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push_str(&format!("{}{}:\n", pad, key));
+                write_sequence(out, items, indent + 2);
+            }
+            _ => out.push_str(&format!("{}{}: {}\n", pad, key, inline(value, indent + 2))),
+        }
+    }
+}
+
+// Each item is written as a block indented past the dash, whose first indentation then
+// becomes the "- "
+fn write_sequence(out: &mut String, items: &[Value], indent: usize) {
+    let pad = " ".repeat(indent);
+    for item in items {
+        let mut block = String::new();
+        match item {
+            Value::Object(map) if !map.is_empty() => write_mapping(&mut block, map, indent + 2),
+            Value::Array(inner) if !inner.is_empty() => write_sequence(&mut block, inner, indent + 2),
+            _ => block = format!("{}{}\n", " ".repeat(indent + 2), inline(item, indent + 2)),
+        }
+        out.push_str(&pad);
+        out.push_str("- ");
+        out.push_str(&block[indent + 2..]);
+    }
+}
+
+fn inline(value: &Value, indent: usize) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => string(s, indent),
+        // JSON is valid YAML flow style
+        Value::Array(_) | Value::Object(_) => value.to_string(),
+    }
+}
+
+// Whether `text` written plain reads back as a string, here and in readers that take YAML
+// 1.1's yes/no/on/off as booleans
+fn reads_as_string(text: &str) -> bool {
+    let keyword = matches!(text.to_lowercase().as_str(), "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n");
+    !keyword && matches!(Yaml::from_str(text), Yaml::String(_))
+}
+
+// Plain when it reads back as the same string, a | block for multi-line text, else quoted
+fn string(text: &str, indent: usize) -> String {
+    let plain_safe = !text.is_empty()
+        && text.trim() == text
+        && reads_as_string(text)
+        && !text.starts_with(['-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@', '`'])
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !text.chars().any(char::is_control);
+    if plain_safe {
+        return text.to_string();
+    }
+    let lines: Vec<&str> = text.strip_suffix('\n').unwrap_or(text).split('\n').collect();
+    let block_safe = text.contains('\n')
+        && !text.ends_with("\n\n")
+        && !text.starts_with([' ', '\n'])
+        && !text.chars().any(|c| c.is_control() && c != '\n');
+    if block_safe {
+        let header = if text.ends_with('\n') { "|" } else { "|-" };
+        let pad = " ".repeat(indent);
+        let body: Vec<String> = lines.iter().map(|line| if line.is_empty() { String::new() } else { format!("{}{}", pad, line) }).collect();
+        return format!("{}\n{}", header, body.join("\n"));
+    }
+    // JSON strings are valid double-quoted YAML
+    serde_json::to_string(text).unwrap_or_default()
+}
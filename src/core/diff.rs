@@ -0,0 +1,167 @@
+// diff module - line diffs between two versions of a text, and the smallest edits
+// that turn one into the other (so carets and undo survive a reformat)
+use crate::core::graphemes::{grapheme_len, grapheme_slice};
+use crate::core::selection::TextPosition;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Beyond this many comparisons the changed middle becomes one hunk
+const MAX_CELLS: usize = 4_000_000;
+
+// Lines `old` of the old text were replaced by lines `new` of the new one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+// Changed line ranges, top to bottom, from a longest common subsequence
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if a.is_empty() && b.is_empty() {
+        return Vec::new();
+    }
+    if a.is_empty() || b.is_empty() || a.len() * b.len() > MAX_CELLS {
+        return vec![Hunk { old: prefix..prefix + a.len(), new: prefix..prefix + b.len() }];
+    }
+
+    // table[i][j] = length of the common subsequence of a[i..] and b[j..]
+    let mut table = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut start_i, mut start_j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            if (start_i, start_j) != (i, j) {
+                hunks.push(Hunk { old: prefix + start_i..prefix + i, new: prefix + start_j..prefix + j });
+            }
+            i += 1;
+            j += 1;
+            (start_i, start_j) = (i, j);
+        } else if j < b.len() && (i == a.len() || table[i][j + 1] >= table[i + 1][j]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    if (start_i, start_j) != (i, j) {
+        hunks.push(Hunk { old: prefix + start_i..prefix + i, new: prefix + start_j..prefix + j });
+    }
+    hunks
+}
+
+// Replacements in positions of `old` that turn it into `new`; a changed line keeps
+// its unchanged start and end so a caret on it stays put
+pub fn minimal_edits(old: &[String], new: &[String]) -> Vec<(TextPosition, TextPosition, String)> {
+    let at = |line, column| TextPosition { line, column };
+    let mut edits = Vec::new();
+    for hunk in diff_lines(old, new) {
+        if hunk.old.len() == hunk.new.len() {
+            // Line for line (a reindent), so each line only loses what changed in it
+            for (line, after) in hunk.old.clone().zip(&new[hunk.new.clone()]) {
+                let (start, old_end, new_end) = changed_middle(&old[line], after);
+                edits.push((at(line, start), at(line, old_end), grapheme_slice(after, start, new_end)));
+            }
+        } else if hunk.old.end < old.len() {
+            // Whole lines, ending at the start of the next unchanged one
+            let text: String = new[hunk.new.clone()].iter().map(|line| format!("{}\n", line)).collect();
+            edits.push((at(hunk.old.start, 0), at(hunk.old.end, 0), text));
+        } else if hunk.old.start > 0 {
+            // At the end of the text, so start from the end of the line before
+            let text: String = new[hunk.new.clone()].iter().map(|line| format!("\n{}", line)).collect();
+            let before = hunk.old.start - 1;
+            let last = old.len() - 1;
+            edits.push((at(before, grapheme_len(&old[before])), at(last, grapheme_len(&old[last])), text));
+        } else {
+            let last = old.len().saturating_sub(1);
+            let end = old.last().map_or(0, |line| grapheme_len(line));
+            edits.push((at(0, 0), at(last, end), new.join("\n")));
+        }
+    }
+    edits
+}
+
+// Grapheme columns where two lines stop agreeing: (start, end in old, end in new)
+fn changed_middle(old: &str, new: &str) -> (usize, usize, usize) {
+    let a: Vec<&str> = old.graphemes(true).collect();
+    let b: Vec<&str> = new.graphemes(true).collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    (prefix, a.len() - suffix, b.len() - suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::edit_history::Edit;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_diff_lines_finds_changed_ranges() {
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nx\nc\ne\nf");
+        assert_eq!(
+            diff_lines(&old, &new),
+            vec![
+                Hunk { old: 1..2, new: 1..2 },
+                Hunk { old: 3..4, new: 3..3 },
+                Hunk { old: 5..5, new: 4..5 },
+            ]
+        );
+        assert!(diff_lines(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_minimal_edits_turn_old_into_new() {
+        let cases = [
+            ("fn main(){\nlet x=1;\n}", "fn main() {\n    let x = 1;\n}\n"),
+            ("a\nb\nc", "c"),
+            ("a\nb", "a\nb\nc\nd"),
+            ("one", "two\nthree"),
+            ("", "x"),
+        ];
+        for (before, after) in cases {
+            let mut text = lines(before);
+            let mut edits = minimal_edits(&text, &lines(after));
+            edits.reverse();
+            for (start, end, new_text) in edits {
+                Edit::ReplaceRange {
+                    start_line: start.line,
+                    start_column: start.column,
+                    end_line: end.line,
+                    end_column: end.column,
+                    old_text: String::new(),
+                    new_text,
+                }
+                .apply(&mut text);
+            }
+            assert_eq!(text.join("\n"), after);
+        }
+    }
+}
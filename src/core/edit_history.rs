@@ -1,5 +1,6 @@
 // edit_history module - delta-based undo/redo system
-use crate::tui::caret::Position;
+use crate::core::graphemes::{grapheme_len, grapheme_to_byte_idx};
+use crate::core::selection::TextPosition;
 
 // Represents a single atomic edit operation that can be undone/redone
 #[derive(Clone, Debug)]
@@ -41,16 +42,16 @@ pub enum Edit {
         old_text: String,
         new_text: String,
     },
+    // Several edits made together (one per cursor), undone as one step
+    Batch(Vec<Edit>),
 }
 
-// A complete edit operation with before/after cursor state
+// A complete edit operation with before/after cursor state (document coordinates)
 #[derive(Clone, Debug)]
 pub struct EditOperation {
     pub edit: Edit,
-    pub cursor_before: Position,
-    pub cursor_after: Position,
-    pub scroll_before: usize,
-    pub scroll_after: usize,
+    pub cursor_before: TextPosition,
+    pub cursor_after: TextPosition,
 }
 
 #[derive(Clone)]
@@ -139,22 +140,20 @@ impl EditHistory {
             (
                 Edit::InsertText { line: l1, column: c1, text: t1 },
                 Edit::InsertText { line: l2, column: c2, text: t2 }
-            ) if l1 == l2 && *c1 + t1.len() == *c2 => {
+            ) if l1 == l2 && *c1 + grapheme_len(t1) == *c2 => {
                 t1.push_str(t2);
                 last.cursor_after = new.cursor_after;
-                last.scroll_after = new.scroll_after;
                 true
             },
             // Merge continuous backspace deletions
             (
                 Edit::DeleteText { line: l1, column: c1, text: t1 },
                 Edit::DeleteText { line: l2, column: c2, text: t2 }
-            ) if l1 == l2 && *c2 == c1.saturating_sub(t2.len()) => {
+            ) if l1 == l2 && *c2 == c1.saturating_sub(grapheme_len(t2)) => {
                 // Prepend the newly deleted text
                 *t1 = format!("{}{}", t2, t1);
                 *c1 = *c2;
                 last.cursor_after = new.cursor_after;
-                last.scroll_after = new.scroll_after;
                 true
             },
             _ => false,
@@ -184,51 +183,30 @@ impl EditHistory {
 
 impl Edit {
     pub fn apply(&self, buffer: &mut Vec<String>) {
-        use unicode_segmentation::UnicodeSegmentation;
-        
         match self {
             Edit::InsertText { line, column, text } => {
-                if let Some(line_content) = buffer.get_mut(*line) {
-                    // Convert grapheme index to byte index
-                    let byte_idx = line_content
-                        .grapheme_indices(true)
-                        .nth(*column)
-                        .map(|(idx, _)| idx)
-                        .unwrap_or(line_content.len());
-                    
-                    line_content.insert_str(byte_idx, text);
-                }
+                replace_range(buffer, at(*line, *column), at(*line, *column), text);
             },
             Edit::DeleteText { line, column, text } => {
-                if let Some(line_content) = buffer.get_mut(*line) {
-                    let grapheme_count: usize = line_content.graphemes(true).count();
-                    
-                    if *column < grapheme_count {
-                        // Find byte range for this grapheme index
-                        let byte_start = line_content
-                            .grapheme_indices(true)
-                            .nth(*column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        let byte_end = byte_start + text.len();
-                        
-                        if byte_end <= line_content.len() {
-                            line_content.drain(byte_start..byte_end);
-                        }
-                    }
-                }
+                let end = end_of_inserted(at(*line, *column), text);
+                replace_range(buffer, at(*line, *column), end, "");
             },
             Edit::InsertLine { line, remaining_text } => {
-                if *line < buffer.len() {
-                    buffer.insert(line + 1, remaining_text.clone());
-                } else {
-                    buffer.push(remaining_text.clone());
+                // Split the line, moving its tail onto a new line below
+                if let Some(current) = buffer.get_mut(*line) {
+                    if current.ends_with(remaining_text.as_str()) {
+                        let keep = current.len() - remaining_text.len();
+                        current.truncate(keep);
+                    }
                 }
+                let idx = (*line + 1).min(buffer.len());
+                buffer.insert(idx, remaining_text.clone());
             },
-            Edit::DeleteLine { line, .. } => {
-                if *line < buffer.len() {
+            Edit::DeleteLine { line, content, .. } => {
+                // Append the line to the previous one and remove it
+                if *line > 0 && *line < buffer.len() {
                     buffer.remove(*line);
+                    buffer[line - 1].push_str(content);
                 }
             },
             Edit::JoinLines { line, .. } => {
@@ -240,70 +218,104 @@ impl Edit {
                 }
             },
             Edit::ReplaceRange { start_line, start_column, end_line, end_column, new_text, .. } => {
-                if start_line == end_line {
-                    // Single line replacement
-                    if let Some(line_content) = buffer.get_mut(*start_line) {
-                        let byte_start = line_content
-                            .grapheme_indices(true)
-                            .nth(*start_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        let byte_end = line_content
-                            .grapheme_indices(true)
-                            .nth(*end_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        line_content.replace_range(byte_start..byte_end, new_text);
-                    }
-                } else {
-                    // Multi-line - remove and insert
-                    for _ in *start_line..=*end_line {
-                        if *start_line < buffer.len() {
-                            buffer.remove(*start_line);
-                        }
-                    }
-                    let new_lines: Vec<&str> = new_text.split('\n').collect();
-                    for (i, line) in new_lines.iter().enumerate() {
-                        buffer.insert(start_line + i, line.to_string());
-                    }
+                replace_range(
+                    buffer,
+                    at(*start_line, *start_column),
+                    at(*end_line, *end_column),
+                    new_text,
+                );
+            },
+            Edit::Batch(edits) => {
+                for edit in edits {
+                    edit.apply(buffer);
                 }
             },
         }
     }
     
+    // Lines the edit replaces when applied: (first line, last line before, last line after)
+    pub fn line_span(&self) -> (usize, usize, usize) {
+        let breaks = |text: &str| text.matches('\n').count();
+        match self {
+            Edit::InsertText { line, text, .. } => (*line, *line, line + breaks(text)),
+            Edit::DeleteText { line, text, .. } => (*line, line + breaks(text), *line),
+            Edit::InsertLine { line, .. } => (*line, *line, line + 1),
+            Edit::DeleteLine { line, .. } => (line.saturating_sub(1), *line, line.saturating_sub(1)),
+            Edit::JoinLines { line, .. } => (*line, line + 1, *line),
+            Edit::ReplaceRange { start_line, end_line, new_text, .. } => {
+                (*start_line, *end_line, start_line + breaks(new_text))
+            }
+            // Batch edits run bottom-up, so each one's lines are those of the original text
+            Edit::Batch(edits) => {
+                let spans: Vec<_> = edits.iter().map(Edit::line_span).collect();
+                let first = spans.iter().map(|span| span.0).min().unwrap_or(0);
+                let old_last = spans.iter().map(|span| span.1).max().unwrap_or(0);
+                let growth: isize = spans.iter().map(|span| span.2 as isize - span.1 as isize).sum();
+                (first, old_last, old_last.saturating_add_signed(growth))
+            }
+        }
+    }
+
+    // Where whole lines went in before an existing line, and how many: a line split at its
+    // start, or lines pasted at column 0. `buffer` is the text after the edit.
+    pub fn lines_inserted_before(&self, buffer: &[String]) -> Option<(usize, usize)> {
+        match self {
+            Edit::InsertLine { line, remaining_text } => {
+                let split_at_start = !remaining_text.is_empty() && buffer.get(*line).is_some_and(String::is_empty);
+                split_at_start.then_some((*line, 1))
+            }
+            Edit::ReplaceRange { start_line, start_column: 0, old_text, new_text, .. }
+                if old_text.is_empty() && new_text.ends_with('\n') =>
+            {
+                Some((*start_line, new_text.matches('\n').count()))
+            }
+            _ => None,
+        }
+    }
+
+    // The edit as (start, old end, new end) replacements in the order they apply, for
+    // positions that follow the text; `buffer` is the text after the edit, which gives
+    // the column a single InsertLine split its line at
+    pub fn changes(&self, buffer: &[String]) -> Vec<(TextPosition, TextPosition, TextPosition)> {
+        match self {
+            Edit::InsertText { line, column, text } => {
+                let start = at(*line, *column);
+                vec![(start, start, end_of_inserted(start, text))]
+            }
+            Edit::DeleteText { line, column, text } => {
+                let start = at(*line, *column);
+                vec![(start, end_of_inserted(start, text), start)]
+            }
+            Edit::InsertLine { line, .. } => {
+                let start = at(*line, buffer.get(*line).map_or(0, |text| grapheme_len(text)));
+                vec![(start, start, at(line + 1, 0))]
+            }
+            Edit::DeleteLine { line, prev_line_end_len, .. } => {
+                let start = at(line.saturating_sub(1), *prev_line_end_len);
+                vec![(start, at(*line, 0), start)]
+            }
+            Edit::JoinLines { line, first_line_end } => {
+                let start = at(*line, *first_line_end);
+                vec![(start, at(line + 1, 0), start)]
+            }
+            Edit::ReplaceRange { start_line, start_column, end_line, end_column, new_text, .. } => {
+                let start = at(*start_line, *start_column);
+                vec![(start, at(*end_line, *end_column), end_of_inserted(start, new_text))]
+            }
+            Edit::Batch(edits) => edits.iter().flat_map(|edit| edit.changes(buffer)).collect(),
+        }
+    }
+
     pub fn reverse(&self, buffer: &mut Vec<String>) {
-        use unicode_segmentation::UnicodeSegmentation;
-        
         match self {
             Edit::InsertText { line, column, text } => {
                 // Remove the inserted text
-                if let Some(line_content) = buffer.get_mut(*line) {
-                    let byte_start = line_content
-                        .grapheme_indices(true)
-                        .nth(*column)
-                        .map(|(idx, _)| idx)
-                        .unwrap_or(line_content.len());
-                    
-                    let byte_end = byte_start + text.len();
-                    
-                    if byte_end <= line_content.len() {
-                        line_content.drain(byte_start..byte_end);
-                    }
-                }
+                let end = end_of_inserted(at(*line, *column), text);
+                replace_range(buffer, at(*line, *column), end, "");
             },
             Edit::DeleteText { line, column, text } => {
                 // Re-insert the deleted text
-                if let Some(line_content) = buffer.get_mut(*line) {
-                    let byte_idx = line_content
-                        .grapheme_indices(true)
-                        .nth(*column)
-                        .map(|(idx, _)| idx)
-                        .unwrap_or(line_content.len());
-                    
-                    line_content.insert_str(byte_idx, text);
-                }
+                replace_range(buffer, at(*line, *column), at(*line, *column), text);
             },
             Edit::InsertLine { line, remaining_text } => {
                 // Remove the inserted line and merge back
@@ -314,55 +326,84 @@ impl Edit {
                     }
                 }
             },
-            Edit::DeleteLine { line, content, prev_line_end_len } => {
-                // Re-insert the deleted line
+            Edit::DeleteLine { line, prev_line_end_len, .. } => {
+                // Split the merged line back at the old end of the previous line
                 if *line > 0 && *line <= buffer.len() {
-                    if let Some(prev) = buffer.get_mut(line - 1) {
-                        // Convert grapheme index to byte index for split
-                        let byte_idx = prev
-                            .grapheme_indices(true)
-                            .nth(*prev_line_end_len)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(prev.len());
-                        
-                        let split_content = prev.split_off(byte_idx);
-                        buffer.insert(*line, format!("{}{}", content, split_content));
-                    }
+                    let prev = &mut buffer[line - 1];
+                    let byte_idx = grapheme_to_byte_idx(prev, *prev_line_end_len);
+                    let split_content = prev.split_off(byte_idx);
+                    buffer.insert(*line, split_content);
                 }
             },
             Edit::JoinLines { line, first_line_end } => {
                 // Split the line back
                 if let Some(current) = buffer.get_mut(*line) {
-                    let byte_idx = current
-                        .grapheme_indices(true)
-                        .nth(*first_line_end)
-                        .map(|(idx, _)| idx)
-                        .unwrap_or(current.len());
-                    
+                    let byte_idx = grapheme_to_byte_idx(current, *first_line_end);
                     let split_content = current.split_off(byte_idx);
                     buffer.insert(line + 1, split_content);
                 }
             },
-            Edit::ReplaceRange { start_line, start_column, end_line, end_column, old_text, .. } => {
-                // Replace back with old text (reverse operation)
-                if start_line == end_line {
-                    if let Some(line_content) = buffer.get_mut(*start_line) {
-                        let byte_start = line_content
-                            .grapheme_indices(true)
-                            .nth(*start_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        let byte_end = line_content
-                            .grapheme_indices(true)
-                            .nth(*end_column)
-                            .map(|(idx, _)| idx)
-                            .unwrap_or(line_content.len());
-                        
-                        line_content.replace_range(byte_start..byte_end, old_text);
-                    }
+            Edit::ReplaceRange { start_line, start_column, old_text, new_text, .. } => {
+                // Remove what was inserted and put the old text back
+                let start = at(*start_line, *start_column);
+                replace_range(buffer, start, end_of_inserted(start, new_text), old_text);
+            },
+            Edit::Batch(edits) => {
+                for edit in edits.iter().rev() {
+                    edit.reverse(buffer);
                 }
             },
         }
     }
-}
\ No newline at end of file
+}
+
+fn at(line: usize, column: usize) -> TextPosition {
+    TextPosition { line, column }
+}
+
+// Where `line` is after an edit that replaced lines first..=old_last with first..=new_last:
+// lines below it move, lines that were deleted land on the last line left
+pub fn line_after_edit(line: usize, first: usize, old_last: usize, new_last: usize) -> usize {
+    if line > old_last {
+        line - old_last + new_last
+    } else if line > new_last && line >= first {
+        new_last
+    } else {
+        line
+    }
+}
+
+// Position just after `text` once inserted at `start`
+pub fn end_of_inserted(start: TextPosition, text: &str) -> TextPosition {
+    match text.rsplit_once('\n') {
+        Some((head, tail)) => TextPosition {
+            line: start.line + head.matches('\n').count() + 1,
+            column: grapheme_len(tail),
+        },
+        None => TextPosition {
+            line: start.line,
+            column: start.column + grapheme_len(text),
+        },
+    }
+}
+
+// Replace the text between two grapheme positions with `text` (which may span lines)
+fn replace_range(buffer: &mut Vec<String>, start: TextPosition, end: TextPosition, text: &str) {
+    if start.line >= buffer.len() {
+        return;
+    }
+    let end_line = end.line.min(buffer.len() - 1);
+
+    let first = &buffer[start.line];
+    let before = first[..grapheme_to_byte_idx(first, start.column)].to_string();
+    let last = &buffer[end_line];
+    let after = last[grapheme_to_byte_idx(last, end.column)..].to_string();
+
+    let mut new_lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+    new_lines[0].insert_str(0, &before);
+    if let Some(last_new) = new_lines.last_mut() {
+        last_new.push_str(&after);
+    }
+
+    buffer.splice(start.line..=end_line, new_lines);
+}
@@ -0,0 +1,2005 @@
+// editor module - front-end independent editing engine shared by the TUI and GUI
+use crate::core::autopair::AutoPairs;
+use crate::core::brackets::BracketMatcher;
+use crate::core::buffer::Buffer;
+use crate::core::edit_history::{end_of_inserted, Edit, EditHistory, EditOperation};
+use crate::core::bookmarks::Bookmarks;
+use crate::core::completion;
+use crate::core::diff;
+use crate::core::folding::{self, Folds};
+use crate::core::graphemes::*;
+use crate::core::lsp::Diagnostic;
+use crate::core::selection::{BlockSelection, Selection, TextPosition};
+use crate::core::snippets::{self, SnippetSession, Snippets, Variables};
+
+// Cursor movements understood by the engine. Visual-row movement (soft wrap) depends on
+// the front end's layout, so front ends compute that target themselves and call move_to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp(usize),
+    PageDown(usize),
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    BufferStart,
+    BufferEnd,
+}
+
+// Reorderings for transform_lines
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineTransform {
+    Sort,
+    Reverse,
+    Unique,
+    Shuffle,
+}
+
+#[derive(Clone)]
+pub struct Editor {
+    pub buffer: Buffer,
+    pub cursor: TextPosition,
+    pub selection: Option<Selection>,
+    // Extra carets for multi-cursor editing, each with its own selection anchor
+    pub secondary: Vec<Selection>,
+    // Rectangular selection; while set it replaces the carets above
+    pub block: Option<BlockSelection>,
+    // Text of the last block copy, so pasting it back lays it out as a block again
+    block_clipboard: Option<String>,
+    pub history: EditHistory,
+    // Collapsed regions; their lines are skipped by motions and hidden by the front ends
+    pub folds: Folds,
+    pub bookmarks: Bookmarks,
+    // The expanded snippet whose tab stops Tab moves through
+    pub snippet: Option<SnippetSession>,
+    // Where the last JSON/TOML/YAML validation failed; the next edit clears it
+    pub data_error: Option<Diagnostic>,
+    // Lines edited since take_line_edits(), as (first, old_last, new_last)
+    line_edits: Vec<(usize, usize, usize)>,
+}
+
+impl Editor {
+    pub fn new(buffer: Buffer) -> Self {
+        Self {
+            buffer,
+            cursor: TextPosition { line: 0, column: 0 },
+            selection: None,
+            secondary: Vec::new(),
+            block: None,
+            block_clipboard: None,
+            history: EditHistory::new(500),
+            folds: Folds::default(),
+            bookmarks: Bookmarks::default(),
+            snippet: None,
+            data_error: None,
+            line_edits: Vec::new(),
+        }
+    }
+
+    pub fn line_len(&self, line: usize) -> usize {
+        self.buffer.lines.get(line).map(|l| grapheme_len(l)).unwrap_or(0)
+    }
+
+    // Keep a position inside the buffer
+    pub fn clamp(&self, pos: TextPosition) -> TextPosition {
+        let line = pos.line.min(self.buffer.lines.len().saturating_sub(1));
+        TextPosition {
+            line,
+            column: pos.column.min(self.line_len(line)),
+        }
+    }
+
+    // Last line with text; the buffer keeps spare empty lines after it
+    pub fn last_content_line(&self) -> usize {
+        self.buffer
+            .lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .unwrap_or(0)
+    }
+
+    // File contents as saved to disk (without the spare trailing lines)
+    pub fn content(&self) -> String {
+        self.buffer.lines[..=self.last_content_line().min(self.buffer.lines.len().saturating_sub(1))]
+            .join("\n")
+    }
+
+    // Cursor and selection
+
+    // Move the cursor; `extend` grows the selection from the old cursor instead of clearing it.
+    // Jumping somewhere (a click, a search match) drops the extra carets.
+    pub fn move_to(&mut self, pos: TextPosition, extend: bool) {
+        let pos = self.clamp(pos);
+        self.folds.reveal(pos.line);
+        self.secondary.clear();
+        self.block = None;
+        if extend {
+            let anchor = self.cursor;
+            self.selection
+                .get_or_insert_with(|| Selection::new(anchor))
+                .update_cursor(pos);
+        } else {
+            self.selection = None;
+        }
+        self.cursor = pos;
+    }
+
+    pub fn move_cursor(&mut self, motion: Motion, extend: bool) {
+        self.move_each(extend, |editor, pos| editor.motion_target(pos, motion));
+    }
+
+    // Move every caret to `target(pos)`; with a single caret this is just move_to
+    pub fn move_each(&mut self, extend: bool, target: impl Fn(&Editor, TextPosition) -> TextPosition) {
+        if self.secondary.is_empty() {
+            let pos = target(self, self.cursor);
+            self.move_to(pos, extend);
+            return;
+        }
+        let carets = self
+            .carets()
+            .into_iter()
+            .map(|caret| {
+                let cursor = self.clamp(target(self, caret.cursor));
+                let anchor = if extend { caret.anchor } else { cursor };
+                Selection { anchor, cursor }
+            })
+            .collect();
+        self.set_carets(carets);
+    }
+
+    // Where a motion would take a cursor at `pos`; lines hidden in folds are stepped over
+    pub fn motion_target(&self, pos: TextPosition, motion: Motion) -> TextPosition {
+        let pos = self.clamp(pos);
+        let prev_line = self.folds.prev_visible(pos.line);
+        let line_count = self.buffer.lines.len();
+        let next_line = self.folds.next_visible(pos.line, line_count);
+        let line_end = |line: usize| TextPosition { line, column: self.line_len(line) };
+        let line_start = |line: usize| TextPosition { line, column: 0 };
+
+        match motion {
+            Motion::Left if pos.column > 0 => TextPosition { line: pos.line, column: pos.column - 1 },
+            Motion::Left => prev_line.map_or(pos, line_end),
+            Motion::Right if pos.column < self.line_len(pos.line) => TextPosition { line: pos.line, column: pos.column + 1 },
+            Motion::Right => next_line.map_or(pos, line_start),
+            Motion::Up => TextPosition { line: prev_line.unwrap_or(pos.line), column: pos.column },
+            Motion::Down => TextPosition { line: next_line.unwrap_or(pos.line), column: pos.column },
+            Motion::PageUp(rows) => TextPosition { line: self.folds.step(pos.line, rows, false, line_count), column: pos.column },
+            Motion::PageDown(rows) => TextPosition { line: self.folds.step(pos.line, rows, true, line_count), column: pos.column },
+            // At a line edge a word motion crosses to the neighbouring line
+            Motion::WordLeft if pos.column == 0 && prev_line.is_some() => prev_line.map_or(pos, line_end),
+            Motion::WordRight if pos.column == self.line_len(pos.line) && next_line.is_some() => {
+                next_line.map_or(pos, line_start)
+            }
+            Motion::WordLeft => TextPosition {
+                line: pos.line,
+                column: prev_word_start(&self.buffer.lines[pos.line], pos.column),
+            },
+            Motion::WordRight => TextPosition {
+                line: pos.line,
+                column: next_word_end(&self.buffer.lines[pos.line], pos.column),
+            },
+            Motion::LineStart => TextPosition { line: pos.line, column: 0 },
+            Motion::LineEnd => TextPosition { line: pos.line, column: self.line_len(pos.line) },
+            Motion::BufferStart => TextPosition { line: 0, column: 0 },
+            Motion::BufferEnd => {
+                // A folded last line shows as its fold's header
+                let last = self.last_content_line();
+                let line = self.folds.prev_visible(last + 1).unwrap_or(last);
+                TextPosition { line, column: pos.column }
+            }
+        }
+    }
+
+    // Bookmarks
+
+    // Add a numbered bookmark on the cursor line, or remove the one there
+    pub fn toggle_bookmark(&mut self) -> bool {
+        self.bookmarks.toggle(self.cursor.line)
+    }
+
+    pub fn name_bookmark(&mut self, name: &str) {
+        self.bookmarks.set(self.cursor.line, name.trim());
+    }
+
+    // Move to the start of the next (or previous) bookmarked line, wrapping round
+    pub fn jump_to_bookmark(&mut self, forward: bool) -> bool {
+        let line = self.cursor.line;
+        let target = if forward { self.bookmarks.next_after(line) } else { self.bookmarks.prev_before(line) };
+        match target {
+            Some(line) => {
+                self.move_to(TextPosition { line, column: 0 }, false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Folding
+
+    // Fold the innermost region around the cursor, which moves to its header line
+    pub fn fold_at_cursor(&mut self, matcher: &BracketMatcher) -> bool {
+        let Some(fold) = folding::region_around(&self.buffer.lines, self.cursor.line, matcher) else {
+            return false;
+        };
+        if !self.folds.fold(fold) {
+            return false;
+        }
+        if fold.hides(self.cursor.line) {
+            self.move_to(TextPosition { line: fold.start, column: self.cursor.column }, false);
+        }
+        self.secondary.retain(|caret| !fold.hides(caret.cursor.line) && !fold.hides(caret.anchor.line));
+        true
+    }
+
+    // Unfold the folded region headed by the cursor's line
+    pub fn unfold_at_cursor(&mut self) -> bool {
+        self.folds.unfold(self.cursor.line)
+    }
+
+    pub fn fold_all(&mut self, matcher: &BracketMatcher) -> bool {
+        let mut changed = false;
+        for fold in folding::all_regions(&self.buffer.lines, matcher) {
+            changed |= self.folds.fold(fold);
+        }
+        // Carets end up on the header of the outermost fold around them
+        if changed {
+            let line = self.folds.prev_visible(self.cursor.line + 1).unwrap_or(self.cursor.line);
+            self.move_to(TextPosition { line, column: self.cursor.column }, false);
+        }
+        changed
+    }
+
+    pub fn unfold_all(&mut self) -> bool {
+        self.folds.clear()
+    }
+
+    pub fn select(&mut self, anchor: TextPosition, cursor: TextPosition) {
+        let (anchor, cursor) = (self.clamp(anchor), self.clamp(cursor));
+        self.folds.reveal(cursor.line);
+        self.secondary.clear();
+        self.block = None;
+        self.selection = Some(Selection { anchor, cursor });
+        self.cursor = cursor;
+    }
+
+    pub fn select_all(&mut self) {
+        let last_line = self.last_content_line();
+        let end = TextPosition { line: last_line, column: self.line_len(last_line) };
+        self.select(TextPosition { line: 0, column: 0 }, end);
+    }
+
+    // Put the cursor on the partner of the bracket at or just before it
+    pub fn jump_to_matching_bracket(&mut self, matcher: &BracketMatcher) -> bool {
+        match matcher.matching(&self.buffer.lines, self.cursor) {
+            Some((_, partner)) => {
+                self.move_to(partner, false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Select both brackets and everything between them: the bracket next to the cursor
+    // and its partner, or else the innermost pair around the cursor
+    pub fn select_to_matching_bracket(&mut self, matcher: &BracketMatcher) -> bool {
+        let lines = &self.buffer.lines;
+        let pair = matcher
+            .matching(lines, self.cursor)
+            .or_else(|| matcher.enclosing(lines, self.cursor));
+        let Some((a, b)) = pair else {
+            return false;
+        };
+        let (open, close) = if (a.line, a.column) < (b.line, b.column) { (a, b) } else { (b, a) };
+        self.select(open, TextPosition { line: close.line, column: close.column + 1 });
+        true
+    }
+
+    // Ordered range of a non-empty selection
+    pub fn active_selection(&self) -> Option<(TextPosition, TextPosition)> {
+        self.selection
+            .as_ref()
+            .filter(|s| s.is_active())
+            .map(|s| s.get_range())
+    }
+
+    // Ordered ranges of every caret's non-empty selection, top to bottom
+    pub fn selection_ranges(&self) -> Vec<(TextPosition, TextPosition)> {
+        self.ranges_of(&self.carets(), self.block)
+    }
+
+    // The same for other carets in this document, e.g. the other pane's
+    pub fn ranges_of(&self, carets: &[Selection], block: Option<BlockSelection>) -> Vec<(TextPosition, TextPosition)> {
+        if let Some(block) = block {
+            return self.block_rows(block).into_iter().filter(|(start, end)| start != end).collect();
+        }
+        let mut ranges: Vec<_> = carets
+            .iter()
+            .filter(|caret| caret.is_active())
+            .map(Selection::get_range)
+            .collect();
+        ranges.sort_by_key(|(start, _)| (start.line, start.column));
+        ranges
+    }
+
+    // Selected text of every caret, one selection per line
+    pub fn selected_text(&self) -> Option<String> {
+        if let Some(block) = self.block {
+            let (left, right) = block.columns();
+            let rows: Vec<String> = self
+                .block_rows(block)
+                .into_iter()
+                .map(|(start, end)| self.text_range(start, end))
+                .collect();
+            return (left < right).then(|| rows.join("\n"));
+        }
+        let texts: Vec<String> = self
+            .selection_ranges()
+            .into_iter()
+            .map(|(start, end)| self.text_range(start, end))
+            .collect();
+        (!texts.is_empty()).then(|| texts.join("\n"))
+    }
+
+    // Selected text for the clipboard; block copies are remembered so they paste as a block
+    pub fn copy_selection(&mut self) -> Option<String> {
+        let text = self.selected_text()?;
+        self.block_clipboard = self.block.is_some().then(|| text.clone());
+        Some(text)
+    }
+
+    // Text between two positions, lines joined with '\n'
+    pub fn text_range(&self, start: TextPosition, end: TextPosition) -> String {
+        let (start, end) = (self.clamp(start), self.clamp(end));
+        if start.line == end.line {
+            return grapheme_slice(&self.buffer.lines[start.line], start.column, end.column);
+        }
+
+        let mut result = String::new();
+        for line_idx in start.line..=end.line {
+            let line = &self.buffer.lines[line_idx];
+            if line_idx == start.line {
+                result.push_str(&grapheme_slice(line, start.column, grapheme_len(line)));
+                result.push('\n');
+            } else if line_idx == end.line {
+                result.push_str(&grapheme_slice(line, 0, end.column));
+            } else {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+        result
+    }
+
+    // Editing - each returns true when the buffer changed
+
+    // Insert text at the cursor, replacing the selection if there is one
+    pub fn insert_text(&mut self, text: &str) -> bool {
+        if self.block.is_some() {
+            if !text.contains('\n') {
+                return self.block_insert(text);
+            }
+            // A line break ends block mode and goes in at the cursor
+            self.block = None;
+        }
+        if !self.secondary.is_empty() {
+            return self.edit_each(|_, _, caret| {
+                let (start, end) = caret.get_range();
+                Some((start, end, text.to_string()))
+            });
+        }
+        // Typing over a selection is one undo step with the selection's removal
+        let cursor_before = self.cursor;
+        let removed = self.remove_selection();
+        if text.is_empty() {
+            let Some(removed) = removed else {
+                return false;
+            };
+            self.record(removed, cursor_before);
+            return true;
+        }
+
+        let pos = self.clamp(self.cursor);
+
+        let edit = if text == "\n" {
+            let line = &self.buffer.lines[pos.line];
+            Edit::InsertLine {
+                line: pos.line,
+                remaining_text: split_at_grapheme(line, pos.column).1.to_string(),
+            }
+        } else if text.contains('\n') {
+            Edit::ReplaceRange {
+                start_line: pos.line,
+                start_column: pos.column,
+                end_line: pos.line,
+                end_column: pos.column,
+                old_text: String::new(),
+                new_text: text.to_string(),
+            }
+        } else {
+            Edit::InsertText {
+                line: pos.line,
+                column: pos.column,
+                text: text.to_string(),
+            }
+        };
+
+        let line_len_before = self.line_len(pos.line);
+        edit.apply(&mut self.buffer.lines);
+
+        self.cursor = if text.contains('\n') {
+            end_of_inserted(pos, text)
+        } else {
+            // A combining mark merges into the previous grapheme, so re-measure the line
+            TextPosition {
+                line: pos.line,
+                column: pos.column + self.line_len(pos.line) - line_len_before,
+            }
+        };
+        let edit = match removed {
+            Some(removed) => Edit::Batch(vec![removed, edit]),
+            None => edit,
+        };
+        self.record(edit, cursor_before);
+        true
+    }
+
+    // Paste: when the clipboard has one line per caret, each caret gets its own line
+    pub fn paste(&mut self, text: &str) -> bool {
+        if self.block.is_some() || self.block_clipboard.as_deref() == Some(text) {
+            return self.paste_block(text);
+        }
+        let lines: Vec<&str> = text.strip_suffix('\n').unwrap_or(text).split('\n').collect();
+        if self.secondary.is_empty() || lines.len() != self.secondary.len() + 1 {
+            return self.insert_text(text);
+        }
+        self.edit_each(|_, rank, caret| {
+            let (start, end) = caret.get_range();
+            Some((start, end, lines[rank].to_string()))
+        })
+    }
+
+    // Remove the selected text; false when nothing is selected
+    pub fn delete_selection(&mut self) -> bool {
+        if self.block.is_some() {
+            return self.block_delete();
+        }
+        if !self.secondary.is_empty() {
+            return self.edit_each(|_, _, caret| {
+                let (start, end) = caret.get_range();
+                caret.is_active().then(|| (start, end, String::new()))
+            });
+        }
+        let cursor_before = self.cursor;
+        let Some(edit) = self.remove_selection() else {
+            return false;
+        };
+        self.record(edit, cursor_before);
+        true
+    }
+
+    // Remove the single caret's selection without recording it, for an edit to record
+    // together with what replaces it
+    fn remove_selection(&mut self) -> Option<Edit> {
+        let Some((start, end)) = self.active_selection() else {
+            self.selection = None;
+            return None;
+        };
+        let edit = Edit::ReplaceRange {
+            start_line: start.line,
+            start_column: start.column,
+            end_line: end.line,
+            end_column: end.column,
+            old_text: self.text_range(start, end),
+            new_text: String::new(),
+        };
+        edit.apply(&mut self.buffer.lines);
+        self.selection = None;
+        self.cursor = start;
+        Some(edit)
+    }
+
+    // Type one character with auto-closing: openers gain their closer, a closer already
+    // after the cursor is stepped over, and selections are wrapped in the pair.
+    // Returns true when the buffer changed (stepping over only moves the cursor).
+    pub fn type_char(&mut self, ch: char, pairs: &AutoPairs) -> bool {
+        let text = ch.to_string();
+        if self.block.is_some() {
+            return self.insert_text(&text);
+        }
+        let carets = self.carets();
+
+        if carets.iter().any(Selection::is_active) {
+            let Some(close) = pairs.closing(ch) else {
+                return self.insert_text(&text);
+            };
+            let single = (carets.len() == 1).then(|| carets[0].get_range());
+            let changed = self.edit_each(|editor, _, caret| {
+                let (start, end) = caret.get_range();
+                Some((start, end, format!("{ch}{}{close}", editor.text_range(start, end))))
+            });
+            // A single wrapped selection stays selected, inside the new pair
+            if let (true, Some((start, end))) = (changed, single) {
+                let shift = usize::from(start.line == end.line);
+                self.select(
+                    TextPosition { line: start.line, column: start.column + 1 },
+                    TextPosition { line: end.line, column: end.column + shift },
+                );
+            }
+            return changed;
+        }
+
+        if pairs.is_closing(ch) && carets.iter().all(|caret| self.char_at(caret.cursor) == Some(ch)) {
+            self.move_each(false, |editor, pos| editor.motion_target(pos, Motion::Right));
+            return false;
+        }
+
+        if let Some(close) = pairs.closing(ch) {
+            let closes = carets.iter().all(|caret| {
+                let before = caret.cursor.column.checked_sub(1).and_then(|column| {
+                    self.char_at(TextPosition { line: caret.cursor.line, column })
+                });
+                pairs.should_close(ch, before, self.char_at(caret.cursor))
+            });
+            if closes && self.edit_each(|_, _, caret| Some((caret.cursor, caret.cursor, format!("{ch}{close}")))) {
+                self.move_each(false, |editor, pos| editor.motion_target(pos, Motion::Left));
+                return true;
+            }
+        }
+        self.insert_text(&text)
+    }
+
+    // Backspace between an empty auto-closed pair removes both characters
+    pub fn backspace_pair(&mut self, pairs: &AutoPairs) -> bool {
+        let carets = self.carets();
+        let in_empty_pair = self.block.is_none()
+            && carets.iter().all(|caret| {
+                let pos = caret.cursor;
+                !caret.is_active()
+                    && pos.column > 0
+                    && self
+                        .char_at(TextPosition { line: pos.line, column: pos.column - 1 })
+                        .and_then(|open| pairs.closing(open))
+                        .is_some_and(|close| self.char_at(pos) == Some(close))
+            });
+        if !in_empty_pair {
+            return self.backspace();
+        }
+        self.edit_each(|_, _, caret| {
+            let pos = caret.cursor;
+            Some((
+                TextPosition { line: pos.line, column: pos.column - 1 },
+                TextPosition { line: pos.line, column: pos.column + 1 },
+                String::new(),
+            ))
+        })
+    }
+
+    // The single-character grapheme at `pos`, if there is one
+    fn char_at(&self, pos: TextPosition) -> Option<char> {
+        let grapheme = grapheme_at(self.buffer.lines.get(pos.line)?, pos.column)?;
+        let mut chars = grapheme.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    }
+
+    // Delete key: the selection, the grapheme after the cursor, or the line break
+    pub fn delete_forward(&mut self) -> bool {
+        if self.block.is_some() {
+            return self.block_delete_char(true);
+        }
+        if !self.secondary.is_empty() {
+            return self.edit_each(|editor, _, caret| {
+                let (start, end) = caret.get_range();
+                let end = if caret.is_active() { end } else { editor.motion_target(start, Motion::Right) };
+                (start != end).then(|| (start, end, String::new()))
+            });
+        }
+        if self.delete_selection() {
+            return true;
+        }
+        let pos = self.clamp(self.cursor);
+        let line_len = self.line_len(pos.line);
+
+        let edit = if pos.column < line_len {
+            Edit::DeleteText {
+                line: pos.line,
+                column: pos.column,
+                text: grapheme_slice(&self.buffer.lines[pos.line], pos.column, pos.column + 1),
+            }
+        } else if pos.line + 1 < self.buffer.lines.len() {
+            Edit::JoinLines {
+                line: pos.line,
+                first_line_end: line_len,
+            }
+        } else {
+            return false;
+        };
+
+        edit.apply(&mut self.buffer.lines);
+        self.cursor = pos;
+        self.record(edit, pos);
+        true
+    }
+
+    // Backspace: the selection, the grapheme before the cursor, or the line break
+    pub fn backspace(&mut self) -> bool {
+        if self.block.is_some() {
+            return self.block_delete_char(false);
+        }
+        if !self.secondary.is_empty() {
+            return self.edit_each(|editor, _, caret| {
+                let (start, end) = caret.get_range();
+                let start = if caret.is_active() { start } else { editor.motion_target(end, Motion::Left) };
+                (start != end).then(|| (start, end, String::new()))
+            });
+        }
+        if self.delete_selection() {
+            return true;
+        }
+        let pos = self.clamp(self.cursor);
+
+        let (edit, cursor_after) = if pos.column > 0 {
+            let column = pos.column - 1;
+            (
+                Edit::DeleteText {
+                    line: pos.line,
+                    column,
+                    text: grapheme_slice(&self.buffer.lines[pos.line], column, pos.column),
+                },
+                TextPosition { line: pos.line, column },
+            )
+        } else if pos.line > 0 {
+            let prev_line_end_len = self.line_len(pos.line - 1);
+            (
+                Edit::DeleteLine {
+                    line: pos.line,
+                    content: self.buffer.lines[pos.line].clone(),
+                    prev_line_end_len,
+                },
+                TextPosition { line: pos.line - 1, column: prev_line_end_len },
+            )
+        } else {
+            return false;
+        };
+
+        edit.apply(&mut self.buffer.lines);
+        self.cursor = cursor_after;
+        self.record(edit, pos);
+        true
+    }
+
+    // Ctrl+Backspace / Ctrl+Delete: the selection, or up to the previous / next word edge
+    pub fn delete_word(&mut self, forward: bool) -> bool {
+        if self.block.is_some() {
+            return self.block_delete_char(forward);
+        }
+        let motion = if forward { Motion::WordRight } else { Motion::WordLeft };
+        self.edit_each(|editor, _, caret| {
+            let (start, end) = if caret.is_active() {
+                caret.get_range()
+            } else if forward {
+                (caret.cursor, editor.motion_target(caret.cursor, motion))
+            } else {
+                (editor.motion_target(caret.cursor, motion), caret.cursor)
+            };
+            (start != end).then(|| (start, end, String::new()))
+        })
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(operation) = self.history.undo() else {
+            return false;
+        };
+        operation.edit.reverse(&mut self.buffer.lines);
+        let (first, old_last, new_last) = operation.edit.line_span();
+        self.track_lines(first, new_last, old_last);
+        self.snippet = None;
+        self.selection = None;
+        self.secondary.clear();
+        self.block = None;
+        self.cursor = self.clamp(operation.cursor_before);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(operation) = self.history.redo() else {
+            return false;
+        };
+        operation.edit.apply(&mut self.buffer.lines);
+        self.track_edit(&operation.edit);
+        self.snippet = None;
+        self.selection = None;
+        self.secondary.clear();
+        self.block = None;
+        self.cursor = self.clamp(operation.cursor_after);
+        true
+    }
+
+    // Multiple cursors
+
+    // Every caret as a selection (anchor == cursor when nothing is selected), primary first
+    pub fn carets(&self) -> Vec<Selection> {
+        let anchor = self.selection.as_ref().map_or(self.cursor, |s| s.anchor);
+        let mut carets = vec![Selection { anchor, cursor: self.cursor }];
+        carets.extend(self.secondary.iter().cloned());
+        carets
+    }
+
+    // Where carets are drawn, primary first; a block has one on every row
+    pub fn caret_positions(&self) -> Vec<TextPosition> {
+        self.positions_of(&self.carets(), self.block)
+    }
+
+    // The same for other carets in this document, e.g. the other pane's
+    pub fn positions_of(&self, carets: &[Selection], block: Option<BlockSelection>) -> Vec<TextPosition> {
+        let Some(block) = block else {
+            return carets.iter().map(|caret| caret.cursor).collect();
+        };
+        let column = block.cursor.column;
+        let mut positions: Vec<TextPosition> = carets.iter().take(1).map(|caret| caret.cursor).collect();
+        positions.extend(
+            block
+                .lines()
+                .filter(|&line| line != block.cursor.line)
+                .map(|line| self.clamp(TextPosition { line, column })),
+        );
+        positions
+    }
+
+    // Replace all carets; the first becomes the primary and carets on the same spot merge
+    fn set_carets(&mut self, carets: Vec<Selection>) {
+        let clamped: Vec<Selection> = carets
+            .into_iter()
+            .map(|caret| Selection {
+                anchor: self.clamp(caret.anchor),
+                cursor: self.clamp(caret.cursor),
+            })
+            .collect();
+        let mut carets = clamped.into_iter();
+        let Some(primary) = carets.next() else {
+            return;
+        };
+        self.cursor = primary.cursor;
+        self.selection = primary.is_active().then_some(primary);
+        self.secondary.clear();
+        self.block = None;
+        for caret in carets {
+            if caret.cursor != self.cursor && !self.secondary.iter().any(|c| c.cursor == caret.cursor) {
+                self.secondary.push(caret);
+            }
+        }
+    }
+
+    // Esc: back to just the primary caret (also leaves block selection and snippet tab stops)
+    pub fn collapse_carets(&mut self) -> bool {
+        let had_extra = !self.secondary.is_empty() || self.block.is_some() || self.snippet.is_some();
+        self.snippet = None;
+        self.secondary.clear();
+        self.block = None;
+        had_extra
+    }
+
+    // New primary caret on the line above the topmost caret, or below the bottom one
+    pub fn add_caret_vertically(&mut self, down: bool) -> bool {
+        let carets = self.carets();
+        let positions = carets.iter().map(|caret| caret.cursor);
+        let edge = if down {
+            positions.max_by_key(|pos| (pos.line, pos.column))
+        } else {
+            positions.min_by_key(|pos| (pos.line, pos.column))
+        };
+        let Some(edge) = edge else {
+            return false;
+        };
+        let line = match down {
+            true if edge.line + 1 < self.buffer.lines.len() => edge.line + 1,
+            false if edge.line > 0 => edge.line - 1,
+            _ => return false,
+        };
+        let pos = self.clamp(TextPosition { line, column: edge.column });
+        self.set_carets(std::iter::once(Selection::new(pos)).chain(carets).collect());
+        true
+    }
+
+    // Select the word under the cursor, or add the next match of the selection as the new primary
+    pub fn add_next_occurrence(&mut self) -> bool {
+        let Some((start, end)) = self.active_selection() else {
+            let (start, end) = self.word_at(self.cursor);
+            if start == end {
+                return false;
+            }
+            let secondary = std::mem::take(&mut self.secondary);
+            self.select(start, end);
+            self.secondary = secondary;
+            return true;
+        };
+
+        let taken: Vec<_> = self.carets().iter().map(Selection::get_range).collect();
+        let free: Vec<_> = self
+            .find_all(&self.text_range(start, end))
+            .into_iter()
+            .filter(|range| !taken.contains(range))
+            .collect();
+        // The first free match after the primary, wrapping around to the top
+        let next = free
+            .iter()
+            .find(|(s, _)| (s.line, s.column) >= (end.line, end.column))
+            .or(free.first());
+        let Some(&(anchor, cursor)) = next else {
+            return false;
+        };
+        let carets = std::iter::once(Selection { anchor, cursor }).chain(self.carets()).collect();
+        self.set_carets(carets);
+        true
+    }
+
+    // A caret on every match of the selection (or of the word under the cursor)
+    pub fn select_all_occurrences(&mut self) -> bool {
+        let (start, end) = self.active_selection().unwrap_or_else(|| self.word_at(self.cursor));
+        let mut carets: Vec<Selection> = self
+            .find_all(&self.text_range(start, end))
+            .into_iter()
+            .map(|(anchor, cursor)| Selection { anchor, cursor })
+            .collect();
+        if carets.is_empty() {
+            return false;
+        }
+        // Keep the primary on the occurrence it started at
+        let primary = carets.iter().position(|caret| caret.anchor == start).unwrap_or(0);
+        carets.swap(0, primary);
+        self.set_carets(carets);
+        true
+    }
+
+    // One caret per line of every multi-line selection, each keeping its part of the line
+    pub fn split_selection_into_lines(&mut self) -> bool {
+        let before = self.secondary.len();
+        let mut carets = Vec::new();
+        for caret in self.carets() {
+            let (start, end) = caret.get_range();
+            if start.line == end.line {
+                carets.push(caret);
+                continue;
+            }
+            for line in start.line..=end.line {
+                let from = if line == start.line { start.column } else { 0 };
+                let to = if line == end.line { end.column } else { self.line_len(line) };
+                // A selection ending at a line start does not include that line
+                if line == end.line && to == 0 {
+                    break;
+                }
+                carets.push(Selection {
+                    anchor: TextPosition { line, column: from },
+                    cursor: TextPosition { line, column: to },
+                });
+            }
+        }
+        self.set_carets(carets);
+        self.secondary.len() != before
+    }
+
+    // Word (or single other grapheme) around a position, as mouse double-click selects it;
+    // a cursor just after a word counts as on it
+    pub fn word_at(&self, pos: TextPosition) -> (TextPosition, TextPosition) {
+        let pos = self.clamp(pos);
+        let line = &self.buffer.lines[pos.line];
+        let after_word = pos.column > 0
+            && grapheme_at(line, pos.column).is_none_or(|g| !is_word_grapheme(g))
+            && grapheme_at(line, pos.column - 1).is_some_and(is_word_grapheme);
+        let column = if after_word { pos.column - 1 } else { pos.column };
+        let (start, end) = word_boundaries(line, column);
+        (
+            TextPosition { line: pos.line, column: start },
+            TextPosition { line: pos.line, column: end },
+        )
+    }
+
+    // Replace the partial word before each caret with `word`, as one undo step
+    pub fn complete_word(&mut self, word: &str) -> bool {
+        if self.block.is_some() {
+            return false;
+        }
+        self.edit_each(|editor, _, caret| {
+            let cursor = caret.cursor;
+            let (start, _) = completion::prefix_at(&editor.buffer.lines[cursor.line], cursor.column);
+            Some((TextPosition { line: cursor.line, column: start }, cursor, word.to_string()))
+        })
+    }
+
+    // Expand the snippet whose trigger is the word before the cursor, or before the selection,
+    // which then fills $SELECTION. The expansion is one undo step; its first tab stop is
+    // selected, with a caret on each mirror.
+    pub fn expand_snippet(&mut self, snippets: &Snippets, filename: &str) -> bool {
+        if self.block.is_some() || !self.secondary.is_empty() {
+            return false;
+        }
+        let (from, to) = self.active_selection().unwrap_or((self.cursor, self.cursor));
+        let line = &self.buffer.lines[from.line];
+        let (trigger_start, trigger) = completion::prefix_at(line, from.column);
+        let Some(body) = snippets.get(&trigger) else {
+            return false;
+        };
+        let indent: String = line.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        let selection = self.text_range(from, to);
+        let expansion = snippets::expand(body, &indent, &Variables { filename, selection: &selection });
+
+        let start = TextPosition { line: from.line, column: trigger_start };
+        let edit = Edit::ReplaceRange {
+            start_line: start.line,
+            start_column: start.column,
+            end_line: to.line,
+            end_column: to.column,
+            old_text: self.text_range(start, to),
+            new_text: expansion.text.clone(),
+        };
+        let cursor_before = self.cursor;
+        edit.apply(&mut self.buffer.lines);
+        self.snippet = None;
+        self.selection = None;
+        self.cursor = end_of_inserted(start, &expansion.text);
+        self.record(edit, cursor_before);
+
+        self.snippet = Some(SnippetSession::new(expansion, start));
+        self.select_snippet_stop();
+        true
+    }
+
+    // Tab / Shift+Tab in an expanded snippet: on to the next or back to the previous tab stop;
+    // reaching the final one ends the snippet
+    pub fn snippet_step(&mut self, forward: bool) -> bool {
+        if !self.snippet.as_mut().is_some_and(|session| session.step(forward)) {
+            return false;
+        }
+        self.select_snippet_stop();
+        true
+    }
+
+    fn select_snippet_stop(&mut self) {
+        let Some(session) = &self.snippet else {
+            return;
+        };
+        let carets = session.current().iter().map(|&(anchor, cursor)| Selection { anchor, cursor }).collect();
+        if session.is_done() {
+            self.snippet = None;
+        }
+        self.set_carets(carets);
+        self.folds.reveal(self.cursor.line);
+    }
+
+    // Apply replacements given in positions of the current text (a rename, a reformat)
+    // as one undo step. The cursor keeps its place in the text around them; extra carets go.
+    pub fn apply_edits(&mut self, edits: &[(TextPosition, TextPosition, String)]) -> bool {
+        let mut edits: Vec<_> = edits.iter().collect();
+        edits.sort_by_key(|(start, _, _)| std::cmp::Reverse((start.line, start.column)));
+
+        // Bottom-up, so each range is still where the caller saw it
+        let mut cursor = self.cursor;
+        let mut applied = Vec::new();
+        let mut lowest: Option<TextPosition> = None;
+        for (start, end, text) in edits {
+            let (start, end) = (self.clamp(*start), self.clamp(*end));
+            // Overlapping edits are malformed; the lower one wins
+            if lowest.is_some_and(|low| (end.line, end.column) > (low.line, low.column)) {
+                continue;
+            }
+            lowest = Some(start);
+            let old_text = self.text_range(start, end);
+            if old_text == *text {
+                continue;
+            }
+            let edit = Edit::ReplaceRange {
+                start_line: start.line,
+                start_column: start.column,
+                end_line: end.line,
+                end_column: end.column,
+                old_text,
+                new_text: text.clone(),
+            };
+            edit.apply(&mut self.buffer.lines);
+            let new_end = end_of_inserted(start, text);
+            if (cursor.line, cursor.column) >= (end.line, end.column) {
+                cursor = shift_after(cursor, end, new_end);
+            } else if (cursor.line, cursor.column) > (start.line, start.column) {
+                cursor = new_end;
+            }
+            applied.push(edit);
+        }
+
+        if applied.is_empty() {
+            return false;
+        }
+        let cursor_before = self.cursor;
+        self.set_carets(vec![Selection::new(cursor)]);
+        self.record(Edit::Batch(applied), cursor_before);
+        true
+    }
+
+    // Replace start..end with `text`, touching only the lines and columns that differ
+    // so carets and undo stay where they make sense after a formatter or filter
+    pub fn replace_minimal(&mut self, start: TextPosition, end: TextPosition, text: &str) -> bool {
+        let (start, end) = (self.clamp(start), self.clamp(end));
+        let old = &self.buffer.lines[start.line..=end.line];
+        let prefix = grapheme_slice(&old[0], 0, start.column);
+        let suffix = grapheme_slice(&old[old.len() - 1], end.column, self.line_len(end.line));
+        let new: Vec<String> = format!("{}{}{}", prefix, text, suffix).split('\n').map(String::from).collect();
+
+        let at = |pos: TextPosition| TextPosition { line: pos.line + start.line, column: pos.column };
+        let edits: Vec<_> = diff::minimal_edits(old, &new)
+            .into_iter()
+            .map(|(from, to, text)| (at(from), at(to), text))
+            .collect();
+        self.apply_edits(&edits)
+    }
+
+    // Replace the whole document, e.g. with a formatter's output
+    pub fn replace_all(&mut self, text: &str) -> bool {
+        let last = self.last_content_line();
+        let end = TextPosition { line: last, column: self.line_len(last) };
+        self.replace_minimal(TextPosition { line: 0, column: 0 }, end, text)
+    }
+
+    // What a shell filter works on: the selection, or else the cursor's line
+    pub fn filter_range(&self) -> (TextPosition, TextPosition) {
+        self.active_selection().unwrap_or_else(|| {
+            let line = self.cursor.line;
+            (TextPosition { line, column: 0 }, TextPosition { line, column: self.line_len(line) })
+        })
+    }
+
+    // Drop spaces and tabs (and stray carriage returns) at the ends of lines, in one undo step
+    pub fn trim_trailing_whitespace(&mut self) -> bool {
+        let edits: Vec<_> = (0..=self.last_content_line())
+            .filter_map(|line| {
+                let text = &self.buffer.lines[line];
+                let kept = grapheme_len(text.trim_end());
+                let len = grapheme_len(text);
+                (kept < len).then(|| (TextPosition { line, column: kept }, TextPosition { line, column: len }, String::new()))
+            })
+            .collect();
+        self.apply_edits(&edits)
+    }
+
+    // Every non-overlapping match of a single-line needle, top to bottom
+    fn find_all(&self, needle: &str) -> Vec<(TextPosition, TextPosition)> {
+        if needle.is_empty() || needle.contains('\n') {
+            return Vec::new();
+        }
+        let needle_len = grapheme_len(needle);
+        let mut matches = Vec::new();
+        for (line_idx, line) in self.buffer.lines.iter().enumerate() {
+            for (byte_idx, _) in line.match_indices(needle) {
+                let column = grapheme_len(&line[..byte_idx]);
+                matches.push((
+                    TextPosition { line: line_idx, column },
+                    TextPosition { line: line_idx, column: column + needle_len },
+                ));
+            }
+        }
+        matches
+    }
+
+    // Apply one change per caret as a single undo step. `change` gets each caret with its rank
+    // from the top and returns the range to replace and the new text, or None to skip it.
+    fn edit_each(
+        &mut self,
+        change: impl Fn(&Editor, usize, &Selection) -> Option<(TextPosition, TextPosition, String)>,
+    ) -> bool {
+        let carets = self.carets();
+        let mut order: Vec<usize> = (0..carets.len()).collect();
+        order.sort_by_key(|&i| {
+            let (start, _) = carets[i].get_range();
+            (start.line, start.column)
+        });
+        let changes: Vec<_> = order
+            .iter()
+            .enumerate()
+            .map(|(rank, &i)| change(self, rank, &carets[i]))
+            .collect();
+
+        // Bottom-up, so an edit never moves the ranges of the edits still to come;
+        // carets already placed below an edit shift with the text after it
+        let mut results: Vec<Selection> = carets.iter().map(|caret| Selection::new(caret.cursor)).collect();
+        let mut placed: Vec<usize> = Vec::new();
+        let mut edits = Vec::new();
+        let mut lowest: Option<TextPosition> = None;
+
+        for (&i, change) in order.iter().zip(changes).rev() {
+            let Some((start, end, text)) = change else {
+                placed.push(i);
+                continue;
+            };
+            let (start, end) = (self.clamp(start), self.clamp(end));
+            // Overlapping ranges (two carets deleting the same line break) are edited once
+            if lowest.is_some_and(|low| (end.line, end.column) > (low.line, low.column)) {
+                placed.push(i);
+                continue;
+            }
+
+            let line_len_before = self.line_len(start.line);
+            let edit = Edit::ReplaceRange {
+                start_line: start.line,
+                start_column: start.column,
+                end_line: end.line,
+                end_column: end.column,
+                old_text: self.text_range(start, end),
+                new_text: text.clone(),
+            };
+            edit.apply(&mut self.buffer.lines);
+
+            let new_end = if start.line == end.line && !text.contains('\n') {
+                // Re-measure: a combining mark merges into the grapheme before it
+                TextPosition {
+                    line: start.line,
+                    column: (end.column + self.line_len(start.line)).saturating_sub(line_len_before),
+                }
+            } else {
+                end_of_inserted(start, &text)
+            };
+            for &j in &placed {
+                results[j].anchor = shift_after(results[j].anchor, end, new_end);
+                results[j].cursor = shift_after(results[j].cursor, end, new_end);
+            }
+            results[i] = Selection::new(new_end);
+            placed.push(i);
+            edits.push(edit);
+            lowest = Some(start);
+        }
+
+        if edits.is_empty() {
+            return false;
+        }
+        let cursor_before = self.cursor;
+        self.set_carets(results);
+        self.record(Edit::Batch(edits), cursor_before);
+        true
+    }
+
+    // Line commands - each is a single undo step
+
+    // Lines touched by the selection (a selection ending at column 0 stops on the line before)
+    fn selected_lines(&self) -> (usize, usize) {
+        if let Some(block) = self.block {
+            return (*block.lines().start(), *block.lines().end());
+        }
+        match self.active_selection() {
+            Some((start, end)) if end.line > start.line && end.column == 0 => (start.line, end.line - 1),
+            Some((start, end)) => (start.line, end.line),
+            None => (self.cursor.line, self.cursor.line),
+        }
+    }
+
+    // Replace lines first..=last with new lines, then move the cursor (and selection) by
+    // `shift` lines; false when nothing changed
+    fn rewrite_lines(&mut self, first: usize, last: usize, new_lines: Vec<String>, shift: isize) -> bool {
+        let last_len = self.line_len(last);
+        let old_text = self.text_range(TextPosition { line: first, column: 0 }, TextPosition { line: last, column: last_len });
+        let new_text = new_lines.join("\n");
+        if old_text == new_text {
+            return false;
+        }
+        let cursor_before = self.cursor;
+        let edit = Edit::ReplaceRange {
+            start_line: first,
+            start_column: 0,
+            end_line: last,
+            end_column: last_len,
+            old_text,
+            new_text,
+        };
+        edit.apply(&mut self.buffer.lines);
+
+        let moved = |pos: TextPosition| TextPosition {
+            line: pos.line.saturating_add_signed(shift),
+            column: pos.column,
+        };
+        let selection = self.selection.take().map(|s| Selection { anchor: moved(s.anchor), cursor: moved(s.cursor) });
+        self.secondary.clear();
+        self.block = None;
+        self.cursor = self.clamp(moved(self.cursor));
+        self.selection = selection.map(|s| Selection { anchor: self.clamp(s.anchor), cursor: self.clamp(s.cursor) });
+        self.record(edit, cursor_before);
+        true
+    }
+
+    fn lines_text(&self, first: usize, last: usize) -> Vec<String> {
+        self.buffer.lines[first..=last].to_vec()
+    }
+
+    // Duplicate the selection after itself, or the current lines below themselves
+    pub fn duplicate(&mut self) -> bool {
+        if let (None, Some((start, end))) = (self.block, self.active_selection()) {
+            let text = self.text_range(start, end);
+            let cursor_before = self.cursor;
+            let edit = Edit::ReplaceRange {
+                start_line: end.line,
+                start_column: end.column,
+                end_line: end.line,
+                end_column: end.column,
+                old_text: String::new(),
+                new_text: text.clone(),
+            };
+            edit.apply(&mut self.buffer.lines);
+            // The copy ends up selected, so repeating keeps duplicating
+            self.secondary.clear();
+            self.selection = Some(Selection { anchor: end, cursor: end_of_inserted(end, &text) });
+            self.cursor = end_of_inserted(end, &text);
+            self.record(edit, cursor_before);
+            return true;
+        }
+        let (first, last) = self.selected_lines();
+        let mut lines = self.lines_text(first, last);
+        lines.extend(self.lines_text(first, last));
+        self.rewrite_lines(first, last, lines, (last - first + 1) as isize)
+    }
+
+    // Swap the current lines with the line above or below them
+    pub fn move_lines(&mut self, down: bool) -> bool {
+        let (first, last) = self.selected_lines();
+        if down {
+            if last >= self.last_content_line() {
+                return false;
+            }
+            let mut lines = vec![self.buffer.lines[last + 1].clone()];
+            lines.extend(self.lines_text(first, last));
+            self.rewrite_lines(first, last + 1, lines, 1)
+        } else {
+            if first == 0 {
+                return false;
+            }
+            let mut lines = self.lines_text(first, last);
+            lines.push(self.buffer.lines[first - 1].clone());
+            self.rewrite_lines(first - 1, last, lines, -1)
+        }
+    }
+
+    // Remove the current lines, line breaks included
+    pub fn delete_lines(&mut self) -> bool {
+        let (first, last) = self.selected_lines();
+        let column = self.cursor.column;
+        let changed = if last + 1 < self.buffer.lines.len() {
+            let mut lines = self.lines_text(first, last + 1);
+            lines.drain(..=last - first);
+            self.rewrite_lines(first, last + 1, lines, 0)
+        } else if first > 0 {
+            let lines = vec![self.buffer.lines[first - 1].clone()];
+            self.rewrite_lines(first - 1, last, lines, 0)
+        } else {
+            self.rewrite_lines(first, last, vec![String::new()], 0)
+        };
+        if changed {
+            self.selection = None;
+            self.cursor = self.clamp(TextPosition { line: first, column });
+        }
+        changed
+    }
+
+    // Join the selected lines (or the current line and the next) with single spaces
+    pub fn join_lines(&mut self) -> bool {
+        let (first, last) = self.selected_lines();
+        let last = if first == last { last + 1 } else { last };
+        if last > self.last_content_line() {
+            return false;
+        }
+        let mut joined = self.buffer.lines[first].trim_end().to_string();
+        for line in &self.buffer.lines[first + 1..=last] {
+            let line = line.trim();
+            if !line.is_empty() && !joined.is_empty() {
+                joined.push(' ');
+            }
+            joined.push_str(line);
+        }
+        let column = grapheme_len(&joined);
+        let changed = self.rewrite_lines(first, last, vec![joined], 0);
+        if changed {
+            self.selection = None;
+            self.cursor = TextPosition { line: first, column };
+        }
+        changed
+    }
+
+    // Reorder the selected lines (the whole document without a multi-line selection)
+    pub fn transform_lines(&mut self, transform: LineTransform) -> bool {
+        let (first, last) = match self.selected_lines() {
+            (first, last) if first < last => (first, last),
+            _ => (0, self.last_content_line()),
+        };
+        let mut lines = self.lines_text(first, last);
+        match transform {
+            LineTransform::Sort => lines.sort(),
+            LineTransform::Reverse => lines.reverse(),
+            LineTransform::Unique => {
+                let mut seen = std::collections::HashSet::new();
+                lines.retain(|line| seen.insert(line.clone()));
+            }
+            LineTransform::Shuffle => shuffle(&mut lines),
+        }
+        let new_last = first + lines.len() - 1;
+        let changed = self.rewrite_lines(first, last, lines, 0);
+        if changed {
+            // Keep the reordered lines selected
+            self.select(
+                TextPosition { line: first, column: 0 },
+                TextPosition { line: new_last, column: self.line_len(new_last) },
+            );
+        }
+        changed
+    }
+
+    // Block (rectangular) selection
+
+    // Alt+Shift+arrows: grow the block from the cursor; columns may go past the line end
+    pub fn extend_block(&mut self, motion: Motion) {
+        let block = self.block.unwrap_or_else(|| BlockSelection::new(self.cursor));
+        let pos = block.cursor;
+        let cursor = match motion {
+            Motion::Left => TextPosition { line: pos.line, column: pos.column.saturating_sub(1) },
+            Motion::Right => TextPosition { line: pos.line, column: pos.column + 1 },
+            Motion::Up | Motion::Down | Motion::PageUp(_) | Motion::PageDown(_) => {
+                let line = self.motion_target(pos, motion).line;
+                TextPosition { line, column: pos.column }
+            }
+            _ => self.motion_target(pos, motion),
+        };
+        self.select_block(block.anchor, cursor);
+    }
+
+    // Alt+drag: a block between two corners
+    pub fn select_block(&mut self, anchor: TextPosition, cursor: TextPosition) {
+        let last_line = self.buffer.lines.len().saturating_sub(1);
+        let anchor = TextPosition { line: anchor.line.min(last_line), column: anchor.column };
+        let cursor = TextPosition { line: cursor.line.min(last_line), column: cursor.column };
+        self.selection = None;
+        self.secondary.clear();
+        self.block = Some(BlockSelection { anchor, cursor });
+        self.cursor = self.clamp(cursor);
+    }
+
+    // The part of each block row that exists in the text
+    fn block_rows(&self, block: BlockSelection) -> Vec<(TextPosition, TextPosition)> {
+        let (left, right) = block.columns();
+        block
+            .lines()
+            .map(|line| {
+                let len = self.line_len(line);
+                (
+                    TextPosition { line, column: left.min(len) },
+                    TextPosition { line, column: right.min(len) },
+                )
+            })
+            .collect()
+    }
+
+    // Type on every row of the block, padding short rows out to the block's column
+    fn block_insert(&mut self, text: &str) -> bool {
+        let Some(block) = self.block else {
+            return false;
+        };
+        let cursor_before = self.cursor;
+        let (left, right) = block.columns();
+        let mut edits = Vec::new();
+        for line in block.lines() {
+            let len = self.line_len(line);
+            let edit = if len < left {
+                let padded = format!("{}{}", " ".repeat(left - len), text);
+                self.replace_in_line(line, len, len, &padded)
+            } else {
+                self.replace_in_line(line, left, right.min(len), text)
+            };
+            edits.push(edit);
+        }
+        // Keep a zero-width block so typing continues on every row
+        self.move_block(block.collapsed_to(left + grapheme_len(text)));
+        self.record_batch(edits, cursor_before)
+    }
+
+    fn block_delete(&mut self) -> bool {
+        let cursor_before = self.cursor;
+        let edits = self.remove_block();
+        self.record_batch(edits, cursor_before)
+    }
+
+    // Remove the block's text without recording it, returning the applied edits
+    fn remove_block(&mut self) -> Vec<Edit> {
+        let Some(block) = self.block else {
+            return Vec::new();
+        };
+        let (left, right) = block.columns();
+        let mut edits = Vec::new();
+        for line in block.lines() {
+            let len = self.line_len(line);
+            if len > left {
+                edits.push(self.replace_in_line(line, left, right.min(len), ""));
+            }
+        }
+        self.move_block(block.collapsed_to(left));
+        edits
+    }
+
+    // Backspace / Delete on every row of a zero-width block
+    fn block_delete_char(&mut self, forward: bool) -> bool {
+        let Some(block) = self.block else {
+            return false;
+        };
+        let (column, right) = block.columns();
+        if column != right {
+            return self.block_delete();
+        }
+        if !forward && column == 0 {
+            return false;
+        }
+        let cursor_before = self.cursor;
+        let start = if forward { column } else { column - 1 };
+        let mut edits = Vec::new();
+        for line in block.lines() {
+            // Rows that end before the block only had virtual space to remove
+            if self.line_len(line) > start {
+                edits.push(self.replace_in_line(line, start, start + 1, ""));
+            }
+        }
+        self.move_block(block.collapsed_to(start));
+        self.record_batch(edits, cursor_before)
+    }
+
+    // Paste each clipboard line on its own row at the block's (or cursor's) column
+    fn paste_block(&mut self, text: &str) -> bool {
+        // Pasting over a block replaces it in the same undo step
+        let cursor_before = self.cursor;
+        let mut edits = self.remove_block();
+        let (top, column) = match self.block {
+            Some(block) => (*block.lines().start(), block.columns().0),
+            None => (self.cursor.line, self.cursor.column),
+        };
+        let rows: Vec<&str> = text.strip_suffix('\n').unwrap_or(text).split('\n').collect();
+
+        // Rows past the end of the buffer are added first
+        let needed = top + rows.len();
+        if needed > self.buffer.lines.len() {
+            let last = self.buffer.lines.len() - 1;
+            let len = self.line_len(last);
+            let breaks = "\n".repeat(needed - self.buffer.lines.len());
+            let edit = Edit::ReplaceRange {
+                start_line: last,
+                start_column: len,
+                end_line: last,
+                end_column: len,
+                old_text: String::new(),
+                new_text: breaks,
+            };
+            edit.apply(&mut self.buffer.lines);
+            edits.push(edit);
+        }
+
+        for (offset, row) in rows.iter().enumerate() {
+            let line = top + offset;
+            let len = self.line_len(line);
+            let edit = if len < column {
+                self.replace_in_line(line, len, len, &format!("{}{}", " ".repeat(column - len), row))
+            } else {
+                self.replace_in_line(line, column, column, row)
+            };
+            edits.push(edit);
+        }
+
+        self.block = None;
+        self.selection = None;
+        let last_row = rows.last().copied().unwrap_or("");
+        self.cursor = TextPosition { line: top + rows.len() - 1, column: column + grapheme_len(last_row) };
+        self.record_batch(edits, cursor_before)
+    }
+
+    fn move_block(&mut self, block: BlockSelection) {
+        self.block = Some(block);
+        self.cursor = self.clamp(block.cursor);
+    }
+
+    // Replace part of one line, returning the applied edit for an undo batch
+    fn replace_in_line(&mut self, line: usize, start: usize, end: usize, text: &str) -> Edit {
+        let edit = Edit::ReplaceRange {
+            start_line: line,
+            start_column: start,
+            end_line: line,
+            end_column: end,
+            old_text: grapheme_slice(&self.buffer.lines[line], start, end),
+            new_text: text.to_string(),
+        };
+        edit.apply(&mut self.buffer.lines);
+        edit
+    }
+
+    fn record_batch(&mut self, edits: Vec<Edit>, cursor_before: TextPosition) -> bool {
+        if edits.is_empty() {
+            return false;
+        }
+        self.record(Edit::Batch(edits), cursor_before);
+        true
+    }
+
+    fn record(&mut self, edit: Edit, cursor_before: TextPosition) {
+        self.track_edit(&edit);
+        if let Some(session) = &mut self.snippet {
+            session.on_edit(&edit.changes(&self.buffer.lines));
+        }
+        self.history.push(EditOperation {
+            edit,
+            cursor_before,
+            cursor_after: self.cursor,
+        });
+    }
+
+    // Keep folds and bookmarks on the lines they belong to after an edit was applied
+    fn track_edit(&mut self, edit: &Edit) {
+        let (first, old_last, new_last) = edit.line_span();
+        self.track_lines(first, old_last, new_last);
+        // Enter at the start of a line moves its text below the new one, and its mark with it
+        if let Some((line, count)) = edit.lines_inserted_before(&self.buffer.lines) {
+            self.bookmarks.move_mark(line, line + count);
+        }
+    }
+
+    // Keep folds and bookmarks on the lines they belong to after lines first..=old_last
+    // became first..=new_last
+    fn track_lines(&mut self, first: usize, old_last: usize, new_last: usize) {
+        self.folds.on_edit(first, old_last, new_last);
+        self.bookmarks.on_edit(first, old_last, new_last);
+        self.data_error = None;
+        self.line_edits.push((first, old_last, new_last));
+    }
+
+    // Lines edited since the last call, oldest first, for what follows the document from
+    // outside it: the other pane's carets and scroll position
+    pub fn take_line_edits(&mut self) -> Vec<(usize, usize, usize)> {
+        std::mem::take(&mut self.line_edits)
+    }
+}
+
+// Fisher-Yates with a small xorshift generator seeded from the clock
+fn shuffle(lines: &mut [String]) {
+    let mut seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x9E37_79B9_7F4A_7C15)
+        | 1;
+    for i in (1..lines.len()).rev() {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        lines.swap(i, (seed % (i as u64 + 1)) as usize);
+    }
+}
+
+// Where a position after `old_end` ends up once the text before it now ends at `new_end`
+fn shift_after(pos: TextPosition, old_end: TextPosition, new_end: TextPosition) -> TextPosition {
+    if pos.line == old_end.line && pos.column >= old_end.column {
+        TextPosition {
+            line: new_end.line,
+            column: new_end.column + pos.column - old_end.column,
+        }
+    } else if pos.line > old_end.line {
+        TextPosition {
+            line: pos.line - old_end.line + new_end.line,
+            column: pos.column,
+        }
+    } else {
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> Editor {
+        Editor::new(Buffer::from_string(text.to_string()))
+    }
+
+    fn at(line: usize, column: usize) -> TextPosition {
+        TextPosition { line, column }
+    }
+
+    #[test]
+    fn test_typing_newline_and_undo_restore_text_and_cursor() {
+        let mut ed = editor("hello world");
+        ed.move_to(at(0, 5), false);
+        ed.insert_text("\n");
+        assert_eq!(ed.buffer.lines[..2], ["hello", " world"]);
+        assert_eq!(ed.cursor, at(1, 0));
+
+        assert!(ed.undo());
+        assert_eq!(ed.buffer.lines[0], "hello world");
+        assert_eq!(ed.cursor, at(0, 5));
+
+        assert!(ed.redo());
+        assert_eq!(ed.buffer.lines[..2], ["hello", " world"]);
+        assert_eq!(ed.cursor, at(1, 0));
+    }
+
+    #[test]
+    fn test_multiline_paste_over_selection_round_trips() {
+        let mut ed = editor("one two\nthree four");
+        ed.select(at(0, 4), at(1, 5));
+        ed.insert_text("2\nX\n3");
+        assert_eq!(ed.content(), "one 2\nX\n3 four");
+        assert_eq!(ed.cursor, at(2, 1));
+
+        // The selection's removal and the insert are one step
+        ed.undo();
+        assert_eq!(ed.content(), "one two\nthree four");
+        assert_eq!(ed.cursor, at(1, 5));
+        ed.redo();
+        assert_eq!(ed.content(), "one 2\nX\n3 four");
+    }
+
+    #[test]
+    fn test_enter_at_line_start_moves_its_bookmark_down() {
+        let mut ed = editor("one\ntwo\nthree");
+        ed.move_to(at(1, 0), false);
+        ed.toggle_bookmark();
+        ed.insert_text("\n");
+        assert_eq!(ed.bookmarks.at(2).map(|m| m.name.as_str()), Some("1"));
+        assert!(ed.bookmarks.at(1).is_none());
+
+        // Lines pasted at the start go in above it too; undo brings it back
+        ed.move_to(at(2, 0), false);
+        ed.insert_text("a\nb\n");
+        assert_eq!(ed.buffer.lines[4], "two");
+        assert!(ed.bookmarks.at(4).is_some());
+        ed.undo();
+        ed.undo();
+        assert!(ed.bookmarks.at(1).is_some());
+    }
+
+    #[test]
+    fn test_backspace_joins_lines_and_undo_splits_them() {
+        let mut ed = editor("ab\ncd");
+        ed.move_to(at(1, 0), false);
+        ed.backspace();
+        assert_eq!(ed.content(), "abcd");
+        assert_eq!(ed.cursor, at(0, 2));
+
+        ed.undo();
+        assert_eq!(ed.content(), "ab\ncd");
+        ed.redo();
+        assert_eq!(ed.content(), "abcd");
+    }
+
+    #[test]
+    fn test_typing_wide_and_combining_characters_uses_grapheme_columns() {
+        let mut ed = editor("");
+        for text in ["日", "e", "\u{301}", "x"] {
+            ed.insert_text(text);
+        }
+        assert_eq!(ed.cursor, at(0, 3));
+        assert_eq!(ed.selected_text(), None);
+
+        ed.undo();
+        assert_eq!(ed.content(), "");
+    }
+
+    #[test]
+    fn test_next_occurrence_carets_type_together_and_undo_as_one_step() {
+        let mut ed = editor("let foo = foo + bar;\nfoo();");
+        ed.move_to(at(0, 5), false);
+        assert!(ed.add_next_occurrence()); // selects the word under the cursor
+        assert!(ed.add_next_occurrence());
+        assert!(ed.add_next_occurrence());
+        assert_eq!(ed.selection_ranges().len(), 3);
+        assert_eq!(ed.cursor, at(1, 3)); // the newest match is the primary
+
+        ed.insert_text("x");
+        assert_eq!(ed.content(), "let x = x + bar;\nx();");
+        ed.backspace();
+        ed.insert_text("value");
+        assert_eq!(ed.content(), "let value = value + bar;\nvalue();");
+        assert_eq!(ed.secondary.len(), 2);
+
+        ed.undo();
+        ed.undo();
+        ed.undo();
+        assert_eq!(ed.content(), "let foo = foo + bar;\nfoo();");
+        assert!(ed.secondary.is_empty());
+    }
+
+    #[test]
+    fn test_paste_spreads_lines_over_carets_and_backspace_joins_each_line() {
+        let mut ed = editor("a\nb\nc");
+        ed.move_to(at(0, 1), false);
+        ed.add_caret_vertically(true);
+        ed.add_caret_vertically(true);
+        assert_eq!(ed.carets().len(), 3);
+
+        ed.paste("1\n2\n3\n");
+        assert_eq!(ed.content(), "a1\nb2\nc3");
+        assert_eq!(ed.selected_text(), None);
+
+        // Newlines at every caret, then backspace takes them out again
+        ed.insert_text("\n");
+        assert_eq!(ed.buffer.lines[..6], ["a1", "", "b2", "", "c3", ""]);
+        ed.backspace();
+        assert_eq!(ed.content(), "a1\nb2\nc3");
+
+        ed.move_cursor(Motion::LineStart, true);
+        assert_eq!(ed.selected_text().as_deref(), Some("a1\nb2\nc3"));
+        assert!(ed.collapse_carets());
+        assert_eq!(ed.selected_text().as_deref(), Some("c3"));
+    }
+
+    #[test]
+    fn test_select_all_occurrences_and_split_selection_into_lines() {
+        let mut ed = editor("ab ab\nab");
+        ed.move_to(at(0, 3), false);
+        assert!(ed.select_all_occurrences());
+        assert_eq!(ed.selection_ranges().len(), 3);
+        assert_eq!(ed.cursor, at(0, 5));
+
+        ed.select(at(0, 1), at(1, 2));
+        assert!(ed.split_selection_into_lines());
+        assert_eq!(ed.selected_text().as_deref(), Some("b ab\nab"));
+        ed.delete_selection();
+        assert_eq!(ed.buffer.lines[..2], ["a", ""]);
+    }
+
+    #[test]
+    fn test_block_selection_pads_short_rows_and_pastes_back_as_block() {
+        let mut ed = editor("id,name\n1\n22,bob");
+        ed.move_to(at(0, 2), false);
+        ed.extend_block(Motion::Down);
+        ed.extend_block(Motion::Down);
+        ed.insert_text("|");
+        assert_eq!(ed.content(), "id|,name\n1 |\n22|,bob");
+        assert_eq!(ed.caret_positions().len(), 3);
+
+        // Select the typed column and cut it out again as one step
+        ed.extend_block(Motion::Left);
+        assert_eq!(ed.copy_selection().as_deref(), Some("|\n|\n|"));
+        ed.delete_selection();
+        assert_eq!(ed.content(), "id,name\n1 \n22,bob");
+
+        // A block copy pastes back as a block, even without a block selected
+        ed.move_to(at(0, 0), false);
+        ed.paste("|\n|\n|");
+        assert_eq!(ed.content(), "|id,name\n|1 \n|22,bob");
+        ed.undo();
+        assert_eq!(ed.content(), "id,name\n1 \n22,bob");
+
+        // Pasting over a block selection replaces it in one step
+        ed.move_to(at(0, 0), false);
+        ed.extend_block(Motion::Right);
+        ed.extend_block(Motion::Right);
+        ed.extend_block(Motion::Down);
+        ed.extend_block(Motion::Down);
+        ed.paste("|\n|\n|");
+        assert_eq!(ed.content(), "|,name\n|\n|,bob");
+        ed.undo();
+        assert_eq!(ed.content(), "id,name\n1 \n22,bob");
+    }
+
+    #[test]
+    fn test_word_motions_and_word_deletion() {
+        let mut ed = editor("let foo_bar = baz(1);\nnext");
+        ed.move_cursor(Motion::WordRight, false);
+        assert_eq!(ed.cursor, at(0, 3));
+        ed.move_cursor(Motion::WordRight, true);
+        assert_eq!(ed.cursor, at(0, 11));
+        assert_eq!(ed.selected_text().as_deref(), Some(" foo_bar"));
+
+        ed.move_to(at(0, 21), false);
+        ed.move_cursor(Motion::WordLeft, false);
+        assert_eq!(ed.cursor, at(0, 19));
+        ed.move_cursor(Motion::WordLeft, false);
+        assert_eq!(ed.cursor, at(0, 18));
+
+        // Line edges are a stop of their own
+        ed.move_to(at(0, 21), false);
+        ed.move_cursor(Motion::WordRight, false);
+        assert_eq!(ed.cursor, at(1, 0));
+
+        ed.move_to(at(0, 17), false);
+        assert!(ed.delete_word(false));
+        assert_eq!(ed.content(), "let foo_bar = (1);\nnext");
+        assert!(ed.delete_word(true));
+        assert_eq!(ed.content(), "let foo_bar = 1);\nnext");
+        ed.undo();
+        ed.undo();
+        assert_eq!(ed.content(), "let foo_bar = baz(1);\nnext");
+    }
+
+    #[test]
+    fn test_line_commands_are_single_undo_steps() {
+        let mut ed = editor("b\na\nc\na");
+        ed.move_to(at(0, 1), false);
+        assert!(ed.move_lines(true));
+        assert_eq!(ed.content(), "a\nb\nc\na");
+        assert_eq!(ed.cursor, at(1, 1));
+
+        assert!(ed.duplicate());
+        assert_eq!(ed.content(), "a\nb\nb\nc\na");
+        assert_eq!(ed.cursor, at(2, 1));
+
+        assert!(ed.delete_lines());
+        assert_eq!(ed.content(), "a\nb\nc\na");
+
+        ed.select_all();
+        assert!(ed.transform_lines(LineTransform::Unique));
+        assert_eq!(ed.content(), "a\nb\nc");
+        ed.select_all();
+        assert!(ed.transform_lines(LineTransform::Reverse));
+        assert_eq!(ed.content(), "c\nb\na");
+        assert!(ed.transform_lines(LineTransform::Sort));
+        assert_eq!(ed.content(), "a\nb\nc");
+
+        ed.move_to(at(0, 0), false);
+        assert!(ed.join_lines());
+        assert_eq!(ed.content(), "a b\nc");
+
+        for _ in 0..7 {
+            ed.undo();
+        }
+        assert_eq!(ed.content(), "b\na\nc\na");
+    }
+
+    #[test]
+    fn test_bracket_jump_and_select() {
+        let matcher = BracketMatcher::new(Some("Rust".to_string()));
+        let mut ed = editor("f(a, [b])");
+        ed.move_to(at(0, 1), false);
+        assert!(ed.jump_to_matching_bracket(&matcher));
+        assert_eq!(ed.cursor, at(0, 8));
+        assert!(ed.jump_to_matching_bracket(&matcher));
+        assert_eq!(ed.cursor, at(0, 1));
+
+        ed.move_to(at(0, 5), false);
+        assert!(ed.select_to_matching_bracket(&matcher));
+        assert_eq!(ed.selected_text().as_deref(), Some("[b]"));
+
+        // Away from a bracket, the innermost pair around the cursor is selected
+        ed.move_to(at(0, 3), false);
+        assert!(ed.select_to_matching_bracket(&matcher));
+        assert_eq!(ed.selected_text().as_deref(), Some("(a, [b])"));
+    }
+
+    #[test]
+    fn test_auto_close_overtype_wrap_and_pair_backspace() {
+        let pairs = AutoPairs::parse("()\"\"''");
+        let mut ed = editor("");
+        assert!(ed.type_char('(', &pairs));
+        assert!(ed.type_char('"', &pairs));
+        assert_eq!(ed.buffer.lines[0], "(\"\")");
+        assert_eq!(ed.cursor, at(0, 2));
+
+        // Stepping over the closers only moves the cursor
+        assert!(!ed.type_char('"', &pairs));
+        assert!(!ed.type_char(')', &pairs));
+        assert_eq!(ed.cursor, at(0, 4));
+
+        // No pairing for an apostrophe after a word
+        ed.insert_text(" don");
+        assert!(ed.type_char('\'', &pairs));
+        assert_eq!(ed.buffer.lines[0], "(\"\") don'");
+
+        ed.select(at(0, 5), at(0, 8));
+        assert!(ed.type_char('(', &pairs));
+        assert_eq!(ed.buffer.lines[0], "(\"\") (don)'");
+        assert_eq!(ed.selected_text().as_deref(), Some("don"));
+
+        ed.move_to(at(0, 2), false);
+        assert!(ed.backspace_pair(&pairs));
+        assert_eq!(ed.buffer.lines[0], "() (don)'");
+    }
+
+    #[test]
+    fn test_motions_skip_folds_and_edits_keep_them_in_place() {
+        let matcher = BracketMatcher::new(Some("Rust".to_string()));
+        let mut ed = editor("use a;\nfn f() {\n    a();\n    b();\n}");
+        ed.move_to(at(2, 2), false);
+        assert!(ed.fold_at_cursor(&matcher));
+        assert_eq!(ed.cursor, at(1, 2));
+
+        ed.move_cursor(Motion::Down, false);
+        assert_eq!(ed.cursor, at(4, 1));
+        ed.move_cursor(Motion::Up, false);
+        assert_eq!(ed.cursor, at(1, 1));
+
+        // A line inserted above moves the fold; undo moves it back
+        ed.move_to(at(0, 6), false);
+        ed.insert_text("\n");
+        assert!(ed.folds.is_folded(2) && ed.folds.is_hidden(4));
+        ed.undo();
+        assert!(ed.folds.is_folded(1) && ed.folds.is_hidden(3));
+
+        // Jumping into a fold opens it
+        ed.move_to(at(3, 0), false);
+        assert!(!ed.folds.is_folded(1));
+    }
+
+    #[test]
+    fn test_completion_replaces_each_prefix_in_one_undo_step() {
+        let mut ed = editor("let val = 1;
+va
+x.va");
+        ed.move_to(at(1, 2), false);
+        ed.add_caret_vertically(true);
+        ed.move_each(false, |editor, pos| editor.motion_target(pos, Motion::LineEnd));
+        assert!(ed.complete_word("value"));
+        assert_eq!(ed.buffer.lines[1..3], ["value", "x.value"]);
+        assert_eq!(ed.cursor, at(2, 7));
+
+        ed.undo();
+        assert_eq!(ed.buffer.lines[1..3], ["va", "x.va"]);
+    }
+
+    #[test]
+    fn test_snippet_expansion_fills_mirrors_and_undoes_in_one_step() {
+        let mut snippets = Snippets::default();
+        snippets.add_json(r#"{ "for": ["for ${1:i} in $2 {", "    $1$0", "}"] }"#);
+        let mut ed = editor("  for");
+        ed.move_to(at(0, 5), false);
+        assert!(ed.expand_snippet(&snippets, "main.rs"));
+        assert_eq!(ed.buffer.lines[..3], ["  for i in  {", "      i", "  }"]);
+        assert_eq!(ed.selected_text().as_deref(), Some("i\ni"));
+
+        // Typing over the first stop changes its mirror too; Tab moves on
+        ed.insert_text("row");
+        assert_eq!(ed.buffer.lines[..2], ["  for row in  {", "      row"]);
+        assert!(ed.snippet_step(true));
+        ed.insert_text("rows");
+        assert_eq!(ed.buffer.lines[0], "  for row in rows {");
+        assert!(ed.snippet_step(true));
+        assert_eq!(ed.cursor, at(1, 9));
+        assert!(ed.snippet.is_none());
+
+        ed.undo();
+        ed.undo();
+        ed.undo();
+        assert_eq!(ed.buffer.lines[0], "  for");
+    }
+
+    #[test]
+    fn test_apply_edits_keeps_the_cursor_in_its_text_and_undoes_in_one_step() {
+        let mut ed = editor("let bad = 1;
+use(bad, bad);");
+        ed.move_to(at(1, 13), false);
+        let rename = |line, column| (at(line, column), at(line, column + 3), "good".to_string());
+        assert!(ed.apply_edits(&[rename(0, 4), rename(1, 9), rename(1, 4)]));
+        assert_eq!(ed.buffer.lines[..2], ["let good = 1;", "use(good, good);"]);
+        assert_eq!(ed.cursor, at(1, 15));
+
+        assert!(!ed.apply_edits(&[(at(0, 4), at(0, 8), "good".to_string())]));
+        assert!(ed.undo());
+        assert_eq!(ed.buffer.lines[..2], ["let bad = 1;", "use(bad, bad);"]);
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_keeps_the_cursor_and_undoes_in_one_step() {
+        let mut ed = editor("let x = 1;  \n\t\nfoo();\r");
+        ed.move_to(at(0, 12), false);
+        assert!(ed.trim_trailing_whitespace());
+        assert_eq!(ed.buffer.lines[..3], ["let x = 1;", "", "foo();"]);
+        assert_eq!(ed.cursor, at(0, 10));
+        assert!(!ed.trim_trailing_whitespace());
+
+        ed.undo();
+        assert_eq!(ed.buffer.lines[..3], ["let x = 1;  ", "\t", "foo();\r"]);
+    }
+
+    #[test]
+    fn test_replace_all_edits_only_what_changed() {
+        let mut ed = editor("fn main(){\nlet x=1;\n  call(x);\n}");
+        ed.move_to(at(2, 6), false);
+        assert!(ed.replace_all("fn main() {\n    let x = 1;\n    call(x);\n}"));
+        assert_eq!(ed.buffer.lines[..4], ["fn main() {", "    let x = 1;", "    call(x);", "}"]);
+        assert_eq!(ed.cursor, at(2, 8));
+        assert!(!ed.replace_all(&ed.content()));
+
+        ed.move_to(at(1, 4), false);
+        let (start, end) = ed.filter_range();
+        assert!(ed.replace_minimal(start, end, "LET X = 1;"));
+        assert_eq!(ed.buffer.lines[1], "LET X = 1;");
+
+        ed.undo();
+        ed.undo();
+        assert_eq!(ed.content(), "fn main(){\nlet x=1;\n  call(x);\n}");
+    }
+}
@@ -0,0 +1,266 @@
+// folding module - collapsible regions found from bracket and indentation structure
+use crate::core::brackets::BracketMatcher;
+use crate::core::selection::TextPosition;
+
+// A region the cursor can fold: the header line `start` stays visible, start+1..=end hide
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    pub fn hides(&self, line: usize) -> bool {
+        line > self.start && line <= self.end
+    }
+}
+
+// The folded regions of one document; folds may nest
+#[derive(Clone, Debug, Default)]
+pub struct Folds {
+    folded: Vec<Fold>,
+}
+
+impl Folds {
+    // Whether `line` is the header of a folded region
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.folded.iter().any(|fold| fold.start == line)
+    }
+
+    pub fn is_hidden(&self, line: usize) -> bool {
+        self.folded.iter().any(|fold| fold.hides(line))
+    }
+
+    // The outermost fold hiding `line`
+    fn outermost(&self, line: usize) -> Option<Fold> {
+        self.folded
+            .iter()
+            .filter(|fold| fold.hides(line))
+            .min_by_key(|fold| (fold.start, usize::MAX - fold.end))
+            .copied()
+    }
+
+    // Lines hidden under the fold whose header is `line`
+    pub fn hidden_after(&self, line: usize) -> usize {
+        self.folded
+            .iter()
+            .filter(|fold| fold.start == line)
+            .map(|fold| fold.end - fold.start)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn fold(&mut self, fold: Fold) -> bool {
+        if fold.end <= fold.start || self.folded.contains(&fold) {
+            return false;
+        }
+        self.folded.push(fold);
+        true
+    }
+
+    // Unfold the region headed by `line`; folds nested inside it stay folded
+    pub fn unfold(&mut self, line: usize) -> bool {
+        let before = self.folded.len();
+        self.folded.retain(|fold| fold.start != line);
+        self.folded.len() != before
+    }
+
+    // Unfold everything hiding `line`, so a jump there shows it
+    pub fn reveal(&mut self, line: usize) -> bool {
+        let before = self.folded.len();
+        self.folded.retain(|fold| !fold.hides(line));
+        self.folded.len() != before
+    }
+
+    pub fn clear(&mut self) -> bool {
+        let had_folds = !self.folded.is_empty();
+        self.folded.clear();
+        had_folds
+    }
+
+    // The first visible line at or after `line`, within `line_count` lines
+    pub fn visible_at_or_after(&self, line: usize, line_count: usize) -> Option<usize> {
+        let line = match self.outermost(line) {
+            Some(fold) => fold.end + 1,
+            None => line,
+        };
+        (line < line_count).then_some(line)
+    }
+
+    // The next visible line below `line`
+    pub fn next_visible(&self, line: usize, line_count: usize) -> Option<usize> {
+        self.visible_at_or_after(line + 1, line_count)
+    }
+
+    // The next visible line above `line`
+    pub fn prev_visible(&self, line: usize) -> Option<usize> {
+        let above = line.checked_sub(1)?;
+        Some(self.outermost(above).map_or(above, |fold| fold.start))
+    }
+
+    // The visible line `count` lines up or down from `line`, stopping at the buffer edges
+    pub fn step(&self, line: usize, count: usize, down: bool, line_count: usize) -> usize {
+        (0..count)
+            .try_fold(line, |line, _| {
+                if down { self.next_visible(line, line_count) } else { self.prev_visible(line) }.ok_or(line)
+            })
+            .unwrap_or_else(|line| line)
+    }
+
+    // Visible lines from `first` down, as shown on screen
+    pub fn visible_lines(&self, first: usize, line_count: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.visible_at_or_after(first, line_count), move |&line| {
+            self.next_visible(line, line_count)
+        })
+    }
+
+    // Keep folds in step with an edit that replaced lines first..=old_last with first..=new_last:
+    // folds below it move, folds it reaches into open up
+    pub fn on_edit(&mut self, first: usize, old_last: usize, new_last: usize) {
+        self.folded.retain_mut(|fold| {
+            if fold.end < first || (first == old_last && old_last == new_last && first == fold.start) {
+                return true;
+            }
+            if fold.start > old_last {
+                fold.start = fold.start - old_last + new_last;
+                fold.end = fold.end - old_last + new_last;
+                return true;
+            }
+            false
+        });
+    }
+}
+
+// Leading whitespace width, None for a blank line
+fn indent(line: &str) -> Option<usize> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(
+        line.chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum(),
+    )
+}
+
+// Column of the last bracket on `line` when it opens something
+fn trailing_opener(lines: &[String], line: usize, matcher: &BracketMatcher) -> Option<usize> {
+    let &(column, ch) = matcher.brackets_in(&lines[line]).last()?;
+    matcher.is_opening(ch).then_some(column)
+}
+
+// Cheap test for the gutter: the line ends with an opening bracket or the next
+// non-blank line is indented deeper
+pub fn is_foldable(lines: &[String], line: usize, matcher: &BracketMatcher) -> bool {
+    let Some(base) = lines.get(line).and_then(|text| indent(text)) else {
+        return false;
+    };
+    if trailing_opener(lines, line, matcher).is_some() {
+        return true;
+    }
+    lines[line + 1..]
+        .iter()
+        .find_map(|text| indent(text))
+        .is_some_and(|next| next > base)
+}
+
+// The region headed by `line`: up to the bracket closing its last opener (which stays
+// visible when it starts its own line), or else the lines indented deeper than it
+pub fn region_at(lines: &[String], line: usize, matcher: &BracketMatcher) -> Option<Fold> {
+    let base = indent(lines.get(line)?)?;
+
+    if let Some(column) = trailing_opener(lines, line, matcher) {
+        if let Some((_, close)) = matcher.matching(lines, TextPosition { line, column }) {
+            let leads_line = lines[close.line]
+                .chars()
+                .take(close.column)
+                .all(char::is_whitespace);
+            let end = if leads_line { close.line - 1 } else { close.line };
+            if end > line {
+                return Some(Fold { start: line, end });
+            }
+        }
+    }
+
+    let mut end = line;
+    for (idx, text) in lines.iter().enumerate().skip(line + 1) {
+        match indent(text) {
+            None => continue,
+            Some(level) if level > base => end = idx,
+            Some(_) => break,
+        }
+    }
+    (end > line).then_some(Fold { start: line, end })
+}
+
+// The innermost region around `line`: its own, or that of the nearest less-indented
+// line above whose region reaches it
+pub fn region_around(lines: &[String], line: usize, matcher: &BracketMatcher) -> Option<Fold> {
+    if let Some(fold) = region_at(lines, line, matcher) {
+        return Some(fold);
+    }
+    let mut limit = lines.get(line).and_then(|text| indent(text)).unwrap_or(usize::MAX);
+    for above in (0..line).rev() {
+        let Some(level) = indent(&lines[above]) else {
+            continue;
+        };
+        if level >= limit {
+            continue;
+        }
+        if let Some(fold) = region_at(lines, above, matcher).filter(|fold| fold.end >= line) {
+            return Some(fold);
+        }
+        limit = level;
+        if limit == 0 {
+            break;
+        }
+    }
+    None
+}
+
+// Every region in the document, for fold-all
+pub fn all_regions(lines: &[String], matcher: &BracketMatcher) -> Vec<Fold> {
+    (0..lines.len())
+        .filter(|&line| is_foldable(lines, line, matcher))
+        .filter_map(|line| region_at(lines, line, matcher))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_regions_from_brackets_and_indentation() {
+        let rust = BracketMatcher::new(Some("Rust".to_string()));
+        let buf = lines(&["fn f() {", "    a();", "    b();", "}", "x"]);
+        assert_eq!(region_at(&buf, 0, &rust), Some(Fold { start: 0, end: 2 }));
+        assert_eq!(region_around(&buf, 2, &rust), Some(Fold { start: 0, end: 2 }));
+        assert_eq!(region_at(&buf, 3, &rust), None);
+
+        let yaml = BracketMatcher::new(Some("YAML".to_string()));
+        let buf = lines(&["a:", "  b: 1", "", "  c: 2", "d: 3"]);
+        assert_eq!(region_at(&buf, 0, &yaml), Some(Fold { start: 0, end: 3 }));
+        assert!(!is_foldable(&buf, 1, &yaml));
+    }
+
+    #[test]
+    fn test_visible_lines_skip_folds_and_edits_move_them() {
+        let mut folds = Folds::default();
+        folds.fold(Fold { start: 1, end: 3 });
+        assert_eq!(folds.visible_lines(0, 6).collect::<Vec<_>>(), vec![0, 1, 4, 5]);
+        assert_eq!(folds.next_visible(1, 6), Some(4));
+        assert_eq!(folds.prev_visible(4), Some(1));
+
+        // A line inserted above shifts the fold; an edit inside it unfolds it
+        folds.on_edit(0, 0, 1);
+        assert!(folds.is_folded(2) && folds.is_hidden(4));
+        folds.on_edit(3, 3, 3);
+        assert!(!folds.is_folded(2) && !folds.is_hidden(4));
+    }
+}
@@ -0,0 +1,203 @@
+// formatter module - piping text through shell commands: a per-file-type formatter for
+// the whole document, or any command the selection is filtered through
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+// Formatters and filters run while the editor waits, so one that hangs is stopped
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// How long output is still read once the command exited. A process it left running in
+// the background can hold the pipes open; what it prints after that is dropped.
+const OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+// The "format" block of settings.json, keyed by the file type shown in the footer, e.g.
+//   "format": { "commands": { "Rust": "rustfmt --edition 2021", "JSON": "jq ." } }
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FormatSettings {
+    pub commands: HashMap<String, String>,
+}
+
+impl FormatSettings {
+    pub fn command_for(&self, filetype: &str) -> Option<&str> {
+        self.commands.get(filetype).map(String::as_str).filter(|command| !command.trim().is_empty())
+    }
+}
+
+// Formatters expect a file that ends in a newline; the editor's text doesn't
+pub fn format_text(command: &str, text: &str, dir: Option<&Path>) -> Result<String, String> {
+    let output = run_command(command, &format!("{}\n", text), dir)?;
+    Ok(output.strip_suffix('\n').unwrap_or(&output).to_string())
+}
+
+// Filtered text only keeps a trailing newline the input had
+pub fn filter_text(command: &str, text: &str, dir: Option<&Path>) -> Result<String, String> {
+    let output = run_command(command, text, dir)?;
+    if text.ends_with('\n') {
+        return Ok(output);
+    }
+    Ok(output.strip_suffix('\n').unwrap_or(&output).to_string())
+}
+
+// Run `command` through the shell with `input` on stdin. A failure gives its stderr
+// on one line, for the footer.
+pub fn run_command(command: &str, input: &str, dir: Option<&Path>) -> Result<String, String> {
+    let output = run_shell(command, input, dir, Some(COMMAND_TIMEOUT))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message: Vec<&str> = stderr.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        if message.is_empty() {
+            return Err(exit_message(command, &output.status));
+        }
+        return Err(message.join(" | "));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("`{}` did not print UTF-8 text", command))
+}
+
+pub fn exit_message(command: &str, status: &ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("`{}` exited with code {}", command, code),
+        None => format!("`{}` was stopped", command),
+    }
+}
+
+// Run `command` through the shell and collect everything it printed. One still running
+// after `timeout` is killed, with whatever it started, and reported as an error.
+pub fn run_shell(command: &str, input: &str, dir: Option<&Path>, timeout: Option<Duration>) -> Result<Output, String> {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.args(["-c", command]);
+        process
+    };
+    // A group of its own, so a timeout stops the processes the command started too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+        process.current_dir(dir);
+    }
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run `{}`: {}", command, e))?;
+
+    // Written and read from threads so a command that answers before reading everything, or
+    // fills one pipe while we wait on the other, can't block us
+    let mut stdin = child.stdin.take().ok_or("Could not write to the command")?;
+    let input = input.to_string();
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let stdout = read_chunks(child.stdout.take());
+    let stderr = read_chunks(child.stderr.take());
+
+    let failed = |e: std::io::Error| format!("`{}` failed: {}", command, e);
+    let status = match timeout {
+        None => child.wait().map_err(failed)?,
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait().map_err(failed)? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    kill_group(&mut child);
+                    return Err(format!("`{}` took longer than {:?} and was stopped", command, timeout));
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+    let until = Instant::now() + OUTPUT_GRACE;
+    Ok(Output { status, stdout: collect(&stdout, until), stderr: collect(&stderr, until) })
+}
+
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// What a pipe prints, in chunks as they arrive; the channel closes at end of file
+fn read_chunks(pipe: Option<impl Read + Send + 'static>) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let Some(mut pipe) = pipe else { return };
+        let mut buffer = [0; 8192];
+        while let Ok(read) = pipe.read(&mut buffer) {
+            if read == 0 || sender.send(buffer[..read].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// Everything read until the pipe closed, or what had arrived by `until`
+fn collect(chunks: &Receiver<Vec<u8>>, until: Instant) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        match chunks.recv_timeout(until.saturating_duration_since(Instant::now())) {
+            Ok(chunk) => bytes.extend(chunk),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return bytes,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_text_keeps_the_inputs_trailing_newline() {
+        assert_eq!(filter_text("tr a-z A-Z", "one\ntwo", None).unwrap(), "ONE\nTWO");
+        assert_eq!(filter_text("tr a-z A-Z", "one\n", None).unwrap(), "ONE\n");
+        assert_eq!(format_text("cat", "x", None).unwrap(), "x");
+    }
+
+    #[test]
+    fn test_failing_command_reports_stderr() {
+        assert_eq!(run_command("echo oops >&2; exit 3", "", None), Err("oops".to_string()));
+        assert_eq!(run_command("exit 3", "", None), Err("`exit 3` exited with code 3".to_string()));
+    }
+
+    #[test]
+    fn test_command_that_hangs_is_stopped() {
+        let started = Instant::now();
+        let result = run_shell("sleep 5", "", None, Some(Duration::from_millis(100)));
+        assert_eq!(result.unwrap_err(), "`sleep 5` took longer than 100ms and was stopped");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_command_left_running_in_the_background_does_not_block() {
+        // The background `sleep` keeps stdout open after the shell exits
+        let started = Instant::now();
+        assert_eq!(run_command("sleep 5 & echo done", "", None).unwrap(), "done\n");
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // A timeout stops what the command started as well
+        let marker = std::env::temp_dir().join(format!("quick-formatter-{}", std::process::id()));
+        let command = format!("(sleep 0.3; touch '{}') & sleep 5", marker.display());
+        let started = Instant::now();
+        assert!(run_shell(&command, "", None, Some(Duration::from_millis(100))).is_err());
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(!marker.exists());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
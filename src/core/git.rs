@@ -0,0 +1,290 @@
+// git module - how a file differs from what is staged, read by running `git`: which lines
+// were added, changed or deleted, each change's diff, and the current branch; and the
+// files git opens in $EDITOR (commit messages, rebase todo lists)
+use crate::core::diff::{diff_lines, Hunk};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+// File types of the files git asks an editor to write
+pub const COMMIT_MESSAGE: &str = "Git Commit";
+pub const REBASE_TODO: &str = "Git Rebase";
+
+// Subject and body widths git's own tools and most hosts wrap commit messages at
+const SUBJECT_WIDTH: usize = 50;
+const BODY_WIDTH: usize = 72;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    // Lines were deleted just above this one (or below, at the end of the file)
+    Deleted,
+}
+
+pub struct GitFile {
+    pub branch: String,
+    // The file as staged (the last commit, unless `git add` changed it), None when it isn't
+    // (a new, untracked file)
+    base: Option<Vec<String>>,
+    // The text the hunks were worked out for
+    lines: Vec<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl GitFile {
+    // None when the file isn't in a git working tree, or git isn't installed
+    pub fn load(path: &str) -> Option<Self> {
+        let path = Path::new(path);
+        let dir = path.parent()?;
+        let name = path.file_name()?.to_str()?;
+        let branch = match git(dir, &["rev-parse", "--abbrev-ref", "HEAD"])?.trim() {
+            // Detached: the commit instead
+            "HEAD" => format!("({})", git(dir, &["rev-parse", "--short", "HEAD"])?.trim()),
+            branch => branch.to_string(),
+        };
+        // The index, as `git diff` compares with, so staged work doesn't show as changed
+        let base = git(dir, &["show", &format!(":./{}", name)]);
+        Some(Self::new(branch, base.as_deref()))
+    }
+
+    // A branch and the staged text, for files read some other way
+    pub fn new(branch: String, base: Option<&str>) -> Self {
+        // Read the way a tab reads a file, so line endings don't count as changes
+        let base = base.map(|text| text.lines().map(String::from).collect());
+        Self { branch, base, lines: Vec::new(), hunks: Vec::new() }
+    }
+
+    // Work the changes out again when the text changed since last time
+    pub fn update(&mut self, lines: &[String]) {
+        if self.lines == lines {
+            return;
+        }
+        self.lines = lines.to_vec();
+        self.hunks = self.base.as_ref().map_or_else(Vec::new, |base| diff_lines(base, lines));
+    }
+
+    pub fn change_at(&self, line: usize) -> Option<LineChange> {
+        let hunk = self.hunk_at(line)?;
+        Some(match (hunk.old.is_empty(), hunk.new.is_empty()) {
+            (_, true) => LineChange::Deleted,
+            (true, false) => LineChange::Added,
+            (false, false) => LineChange::Modified,
+        })
+    }
+
+    // The change covering a line; a deletion belongs to the line after it, or the last line
+    pub fn hunk_at(&self, line: usize) -> Option<&Hunk> {
+        let last = self.lines.len().saturating_sub(1);
+        self.hunks.iter().find(|hunk| {
+            hunk.new.contains(&line) || (hunk.new.is_empty() && hunk.new.start.min(last) == line)
+        })
+    }
+
+    // The whole text with a change put back the way it is staged
+    pub fn reverted(&self, hunk: &Hunk) -> String {
+        let base = self.base.as_deref().unwrap_or_default();
+        let mut lines = self.lines.clone();
+        lines.splice(hunk.new.clone(), base[hunk.old.clone()].iter().cloned());
+        lines.join("\n")
+    }
+
+    // A change as `git diff` shows it, without context lines
+    pub fn hunk_diff(&self, hunk: &Hunk) -> Vec<String> {
+        let base = self.base.as_deref().unwrap_or_default();
+        // Empty ranges count from the line before them, as in unified diffs
+        let start = |range: &std::ops::Range<usize>| if range.is_empty() { range.start } else { range.start + 1 };
+        let mut diff = vec![format!(
+            "@@ -{},{} +{},{} @@",
+            start(&hunk.old),
+            hunk.old.len(),
+            start(&hunk.new),
+            hunk.new.len()
+        )];
+        diff.extend(base[hunk.old.clone()].iter().map(|line| format!("-{}", line)));
+        diff.extend(self.lines[hunk.new.clone()].iter().map(|line| format!("+{}", line)));
+        diff
+    }
+}
+
+// What git knows of each tab's file, read on a thread of its own so opening or switching
+// tabs doesn't wait on git. A tab shows no changes until its first read comes back.
+#[derive(Default)]
+pub struct GitFiles {
+    // None when the file isn't in a git working tree
+    files: HashMap<u64, Option<GitFile>>,
+    loading: HashMap<u64, Receiver<Option<GitFile>>>,
+}
+
+impl GitFiles {
+    // Read a tab's file unless it was read already or is being read
+    pub fn load(&mut self, tab_id: u64, path: Option<&str>) {
+        if !self.files.contains_key(&tab_id) && !self.loading.contains_key(&tab_id) {
+            self.reload(tab_id, path);
+        }
+    }
+
+    // Read a tab's file again: saved under a new name, or committed. What was read before
+    // is shown until then.
+    pub fn reload(&mut self, tab_id: u64, path: Option<&str>) {
+        let Some(path) = path.map(String::from) else {
+            self.loading.remove(&tab_id);
+            self.files.insert(tab_id, None);
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(GitFile::load(&path));
+        });
+        self.loading.insert(tab_id, receiver);
+    }
+
+    // Take in the reads that finished; true when one did
+    pub fn poll(&mut self) -> bool {
+        let mut finished = Vec::new();
+        self.loading.retain(|id, receiver| match receiver.try_recv() {
+            Ok(file) => {
+                finished.push((*id, file));
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+            Err(TryRecvError::Disconnected) => false,
+        });
+        let changed = !finished.is_empty();
+        self.files.extend(finished);
+        changed
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.loading.is_empty()
+    }
+
+    pub fn is_loading_tab(&self, tab_id: u64) -> bool {
+        self.loading.contains_key(&tab_id)
+    }
+
+    pub fn get(&self, tab_id: u64) -> Option<&GitFile> {
+        self.files.get(&tab_id)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, tab_id: u64) -> Option<&mut GitFile> {
+        self.files.get_mut(&tab_id)?.as_mut()
+    }
+
+    // For a file read some other way
+    #[cfg(test)]
+    pub fn insert(&mut self, tab_id: u64, file: Option<GitFile>) {
+        self.loading.remove(&tab_id);
+        self.files.insert(tab_id, file);
+    }
+
+    // Forget the tabs that were closed
+    pub fn retain(&mut self, tab_ids: &[u64]) {
+        self.files.retain(|id, _| tab_ids.contains(id));
+        self.loading.retain(|id, _| tab_ids.contains(id));
+    }
+}
+
+// The file type of a file git opened in $EDITOR, by its name
+pub fn message_filetype(name: &str) -> Option<&'static str> {
+    match name {
+        "COMMIT_EDITMSG" | "MERGE_MSG" | "SQUASH_MSG" | "TAG_EDITMSG" | "NOTES_EDITMSG" | "EDIT_DESCRIPTION" => {
+            Some(COMMIT_MESSAGE)
+        }
+        "git-rebase-todo" => Some(REBASE_TODO),
+        _ => None,
+    }
+}
+
+// The column a commit message line should end by: 50 for the subject, 72 below it. None
+// for comment lines, which git leaves out of the message.
+pub fn message_width(line: usize, text: &str) -> Option<usize> {
+    if text.starts_with('#') {
+        None
+    } else if line == 0 {
+        Some(SUBJECT_WIDTH)
+    } else {
+        Some(BODY_WIDTH)
+    }
+}
+
+// What a git command printed, when it ran and succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_changes_against_the_staged_text() {
+        let mut file = GitFile::new("main".to_string(), Some("a\nb\nc\nd\n"));
+        file.update(&lines("B\nc\nnew\nd"));
+        let changes: Vec<_> = (0..4).map(|line| file.change_at(line)).collect();
+        assert_eq!(changes, vec![Some(LineChange::Modified), None, Some(LineChange::Added), None]);
+
+        let hunk = file.hunk_at(0).unwrap().clone();
+        assert_eq!(file.hunk_diff(&hunk), vec!["@@ -1,2 +1,1 @@", "-a", "-b", "+B"]);
+        assert_eq!(file.reverted(&hunk), "a\nb\nc\nnew\nd");
+
+        file.update(&lines("a\nc\nd"));
+        assert_eq!(file.change_at(1), Some(LineChange::Deleted));
+        // Nothing to compare a new file with
+        let mut untracked = GitFile::new("main".to_string(), None);
+        untracked.update(&lines("x"));
+        assert_eq!(untracked.change_at(0), None);
+    }
+
+    #[test]
+    fn test_staged_lines_are_not_changes() {
+        let dir = std::env::temp_dir().join(format!("quick-git-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        let run = |args: &[&str]| assert!(git(&dir, args).is_some(), "git {:?} failed", args);
+        run(&["init", "-q"]);
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        run(&["add", "notes.txt"]);
+        run(&["-c", "user.name=quick", "-c", "user.email=quick@example.com", "-c", "commit.gpgsign=false", "commit", "-qm", "notes"]);
+        std::fs::write(&path, "one\nTWO\n").unwrap();
+        run(&["add", "notes.txt"]);
+
+        let mut files = GitFiles::default();
+        files.load(1, path.to_str());
+        files.load(2, None);
+        assert!(files.is_loading_tab(1) && !files.is_loading_tab(2));
+        while files.is_loading() {
+            files.poll();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        // Only the line that isn't staged yet is a change, and reverting keeps the staged one
+        let file = files.get_mut(1).unwrap();
+        file.update(&lines("one\nTWO\nthree"));
+        let changes: Vec<_> = (0..3).map(|line| file.change_at(line)).collect();
+        assert_eq!(changes, vec![None, None, Some(LineChange::Added)]);
+        let hunk = file.hunk_at(2).unwrap().clone();
+        assert_eq!(file.reverted(&hunk), "one\nTWO");
+        assert!(files.get(2).is_none());
+
+        files.retain(&[2]);
+        assert!(files.get(1).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_message_files_and_widths() {
+        assert_eq!(message_filetype("COMMIT_EDITMSG"), Some(COMMIT_MESSAGE));
+        assert_eq!(message_filetype("git-rebase-todo"), Some(REBASE_TODO));
+        assert_eq!(message_filetype("notes.txt"), None);
+        assert_eq!(message_width(0, "Subject"), Some(50));
+        assert_eq!(message_width(2, "Body"), Some(72));
+        assert_eq!(message_width(3, "# Please enter the commit message"), None);
+    }
+}
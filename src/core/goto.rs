@@ -0,0 +1,87 @@
+// goto module - parses go-to targets typed in a prompt or passed on the command line
+use crate::core::selection::TextPosition;
+
+// Parse "line", "line:col" or a relative "+N" / "-N" into a 0-based position.
+// Lines and columns are typed 1-based; the editor clamps the result to the buffer.
+pub fn parse_target(input: &str, current: TextPosition) -> Option<TextPosition> {
+    let input = input.trim();
+
+    if let Some(offset) = input.strip_prefix('+') {
+        let offset: usize = offset.parse().ok()?;
+        return Some(TextPosition { line: current.line.saturating_add(offset), column: current.column });
+    }
+    if let Some(offset) = input.strip_prefix('-') {
+        let offset: usize = offset.parse().ok()?;
+        return Some(TextPosition { line: current.line.saturating_sub(offset), column: current.column });
+    }
+
+    let (line, column) = match input.split_once(':') {
+        Some((line, column)) => (line, Some(column)),
+        None => (input, None),
+    };
+    let line: usize = line.parse().ok()?;
+    let column: usize = match column {
+        Some(column) => column.parse().ok()?,
+        None => 1,
+    };
+    Some(TextPosition { line: line.saturating_sub(1), column: column.saturating_sub(1) })
+}
+
+// Split "path:line[:col]" as printed by compilers and grep into the path and a 0-based position.
+// Arguments without a numeric suffix come back unchanged.
+pub fn split_location(arg: &str) -> (&str, Option<TextPosition>) {
+    // grep and rustc sometimes leave a trailing colon after the column
+    let trimmed = arg.strip_suffix(':').unwrap_or(arg);
+
+    let numeric_tail = |s: &str| -> Option<(usize, usize)> {
+        let (head, tail) = s.rsplit_once(':')?;
+        if head.is_empty() {
+            return None;
+        }
+        tail.parse().ok().map(|n| (head.len(), n))
+    };
+
+    let Some((head_len, last)) = numeric_tail(trimmed) else {
+        return (arg, None);
+    };
+    let head = &trimmed[..head_len];
+
+    let (path, line, column) = match numeric_tail(head) {
+        Some((path_len, line)) => (&head[..path_len], line, last),
+        None => (head, last, 1),
+    };
+    let pos = TextPosition { line: line.saturating_sub(1), column: column.saturating_sub(1) };
+    (path, Some(pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize, column: usize) -> TextPosition {
+        TextPosition { line, column }
+    }
+
+    #[test]
+    fn test_parse_absolute_and_relative_targets() {
+        let here = pos(9, 4);
+        assert_eq!(parse_target("42", here), Some(pos(41, 0)));
+        assert_eq!(parse_target(" 42:7 ", here), Some(pos(41, 6)));
+        assert_eq!(parse_target("+3", here), Some(pos(12, 4)));
+        assert_eq!(parse_target("-20", here), Some(pos(0, 4)));
+        assert_eq!(parse_target("0", here), Some(pos(0, 0)));
+        assert_eq!(parse_target("", here), None);
+        assert_eq!(parse_target("abc", here), None);
+        assert_eq!(parse_target("4:x", here), None);
+    }
+
+    #[test]
+    fn test_split_location_suffixes() {
+        assert_eq!(split_location("src/foo.rs:42:7"), ("src/foo.rs", Some(pos(41, 6))));
+        assert_eq!(split_location("src/foo.rs:42:7:"), ("src/foo.rs", Some(pos(41, 6))));
+        assert_eq!(split_location("src/foo.rs:42"), ("src/foo.rs", Some(pos(41, 0))));
+        assert_eq!(split_location("src/foo.rs"), ("src/foo.rs", None));
+        assert_eq!(split_location("notes:draft"), ("notes:draft", None));
+        assert_eq!(split_location(":12"), (":12", None));
+    }
+}
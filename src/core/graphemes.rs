@@ -33,69 +33,132 @@ pub fn grapheme_slice(s: &str, start: usize, end: usize) -> String {
         .collect()
 }
 
-// Insert string at grapheme position
-pub fn insert_at_grapheme(s: &mut String, grapheme_idx: usize, text: &str) {
-    let byte_idx = grapheme_to_byte_idx(s, grapheme_idx);
-    s.insert_str(byte_idx, text);
-}
-
-// Remove grapheme at position
-pub fn remove_grapheme_at(s: &mut String, grapheme_idx: usize) -> Option<String> {
-    let byte_start = grapheme_to_byte_idx(s, grapheme_idx);
-    let byte_end = grapheme_to_byte_idx(s, grapheme_idx + 1);
-
-    if byte_start < s.len() {
-        let removed: String = s.drain(byte_start..byte_end).collect();
-        Some(removed)
-    } else {
-        None
-    }
-}
-
 // Split string at grapheme position
 pub fn split_at_grapheme(s: &str, grapheme_idx: usize) -> (&str, &str) {
     let byte_idx = grapheme_to_byte_idx(s, grapheme_idx);
     s.split_at(byte_idx)
 }
 
-// Wrap a single logical line into visual lines, given a max column width (in terminal columns)
-// Uses grapheme boundaries and Unicode visual width to avoid splitting combined characters.
-pub fn wrap_line_to_width(line: &str, max_cols: usize) -> Vec<String> {
+// Break a line into visual rows of at most max_cols terminal columns, returned as grapheme
+// ranges [start, end) so callers can map between logical columns and visual rows.
+pub fn wrap_ranges(line: &str, max_cols: usize) -> Vec<(usize, usize)> {
     if max_cols == 0 {
-        return vec![line.to_string()];
+        return vec![(0, grapheme_len(line))];
     }
 
-    let mut parts: Vec<String> = Vec::new();
-    let mut current = String::new();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
     let mut current_width: usize = 0;
 
-    for g in line.graphemes(true) {
+    for (idx, g) in line.graphemes(true).enumerate() {
         let w = UnicodeWidthStr::width(g);
-        if current_width + w > max_cols && !current.is_empty() {
-            parts.push(current);
-            current = String::new();
+        if current_width + w > max_cols && idx > start {
+            ranges.push((start, idx));
+            start = idx;
             current_width = 0;
         }
-        current.push_str(g);
         current_width += w;
     }
 
-    parts.push(current);
-    parts
+    ranges.push((start, grapheme_len(line)));
+    ranges
+}
+
+pub fn is_word_grapheme(g: &str) -> bool {
+    g.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
-// Wrap multi-line text (may contain newlines) into a sequence of lines fit for a given max_cols.
-// Preserves explicit newline breaks and blank lines.
-pub fn wrap_text_to_width(text: &str, max_cols: usize) -> Vec<String> {
-    let mut out: Vec<String> = Vec::new();
-    for line in text.split('\n') {
-        if line.is_empty() {
-            // preserve blank lines
-            out.push(String::new());
-            continue;
+// Grapheme range of the word at `col`; any other grapheme is a word of its own
+pub fn word_boundaries(line: &str, col: usize) -> (usize, usize) {
+    let grapheme_count = grapheme_len(line);
+
+    if col >= grapheme_count || grapheme_count == 0 {
+        return (col, col);
+    }
+
+    let current = grapheme_at(line, col).unwrap_or("");
+    if !is_word_grapheme(current) {
+        return (col, col + 1);
+    }
+
+    let mut start = col;
+    while start > 0 {
+        if let Some(g) = grapheme_at(line, start - 1) {
+            if is_word_grapheme(g) {
+                start -= 1;
+            } else {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let mut end = col;
+    while end < grapheme_count {
+        if let Some(g) = grapheme_at(line, end) {
+            if is_word_grapheme(g) {
+                end += 1;
+            } else {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+
+    (start, end)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Space,
+    Word,
+    Punct,
+}
+
+// Words are runs of word graphemes (as double-click selects them); runs of other
+// non-space graphemes count as words too
+fn grapheme_classes(line: &str) -> Vec<CharClass> {
+    line.graphemes(true)
+        .map(|g| {
+            if g.chars().all(char::is_whitespace) {
+                CharClass::Space
+            } else if is_word_grapheme(g) {
+                CharClass::Word
+            } else {
+                CharClass::Punct
+            }
+        })
+        .collect()
+}
+
+// Start of the word before `col`, skipping spaces
+pub fn prev_word_start(line: &str, col: usize) -> usize {
+    let classes = grapheme_classes(line);
+    let mut column = col.min(classes.len());
+    while column > 0 && classes[column - 1] == CharClass::Space {
+        column -= 1;
+    }
+    if let Some(&class) = column.checked_sub(1).and_then(|c| classes.get(c)) {
+        while column > 0 && classes[column - 1] == class {
+            column -= 1;
+        }
+    }
+    column
+}
+
+// End of the word after `col`, skipping spaces
+pub fn next_word_end(line: &str, col: usize) -> usize {
+    let classes = grapheme_classes(line);
+    let mut column = col.min(classes.len());
+    while column < classes.len() && classes[column] == CharClass::Space {
+        column += 1;
+    }
+    if let Some(&class) = classes.get(column) {
+        while column < classes.len() && classes[column] == class {
+            column += 1;
         }
-        let wrapped = wrap_line_to_width(line, max_cols);
-        out.extend(wrapped);
     }
-    out
+    column
 }
@@ -0,0 +1,593 @@
+// lsp module - language server client: one server per configured file type, open tabs kept
+// in sync with it, and the diagnostics, hover, definitions, completions and renames it offers
+mod protocol;
+mod server;
+
+use crate::core::editor::Editor;
+use crate::core::selection::TextPosition;
+use protocol::{path_to_uri, position_json, uri_to_path};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use server::Server;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+// Longest wait for a reply the user is waiting on (hover, rename...)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The "lsp" block of settings.json, keyed by the file type shown in the footer, e.g.
+//   "lsp": { "servers": {
+//       "Rust": { "command": "rust-analyzer" },
+//       "Python": { "command": "pylsp", "args": ["-v"], "env": { "PYTHONPATH": "src" } }
+//   } }
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LspSettings {
+    pub servers: HashMap<String, ServerConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub start: TextPosition,
+    pub end: TextPosition,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    // Columns it covers on `line`, the end open for lines it runs past; an empty range
+    // still marks one character
+    pub fn columns_on(&self, line: usize) -> Option<(usize, usize)> {
+        if line < self.start.line || line > self.end.line {
+            return None;
+        }
+        let start = if line == self.start.line { self.start.column } else { 0 };
+        let end = if line == self.end.line { self.end.column } else { usize::MAX };
+        Some((start, end.max(start + 1)))
+    }
+
+    pub fn covers(&self, pos: TextPosition) -> bool {
+        self.columns_on(pos.line).is_some_and(|(start, end)| pos.column >= start && pos.column <= end)
+    }
+
+    // One line for diagnostics lists: "12:5 error: expected `;`"
+    pub fn summary(&self) -> String {
+        let message = self.message.lines().next().unwrap_or("");
+        format!("{}:{} {}: {}", self.start.line + 1, self.start.column + 1, self.severity.label(), message)
+    }
+}
+
+// A place a definition lives
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub path: String,
+    pub pos: TextPosition,
+}
+
+// Replace start..end with the text, positions in the editor's grapheme columns
+pub type TextEdit = (TextPosition, TextPosition, String);
+
+// A server's answer, with the file and position it was asked about so the front end can tell
+// whether it still applies
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    Hover { path: String, pos: TextPosition, text: Option<String> },
+    Definition { path: String, pos: TextPosition, locations: Vec<Location> },
+    Completion { path: String, pos: TextPosition, words: Vec<String> },
+    // The edits renaming the symbol, per file
+    Rename { new_name: String, files: Vec<(String, Vec<TextEdit>)> },
+}
+
+// What a request sent was about, to make sense of its reply
+enum Asked {
+    Hover { path: String, pos: TextPosition },
+    Definition { path: String, pos: TextPosition },
+    Completion { path: String, pos: TextPosition },
+    // The file's version, as edits from before then don't apply to a newer one
+    Rename { path: String, version: i64, new_name: String },
+}
+
+// The server's copy of an open file
+struct Document {
+    filetype: String,
+    version: i64,
+    lines: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct LspManager {
+    settings: LspSettings,
+    // Running servers by file type
+    servers: HashMap<String, Server>,
+    // File types whose server would not start, not retried until restart
+    failed: HashMap<String, String>,
+    documents: HashMap<String, Document>,
+    diagnostics: HashMap<String, Vec<Diagnostic>>,
+    // Requests waiting for a reply, by server file type and request id
+    asked: HashMap<(String, u64), Asked>,
+    // Replies and failures since the front end last took them
+    replies: Vec<Result<Reply, String>>,
+}
+
+impl LspManager {
+    pub fn new(settings: LspSettings) -> Self {
+        Self { settings, ..Self::default() }
+    }
+
+    // Whether any server is running, so the front end keeps polling for diagnostics
+    pub fn is_active(&self) -> bool {
+        !self.servers.is_empty()
+    }
+
+    // Bring the server's copy of the file at `path` up to date with `editor`: opened the first
+    // time (starting the server for its file type), then sent only the changed range. Files
+    // without a path or a configured server are left alone; a server failing to start is
+    // reported once.
+    pub fn sync(&mut self, path: Option<&str>, filetype: Option<&str>, editor: &Editor) -> Result<(), String> {
+        let (Some(path), Some(filetype)) = (path, filetype) else {
+            return Ok(());
+        };
+        if !self.start_server(filetype, path)? {
+            return Ok(());
+        }
+        let Some(server) = self.servers.get_mut(filetype) else {
+            return Ok(());
+        };
+        let lines = &editor.buffer.lines[..=editor.last_content_line()];
+        let uri = path_to_uri(path);
+
+        let Some(document) = self.documents.get_mut(path) else {
+            let params = json!({
+                "textDocument": { "uri": uri, "languageId": language_id(filetype), "version": 1, "text": lines.join("\n") },
+            });
+            server.notify("textDocument/didOpen", params).map_err(|e| e.to_string())?;
+            let document = Document { filetype: filetype.to_string(), version: 1, lines: lines.to_vec() };
+            self.documents.insert(path.to_string(), document);
+            return Ok(());
+        };
+
+        // Before the handshake completes the whole text is sent, which every server takes
+        let capabilities = server.capabilities.as_ref().unwrap_or(&Value::Null);
+        let change = match protocol::sync_kind(capabilities) {
+            0 => None,
+            2 => protocol::content_change(&document.lines, lines),
+            _ => (document.lines != lines).then(|| json!({ "text": lines.join("\n") })),
+        };
+        if let Some(change) = change {
+            document.version += 1;
+            document.lines = lines.to_vec();
+            let params = json!({
+                "textDocument": { "uri": uri, "version": document.version },
+                "contentChanges": [change],
+            });
+            server.notify("textDocument/didChange", params).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    // Close the documents no tab holds any more (a tab was closed, or saved under another
+    // name): their server forgets them, and their diagnostics go
+    pub fn keep_open(&mut self, paths: &[&str]) {
+        let closed: Vec<String> = self.documents.keys().filter(|path| !paths.contains(&path.as_str())).cloned().collect();
+        for path in closed {
+            let Some(document) = self.documents.remove(&path) else {
+                continue;
+            };
+            self.diagnostics.remove(&path);
+            if let Some(server) = self.servers.get_mut(&document.filetype) {
+                let _ = server.notify("textDocument/didClose", json!({ "textDocument": { "uri": path_to_uri(&path) } }));
+            }
+        }
+    }
+
+    // Start the server for `filetype` unless it runs already; false when none is configured
+    fn start_server(&mut self, filetype: &str, path: &str) -> Result<bool, String> {
+        if self.servers.contains_key(filetype) {
+            return Ok(true);
+        }
+        let Some(config) = self.settings.servers.get(filetype) else {
+            return Ok(false);
+        };
+        if self.failed.contains_key(filetype) {
+            return Ok(false);
+        }
+        // Returns right away; the handshake completes while polling
+        match Server::start(config, &workspace_root(path)) {
+            Ok(server) => {
+                self.servers.insert(filetype.to_string(), server);
+                Ok(true)
+            }
+            Err(e) => {
+                let message = format!("{} language server ({}) failed: {}", filetype, config.command, e);
+                self.failed.insert(filetype.to_string(), message.clone());
+                Err(message)
+            }
+        }
+    }
+
+    // Take in what the servers published and answered since the last call; true when
+    // diagnostics changed or replies came, for replies()
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        let mut answered = Vec::new();
+        let mut stopped = Vec::new();
+        for (filetype, server) in &mut self.servers {
+            for message in server.drain() {
+                if message["method"] != "textDocument/publishDiagnostics" {
+                    continue;
+                }
+                let params = &message["params"];
+                let Some(path) = params["uri"].as_str().and_then(uri_to_path) else {
+                    continue;
+                };
+                let lines = self.documents.get(&path).map(|doc| doc.lines.as_slice()).unwrap_or_default();
+                self.diagnostics.insert(path, protocol::parse_diagnostics(lines, params));
+                changed = true;
+            }
+            answered.extend(server.take_replies().into_iter().map(|(id, result)| (filetype.clone(), id, result)));
+            if let Some(failure) = &server.failure {
+                stopped.push((filetype.clone(), format!("{} language server failed: {}", filetype, failure)));
+            } else if !server.is_running() {
+                stopped.push((filetype.clone(), format!("{} language server stopped", filetype)));
+            }
+        }
+        for (filetype, id, result) in answered {
+            if let Some(asked) = self.asked.remove(&(filetype, id)) {
+                let reply = result.and_then(|result| self.reply(asked, &result));
+                self.replies.push(reply);
+            }
+        }
+        // A crashed server is not restarted; its files reopen if it is started again
+        for (filetype, message) in stopped {
+            self.servers.remove(&filetype);
+            self.documents.retain(|_, doc| doc.filetype != filetype);
+            self.asked.retain(|(asked_of, _), _| *asked_of != filetype);
+            self.failed.insert(filetype, message.clone());
+            self.replies.push(Err(message));
+        }
+        changed || !self.replies.is_empty()
+    }
+
+    // Replies and failures taken in by poll(), oldest first
+    pub fn replies(&mut self) -> Vec<Result<Reply, String>> {
+        std::mem::take(&mut self.replies)
+    }
+
+    fn reply(&self, asked: Asked, result: &Value) -> Result<Reply, String> {
+        Ok(match asked {
+            Asked::Hover { path, pos } => Reply::Hover { path, pos, text: protocol::hover_text(result) },
+            Asked::Definition { path, pos } => {
+                Reply::Definition { path, pos, locations: protocol::parse_locations(result, |path| self.lines_of(path)) }
+            }
+            Asked::Completion { path, pos } => Reply::Completion { path, pos, words: protocol::completion_words(result) },
+            Asked::Rename { path, version, new_name } => {
+                if self.documents.get(&path).is_none_or(|document| document.version != version) {
+                    return Err("The file changed while renaming; try again".to_string());
+                }
+                let files = protocol::workspace_edits(result).into_iter().filter_map(|(uri, edits)| {
+                    let path = uri_to_path(&uri)?;
+                    let lines = self.lines_of(&path);
+                    let edits = edits
+                        .iter()
+                        .map(|edit| {
+                            let (start, end) = protocol::range_from_json(&lines, &edit["range"]);
+                            (start, end, edit["newText"].as_str().unwrap_or("").to_string())
+                        })
+                        .collect();
+                    Some((path, edits))
+                });
+                Reply::Rename { new_name, files: files.collect() }
+            }
+        })
+    }
+
+    pub fn diagnostics(&self, path: Option<&str>) -> &[Diagnostic] {
+        path.and_then(|path| self.diagnostics.get(path)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // Ask for the documentation of the symbol at `pos`. This and the requests below return
+    // once sent; the answer comes from replies() after a poll(), or an error if the server
+    // takes too long.
+    pub fn hover(&mut self, path: &str, pos: TextPosition) -> Result<(), String> {
+        self.request(path, "textDocument/hover", pos, json!({}), Asked::Hover { path: path.to_string(), pos })
+    }
+
+    pub fn definition(&mut self, path: &str, pos: TextPosition) -> Result<(), String> {
+        self.request(path, "textDocument/definition", pos, json!({}), Asked::Definition { path: path.to_string(), pos })
+    }
+
+    // Words the server suggests at `pos`
+    pub fn completion(&mut self, path: &str, pos: TextPosition) -> Result<(), String> {
+        self.request(path, "textDocument/completion", pos, json!({}), Asked::Completion { path: path.to_string(), pos })
+    }
+
+    // The edits renaming the symbol at `pos`, per file
+    pub fn rename(&mut self, path: &str, pos: TextPosition, new_name: &str) -> Result<(), String> {
+        let version = self.documents.get(path).map_or(0, |document| document.version);
+        let asked = Asked::Rename { path: path.to_string(), version, new_name: new_name.to_string() };
+        self.request(path, "textDocument/rename", pos, json!({ "newName": new_name }), asked)
+    }
+
+    // A request about `pos` in a synced file
+    fn request(&mut self, path: &str, method: &str, pos: TextPosition, extra: Value, asked: Asked) -> Result<(), String> {
+        let document = self.documents.get(path).ok_or("No language server for this file")?;
+        let server = self.servers.get_mut(&document.filetype).ok_or("Language server stopped")?;
+        let mut params = json!({
+            "textDocument": { "uri": path_to_uri(path) },
+            "position": position_json(&document.lines, pos),
+        });
+        if let (Some(params), Value::Object(extra)) = (params.as_object_mut(), extra) {
+            params.extend(extra);
+        }
+        let id = server.request(method, params, REQUEST_TIMEOUT)?;
+        self.asked.insert((document.filetype.clone(), id), asked);
+        Ok(())
+    }
+
+    // Lines of a file as the server sees it: the synced copy, or else the file on disk
+    fn lines_of(&self, path: &str) -> Vec<String> {
+        match self.documents.get(path) {
+            Some(document) => document.lines.clone(),
+            None => std::fs::read_to_string(path).unwrap_or_default().lines().map(String::from).collect(),
+        }
+    }
+}
+
+// The nearest folder above `path` holding a .git directory, else the file's own folder
+fn workspace_root(path: &str) -> String {
+    let dir = Path::new(path).parent().unwrap_or(Path::new("/"));
+    let root = dir.ancestors().find(|d| d.join(".git").exists()).unwrap_or(dir);
+    root.to_string_lossy().into_owned()
+}
+
+// LSP language identifiers for the file types that differ from the lowercased name
+fn language_id(filetype: &str) -> String {
+    match filetype {
+        "C++" => "cpp".to_string(),
+        "C#" => "csharp".to_string(),
+        "React JSX" => "javascriptreact".to_string(),
+        "React TSX" => "typescriptreact".to_string(),
+        "Shell Script" | "Bash Script" | "Zsh Script" => "shellscript".to_string(),
+        "Sass" => "scss".to_string(),
+        "SQL Query" => "sql".to_string(),
+        "Batch File" => "bat".to_string(),
+        "Text File" => "plaintext".to_string(),
+        other => other.to_lowercase().replace(' ', ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::server::{read_message, write_message};
+    use super::*;
+    use crate::core::buffer::Buffer;
+    use std::io::BufReader;
+    use std::time::Instant;
+
+    // The test binary runs itself as the stub server, picking this test with the variable set
+    const STUB_ENV: &str = "QUICK_LSP_STUB";
+    const TEST_NAME: &str = "core::lsp::tests::test_client_against_stub_server";
+
+    fn pos(line: usize, column: usize) -> TextPosition {
+        TextPosition { line, column }
+    }
+
+    // Byte offset of an LSP position in `text`
+    fn offset(text: &str, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap() as usize;
+        let start: usize = text.split('\n').take(line).map(|l| l.len() + 1).sum();
+        let mut units = position["character"].as_u64().unwrap() as usize;
+        let rest = text[start..].split('\n').next().unwrap_or("");
+        let mut bytes = 0;
+        for c in rest.chars() {
+            if units == 0 {
+                break;
+            }
+            units = units.saturating_sub(c.len_utf16());
+            bytes += c.len_utf8();
+        }
+        start + bytes
+    }
+
+    // Every "bad" in the text as an LSP range
+    fn bad_words(text: &str) -> Vec<Value> {
+        let mut ranges = Vec::new();
+        for (line, content) in text.split('\n').enumerate() {
+            for (idx, _) in content.match_indices("bad") {
+                let character = content[..idx].encode_utf16().count();
+                ranges.push(json!({
+                    "start": { "line": line, "character": character },
+                    "end": { "line": line, "character": character + 3 },
+                }));
+            }
+        }
+        ranges
+    }
+
+    // Keeps the document, flags every "bad" as an error, hovers with the hovered line and
+    // renames by replacing each "bad"
+    fn run_stub_server() {
+        let mut input = BufReader::new(std::io::stdin());
+        let mut out = std::io::stdout();
+        let (mut uri, mut text) = (String::new(), String::new());
+        // Documents opened and not closed; opening one twice is flagged
+        let mut open = Vec::new();
+        while let Ok(Some(message)) = read_message(&mut input) {
+            let params = &message["params"];
+            let result = match message["method"].as_str().unwrap_or("") {
+                "initialize" => {
+                    if std::env::var(STUB_ENV).is_ok_and(|mode| mode == "slow") {
+                        std::thread::sleep(REQUEST_TIMEOUT + Duration::from_millis(500));
+                    }
+                    json!({ "capabilities": { "textDocumentSync": 2 } })
+                }
+                "textDocument/didClose" => {
+                    open.retain(|opened| *opened != params["textDocument"]["uri"]);
+                    continue;
+                }
+                "textDocument/didOpen" | "textDocument/didChange" => {
+                    if let Some(opened) = params["textDocument"]["text"].as_str() {
+                        uri = params["textDocument"]["uri"].as_str().unwrap().to_string();
+                        text = opened.to_string();
+                        if open.contains(&uri) {
+                            text.push_str("\nbad: opened twice");
+                        }
+                        open.push(uri.clone());
+                    }
+                    for change in params["contentChanges"].as_array().into_iter().flatten() {
+                        let (start, end) = (offset(&text, &change["range"]["start"]), offset(&text, &change["range"]["end"]));
+                        text.replace_range(start..end, change["text"].as_str().unwrap());
+                    }
+                    let diagnostics: Vec<Value> = bad_words(&text)
+                        .into_iter()
+                        .map(|range| json!({ "range": range, "severity": 1, "message": "bad word" }))
+                        .collect();
+                    let params = json!({ "uri": uri, "diagnostics": diagnostics });
+                    write_message(&mut out, &json!({ "method": "textDocument/publishDiagnostics", "params": params })).unwrap();
+                    continue;
+                }
+                "textDocument/hover" => {
+                    let line = text.split('\n').nth(params["position"]["line"].as_u64().unwrap() as usize);
+                    json!({ "contents": { "kind": "plaintext", "value": line } })
+                }
+                "textDocument/definition" => json!([{
+                    "uri": uri,
+                    "range": { "start": { "line": 0, "character": 3 }, "end": { "line": 0, "character": 7 } },
+                }]),
+                "textDocument/completion" => {
+                    json!({ "isIncomplete": false, "items": [{ "label": "alpha" }, { "label": "beta()", "insertText": "beta" }] })
+                }
+                "textDocument/rename" => {
+                    let edits: Vec<Value> = bad_words(&text)
+                        .into_iter()
+                        .map(|range| json!({ "range": range, "newText": params["newName"] }))
+                        .collect();
+                    json!({ "changes": { uri.clone(): edits } })
+                }
+                "shutdown" => Value::Null,
+                "exit" => break,
+                _ => continue,
+            };
+            write_message(&mut out, &json!({ "jsonrpc": "2.0", "id": message["id"], "result": result })).unwrap();
+        }
+    }
+
+    // The next reply or failure, polling as the front ends do
+    fn next_reply(lsp: &mut LspManager) -> Result<Reply, String> {
+        wait_for(lsp, |lsp| !lsp.replies.is_empty());
+        lsp.replies.remove(0)
+    }
+
+    fn wait_for(lsp: &mut LspManager, done: impl Fn(&LspManager) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(lsp) {
+            assert!(Instant::now() < deadline, "stub server did not answer");
+            lsp.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    // A manager whose Rust server is this test binary running the stub; "slow" makes it take
+    // longer over the handshake than a request may wait
+    fn stub_manager(mode: &str) -> LspManager {
+        let config = ServerConfig {
+            command: std::env::current_exe().unwrap().to_string_lossy().into_owned(),
+            args: [TEST_NAME, "--exact", "--quiet", "--nocapture", "--test-threads=1"].map(String::from).to_vec(),
+            env: HashMap::from([(STUB_ENV.to_string(), mode.to_string())]),
+        };
+        LspManager::new(LspSettings { servers: HashMap::from([("Rust".to_string(), config)]) })
+    }
+
+    #[test]
+    fn test_client_against_stub_server() {
+        if std::env::var_os(STUB_ENV).is_some() {
+            run_stub_server();
+            return;
+        }
+        let mut lsp = stub_manager("1");
+        let path = "/tmp/quick-lsp-stub/main.rs";
+        let mut editor = Editor::new(Buffer::from_string("fn main() {\n    let 😀 = bad;\n}".to_string()));
+
+        // Diagnostics come back in grapheme columns: the emoji is two UTF-16 units
+        lsp.sync(Some(path), Some("Rust"), &editor).unwrap();
+        wait_for(&mut lsp, |lsp| !lsp.diagnostics(Some(path)).is_empty());
+        let diagnostic = &lsp.diagnostics(Some(path))[0];
+        assert_eq!((diagnostic.start, diagnostic.end), (pos(1, 12), pos(1, 15)));
+        assert_eq!(diagnostic.severity, Severity::Error);
+
+        lsp.rename(path, pos(1, 13), "fine").unwrap();
+        let Reply::Rename { files, .. } = next_reply(&mut lsp).unwrap() else { panic!("not a rename") };
+        assert_eq!(files.len(), 1);
+        assert!(editor.apply_edits(&files[0].1));
+        assert_eq!(editor.buffer.lines[1], "    let 😀 = fine;");
+
+        // Only the changed range goes over; the server's copy still matches
+        lsp.sync(Some(path), Some("Rust"), &editor).unwrap();
+        wait_for(&mut lsp, |lsp| lsp.diagnostics(Some(path)).is_empty());
+        lsp.hover(path, pos(1, 4)).unwrap();
+        let text = Some("let 😀 = fine;".to_string());
+        assert_eq!(next_reply(&mut lsp), Ok(Reply::Hover { path: path.to_string(), pos: pos(1, 4), text }));
+
+        lsp.definition(path, pos(1, 8)).unwrap();
+        let locations = vec![Location { path: path.to_string(), pos: pos(0, 3) }];
+        assert_eq!(next_reply(&mut lsp), Ok(Reply::Definition { path: path.to_string(), pos: pos(1, 8), locations }));
+        lsp.completion(path, pos(1, 10)).unwrap();
+        let Reply::Completion { words, .. } = next_reply(&mut lsp).unwrap() else { panic!("not a completion") };
+        assert_eq!(words, vec!["alpha", "beta"]);
+
+        // A rename answered after the file changed would edit the wrong places
+        lsp.rename(path, pos(1, 13), "good").unwrap();
+        editor.buffer.lines[0].insert_str(0, "// ");
+        lsp.sync(Some(path), Some("Rust"), &editor).unwrap();
+        assert_eq!(next_reply(&mut lsp), Err("The file changed while renaming; try again".to_string()));
+    }
+
+    #[test]
+    fn test_requests_wait_out_a_slow_handshake_and_closed_files_are_closed() {
+        let mut lsp = stub_manager("slow");
+        let path = "/tmp/quick-lsp-stub/slow.rs";
+        let editor = Editor::new(Buffer::from_string("fn main() {}".to_string()));
+
+        // Asked before the server answered initialize; the wait starts once it is sent
+        lsp.sync(Some(path), Some("Rust"), &editor).unwrap();
+        lsp.hover(path, pos(0, 3)).unwrap();
+        let text = Some("fn main() {}".to_string());
+        assert_eq!(next_reply(&mut lsp), Ok(Reply::Hover { path: path.to_string(), pos: pos(0, 3), text }));
+
+        // Once no tab holds it, the server is told; opening it again is a fresh start
+        lsp.keep_open(&[]);
+        assert!(lsp.hover(path, pos(0, 3)).is_err());
+        lsp.sync(Some(path), Some("Rust"), &editor).unwrap();
+        lsp.hover(path, pos(0, 0)).unwrap();
+        assert!(matches!(next_reply(&mut lsp), Ok(Reply::Hover { .. })));
+        assert!(lsp.diagnostics(Some(path)).is_empty(), "{:?}", lsp.diagnostics(Some(path)));
+    }
+}
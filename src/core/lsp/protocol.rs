@@ -0,0 +1,276 @@
+// protocol module - converting between editor positions and LSP JSON
+use super::{Diagnostic, Location, Severity};
+use crate::core::graphemes::grapheme_len;
+use crate::core::selection::TextPosition;
+use serde_json::{json, Value};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Characters kept as they are in file URIs
+fn is_uri_safe(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte)
+}
+
+pub fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from("file://");
+    // Windows paths start with a drive letter: file:///C:/...
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        if is_uri_safe(byte) || byte == b':' {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match decoded {
+            Some(value) => {
+                bytes.push(value);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // "/C:/..." back to "C:/..."
+    let is_drive = path.len() > 2 && path.as_bytes()[2] == b':' && path.as_bytes()[1].is_ascii_alphabetic();
+    Some(if is_drive { path[1..].to_string() } else { path })
+}
+
+// LSP columns count UTF-16 code units; the editor counts graphemes
+pub fn to_utf16(line: &str, column: usize) -> usize {
+    line.graphemes(true).take(column).map(|g| g.encode_utf16().count()).sum()
+}
+
+// Grapheme column holding UTF-16 offset `units`, rounding into the grapheme it falls in
+pub fn from_utf16(line: &str, units: usize) -> usize {
+    let mut count = 0;
+    for (idx, grapheme) in line.graphemes(true).enumerate() {
+        if count >= units {
+            return idx;
+        }
+        count += grapheme.encode_utf16().count();
+        if count > units {
+            return idx;
+        }
+    }
+    grapheme_len(line)
+}
+
+pub fn position_json(lines: &[String], pos: TextPosition) -> Value {
+    let line = lines.get(pos.line).map(String::as_str).unwrap_or("");
+    json!({ "line": pos.line, "character": to_utf16(line, pos.column) })
+}
+
+pub fn position_from_json(lines: &[String], value: &Value) -> TextPosition {
+    let line = value["line"].as_u64().unwrap_or(0) as usize;
+    let units = value["character"].as_u64().unwrap_or(0) as usize;
+    let column = lines.get(line).map_or(0, |text| from_utf16(text, units));
+    TextPosition { line, column }
+}
+
+pub fn range_from_json(lines: &[String], value: &Value) -> (TextPosition, TextPosition) {
+    (position_from_json(lines, &value["start"]), position_from_json(lines, &value["end"]))
+}
+
+// Line and UTF-16 column of byte offset `byte` in `text`
+fn offset_position(text: &str, byte: usize) -> Value {
+    let before = &text[..byte];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    json!({ "line": line, "character": before[line_start..].encode_utf16().count() })
+}
+
+// The smallest single replacement turning `old` into `new`, as an incremental
+// textDocument/didChange entry; None when nothing changed
+pub fn content_change(old: &[String], new: &[String]) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    let (old, new) = (old.join("\n"), new.join("\n"));
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+
+    let mut prefix = old_bytes.iter().zip(new_bytes).take_while(|(a, b)| a == b).count();
+    while !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old_bytes
+        .iter()
+        .rev()
+        .zip(new_bytes.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    Some(json!({
+        "range": {
+            "start": offset_position(&old, prefix),
+            "end": offset_position(&old, old.len() - suffix),
+        },
+        "text": &new[prefix..new.len() - suffix],
+    }))
+}
+
+// How the server wants document changes: 1 = whole text, 2 = incremental (0 = none)
+pub fn sync_kind(capabilities: &Value) -> u64 {
+    let sync = &capabilities["textDocumentSync"];
+    sync.as_u64().or_else(|| sync["change"].as_u64()).unwrap_or(1)
+}
+
+pub fn parse_diagnostics(lines: &[String], params: &Value) -> Vec<Diagnostic> {
+    let Some(items) = params["diagnostics"].as_array() else {
+        return Vec::new();
+    };
+    let mut diagnostics: Vec<Diagnostic> = items
+        .iter()
+        .map(|item| {
+            let (start, end) = range_from_json(lines, &item["range"]);
+            let severity = match item["severity"].as_u64() {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            };
+            let message = match item["source"].as_str() {
+                Some(source) => format!("{}: {}", source, item["message"].as_str().unwrap_or("")),
+                None => item["message"].as_str().unwrap_or("").to_string(),
+            };
+            Diagnostic { start, end, severity, message }
+        })
+        .collect();
+    diagnostics.sort_by_key(|d| (d.start.line, d.start.column));
+    diagnostics
+}
+
+// Hover contents come as a string, markup, a marked string or a list of those
+pub fn hover_text(result: &Value) -> Option<String> {
+    fn text(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().filter_map(text).collect();
+                (!parts.is_empty()).then(|| parts.join("\n"))
+            }
+            Value::Object(map) => map.get("value").and_then(Value::as_str).map(String::from),
+            _ => None,
+        }
+    }
+    text(&result["contents"]).map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+// Definition results: a location, a list of locations, or location links. Positions are
+// converted with `lines_of`, which reads the lines of the file at a path.
+pub fn parse_locations(result: &Value, lines_of: impl Fn(&str) -> Vec<String>) -> Vec<Location> {
+    let items = match result {
+        Value::Array(items) => items.clone(),
+        Value::Object(_) => vec![result.clone()],
+        _ => Vec::new(),
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let uri = item["uri"].as_str().or_else(|| item["targetUri"].as_str())?;
+            let range = if item["uri"].is_string() { &item["range"] } else { &item["targetSelectionRange"] };
+            let path = uri_to_path(uri)?;
+            let pos = position_from_json(&lines_of(&path), &range["start"]);
+            Some(Location { path, pos })
+        })
+        .collect()
+}
+
+// Completion results: a list of items or a completion list. Snippet-style insert texts
+// fall back to the plain label.
+pub fn completion_words(result: &Value) -> Vec<String> {
+    let items = result.as_array().or_else(|| result["items"].as_array());
+    items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let plain = item["insertTextFormat"].as_u64() != Some(2);
+            let insert = item["textEdit"]["newText"].as_str().or_else(|| item["insertText"].as_str());
+            insert
+                .filter(|_| plain)
+                .or_else(|| item["filterText"].as_str())
+                .or_else(|| item["label"].as_str())
+                .map(|text| text.trim().to_string())
+        })
+        .filter(|text| !text.is_empty() && !text.contains('\n'))
+        .collect()
+}
+
+// The text edits of a workspace edit, per file URI, from `changes` and `documentChanges`
+pub fn workspace_edits(result: &Value) -> Vec<(String, Vec<Value>)> {
+    let mut files: Vec<(String, Vec<Value>)> = Vec::new();
+    if let Some(changes) = result["changes"].as_object() {
+        for (uri, edits) in changes {
+            files.push((uri.clone(), edits.as_array().cloned().unwrap_or_default()));
+        }
+    }
+    // File creations and renames have no `edits` and are skipped
+    for change in result["documentChanges"].as_array().into_iter().flatten() {
+        if let (Some(uri), Some(edits)) = (change["textDocument"]["uri"].as_str(), change["edits"].as_array()) {
+            files.push((uri.to_string(), edits.clone()));
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_utf16_columns_and_uris_round_trip() {
+        // The emoji is one grapheme but two UTF-16 units
+        let line = "a😀e\u{301}b";
+        assert_eq!(to_utf16(line, 2), 3);
+        assert_eq!(from_utf16(line, 3), 2);
+        assert_eq!(from_utf16(line, 2), 1);
+        assert_eq!(from_utf16(line, 99), 4);
+
+        let uri = path_to_uri("/tmp/my file#1.rs");
+        assert_eq!(uri, "file:///tmp/my%20file%231.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some("/tmp/my file#1.rs"));
+        assert_eq!(uri_to_path("file:///C:/src/a.rs").as_deref(), Some("C:/src/a.rs"));
+    }
+
+    #[test]
+    fn test_content_change_is_the_smallest_replacement() {
+        let old = lines(&["fn main() {", "    a😀b", "}"]);
+        let new = lines(&["fn main() {", "    a😀xyb", "}"]);
+        let change = content_change(&old, &new).unwrap();
+        assert_eq!(change["range"]["start"], json!({ "line": 1, "character": 7 }));
+        assert_eq!(change["range"]["end"], json!({ "line": 1, "character": 7 }));
+        assert_eq!(change["text"], "xy");
+
+        let joined = content_change(&new, &lines(&["fn main() {}"])).unwrap();
+        assert_eq!(joined["range"]["start"], json!({ "line": 0, "character": 11 }));
+        assert_eq!(joined["range"]["end"], json!({ "line": 2, "character": 0 }));
+        assert_eq!(joined["text"], "");
+        assert!(content_change(&old, &old).is_none());
+    }
+}
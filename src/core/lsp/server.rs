@@ -0,0 +1,251 @@
+// server module - a language server process spoken to over stdin/stdout with
+// Content-Length framed JSON-RPC messages
+use super::ServerConfig;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+// Longest wait for the initialize reply; servers index the project first
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+// The first request is always initialize
+const INITIALIZE_ID: u64 = 1;
+
+pub fn write_message(out: &mut impl Write, message: &Value) -> Result<(), Error> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
+
+// Next message, None at the end of the stream. Stray lines before the headers (some servers
+// log to stdout) are skipped.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, Error> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+pub struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    // Messages from the reader thread
+    incoming: Receiver<Value>,
+    // Notifications not yet drained
+    queued: Vec<Value>,
+    // Requests not yet answered: the method, how long to wait, and when to stop waiting,
+    // counted from when the request is written rather than held back
+    pending: HashMap<u64, (String, Duration, Option<Instant>)>,
+    // Answers to requests, by id, for take_replies(); a request that timed out gets an error
+    replies: Vec<(u64, Result<Value, String>)>,
+    // Messages held back until the initialize handshake completes
+    outbox: Vec<Value>,
+    next_id: u64,
+    // Known once the server answered initialize
+    pub capabilities: Option<Value>,
+    // Why the server can't be used, when the handshake failed
+    pub failure: Option<String>,
+}
+
+impl Server {
+    // Spawn the server and start the initialize handshake for the project at `root`. Nothing
+    // waits for the reply: messages sent meanwhile are held back until it comes, in drain().
+    pub fn start(config: &ServerConfig, root: &str) -> Result<Self, Error> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // Server logs would scribble over the terminal UI
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| Error::other("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| Error::other("no stdout"))?;
+
+        let (sender, incoming) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut server = Self {
+            child,
+            stdin,
+            incoming,
+            queued: Vec::new(),
+            pending: HashMap::new(),
+            replies: Vec::new(),
+            outbox: Vec::new(),
+            next_id: INITIALIZE_ID,
+            capabilities: None,
+            failure: None,
+        };
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": super::protocol::path_to_uri(root),
+            "workspaceFolders": [{ "uri": super::protocol::path_to_uri(root), "name": root }],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "didSave": false },
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "definition": { "linkSupport": true },
+                    "rename": { "prepareSupport": false },
+                    "publishDiagnostics": { "relatedInformation": false },
+                },
+                "general": { "positionEncodings": ["utf-16"] },
+            },
+        });
+        let message = json!({ "jsonrpc": "2.0", "id": INITIALIZE_ID, "method": "initialize", "params": params });
+        write_message(&mut server.stdin, &message)?;
+        server.next_id += 1;
+        server.pending.insert(INITIALIZE_ID, ("initialize".to_string(), INITIALIZE_TIMEOUT, Some(Instant::now() + INITIALIZE_TIMEOUT)));
+        Ok(server)
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), Error> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    // Send a request; its result turns up in take_replies() under the returned id, or an
+    // error once `timeout` passes without one
+    pub fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<u64, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send(message).map_err(|e| format!("Language server stopped: {}", e))?;
+        let deadline = self.capabilities.is_some().then(|| Instant::now() + timeout);
+        self.pending.insert(id, (method.to_string(), timeout, deadline));
+        Ok(id)
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), Error> {
+        if self.capabilities.is_none() {
+            self.outbox.push(message);
+            return Ok(());
+        }
+        write_message(&mut self.stdin, &message)
+    }
+
+    // Notifications received so far, without waiting. Replies are set aside for
+    // take_replies(), and requests waiting too long are given up on.
+    pub fn drain(&mut self) -> Vec<Value> {
+        while let Ok(message) = self.incoming.try_recv() {
+            self.handle_incoming(message);
+        }
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, _, deadline))| deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            if let Some((method, _, _)) = self.pending.remove(&id) {
+                self.answered(id, Err(format!("No reply to {} from the language server", method)));
+            }
+        }
+        std::mem::take(&mut self.queued)
+    }
+
+    pub fn take_replies(&mut self) -> Vec<(u64, Result<Value, String>)> {
+        std::mem::take(&mut self.replies)
+    }
+
+    // Requests from the server (progress tokens, configuration) get an empty answer so it
+    // does not wait on us; notifications are kept for drain()
+    fn handle_incoming(&mut self, message: Value) {
+        if message.get("method").is_none() {
+            let Some(id) = message["id"].as_u64().filter(|id| self.pending.remove(id).is_some()) else {
+                return;
+            };
+            let result = match message.get("error") {
+                Some(error) => Err(error["message"].as_str().unwrap_or("Request failed").to_string()),
+                None => Ok(message["result"].clone()),
+            };
+            return self.answered(id, result);
+        }
+        match message.get("id") {
+            Some(id) => {
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": null });
+                let _ = write_message(&mut self.stdin, &reply);
+            }
+            None => self.queued.push(message),
+        }
+    }
+
+    // The handshake completes with the initialize reply, letting held back messages go; the
+    // requests among them start waiting for their replies only then
+    fn answered(&mut self, id: u64, result: Result<Value, String>) {
+        if id != INITIALIZE_ID {
+            return self.replies.push((id, result));
+        }
+        match result {
+            Ok(reply) => {
+                self.capabilities = Some(reply["capabilities"].clone());
+                let initialized = json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} });
+                let sent = std::iter::once(initialized)
+                    .chain(std::mem::take(&mut self.outbox))
+                    .try_for_each(|message| write_message(&mut self.stdin, &message));
+                if let Err(e) = sent {
+                    self.failure = Some(e.to_string());
+                }
+                let now = Instant::now();
+                for (_, timeout, deadline) in self.pending.values_mut() {
+                    deadline.get_or_insert(now + *timeout);
+                }
+            }
+            Err(e) => self.failure = Some(e),
+        }
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Server {
+    // Ask politely, then make sure the process is gone
+    fn drop(&mut self) {
+        if self.capabilities.is_some() && self.is_running() {
+            let deadline = Instant::now() + Duration::from_millis(300);
+            if let Ok(id) = self.request("shutdown", Value::Null, Duration::from_millis(300)) {
+                while self.pending.contains_key(&id) && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                    self.drain();
+                }
+            }
+            let _ = self.notify("exit", Value::Null);
+            let deadline = Instant::now() + Duration::from_millis(300);
+            while self.is_running() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
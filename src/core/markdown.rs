@@ -0,0 +1,518 @@
+// markdown module - a small Markdown reader for the preview: headings, paragraphs with
+// emphasis, code, links and images, lists, quotes, fenced code, tables and rules. Every
+// block keeps the line it starts on so the preview can follow the editor's scrolling.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub line: usize,
+    pub kind: BlockKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    Heading(u8, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    // One item per block; nesting is the depth
+    ListItem { depth: usize, marker: ListMarker, checked: Option<bool>, text: Vec<Inline> },
+    Quote(Vec<Inline>),
+    Code { language: String, code: String },
+    Table { align: Vec<Align>, header: Vec<Vec<Inline>>, rows: Vec<Vec<Vec<Inline>>> },
+    Rule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMarker {
+    Bullet,
+    Number(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text { text: String, style: Style },
+    Image { alt: String, src: String, link: Option<String> },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub strike: bool,
+    pub code: bool,
+    pub link: Option<String>,
+}
+
+pub fn parse(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    // Text of the paragraph (or list item, or quote) still being read, and where it began
+    let mut open: Option<(usize, String)> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        let indent = indent_of(line);
+
+        if trimmed.is_empty() {
+            close(&mut blocks, &mut open);
+            i += 1;
+            continue;
+        }
+
+        // Setext headings underline the paragraph above them
+        if let Some((start, text)) = open.as_ref().filter(|_| is_paragraph(&blocks)) {
+            let level = if is_underline(trimmed, '=') { 1 } else if is_underline(trimmed, '-') { 2 } else { 0 };
+            if level > 0 && !text.contains('\n') {
+                blocks.pop();
+                blocks.push(Block { line: *start, kind: BlockKind::Heading(level, inlines(text.trim())) });
+                open = None;
+                i += 1;
+                continue;
+            }
+        }
+
+        if let Some(fence) = fence_of(trimmed) {
+            close(&mut blocks, &mut open);
+            let language = trimmed[fence.len()..].split_whitespace().next().unwrap_or("").to_string();
+            let mut code = Vec::new();
+            let mut end = i + 1;
+            while end < lines.len() && !lines[end].trim_start().starts_with(fence.as_str()) {
+                code.push(strip_indent(lines[end], indent));
+                end += 1;
+            }
+            blocks.push(Block { line: i, kind: BlockKind::Code { language, code: code.join("\n") } });
+            i = end + 1;
+            continue;
+        }
+
+        // Indented code, unless it continues a paragraph or a list item
+        if indent >= 4 && open.is_none() && !matches!(blocks.last(), Some(Block { kind: BlockKind::ListItem { .. }, .. })) {
+            let mut code = Vec::new();
+            let mut end = i;
+            while end < lines.len() && (indent_of(lines[end]) >= 4 || lines[end].trim().is_empty()) {
+                code.push(strip_indent(lines[end], 4));
+                end += 1;
+            }
+            while code.last().is_some_and(|line| line.trim().is_empty()) {
+                code.pop();
+            }
+            blocks.push(Block { line: i, kind: BlockKind::Code { language: String::new(), code: code.join("\n") } });
+            i = end;
+            continue;
+        }
+
+        if let Some((level, text)) = heading_of(trimmed) {
+            close(&mut blocks, &mut open);
+            blocks.push(Block { line: i, kind: BlockKind::Heading(level, inlines(text)) });
+            i += 1;
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            close(&mut blocks, &mut open);
+            blocks.push(Block { line: i, kind: BlockKind::Rule });
+            i += 1;
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            let rest = rest.strip_prefix(' ').unwrap_or(rest);
+            if !matches!(blocks.last(), Some(Block { kind: BlockKind::Quote(_), .. })) || open.is_none() {
+                close(&mut blocks, &mut open);
+                blocks.push(Block { line: i, kind: BlockKind::Quote(Vec::new()) });
+            }
+            continue_text(&mut open, i, rest);
+            i += 1;
+            continue;
+        }
+
+        if let Some((marker, rest)) = list_marker_of(trimmed) {
+            close(&mut blocks, &mut open);
+            let (checked, rest) = match rest.get(..4) {
+                Some("[ ] ") => (Some(false), &rest[4..]),
+                Some("[x] " | "[X] ") => (Some(true), &rest[4..]),
+                _ => (None, rest),
+            };
+            let depth = indent / 2;
+            blocks.push(Block { line: i, kind: BlockKind::ListItem { depth, marker, checked, text: Vec::new() } });
+            continue_text(&mut open, i, rest);
+            i += 1;
+            continue;
+        }
+
+        if trimmed.contains('|') && lines.get(i + 1).is_some_and(|next| is_delimiter_row(next)) {
+            close(&mut blocks, &mut open);
+            let header: Vec<Vec<Inline>> = cells_of(trimmed).iter().map(|cell| inlines(cell)).collect();
+            let align = cells_of(lines[i + 1]).iter().map(|cell| align_of(cell)).collect();
+            let mut rows = Vec::new();
+            let mut end = i + 2;
+            while end < lines.len() && lines[end].contains('|') && !lines[end].trim().is_empty() {
+                rows.push(cells_of(lines[end]).iter().map(|cell| inlines(cell)).collect());
+                end += 1;
+            }
+            blocks.push(Block { line: i, kind: BlockKind::Table { align, header, rows } });
+            i = end;
+            continue;
+        }
+
+        // Anything else carries on the open paragraph, list item or quote, or starts a paragraph
+        if open.is_none() {
+            blocks.push(Block { line: i, kind: BlockKind::Paragraph(Vec::new()) });
+        }
+        continue_text(&mut open, i, line.trim_start());
+        i += 1;
+    }
+    close(&mut blocks, &mut open);
+    blocks
+}
+
+// Add a line to the open text; a line ending in two spaces or a backslash breaks it
+fn continue_text(open: &mut Option<(usize, String)>, line: usize, text: &str) {
+    let (_, buffer) = open.get_or_insert_with(|| (line, String::new()));
+    if !buffer.is_empty() {
+        if buffer.ends_with("  ") || buffer.ends_with('\\') {
+            let kept = buffer.trim_end().trim_end_matches('\\').len();
+            buffer.truncate(kept);
+            buffer.push('\n');
+        } else {
+            buffer.push(' ');
+        }
+    }
+    buffer.push_str(text);
+}
+
+// Parse the open text into the block it belongs to, the last one pushed
+fn close(blocks: &mut [Block], open: &mut Option<(usize, String)>) {
+    let Some((_, text)) = open.take() else { return };
+    let text = inlines(text.trim_end());
+    if let Some(BlockKind::Paragraph(spans) | BlockKind::Quote(spans) | BlockKind::ListItem { text: spans, .. }) =
+        blocks.last_mut().map(|block| &mut block.kind)
+    {
+        *spans = text;
+    }
+}
+
+fn is_paragraph(blocks: &[Block]) -> bool {
+    matches!(blocks.last(), Some(Block { kind: BlockKind::Paragraph(_), .. }))
+}
+
+fn indent_of(line: &str) -> usize {
+    line.chars().take_while(|c| *c == ' ' || *c == '\t').map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+// The line without up to `width` columns of its indentation
+fn strip_indent(line: &str, width: usize) -> &str {
+    let mut columns = 0;
+    for (idx, c) in line.char_indices() {
+        if columns >= width || (c != ' ' && c != '\t') {
+            return &line[idx..];
+        }
+        columns += if c == '\t' { 4 } else { 1 };
+    }
+    ""
+}
+
+fn fence_of(trimmed: &str) -> Option<String> {
+    ['`', '~'].into_iter().find_map(|mark| {
+        let count = trimmed.chars().take_while(|c| *c == mark).count();
+        (count >= 3).then(|| mark.to_string().repeat(count))
+    })
+}
+
+fn heading_of(trimmed: &str) -> Option<(u8, &str)> {
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    // Closing hashes are decoration
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with(' ') => stripped.trim_end(),
+        _ => text,
+    };
+    Some((level as u8, text))
+}
+
+fn is_underline(trimmed: &str, mark: char) -> bool {
+    !trimmed.is_empty() && trimmed.chars().all(|c| c == mark)
+}
+
+fn is_rule(trimmed: &str) -> bool {
+    ['-', '*', '_'].into_iter().any(|mark| {
+        trimmed.chars().filter(|c| *c == mark).count() >= 3 && trimmed.chars().all(|c| c == mark || c == ' ')
+    })
+}
+
+fn list_marker_of(trimmed: &str) -> Option<(ListMarker, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(bullet) {
+            return Some((ListMarker::Bullet, rest.trim_start()));
+        }
+    }
+    if matches!(trimmed, "-" | "*" | "+") {
+        return Some((ListMarker::Bullet, ""));
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let rest = &trimmed[digits..];
+    if (1..=9).contains(&digits) && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        return Some((ListMarker::Number(trimmed[..digits].parse().ok()?), rest[2..].trim_start()));
+    }
+    None
+}
+
+fn cells_of(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    // A `\|` is a pipe inside a cell
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+fn is_delimiter_row(line: &str) -> bool {
+    let cells = cells_of(line);
+    line.contains('-')
+        && cells.iter().all(|cell| {
+            let cell = cell.trim_start_matches(':').trim_end_matches(':');
+            !cell.is_empty() && cell.chars().all(|c| c == '-')
+        })
+}
+
+fn align_of(cell: &str) -> Align {
+    match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Align::Center,
+        (false, true) => Align::Right,
+        _ => Align::Left,
+    }
+}
+
+pub fn inlines(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    read_inlines(&chars, Style::default(), &mut spans);
+    spans
+}
+
+fn read_inlines(chars: &[char], base: Style, spans: &mut Vec<Inline>) {
+    let mut style = base;
+    let mut text = String::new();
+    let mut i = 0;
+
+    // Flush the text read so far in the style it was read in
+    fn flush(text: &mut String, style: &Style, spans: &mut Vec<Inline>) {
+        if !text.is_empty() {
+            spans.push(Inline::Text { text: std::mem::take(text), style: style.clone() });
+        }
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c == '\\' && next.is_some_and(|n| n.is_ascii_punctuation()) {
+            text.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+
+        if c == '`' {
+            let run = chars[i..].iter().take_while(|c| **c == '`').count();
+            if let Some(end) = find_run(chars, i + run, '`', run) {
+                flush(&mut text, &style, spans);
+                let code: String = chars[i + run..end].iter().collect();
+                let code_style = Style { code: true, ..style.clone() };
+                spans.push(Inline::Text { text: code.trim().to_string(), style: code_style });
+                i = end + run;
+                continue;
+            }
+            text.extend(&chars[i..i + run]);
+            i += run;
+            continue;
+        }
+
+        // ![alt](src) and [text](url)
+        let image = c == '!' && next == Some('[');
+        if c == '[' || image {
+            let open = if image { i + 1 } else { i };
+            if let Some((label_end, target, after)) = link_at(chars, open) {
+                flush(&mut text, &style, spans);
+                let label = &chars[open + 1..label_end];
+                if image {
+                    let alt = label.iter().collect();
+                    spans.push(Inline::Image { alt, src: target, link: style.link.clone() });
+                } else {
+                    read_inlines(label, Style { link: Some(target), ..style.clone() }, spans);
+                }
+                i = after;
+                continue;
+            }
+        }
+
+        // <https://...>
+        if c == '<' {
+            if let Some(end) = chars[i..].iter().position(|c| *c == '>') {
+                let url: String = chars[i + 1..i + end].iter().collect();
+                if url.contains("://") && !url.contains(' ') {
+                    flush(&mut text, &style, spans);
+                    spans.push(Inline::Text { text: url.clone(), style: Style { link: Some(url), ..style.clone() } });
+                    i += end + 1;
+                    continue;
+                }
+            }
+        }
+
+        // Emphasis: ** and __ bold, * and _ italic, ~~ strikethrough. An opening mark needs
+        // text right after it and a closing mark later on; a closing one text right before it.
+        if c == '*' || c == '_' || (c == '~' && next == Some('~')) {
+            let double = next == Some(c);
+            let width = if double { 2 } else { 1 };
+            let active = match (c, double) {
+                ('~', _) => style.strike,
+                (_, true) => style.bold,
+                _ => style.italic,
+            };
+            let before = i.checked_sub(1).map(|b| chars[b]);
+            let after = chars.get(i + width).copied();
+            let intraword = c == '_' && before.is_some_and(char::is_alphanumeric) && after.is_some_and(char::is_alphanumeric);
+            let toggles = !intraword
+                && if active {
+                    before.is_some_and(|b| !b.is_whitespace())
+                } else {
+                    after.is_some_and(|a| !a.is_whitespace()) && find_run(chars, i + width, c, width).is_some()
+                };
+            if toggles {
+                flush(&mut text, &style, spans);
+                match (c, double) {
+                    ('~', _) => style.strike = !style.strike,
+                    (_, true) => style.bold = !style.bold,
+                    _ => style.italic = !style.italic,
+                }
+                i += width;
+                continue;
+            }
+        }
+
+        text.push(c);
+        i += 1;
+    }
+    flush(&mut text, &style, spans);
+}
+
+// Index of the next run of exactly `len` `mark`s from `from`
+fn find_run(chars: &[char], from: usize, mark: char, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == mark {
+            let run = chars[i..].iter().take_while(|c| **c == mark).count();
+            if run == len {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+// For a `[` at `open`: where its label ends, the target of the `(...)` after it without
+// any "title", and the index after the `)`
+fn link_at(chars: &[char], open: usize) -> Option<(usize, String, usize)> {
+    let mut depth = 0;
+    let mut label_end = None;
+    for (j, c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    label_end = Some(j);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let label_end = label_end?;
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+    let close = label_end + 2 + chars[label_end + 2..].iter().position(|c| *c == ')')?;
+    let inside: String = chars[label_end + 2..close].iter().collect();
+    let target = inside.split_whitespace().next().unwrap_or("").trim_start_matches('<').trim_end_matches('>');
+    Some((label_end, target.to_string(), close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(spans: &[Inline]) -> Vec<(String, bool, bool, bool, Option<String>)> {
+        spans
+            .iter()
+            .map(|span| match span {
+                Inline::Text { text, style } => (text.clone(), style.bold, style.italic, style.code, style.link.clone()),
+                Inline::Image { alt, src, .. } => (format!("{}={}", alt, src), false, false, false, None),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blocks_and_their_lines() {
+        let blocks = parse(
+            "# Title #\n\nSome *text*\ngoes on.\n\n- [x] done\n  1. nested\n\n```rust\nfn main() {}\n```\n| a | b |\n|:--|--:|\n| 1 | 2 |\n---\nSub\n===\n> quoted\n> more",
+        );
+        let lines: Vec<usize> = blocks.iter().map(|block| block.line).collect();
+        assert_eq!(lines, vec![0, 2, 5, 6, 8, 11, 14, 15, 17]);
+        assert_eq!(blocks[0].kind, BlockKind::Heading(1, inlines("Title")));
+        assert_eq!(blocks[1].kind, BlockKind::Paragraph(inlines("Some *text* goes on.")));
+        assert!(matches!(blocks[2].kind, BlockKind::ListItem { depth: 0, checked: Some(true), .. }));
+        assert!(matches!(blocks[3].kind, BlockKind::ListItem { depth: 1, marker: ListMarker::Number(1), .. }));
+        assert_eq!(blocks[4].kind, BlockKind::Code { language: "rust".into(), code: "fn main() {}".into() });
+        let BlockKind::Table { align, rows, .. } = &blocks[5].kind else { panic!() };
+        assert_eq!((align.clone(), rows.len()), (vec![Align::Left, Align::Right], 1));
+        assert_eq!(blocks[6].kind, BlockKind::Rule);
+        assert_eq!(blocks[7].kind, BlockKind::Heading(1, inlines("Sub")));
+        assert_eq!(blocks[8].kind, BlockKind::Quote(inlines("quoted more")));
+    }
+
+    #[test]
+    fn test_inline_styles_links_and_images() {
+        let spans = inlines("a **b** _c_ `d*` [e](http://x \"t\") ![f](img/g.png) 2 * 3 snake_case_name");
+        assert_eq!(
+            text(&spans),
+            vec![
+                ("a ".into(), false, false, false, None),
+                ("b".into(), true, false, false, None),
+                (" ".into(), false, false, false, None),
+                ("c".into(), false, true, false, None),
+                (" ".into(), false, false, false, None),
+                ("d*".into(), false, false, true, None),
+                (" ".into(), false, false, false, None),
+                ("e".into(), false, false, false, Some("http://x".into())),
+                (" ".into(), false, false, false, None),
+                ("f=img/g.png".into(), false, false, false, None),
+                (" 2 * 3 snake_case_name".into(), false, false, false, None),
+            ]
+        );
+    }
+}
@@ -2,8 +2,27 @@ pub mod actions;
 pub mod shortcuts;
 pub mod selection;
 pub mod edit_history;
+pub mod editor;
 pub mod tabs;
 pub mod buffer;
 pub mod updater; 
 pub mod syntax;
-pub mod graphemes;
\ No newline at end of file
+pub mod graphemes;
+pub mod wrap;
+pub mod goto;
+pub mod brackets;
+pub mod autopair;
+pub mod folding;
+pub mod bookmarks;
+pub mod completion;
+pub mod snippets;
+pub mod lsp;
+pub mod diff;
+pub mod formatter;
+pub mod save_hooks;
+pub mod data;
+pub mod markdown;
+pub mod panes;
+pub mod compare;
+pub mod git;
+pub mod settings;
@@ -0,0 +1,142 @@
+// panes module - a screen split in two panes, each with its own tab, carets and scroll
+// position. Only the focused pane is edited; the other keeps where it was, and when both
+// show the same tab they share the document, so edits show up in both.
+use crate::core::edit_history::line_after_edit;
+use crate::core::editor::Editor;
+use crate::core::selection::{BlockSelection, Selection, TextPosition};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitDirection {
+    // Side by side
+    Vertical,
+    // One above the other
+    Horizontal,
+}
+
+// Where a pane is in its tab: everything about a view of a document that isn't the document
+#[derive(Clone, Debug)]
+pub struct PaneView {
+    pub tab_id: u64,
+    pub cursor: TextPosition,
+    pub selection: Option<Selection>,
+    pub secondary: Vec<Selection>,
+    pub block: Option<BlockSelection>,
+    // (first line shown, first column shown)
+    pub scroll: (usize, usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Split {
+    pub direction: SplitDirection,
+    // The pane without focus
+    pub other: PaneView,
+    // Whether the focused pane is the right (or bottom) one
+    pub focus_second: bool,
+}
+
+impl PaneView {
+    pub fn new(tab_id: u64, editor: &Editor, scroll: (usize, usize)) -> Self {
+        Self {
+            tab_id,
+            cursor: editor.cursor,
+            selection: editor.selection.clone(),
+            secondary: editor.secondary.clone(),
+            block: editor.block,
+            scroll,
+        }
+    }
+
+    // Every caret as a selection, primary first, as Editor::carets()
+    pub fn carets(&self) -> Vec<Selection> {
+        let anchor = self.selection.as_ref().map_or(self.cursor, |s| s.anchor);
+        let mut carets = vec![Selection { anchor, cursor: self.cursor }];
+        carets.extend(self.secondary.iter().cloned());
+        carets
+    }
+
+    // What this pane's carets select in `editor`, and where they are drawn, primary first
+    pub fn selection_ranges(&self, editor: &Editor) -> Vec<(TextPosition, TextPosition)> {
+        editor.ranges_of(&self.clamped_carets(editor), self.fitting_block(editor))
+    }
+
+    pub fn caret_positions(&self, editor: &Editor) -> Vec<TextPosition> {
+        editor.positions_of(&self.clamped_carets(editor), self.fitting_block(editor))
+    }
+
+    // A block selection is dropped rather than cut down when the document no longer holds it
+    fn fitting_block(&self, editor: &Editor) -> Option<BlockSelection> {
+        self.block.filter(|block| fits(editor, block.anchor) && fits(editor, block.cursor))
+    }
+
+    fn clamped_carets(&self, editor: &Editor) -> Vec<Selection> {
+        let clamp = |caret: Selection| Selection { anchor: editor.clamp(caret.anchor), cursor: editor.clamp(caret.cursor) };
+        self.carets().into_iter().map(clamp).collect()
+    }
+
+    // Follow an edit to the document made in the other pane, as bookmarks do
+    pub fn on_edit(&mut self, first: usize, old_last: usize, new_last: usize) {
+        let shift = |pos: &mut TextPosition| pos.line = line_after_edit(pos.line, first, old_last, new_last);
+        shift(&mut self.cursor);
+        let selections = self.selection.iter_mut().chain(&mut self.secondary);
+        for selection in selections {
+            shift(&mut selection.anchor);
+            shift(&mut selection.cursor);
+        }
+        if let Some(block) = &mut self.block {
+            shift(&mut block.anchor);
+            shift(&mut block.cursor);
+        }
+        self.scroll.0 = line_after_edit(self.scroll.0, first, old_last, new_last);
+    }
+
+    // Give `editor` this pane's carets as it takes focus, and return its scroll position.
+    // What is put in the editor is kept inside the document, which may have shrunk meanwhile.
+    pub fn focus(self, editor: &mut Editor) -> (usize, usize) {
+        let block = self.fitting_block(editor);
+        let caret_fits = |s: &Selection| fits(editor, s.anchor) && fits(editor, s.cursor);
+        let selection = self.selection.filter(caret_fits);
+        let secondary = if self.secondary.iter().all(caret_fits) { self.secondary } else { Vec::new() };
+        let scroll = (self.scroll.0.min(editor.buffer.lines.len().saturating_sub(1)), self.scroll.1);
+        editor.cursor = editor.clamp(self.cursor);
+        (editor.selection, editor.secondary, editor.block) = (selection, secondary, block);
+        scroll
+    }
+}
+
+fn fits(editor: &Editor, pos: TextPosition) -> bool {
+    editor.clamp(pos) == pos
+}
+
+impl Split {
+    // Split the screen with the focused pane's view copied into the new one, which gets focus
+    pub fn new(direction: SplitDirection, current: PaneView) -> Self {
+        Self { direction, other: current, focus_second: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer::Buffer;
+
+    #[test]
+    fn test_other_pane_follows_edits_and_takes_focus_inside_the_document() {
+        let mut editor = Editor::new(Buffer::from_string("one\ntwo\nthree\nfour".to_string()));
+        let mut other = PaneView::new(7, &editor, (2, 0));
+        other.cursor = TextPosition { line: 3, column: 2 };
+
+        // Enter on the first line, in the focused pane
+        editor.cursor = TextPosition { line: 0, column: 3 };
+        editor.insert_text("\n");
+        for (first, old_last, new_last) in editor.take_line_edits() {
+            other.on_edit(first, old_last, new_last);
+        }
+        assert_eq!((other.cursor, other.scroll), (TextPosition { line: 4, column: 2 }, (3, 0)));
+        assert!(editor.take_line_edits().is_empty());
+
+        // The focused pane deleted the lines the other one was on
+        editor.buffer = Buffer { lines: vec!["one".to_string()] };
+        let scroll = other.focus(&mut editor);
+        assert_eq!((editor.cursor, scroll), (TextPosition { line: 0, column: 2 }, (0, 0)));
+    }
+}
@@ -0,0 +1,249 @@
+// save_hooks module - what happens to a document on save: cleanups and the formatter
+// before it is written, and a shell command after (tests, a service reload...)
+use crate::core::editor::Editor;
+use crate::core::formatter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+// The "save" block of settings.json. The top-level options apply to every file; the
+// "filetypes" entries (names as shown in the footer) override them, e.g.
+//   "save": { "trim_trailing_whitespace": true, "final_newline": true,
+//     "filetypes": { "Rust": { "format": true, "after": "cargo check" },
+//                    "Markdown": { "trim_trailing_whitespace": false } } }
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SaveSettings {
+    #[serde(flatten)]
+    pub all: HookSettings,
+    pub filetypes: HashMap<String, HookSettings>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HookSettings {
+    pub trim_trailing_whitespace: Option<bool>,
+    pub final_newline: Option<bool>,
+    // "lf" or "crlf"; unset keeps the lines as they are, joined with \n
+    pub line_endings: Option<LineEnding>,
+    // Run the file type's formatter from the "format" block
+    pub format: Option<bool>,
+    pub after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+// The hooks that apply to one file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaveHooks {
+    pub trim_trailing_whitespace: bool,
+    pub final_newline: bool,
+    pub line_endings: Option<LineEnding>,
+    pub format: bool,
+    pub after: Option<String>,
+}
+
+impl SaveSettings {
+    pub fn for_filetype(&self, filetype: Option<&str>) -> SaveHooks {
+        let name = filetype.unwrap_or("").trim();
+        let own = self
+            .filetypes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, hooks)| hooks.clone())
+            .unwrap_or_default();
+        let all = &self.all;
+        SaveHooks {
+            trim_trailing_whitespace: own.trim_trailing_whitespace.or(all.trim_trailing_whitespace).unwrap_or(false),
+            final_newline: own.final_newline.or(all.final_newline).unwrap_or(false),
+            line_endings: own.line_endings.or(all.line_endings),
+            format: own.format.or(all.format).unwrap_or(false),
+            // An empty command turns off the one set for every file
+            after: own.after.or(all.after.clone()).filter(|command| !command.trim().is_empty()),
+        }
+    }
+}
+
+impl SaveHooks {
+    // Edit the document before it is written, as one undo step each, so what is on screen
+    // is what gets saved. Returns whether the text changed, and the formatter's error: a
+    // formatter that fails, or is stopped for taking too long, leaves the text alone and
+    // the file is saved unformatted.
+    pub fn before_save(&self, editor: &mut Editor, formatter: Option<&str>, dir: Option<&Path>) -> (bool, Option<String>) {
+        let mut changed = false;
+        let mut error = None;
+        if self.format {
+            match formatter {
+                Some(command) => match formatter::format_text(command, &editor.content(), dir) {
+                    Ok(text) => changed |= editor.replace_all(&text),
+                    Err(e) => error = Some(format!("Saved without formatting: {}", e)),
+                },
+                None => error = Some("Format on save: no formatter set for this file type".to_string()),
+            }
+        }
+        if self.trim_trailing_whitespace {
+            changed |= editor.trim_trailing_whitespace();
+        }
+        (changed, error)
+    }
+
+    // The file contents written to disk for the document's text
+    pub fn file_text(&self, content: &str) -> String {
+        let mut text = match self.line_endings {
+            None => content.to_string(),
+            Some(ending) => {
+                let newline = if ending == LineEnding::Crlf { "\r\n" } else { "\n" };
+                content
+                    .split('\n')
+                    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                    .collect::<Vec<_>>()
+                    .join(newline)
+            }
+        };
+        if self.final_newline && !text.is_empty() && !text.ends_with('\n') {
+            text.push_str(if self.line_endings == Some(LineEnding::Crlf) { "\r\n" } else { "\n" });
+        }
+        text
+    }
+}
+
+// The post-save command, run in the background so a test suite doesn't freeze the editor
+#[derive(Default)]
+pub struct AfterSave {
+    running: Option<(String, Receiver<Result<String, String>>)>,
+    // The formatter's error from the same save, kept so the command's result doesn't hide it
+    earlier: Option<String>,
+}
+
+impl AfterSave {
+    // Start `command` in the saved file's directory, with the error `before_save` reported
+    // for the same save; one still running is left to finish unreported
+    pub fn start(&mut self, command: &str, dir: Option<PathBuf>, earlier: Option<String>) {
+        let (sender, receiver) = mpsc::channel();
+        let owned = command.to_string();
+        std::thread::spawn(move || {
+            let _ = sender.send(summary(&owned, dir.as_deref()));
+        });
+        self.running = Some((command.to_string(), receiver));
+        self.earlier = earlier;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.running.as_ref().map(|(command, _)| command.as_str())
+    }
+
+    // The footer line while the command runs, after the save's error if it had one
+    pub fn status(&self) -> Option<Result<String, String>> {
+        let running = format!("Running `{}`...", self.command()?);
+        Some(match &self.earlier {
+            Some(error) => Err(format!("{} - {}", error, running)),
+            None => Ok(running),
+        })
+    }
+
+    // The command's result once it has finished: a one-line message for the footer
+    pub fn poll(&mut self) -> Option<Result<String, String>> {
+        let (_, receiver) = self.running.as_ref()?;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err("The after-save command stopped".to_string()),
+        };
+        self.running = None;
+        // A save that went wrong stays an error, whatever the command did
+        Some(match self.earlier.take() {
+            Some(error) => Err(format!("{} - {}", error, result.unwrap_or_else(|e| e))),
+            None => result,
+        })
+    }
+}
+
+// "`cargo check` done: <last line it printed>", or its last error line on failure
+fn summary(command: &str, dir: Option<&Path>) -> Result<String, String> {
+    // Runs in the background, so a long test suite is left to finish
+    let output = formatter::run_shell(command, "", dir, None)?;
+    let printed = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let last = printed.lines().map(str::trim).rfind(|line| !line.is_empty());
+    match (output.status.success(), last) {
+        (true, Some(line)) => Ok(format!("`{}` done: {}", command, line)),
+        (true, None) => Ok(format!("`{}` done", command)),
+        (false, Some(line)) => Err(format!("{}: {}", formatter::exit_message(command, &output.status), line)),
+        (false, None) => Err(formatter::exit_message(command, &output.status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer::Buffer;
+
+    #[test]
+    fn test_filetype_hooks_override_the_general_ones() {
+        let settings: SaveSettings = serde_json::from_str(
+            r#"{ "trim_trailing_whitespace": true, "after": "make",
+                 "filetypes": { "markdown": { "trim_trailing_whitespace": false, "after": "" },
+                                "Rust": { "format": true, "line_endings": "crlf" } } }"#,
+        )
+        .unwrap();
+        let rust = settings.for_filetype(Some("Rust"));
+        assert!(rust.trim_trailing_whitespace && rust.format);
+        assert_eq!(rust.after.as_deref(), Some("make"));
+        let markdown = settings.for_filetype(Some("Markdown"));
+        assert!(!markdown.trim_trailing_whitespace && markdown.after.is_none());
+        assert_eq!(settings.for_filetype(None).line_endings, None);
+    }
+
+    #[test]
+    fn test_file_text_normalises_line_endings_and_adds_final_newline() {
+        let hooks = SaveHooks { final_newline: true, line_endings: Some(LineEnding::Crlf), ..Default::default() };
+        assert_eq!(hooks.file_text("a\r\nb\nc"), "a\r\nb\r\nc\r\n");
+        assert_eq!(hooks.file_text(""), "");
+        assert_eq!(SaveHooks::default().file_text("a\nb"), "a\nb");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_formatter_leaves_the_text_to_save_unformatted() {
+        let mut editor = Editor::new(Buffer::from_string("fn main() {}  ".to_string()));
+        let hooks = SaveHooks { format: true, trim_trailing_whitespace: true, ..Default::default() };
+        let (changed, error) = hooks.before_save(&mut editor, Some("exit 3"), None);
+        assert_eq!(error.as_deref(), Some("Saved without formatting: `exit 3` exited with code 3"));
+        // The other hooks still run
+        assert!(changed);
+        assert_eq!(editor.content(), "fn main() {}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_formatter_error_outlives_the_after_save_command() {
+        let mut editor = Editor::new(Buffer::from_string("x".to_string()));
+        let hooks = SaveHooks { format: true, after: Some("echo checked".to_string()), ..Default::default() };
+        let (_, error) = hooks.before_save(&mut editor, Some("exit 3"), None);
+        let mut after_save = AfterSave::default();
+        after_save.start(hooks.after.as_deref().unwrap(), None, error);
+        assert_eq!(
+            after_save.status(),
+            Some(Err("Saved without formatting: `exit 3` exited with code 3 - Running `echo checked`...".to_string()))
+        );
+        let result = loop {
+            if let Some(result) = after_save.poll() {
+                break result;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(result, Err("Saved without formatting: `exit 3` exited with code 3 - `echo checked` done: checked".to_string()));
+        // The next save starts clean
+        after_save.start("true", None, None);
+        assert_eq!(after_save.status(), Some(Ok("Running `true`...".to_string())));
+    }
+}
@@ -45,4 +45,40 @@ impl Selection {
     //     self.anchor = pos;
     //     self.cursor = pos;
     // }
-}
\ No newline at end of file
+}
+// Rectangular (column) selection between two corners. Columns may lie past the end of
+// short lines; edits pad those lines with spaces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockSelection {
+    pub anchor: TextPosition,
+    pub cursor: TextPosition,
+}
+
+impl BlockSelection {
+    pub fn new(pos: TextPosition) -> Self {
+        Self {
+            anchor: pos,
+            cursor: pos,
+        }
+    }
+
+    pub fn lines(&self) -> std::ops::RangeInclusive<usize> {
+        self.anchor.line.min(self.cursor.line)..=self.anchor.line.max(self.cursor.line)
+    }
+
+    // Left and right column; equal when the block is just a caret on every row
+    pub fn columns(&self) -> (usize, usize) {
+        (
+            self.anchor.column.min(self.cursor.column),
+            self.anchor.column.max(self.cursor.column),
+        )
+    }
+
+    // Same rows, with both edges moved to one column
+    pub fn collapsed_to(&self, column: usize) -> Self {
+        Self {
+            anchor: TextPosition { line: self.anchor.line, column },
+            cursor: TextPosition { line: self.cursor.line, column },
+        }
+    }
+}
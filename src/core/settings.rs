@@ -0,0 +1,46 @@
+// settings module - user preferences stored in ~/.quicknotepad/settings.json
+use crate::core::autopair::AutoCloseSettings;
+use crate::core::completion::CompletionSettings;
+use crate::core::formatter::FormatSettings;
+use crate::core::lsp::LspSettings;
+use crate::core::save_hooks::SaveSettings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    // Wrap long lines visually instead of scrolling horizontally
+    pub soft_wrap: bool,
+    // Wrap at this column instead of the window width (if narrower)
+    pub wrap_column: Option<usize>,
+    // Auto-closing brackets and quotes, with per-file-type pairs
+    pub auto_close: AutoCloseSettings,
+    // Word completion popup while typing
+    pub completion: CompletionSettings,
+    // Language servers per file type, for diagnostics, hover, definitions and renames
+    pub lsp: LspSettings,
+    // Formatter command per file type, for "format document"
+    pub format: FormatSettings,
+    // Cleanups and formatting before a save, and a command after it, per file type
+    pub save: SaveSettings,
+}
+
+impl Settings {
+    // Missing or malformed settings fall back to defaults
+    pub fn load() -> Self {
+        fs::read_to_string(Self::get_settings_file_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn get_settings_file_path() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        let mut path = PathBuf::from(home);
+        path.push(".quicknotepad");
+        path.push("settings.json");
+        path
+    }
+}
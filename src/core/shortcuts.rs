@@ -69,6 +69,30 @@ impl Shortcuts {
                 Action::MaxRight,
                 "Move to end of line",
             ),
+            (
+                KeyCode::Left,
+                KeyModifiers::CONTROL,
+                Action::WordLeft,
+                "Move to previous word",
+            ),
+            (
+                KeyCode::Right,
+                KeyModifiers::CONTROL,
+                Action::WordRight,
+                "Move to next word",
+            ),
+            (
+                KeyCode::Left,
+                KeyModifiers::CONTROL.union(KeyModifiers::SHIFT),
+                Action::SelectWordLeft,
+                "Select to previous word",
+            ),
+            (
+                KeyCode::Right,
+                KeyModifiers::CONTROL.union(KeyModifiers::SHIFT),
+                Action::SelectWordRight,
+                "Select to next word",
+            ),
             (
                 KeyCode::Enter,
                 KeyModifiers::empty(),
@@ -93,6 +117,18 @@ impl Shortcuts {
                 Action::Delete,
                 "Delete at cursor",
             ),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::CONTROL,
+                Action::DeleteWordLeft,
+                "Delete previous word",
+            ),
+            (
+                KeyCode::Delete,
+                KeyModifiers::CONTROL,
+                Action::DeleteWordRight,
+                "Delete next word",
+            ),
             (
                 KeyCode::Char('g'),
                 KeyModifiers::CONTROL,
@@ -159,17 +195,380 @@ impl Shortcuts {
                 Action::Search,
                 "Search",
             ),
+            (
+                KeyCode::Char('l'),
+                KeyModifiers::CONTROL,
+                Action::GoToLine,
+                "Go to line[:col]",
+            ),
+            (
+                KeyCode::Char('b'),
+                KeyModifiers::CONTROL,
+                Action::JumpToBracket,
+                "Jump to matching bracket",
+            ),
+            (
+                KeyCode::Char('b'),
+                KeyModifiers::ALT,
+                Action::SelectToBracket,
+                "Select to matching bracket",
+            ),
+            (
+                KeyCode::Char('f'),
+                KeyModifiers::ALT,
+                Action::Fold,
+                "Fold region",
+            ),
+            (
+                KeyCode::Char('e'),
+                KeyModifiers::ALT,
+                Action::Unfold,
+                "Unfold region",
+            ),
+            (
+                KeyCode::Char('F'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::FoldAll,
+                "Fold all",
+            ),
+            (
+                KeyCode::Char('E'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::UnfoldAll,
+                "Unfold all",
+            ),
+            (
+                KeyCode::Char('m'),
+                KeyModifiers::ALT,
+                Action::ToggleBookmark,
+                "Toggle bookmark",
+            ),
+            (
+                KeyCode::Char('M'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::NameBookmark,
+                "Name bookmark",
+            ),
+            (
+                KeyCode::Char('n'),
+                KeyModifiers::ALT,
+                Action::NextBookmark,
+                "Next bookmark",
+            ),
+            (
+                KeyCode::Char('p'),
+                KeyModifiers::ALT,
+                Action::PrevBookmark,
+                "Previous bookmark",
+            ),
+            (
+                KeyCode::Char('k'),
+                KeyModifiers::ALT,
+                Action::ListBookmarks,
+                "List bookmarks",
+            ),
+            (
+                KeyCode::Char(' '),
+                KeyModifiers::CONTROL,
+                Action::Complete,
+                "Complete word",
+            ),
+            (
+                KeyCode::Char('i'),
+                KeyModifiers::ALT,
+                Action::Hover,
+                "Show hover info and diagnostics at cursor",
+            ),
+            (
+                KeyCode::F(12),
+                KeyModifiers::empty(),
+                Action::GoToDefinition,
+                "Go to definition",
+            ),
+            (
+                KeyCode::Char('r'),
+                KeyModifiers::CONTROL,
+                Action::Rename,
+                "Rename symbol",
+            ),
+            (
+                KeyCode::Char('e'),
+                KeyModifiers::CONTROL,
+                Action::ListDiagnostics,
+                "List diagnostics",
+            ),
+            (
+                KeyCode::Char('q'),
+                KeyModifiers::ALT,
+                Action::FormatDocument,
+                "Format document with the file type's formatter",
+            ),
+            (
+                KeyCode::Char('x'),
+                KeyModifiers::ALT,
+                Action::FilterSelection,
+                "Filter selection (or line) through a shell command",
+            ),
+            (
+                KeyCode::Char('v'),
+                KeyModifiers::ALT,
+                Action::ValidateData,
+                "Validate JSON / TOML / YAML",
+            ),
+            (
+                KeyCode::Char('t'),
+                KeyModifiers::ALT,
+                Action::PrettyPrintJson,
+                "Pretty-print JSON",
+            ),
+            (
+                KeyCode::Char('T'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::MinifyJson,
+                "Minify JSON",
+            ),
+            (
+                KeyCode::Char('o'),
+                KeyModifiers::ALT,
+                Action::SortJsonKeys,
+                "Sort JSON object keys",
+            ),
+            (
+                KeyCode::Char('y'),
+                KeyModifiers::ALT,
+                Action::ConvertData,
+                "Convert between JSON, TOML and YAML into a new tab",
+            ),
+            (
+                KeyCode::Char('w'),
+                KeyModifiers::ALT,
+                Action::SplitSideBySide,
+                "Split the screen side by side",
+            ),
+            (
+                KeyCode::Char('W'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::SplitStacked,
+                "Split the screen one above the other",
+            ),
+            (
+                KeyCode::Char('o'),
+                KeyModifiers::CONTROL,
+                Action::FocusOtherPane,
+                "Focus the other pane",
+            ),
+            (
+                KeyCode::Char('w'),
+                KeyModifiers::CONTROL,
+                Action::CloseSplit,
+                "Close the split (keep the focused pane)",
+            ),
+            (
+                KeyCode::Char('D'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::CompareWithSaved,
+                "Compare with the saved file (n/p changes, </> copy, Esc closes)",
+            ),
+            (
+                KeyCode::Char('C'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::CompareTabs,
+                "Compare with another tab",
+            ),
+            (
+                KeyCode::Char('g'),
+                KeyModifiers::ALT,
+                Action::ShowHunkDiff,
+                "Show the git change at the cursor",
+            ),
+            (
+                KeyCode::Char('G'),
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::RevertHunk,
+                "Revert the git change at the cursor to the staged version",
+            ),
             (
                 KeyCode::Char('u'),
                 KeyModifiers::CONTROL,
                 Action::CheckUpdate,
                 "Check for updates",
             ),
+            (
+                KeyCode::Char('z'),
+                KeyModifiers::ALT,
+                Action::ToggleSoftWrap,
+                "Toggle soft wrap",
+            ),
+            (
+                KeyCode::Up,
+                KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                Action::AddCursorAbove,
+                "Add cursor above",
+            ),
+            (
+                KeyCode::Down,
+                KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+                Action::AddCursorBelow,
+                "Add cursor below",
+            ),
+            (
+                KeyCode::Char('d'),
+                KeyModifiers::CONTROL,
+                Action::AddNextOccurrence,
+                "Add next occurrence",
+            ),
+            (
+                KeyCode::Char('d'),
+                KeyModifiers::ALT,
+                Action::SelectAllOccurrences,
+                "Select all occurrences",
+            ),
+            (
+                KeyCode::Char('l'),
+                KeyModifiers::ALT,
+                Action::SplitSelectionIntoLines,
+                "Split selection into lines",
+            ),
+            (
+                KeyCode::Left,
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::BlockSelectLeft,
+                "Block select left",
+            ),
+            (
+                KeyCode::Right,
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::BlockSelectRight,
+                "Block select right",
+            ),
+            (
+                KeyCode::Up,
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::BlockSelectUp,
+                "Block select up",
+            ),
+            (
+                KeyCode::Down,
+                KeyModifiers::ALT.union(KeyModifiers::SHIFT),
+                Action::BlockSelectDown,
+                "Block select down",
+            ),
+            (
+                KeyCode::Char('c'),
+                KeyModifiers::ALT,
+                Action::DuplicateLine,
+                "Duplicate line or selection",
+            ),
+            (
+                KeyCode::Up,
+                KeyModifiers::ALT,
+                Action::MoveLineUp,
+                "Move line up",
+            ),
+            (
+                KeyCode::Down,
+                KeyModifiers::ALT,
+                Action::MoveLineDown,
+                "Move line down",
+            ),
+            (
+                KeyCode::Char('k'),
+                KeyModifiers::CONTROL,
+                Action::DeleteLine,
+                "Delete line",
+            ),
+            (
+                KeyCode::Char('j'),
+                KeyModifiers::ALT,
+                Action::JoinLines,
+                "Join lines",
+            ),
+            (
+                KeyCode::Char('s'),
+                KeyModifiers::ALT,
+                Action::SortLines,
+                "Sort lines",
+            ),
+            (
+                KeyCode::Char('r'),
+                KeyModifiers::ALT,
+                Action::ReverseLines,
+                "Reverse lines",
+            ),
+            (
+                KeyCode::Char('u'),
+                KeyModifiers::ALT,
+                Action::UniqueLines,
+                "Remove duplicate lines",
+            ),
+            (
+                KeyCode::Char('h'),
+                KeyModifiers::ALT,
+                Action::ShuffleLines,
+                "Shuffle lines",
+            ),
+            (
+                KeyCode::Esc,
+                KeyModifiers::empty(),
+                Action::CollapseCursors,
+                "Back to a single cursor",
+            ),
         ]
     }
 
     pub fn resolve(&mut self, event: &KeyEvent) -> Option<Action> {
         match (event.code, event.modifiers) {
+            // Multiple cursors
+            (KeyCode::Up, m) if m.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                Some(Action::AddCursorAbove)
+            }
+            (KeyCode::Down, m) if m.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                Some(Action::AddCursorBelow)
+            }
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => Some(Action::AddNextOccurrence),
+            (KeyCode::Char('d'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SelectAllOccurrences),
+            (KeyCode::Char('l'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SplitSelectionIntoLines),
+            (KeyCode::Esc, _) => Some(Action::CollapseCursors),
+
+            // Block selection with Alt+Shift
+            (KeyCode::Left, m) if m.contains(KeyModifiers::ALT | KeyModifiers::SHIFT) => {
+                Some(Action::BlockSelectLeft)
+            }
+            (KeyCode::Right, m) if m.contains(KeyModifiers::ALT | KeyModifiers::SHIFT) => {
+                Some(Action::BlockSelectRight)
+            }
+            (KeyCode::Up, m) if m.contains(KeyModifiers::ALT | KeyModifiers::SHIFT) => {
+                Some(Action::BlockSelectUp)
+            }
+            (KeyCode::Down, m) if m.contains(KeyModifiers::ALT | KeyModifiers::SHIFT) => {
+                Some(Action::BlockSelectDown)
+            }
+
+            // Line commands
+            (KeyCode::Up, m) if m == KeyModifiers::ALT => Some(Action::MoveLineUp),
+            (KeyCode::Down, m) if m == KeyModifiers::ALT => Some(Action::MoveLineDown),
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => Some(Action::DeleteLine),
+            (KeyCode::Char('c'), KeyModifiers::ALT) => Some(Action::DuplicateLine),
+            (KeyCode::Char('j'), KeyModifiers::ALT) => Some(Action::JoinLines),
+            (KeyCode::Char('s'), KeyModifiers::ALT) => Some(Action::SortLines),
+            (KeyCode::Char('r'), KeyModifiers::ALT) => Some(Action::ReverseLines),
+            (KeyCode::Char('u'), KeyModifiers::ALT) => Some(Action::UniqueLines),
+            (KeyCode::Char('h'), KeyModifiers::ALT) => Some(Action::ShuffleLines),
+
+            // Word-wise movement and deletion with Ctrl
+            (KeyCode::Left, m) if m.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
+                Some(Action::SelectWordLeft)
+            }
+            (KeyCode::Right, m) if m.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
+                Some(Action::SelectWordRight)
+            }
+            (KeyCode::Left, m) if m.contains(KeyModifiers::CONTROL) => Some(Action::WordLeft),
+            (KeyCode::Right, m) if m.contains(KeyModifiers::CONTROL) => Some(Action::WordRight),
+            (KeyCode::Backspace, m) if m.contains(KeyModifiers::CONTROL) => Some(Action::DeleteWordLeft),
+            // Many terminals send Ctrl+Backspace as Ctrl+H
+            (KeyCode::Char('h'), KeyModifiers::CONTROL) => Some(Action::DeleteWordLeft),
+            (KeyCode::Delete, m) if m.contains(KeyModifiers::CONTROL) => Some(Action::DeleteWordRight),
+
             // Movement with Shift = Selection
             (KeyCode::Left, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectLeft),
             (KeyCode::Right, m) if m.contains(KeyModifiers::SHIFT) => Some(Action::SelectRight),
@@ -191,7 +590,8 @@ impl Shortcuts {
             (KeyCode::End, _) => Some(Action::MaxRight),
 
             (KeyCode::Enter, _) => Some(Action::NextLine),
-            (KeyCode::Tab, _) => Some(Action::Print), // Tab inserts 4 spaces
+            (KeyCode::Tab, _) => Some(Action::Print), // Tab expands a snippet or inserts 4 spaces
+            (KeyCode::BackTab, _) => Some(Action::Print), // Shift+Tab goes back a snippet tab stop
             (KeyCode::Backspace, _) => Some(Action::Backspace),
             (KeyCode::Delete, _) => Some(Action::Delete),
             (KeyCode::Char('g'), KeyModifiers::CONTROL) => Some(Action::ToggleCtrlShortcuts),
@@ -205,7 +605,41 @@ impl Shortcuts {
             (KeyCode::Char('z'), KeyModifiers::CONTROL) => Some(Action::Undo),
             (KeyCode::Char('y'), KeyModifiers::CONTROL) => Some(Action::Redo),
             (KeyCode::Char('f'), KeyModifiers::CONTROL) => Some(Action::Search),
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => Some(Action::GoToLine),
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) => Some(Action::JumpToBracket),
+            (KeyCode::Char('b'), KeyModifiers::ALT) => Some(Action::SelectToBracket),
+            (KeyCode::Char('f'), KeyModifiers::ALT) => Some(Action::Fold),
+            (KeyCode::Char('e'), KeyModifiers::ALT) => Some(Action::Unfold),
+            // Terminals report Alt+Shift+letter as the uppercase letter, with or without SHIFT
+            (KeyCode::Char('F'), m) if m.contains(KeyModifiers::ALT) => Some(Action::FoldAll),
+            (KeyCode::Char('E'), m) if m.contains(KeyModifiers::ALT) => Some(Action::UnfoldAll),
+            (KeyCode::Char('m'), KeyModifiers::ALT) => Some(Action::ToggleBookmark),
+            (KeyCode::Char('M'), m) if m.contains(KeyModifiers::ALT) => Some(Action::NameBookmark),
+            (KeyCode::Char('n'), KeyModifiers::ALT) => Some(Action::NextBookmark),
+            (KeyCode::Char('p'), KeyModifiers::ALT) => Some(Action::PrevBookmark),
+            (KeyCode::Char('k'), KeyModifiers::ALT) => Some(Action::ListBookmarks),
+            (KeyCode::Char(' '), KeyModifiers::CONTROL) => Some(Action::Complete),
+            (KeyCode::Char('i'), KeyModifiers::ALT) => Some(Action::Hover),
+            (KeyCode::F(12), _) => Some(Action::GoToDefinition),
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => Some(Action::Rename),
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) => Some(Action::ListDiagnostics),
+            (KeyCode::Char('q'), KeyModifiers::ALT) => Some(Action::FormatDocument),
+            (KeyCode::Char('x'), KeyModifiers::ALT) => Some(Action::FilterSelection),
+            (KeyCode::Char('v'), KeyModifiers::ALT) => Some(Action::ValidateData),
+            (KeyCode::Char('t'), KeyModifiers::ALT) => Some(Action::PrettyPrintJson),
+            (KeyCode::Char('T'), m) if m.contains(KeyModifiers::ALT) => Some(Action::MinifyJson),
+            (KeyCode::Char('o'), KeyModifiers::ALT) => Some(Action::SortJsonKeys),
+            (KeyCode::Char('y'), KeyModifiers::ALT) => Some(Action::ConvertData),
+            (KeyCode::Char('w'), KeyModifiers::ALT) => Some(Action::SplitSideBySide),
+            (KeyCode::Char('W'), m) if m.contains(KeyModifiers::ALT) => Some(Action::SplitStacked),
+            (KeyCode::Char('o'), KeyModifiers::CONTROL) => Some(Action::FocusOtherPane),
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => Some(Action::CloseSplit),
+            (KeyCode::Char('D'), m) if m.contains(KeyModifiers::ALT) => Some(Action::CompareWithSaved),
+            (KeyCode::Char('C'), m) if m.contains(KeyModifiers::ALT) => Some(Action::CompareTabs),
+            (KeyCode::Char('g'), KeyModifiers::ALT) => Some(Action::ShowHunkDiff),
+            (KeyCode::Char('G'), m) if m.contains(KeyModifiers::ALT) => Some(Action::RevertHunk),
             (KeyCode::Char('u'), KeyModifiers::CONTROL) => Some(Action::CheckUpdate),
+            (KeyCode::Char('z'), m) if m.contains(KeyModifiers::ALT) => Some(Action::ToggleSoftWrap),
             // Tab switching - Ctrl+Number (existing)
             (KeyCode::Char('1'), KeyModifiers::CONTROL) => Some(Action::SwitchTab(1)),
             (KeyCode::Char('2'), KeyModifiers::CONTROL) => Some(Action::SwitchTab(2)),
@@ -313,6 +747,7 @@ impl Shortcuts {
             string.push_str("Shift+");
         }
         match code {
+            KeyCode::Char(' ') => string.push_str("Space"),
             KeyCode::Char(character) => string.push(character.to_ascii_uppercase()),
             KeyCode::Left => string.push_str("Left Arrow"),
             KeyCode::Right => string.push_str("Right Arrow"),
@@ -326,6 +761,8 @@ impl Shortcuts {
             KeyCode::End => string.push_str("End"),
             KeyCode::PageUp => string.push_str("Page Up"),
             KeyCode::PageDown => string.push_str("Page Down"),
+            KeyCode::Esc => string.push_str("Esc"),
+            KeyCode::F(number) => string.push_str(&format!("F{}", number)),
             _ => string.push_str("Unknown"),
         }
         string
@@ -344,7 +781,15 @@ impl Shortcuts {
             string.push_str("S-");
         }
         match code {
+            KeyCode::Char(' ') => string.push_str("Spc"),
             KeyCode::Char(character) => string.push(character.to_ascii_uppercase()),
+            KeyCode::Left => string.push('←'),
+            KeyCode::Right => string.push('→'),
+            KeyCode::Up => string.push('↑'),
+            KeyCode::Down => string.push('↓'),
+            KeyCode::Backspace => string.push_str("Bksp"),
+            KeyCode::Delete => string.push_str("Del"),
+            KeyCode::F(number) => string.push_str(&format!("F{}", number)),
             _ => string.push('?'),
         }
         string
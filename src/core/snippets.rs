@@ -0,0 +1,350 @@
+// snippets module - user snippets expanded by trigger word + Tab, with tab stops and variables
+use crate::core::edit_history::end_of_inserted;
+use crate::core::selection::TextPosition;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+// A snippet file in ~/.quicknotepad/snippets/ maps trigger words to bodies, given as one
+// string or a list of lines, e.g. rust.json:
+//   { "fn": "fn ${1:name}($2) {\n    $0\n}", "main": ["fn main() {", "    $0", "}"] }
+// Files are named after the file type shown in the footer, lowercase with spaces as
+// underscores (text_file.json); all.json applies to every file type.
+// In a body, $1 or ${1:default} is a tab stop (the same number again mirrors it), $0 is
+// where the cursor ends up, and $FILENAME, $DATE and $SELECTION are filled in.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Body {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct Snippets {
+    bodies: HashMap<String, String>,
+}
+
+impl Snippets {
+    // The snippets for `filetype`, falling back to all.json; read on each use so edits to
+    // the files apply straight away
+    pub fn load(filetype: Option<&str>) -> Self {
+        let mut snippets = Self::default();
+        let name = filetype.unwrap_or("text file").trim().to_lowercase().replace(' ', "_");
+        for file in ["all".to_string(), name] {
+            let path = Self::snippets_dir().join(format!("{file}.json"));
+            if let Ok(content) = fs::read_to_string(path) {
+                snippets.add_json(&content);
+            }
+        }
+        snippets
+    }
+
+    // Add the snippets of one file, replacing those with the same trigger; a malformed file is skipped
+    pub fn add_json(&mut self, json: &str) {
+        let Ok(bodies) = serde_json::from_str::<HashMap<String, Body>>(json) else {
+            return;
+        };
+        for (trigger, body) in bodies {
+            let body = match body {
+                Body::Text(text) => text,
+                Body::Lines(lines) => lines.join("\n"),
+            };
+            self.bodies.insert(trigger, body);
+        }
+    }
+
+    pub fn get(&self, trigger: &str) -> Option<&str> {
+        self.bodies.get(trigger).map(String::as_str)
+    }
+
+    fn snippets_dir() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        let mut path = PathBuf::from(home);
+        path.push(".quicknotepad");
+        path.push("snippets");
+        path
+    }
+}
+
+// Values for the variables a body may use
+pub struct Variables<'a> {
+    pub filename: &'a str,
+    pub selection: &'a str,
+}
+
+// Parsed body: literal text, tab stops and variables, the last two with optional defaults
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    Stop(u32, Option<Vec<Piece>>),
+    Variable(String, Option<Vec<Piece>>),
+}
+
+// Parse until the end or an unescaped `}` closing the placeholder we are inside
+fn parse(chars: &mut std::iter::Peekable<std::str::Chars>, nested: bool) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|n| matches!(n, '$' | '\\' | '}')) => {
+                text.extend(chars.next());
+            }
+            '}' if nested => break,
+            '$' => {
+                let braced = chars.next_if_eq(&'{').is_some();
+                let mut name = String::new();
+                while let Some(n) = chars.next_if(|n| n.is_ascii_alphanumeric() || *n == '_') {
+                    name.push(n);
+                }
+                let default = match braced {
+                    true if chars.next_if_eq(&':').is_some() => Some(parse(chars, true)),
+                    true => {
+                        chars.next_if_eq(&'}');
+                        None
+                    }
+                    false => None,
+                };
+                let piece = if let Ok(number) = name.parse() {
+                    Piece::Stop(number, default)
+                } else if name.starts_with(|n: char| n.is_ascii_alphabetic() || n == '_') {
+                    Piece::Variable(name, default)
+                } else {
+                    text.push('$');
+                    continue;
+                };
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(piece);
+            }
+            _ => text.push(c),
+        }
+    }
+    pieces.push(Piece::Text(text));
+    pieces
+}
+
+// Tab stop ranges, in the order Tab visits them: each entry holds one stop and its mirrors
+pub type Stops = Vec<Vec<(TextPosition, TextPosition)>>;
+
+// A snippet body filled in, with its tab stops relative to the start of `text`
+#[derive(Debug)]
+pub struct Expansion {
+    pub text: String,
+    pub stops: Stops,
+}
+
+struct Renderer<'a> {
+    text: String,
+    indent: &'a str,
+    variables: &'a Variables<'a>,
+    defaults: HashMap<u32, &'a [Piece]>,
+    stops: HashMap<u32, Vec<(TextPosition, TextPosition)>>,
+}
+
+impl<'a> Renderer<'a> {
+    fn position(&self) -> TextPosition {
+        end_of_inserted(TextPosition { line: 0, column: 0 }, &self.text)
+    }
+
+    fn render(&mut self, pieces: &'a [Piece]) {
+        for piece in pieces {
+            match piece {
+                // Later lines keep the indentation of the line the trigger was on
+                Piece::Text(text) => self.text.push_str(&text.replace('\n', &format!("\n{}", self.indent))),
+                Piece::Stop(number, default) => {
+                    let start = self.position();
+                    // A mirror without a default of its own shows that of its first placeholder
+                    if let Some(pieces) = default.as_deref().or_else(|| self.defaults.get(number).copied()) {
+                        self.render(pieces);
+                    }
+                    let end = self.position();
+                    self.stops.entry(*number).or_default().push((start, end));
+                }
+                Piece::Variable(name, default) => {
+                    let value = match name.as_str() {
+                        "FILENAME" => self.variables.filename.to_string(),
+                        "DATE" => today(),
+                        "SELECTION" => self.variables.selection.to_string(),
+                        _ => String::new(),
+                    };
+                    match default {
+                        Some(pieces) if value.is_empty() => self.render(pieces),
+                        _ => self.text.push_str(&value),
+                    }
+                }
+            }
+        }
+    }
+}
+
+// First placeholder default of each tab stop, searching nested placeholders too
+fn collect_defaults<'a>(pieces: &'a [Piece], defaults: &mut HashMap<u32, &'a [Piece]>) {
+    for piece in pieces {
+        match piece {
+            Piece::Stop(number, Some(default)) => {
+                defaults.entry(*number).or_insert(default.as_slice());
+                collect_defaults(default, defaults);
+            }
+            Piece::Variable(_, Some(default)) => collect_defaults(default, defaults),
+            _ => {}
+        }
+    }
+}
+
+// Fill in `body` for a trigger on a line indented by `indent`. Stops are ordered $1, $2, ...
+// with $0 (or the end of the text when there is none) last.
+pub fn expand(body: &str, indent: &str, variables: &Variables) -> Expansion {
+    let pieces = parse(&mut body.chars().peekable(), false);
+    let mut defaults = HashMap::new();
+    collect_defaults(&pieces, &mut defaults);
+    let mut renderer = Renderer { text: String::new(), indent, variables, defaults, stops: HashMap::new() };
+    renderer.render(&pieces);
+
+    let end = renderer.position();
+    let final_stop = renderer.stops.remove(&0).unwrap_or_else(|| vec![(end, end)]);
+    let mut numbers: Vec<u32> = renderer.stops.keys().copied().collect();
+    numbers.sort_unstable();
+    let mut stops: Stops = numbers.into_iter().filter_map(|n| renderer.stops.remove(&n)).collect();
+    stops.push(final_stop);
+    Expansion { text: renderer.text, stops }
+}
+
+// Today's date as YYYY-MM-DD (UTC)
+fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_date(secs / 86_400);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Days since 1970-01-01 to a calendar date (Howard Hinnant's algorithm)
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+// An expanded snippet being filled in: where its tab stops are now and which one is active
+#[derive(Clone, Debug)]
+pub struct SnippetSession {
+    stops: Stops,
+    current: usize,
+}
+
+impl SnippetSession {
+    // Place an expansion inserted at `start`, with its first stop active
+    pub fn new(expansion: Expansion, start: TextPosition) -> Self {
+        let place = |pos: TextPosition| match pos.line {
+            0 => TextPosition { line: start.line, column: start.column + pos.column },
+            line => TextPosition { line: start.line + line, column: pos.column },
+        };
+        let stops: Stops = expansion
+            .stops
+            .into_iter()
+            .map(|ranges| ranges.into_iter().map(|(s, e)| (place(s), place(e))).collect())
+            .collect();
+        Self { stops, current: 0 }
+    }
+
+    // Ranges of the active stop, the first of them being where the primary caret goes
+    pub fn current(&self) -> &[(TextPosition, TextPosition)] {
+        &self.stops[self.current]
+    }
+
+    // Whether the active stop is the final one, which ends the snippet
+    pub fn is_done(&self) -> bool {
+        self.current + 1 >= self.stops.len()
+    }
+
+    // Make the next or previous stop active; false when there is none that way
+    pub fn step(&mut self, forward: bool) -> bool {
+        match forward {
+            true if !self.is_done() => self.current += 1,
+            false if self.current > 0 => self.current -= 1,
+            _ => return false,
+        }
+        true
+    }
+
+    // Follow an edit given as (start, old end, new end) replacements: a stop's start stays in
+    // front of text inserted at it while its end moves behind it, so typing grows the stop
+    pub fn on_edit(&mut self, changes: &[(TextPosition, TextPosition, TextPosition)]) {
+        for &change in changes {
+            for (start, end) in self.stops.iter_mut().flatten() {
+                *start = map_position(*start, change, false);
+                *end = map_position(*end, change, true);
+            }
+        }
+    }
+}
+
+fn map_position(
+    pos: TextPosition,
+    (start, old_end, new_end): (TextPosition, TextPosition, TextPosition),
+    stick_to_end: bool,
+) -> TextPosition {
+    let key = |p: TextPosition| (p.line, p.column);
+    if key(pos) < key(start) || (pos == start && !stick_to_end) {
+        pos
+    } else if key(pos) >= key(old_end) {
+        if pos.line == old_end.line {
+            TextPosition { line: new_end.line, column: new_end.column + pos.column - old_end.column }
+        } else {
+            TextPosition { line: pos.line - old_end.line + new_end.line, column: pos.column }
+        }
+    } else if stick_to_end {
+        new_end
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> TextPosition {
+        TextPosition { line, column }
+    }
+
+    #[test]
+    fn test_expand_stops_mirrors_variables_and_indent() {
+        let variables = Variables { filename: "main.rs", selection: "" };
+        let body = "fn ${1:name}(\\$x: $2) {\n    ${SELECTION:todo!()} // $FILENAME\n}$1";
+        let expansion = expand(body, "  ", &variables);
+        assert_eq!(expansion.text, "fn name($x: ) {\n      todo!() // main.rs\n  }name");
+        assert_eq!(
+            expansion.stops,
+            vec![
+                vec![(at(0, 3), at(0, 7)), (at(2, 3), at(2, 7))],
+                vec![(at(0, 12), at(0, 12))],
+                vec![(at(2, 7), at(2, 7))],
+            ]
+        );
+
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(20_743), (2026, 10, 17));
+    }
+
+    #[test]
+    fn test_session_stops_follow_edits() {
+        let expansion = expand("a${1:xy}b$1 $0", "", &Variables { filename: "", selection: "" });
+        let mut session = SnippetSession::new(expansion, at(3, 2));
+        assert_eq!(session.current(), [(at(3, 3), at(3, 5)), (at(3, 6), at(3, 8))]);
+
+        // Both mirrors replaced by "long", bottom-up as a batch edit applies them
+        session.on_edit(&[(at(3, 6), at(3, 8), at(3, 10)), (at(3, 3), at(3, 5), at(3, 7))]);
+        assert_eq!(session.current(), [(at(3, 3), at(3, 7)), (at(3, 8), at(3, 12))]);
+        assert!(session.step(true) && session.is_done());
+        assert_eq!(session.current(), [(at(3, 13), at(3, 13))]);
+    }
+}
@@ -46,6 +46,489 @@ pub struct Token {
     pub token_type: TokenType,
 }
 
+// Keywords and built-in types per language, used for highlighting and offered as completions
+const RUST_KEYWORDS: &[&str] = &[
+    "fn", "let", "mut", "const", "static", "if", "else", "match", "for", "while", "loop",
+    "break", "continue", "return", "pub", "mod", "use", "impl", "trait", "struct", "enum",
+    "type", "where", "async", "await", "move", "ref", "self", "Self", "super", "crate",
+    "as", "unsafe", "extern", "in",
+];
+const RUST_TYPES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+    "f32", "f64", "bool", "char", "str", "String", "Vec", "Option", "Result", "Box", "Rc",
+    "Arc", "Cell", "RefCell",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "def", "class", "if", "elif", "else", "for", "while", "break", "continue", "return",
+    "import", "from", "as", "try", "except", "finally", "raise", "with", "lambda", "yield",
+    "async", "await", "pass", "None", "True", "False", "and", "or", "not", "in", "is",
+    "global", "nonlocal",
+];
+const PYTHON_TYPES: &[&str] = &[
+    "int", "str", "float", "bool", "list", "dict", "tuple", "set",
+];
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "function",
+    "const",
+    "let",
+    "var",
+    "if",
+    "else",
+    "for",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "break",
+    "continue",
+    "return",
+    "class",
+    "extends",
+    "import",
+    "export",
+    "from",
+    "async",
+    "await",
+    "try",
+    "catch",
+    "finally",
+    "throw",
+    "new",
+    "this",
+    "super",
+    "typeof",
+    "instanceof",
+    "delete",
+    "void",
+    "null",
+    "undefined",
+    "true",
+    "false",
+];
+const JAVASCRIPT_TYPES: &[&str] = &[
+    "Array", "Object", "String", "Number", "Boolean", "Function", "Promise", "Map", "Set",
+    "WeakMap", "WeakSet",
+];
+
+const C_KEYWORDS: &[&str] = &[
+    "if", "else", "while", "for", "do", "switch", "case", "break", "continue", "return",
+    "goto", "typedef", "struct", "union", "enum", "sizeof", "static", "extern", "const",
+    "volatile", "inline", "auto", "register",
+];
+const C_TYPES: &[&str] = &[
+    "void", "int", "char", "short", "long", "float", "double", "signed", "unsigned",
+    "size_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "int8_t", "int16_t",
+    "int32_t", "int64_t",
+];
+
+const JAVA_KEYWORDS: &[&str] = &[
+    "public",
+    "private",
+    "protected",
+    "static",
+    "final",
+    "abstract",
+    "class",
+    "interface",
+    "extends",
+    "implements",
+    "import",
+    "package",
+    "new",
+    "return",
+    "if",
+    "else",
+    "for",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "break",
+    "continue",
+    "try",
+    "catch",
+    "finally",
+    "throw",
+    "throws",
+    "void",
+    "this",
+    "super",
+    "synchronized",
+    "volatile",
+    "transient",
+    "native",
+    "strictfp",
+    "enum",
+    "assert",
+    "instanceof",
+    "default",
+    "true",
+    "false",
+    "null",
+];
+const JAVA_TYPES: &[&str] = &[
+    "int",
+    "long",
+    "short",
+    "byte",
+    "char",
+    "float",
+    "double",
+    "boolean",
+    "String",
+    "Integer",
+    "Long",
+    "Short",
+    "Byte",
+    "Character",
+    "Float",
+    "Double",
+    "Boolean",
+    "Object",
+    "List",
+    "ArrayList",
+    "Map",
+    "HashMap",
+    "Set",
+    "HashSet",
+    "Collection",
+];
+
+const GO_KEYWORDS: &[&str] = &[
+    "func",
+    "var",
+    "const",
+    "type",
+    "struct",
+    "interface",
+    "package",
+    "import",
+    "if",
+    "else",
+    "for",
+    "range",
+    "switch",
+    "case",
+    "break",
+    "continue",
+    "return",
+    "defer",
+    "go",
+    "select",
+    "chan",
+    "map",
+    "true",
+    "false",
+    "nil",
+    "goto",
+    "fallthrough",
+    "default",
+];
+const GO_TYPES: &[&str] = &[
+    "int",
+    "int8",
+    "int16",
+    "int32",
+    "int64",
+    "uint",
+    "uint8",
+    "uint16",
+    "uint32",
+    "uint64",
+    "float32",
+    "float64",
+    "string",
+    "bool",
+    "byte",
+    "rune",
+    "error",
+    "complex64",
+    "complex128",
+];
+
+const RUBY_KEYWORDS: &[&str] = &[
+    "def",
+    "end",
+    "class",
+    "module",
+    "if",
+    "elsif",
+    "else",
+    "unless",
+    "case",
+    "when",
+    "while",
+    "until",
+    "for",
+    "break",
+    "next",
+    "redo",
+    "retry",
+    "return",
+    "yield",
+    "super",
+    "self",
+    "nil",
+    "true",
+    "false",
+    "and",
+    "or",
+    "not",
+    "begin",
+    "rescue",
+    "ensure",
+    "raise",
+    "require",
+    "include",
+    "extend",
+    "attr_reader",
+    "attr_writer",
+    "attr_accessor",
+    "alias",
+    "undef",
+];
+const RUBY_TYPES: &[&str] = &[
+    "Array", "Hash", "String", "Integer", "Float", "Symbol", "Proc",
+];
+
+const PHP_KEYWORDS: &[&str] = &[
+    "function",
+    "class",
+    "public",
+    "private",
+    "protected",
+    "static",
+    "final",
+    "abstract",
+    "interface",
+    "extends",
+    "implements",
+    "new",
+    "return",
+    "if",
+    "else",
+    "elseif",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "break",
+    "continue",
+    "try",
+    "catch",
+    "finally",
+    "throw",
+    "namespace",
+    "use",
+    "const",
+    "var",
+    "echo",
+    "print",
+    "true",
+    "false",
+    "null",
+    "require",
+    "require_once",
+    "include",
+    "include_once",
+    "as",
+];
+const PHP_TYPES: &[&str] = &[
+    "int", "string", "bool", "float", "array", "object", "mixed", "void", "callable",
+    "iterable",
+];
+
+const SWIFT_KEYWORDS: &[&str] = &[
+    "func",
+    "var",
+    "let",
+    "class",
+    "struct",
+    "enum",
+    "protocol",
+    "extension",
+    "if",
+    "else",
+    "guard",
+    "switch",
+    "case",
+    "for",
+    "while",
+    "repeat",
+    "break",
+    "continue",
+    "return",
+    "throw",
+    "throws",
+    "rethrows",
+    "try",
+    "catch",
+    "import",
+    "public",
+    "private",
+    "fileprivate",
+    "internal",
+    "static",
+    "final",
+    "override",
+    "mutating",
+    "nonmutating",
+    "lazy",
+    "weak",
+    "unowned",
+    "self",
+    "Self",
+    "super",
+    "init",
+    "deinit",
+    "subscript",
+    "true",
+    "false",
+    "nil",
+    "as",
+    "is",
+    "in",
+    "inout",
+    "associatedtype",
+    "typealias",
+];
+const SWIFT_TYPES: &[&str] = &[
+    "Int",
+    "Int8",
+    "Int16",
+    "Int32",
+    "Int64",
+    "UInt",
+    "UInt8",
+    "UInt16",
+    "UInt32",
+    "UInt64",
+    "Float",
+    "Double",
+    "String",
+    "Bool",
+    "Character",
+    "Array",
+    "Dictionary",
+    "Set",
+    "Optional",
+    "Any",
+    "AnyObject",
+    "Void",
+];
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do",
+    "done", "function", "select", "time", "in", "break", "continue", "return", "exit",
+    "export", "local", "readonly", "declare", "eval", "exec", "shift", "test", "source",
+    "alias", "unalias",
+];
+const SHELL_TYPES: &[&str] = &["true", "false"];
+
+const SQL_KEYWORDS: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "CREATE",
+    "DROP",
+    "ALTER",
+    "TABLE",
+    "DATABASE",
+    "INDEX",
+    "VIEW",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "OUTER",
+    "ON",
+    "AS",
+    "AND",
+    "OR",
+    "NOT",
+    "IN",
+    "LIKE",
+    "BETWEEN",
+    "IS",
+    "NULL",
+    "ORDER",
+    "BY",
+    "GROUP",
+    "HAVING",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "DISTINCT",
+    "COUNT",
+    "SUM",
+    "AVG",
+    "MAX",
+    "MIN",
+    "PRIMARY",
+    "KEY",
+    "FOREIGN",
+    "REFERENCES",
+    "CONSTRAINT",
+    "DEFAULT",
+    "AUTO_INCREMENT",
+    "CASCADE",
+    "SET",
+    "VALUES",
+    "INTO",
+];
+const SQL_TYPES: &[&str] = &[
+    "INT",
+    "INTEGER",
+    "VARCHAR",
+    "CHAR",
+    "TEXT",
+    "DATE",
+    "DATETIME",
+    "TIMESTAMP",
+    "BOOLEAN",
+    "FLOAT",
+    "DOUBLE",
+    "DECIMAL",
+    "BLOB",
+];
+
+// Commands of a `git rebase -i` todo list, long and short
+const REBASE_COMMANDS: &[&str] = &[
+    "pick", "p", "reword", "r", "edit", "e", "squash", "s", "fixup", "f", "exec", "x",
+    "break", "b", "drop", "d", "label", "l", "reset", "t", "merge", "m", "update-ref", "u",
+];
+
+#[derive(Clone, Copy)]
+enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    C,
+    Java,
+    Go,
+    Ruby,
+    Php,
+    Swift,
+    Shell,
+    Html,
+    Css,
+    Json,
+    Toml,
+    Yaml,
+    Config,
+    Sql,
+    Markdown,
+    GitCommit,
+    GitRebase,
+}
+
 pub struct SyntaxHighlighter {
     file_type: Option<String>,
 }
@@ -55,7 +538,8 @@ impl SyntaxHighlighter {
         Self { file_type }
     }
 
-    pub fn highlight_line(&self, line: &str) -> Vec<Token> {
+    // Which highlighting rules apply to the file type, if any
+    fn language(&self) -> Option<Language> {
         // Normalize file type to lower-case for robust matching; accept both
         // friendly names ("Python", "JavaScript") and common extensions ("py", "js").
         if let Some(ref ft_raw) = self.file_type {
@@ -64,58 +548,58 @@ impl SyntaxHighlighter {
 
             // Rust
             if ft == "rust" || ft == "rs" {
-                return self.highlight_rust(line);
+                return Some(Language::Rust);
             }
 
             // Python
             if ft == "python" || ft == "py" {
-                return self.highlight_python(line);
+                return Some(Language::Python);
             }
 
             // JavaScript / TypeScript / JSX / TSX
             if ft == "javascript" || ft == "js" || ft == "mjs" || ft.contains("javascript") {
-                return self.highlight_javascript(line);
+                return Some(Language::JavaScript);
             }
             if ft == "typescript" || ft == "ts" || ft == "tsx" || ft.contains("typescript") {
-                return self.highlight_javascript(line);
+                return Some(Language::JavaScript);
             }
             if ft.contains("jsx") || ft.contains("tsx") || ft.contains("react") {
-                return self.highlight_javascript(line);
+                return Some(Language::JavaScript);
             }
 
             // C-family
             if ft == "c" || ft == "c++" || ft == "cpp" || ft == "cc" || ft == "c#" {
-                return self.highlight_c(line);
+                return Some(Language::C);
             }
 
             // Java / Kotlin / Scala
             if ft == "java" || ft == "kotlin" || ft == "scala" {
-                return self.highlight_java(line);
+                return Some(Language::Java);
             }
 
             // Go
             if ft == "go" || ft == "golang" {
-                return self.highlight_go(line);
+                return Some(Language::Go);
             }
 
             // Ruby
             if ft == "ruby" || ft == "rb" {
-                return self.highlight_ruby(line);
+                return Some(Language::Ruby);
             }
 
             // PHP
             if ft == "php" {
-                return self.highlight_php(line);
+                return Some(Language::Php);
             }
 
             // Swift
             if ft == "swift" {
-                return self.highlight_swift(line);
+                return Some(Language::Swift);
             }
 
             // Shell scripts
             if ft.contains("shell") || ft == "sh" || ft == "bash" || ft == "zsh" {
-                return self.highlight_shell(line);
+                return Some(Language::Shell);
             }
 
             // Web markup
@@ -125,145 +609,117 @@ impl SyntaxHighlighter {
                 || ft.contains("html")
                 || ft.contains("xml")
             {
-                return self.highlight_html(line);
+                return Some(Language::Html);
             }
             if ft == "css" || ft == "sass" || ft.contains("css") {
-                return self.highlight_css(line);
+                return Some(Language::Css);
             }
 
             // Data formats
             if ft == "json" {
-                return self.highlight_json(line);
+                return Some(Language::Json);
             }
             if ft == "toml" {
-                return self.highlight_toml(line);
+                return Some(Language::Toml);
             }
             if ft == "yaml" || ft == "yml" {
-                return self.highlight_yaml(line);
+                return Some(Language::Yaml);
             }
 
             // Configuration files
             if ft.contains("config") || ft == "env" || ft == "environment" {
-                return self.highlight_config(line);
+                return Some(Language::Config);
             }
 
             // SQL
             if ft.contains("sql") || ft == "sql query" {
-                return self.highlight_sql(line);
+                return Some(Language::Sql);
             }
 
             // Markdown
             if ft == "markdown" || ft == "md" {
-                return self.highlight_markdown(line);
+                return Some(Language::Markdown);
+            }
+
+            // Files git opens in $EDITOR
+            if ft == "git commit" {
+                return Some(Language::GitCommit);
+            }
+            if ft == "git rebase" {
+                return Some(Language::GitRebase);
             }
 
             // Other languages that can use generic C-like highlighting
             if ft == "dart" || ft == "zig" || ft == "nim" {
-                return self.highlight_c(line);
+                return Some(Language::C);
             }
         }
 
-        // Default
-        vec![Token {
-            text: line.to_string(),
-            token_type: TokenType::Normal,
-        }]
+        None
+    }
+
+    pub fn highlight_line(&self, line: &str) -> Vec<Token> {
+        match self.language() {
+            Some(Language::Rust) => self.highlight_rust(line),
+            Some(Language::Python) => self.highlight_python(line),
+            Some(Language::JavaScript) => self.highlight_javascript(line),
+            Some(Language::C) => self.highlight_c(line),
+            Some(Language::Java) => self.highlight_java(line),
+            Some(Language::Go) => self.highlight_go(line),
+            Some(Language::Ruby) => self.highlight_ruby(line),
+            Some(Language::Php) => self.highlight_php(line),
+            Some(Language::Swift) => self.highlight_swift(line),
+            Some(Language::Shell) => self.highlight_shell(line),
+            Some(Language::Html) => self.highlight_html(line),
+            Some(Language::Css) => self.highlight_css(line),
+            Some(Language::Json) => self.highlight_json(line),
+            Some(Language::Toml) => self.highlight_toml(line),
+            Some(Language::Yaml) => self.highlight_yaml(line),
+            Some(Language::Config) => self.highlight_config(line),
+            Some(Language::Sql) => self.highlight_sql(line),
+            Some(Language::Markdown) => self.highlight_markdown(line),
+            Some(Language::GitCommit) | Some(Language::GitRebase) => self.highlight_git(line),
+            // Default
+            None => vec![Token {
+                text: line.to_string(),
+                token_type: TokenType::Normal,
+            }],
+        }
+    }
+
+    // Keywords and built-in type names of the file type's language
+    pub fn keywords(&self) -> Vec<&'static str> {
+        let lists: [&[&str]; 2] = match self.language() {
+            Some(Language::Rust) => [RUST_KEYWORDS, RUST_TYPES],
+            Some(Language::Python) => [PYTHON_KEYWORDS, PYTHON_TYPES],
+            Some(Language::JavaScript) => [JAVASCRIPT_KEYWORDS, JAVASCRIPT_TYPES],
+            Some(Language::C) => [C_KEYWORDS, C_TYPES],
+            Some(Language::Java) => [JAVA_KEYWORDS, JAVA_TYPES],
+            Some(Language::Go) => [GO_KEYWORDS, GO_TYPES],
+            Some(Language::Ruby) => [RUBY_KEYWORDS, RUBY_TYPES],
+            Some(Language::Php) => [PHP_KEYWORDS, PHP_TYPES],
+            Some(Language::Swift) => [SWIFT_KEYWORDS, SWIFT_TYPES],
+            Some(Language::Shell) => [SHELL_KEYWORDS, SHELL_TYPES],
+            Some(Language::Sql) => [SQL_KEYWORDS, SQL_TYPES],
+            _ => [&[], &[]],
+        };
+        lists.concat()
     }
 
     fn highlight_rust(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "fn", "let", "mut", "const", "static", "if", "else", "match", "for", "while", "loop",
-            "break", "continue", "return", "pub", "mod", "use", "impl", "trait", "struct", "enum",
-            "type", "where", "async", "await", "move", "ref", "self", "Self", "super", "crate",
-            "as", "unsafe", "extern", "in",
-        ];
-
-        let types = [
-            "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
-            "f32", "f64", "bool", "char", "str", "String", "Vec", "Option", "Result", "Box", "Rc",
-            "Arc", "Cell", "RefCell",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, RUST_KEYWORDS, RUST_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_python(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "def", "class", "if", "elif", "else", "for", "while", "break", "continue", "return",
-            "import", "from", "as", "try", "except", "finally", "raise", "with", "lambda", "yield",
-            "async", "await", "pass", "None", "True", "False", "and", "or", "not", "in", "is",
-            "global", "nonlocal",
-        ];
-
-        let types = [
-            "int", "str", "float", "bool", "list", "dict", "tuple", "set",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "#", &[], &[])
+        self.tokenize_line(line, PYTHON_KEYWORDS, PYTHON_TYPES, "#", &[], &[])
     }
 
     fn highlight_javascript(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "function",
-            "const",
-            "let",
-            "var",
-            "if",
-            "else",
-            "for",
-            "while",
-            "do",
-            "switch",
-            "case",
-            "break",
-            "continue",
-            "return",
-            "class",
-            "extends",
-            "import",
-            "export",
-            "from",
-            "async",
-            "await",
-            "try",
-            "catch",
-            "finally",
-            "throw",
-            "new",
-            "this",
-            "super",
-            "typeof",
-            "instanceof",
-            "delete",
-            "void",
-            "null",
-            "undefined",
-            "true",
-            "false",
-        ];
-
-        let types = [
-            "Array", "Object", "String", "Number", "Boolean", "Function", "Promise", "Map", "Set",
-            "WeakMap", "WeakSet",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, JAVASCRIPT_KEYWORDS, JAVASCRIPT_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_c(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "if", "else", "while", "for", "do", "switch", "case", "break", "continue", "return",
-            "goto", "typedef", "struct", "union", "enum", "sizeof", "static", "extern", "const",
-            "volatile", "inline", "auto", "register",
-        ];
-
-        let types = [
-            "void", "int", "char", "short", "long", "float", "double", "signed", "unsigned",
-            "size_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "int8_t", "int16_t",
-            "int32_t", "int64_t",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, C_KEYWORDS, C_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_json(&self, line: &str) -> Vec<Token> {
@@ -456,327 +912,54 @@ impl SyntaxHighlighter {
         }]
     }
 
+    // Comment lines (which git drops) greyed out; a todo list's commands as keywords
+    fn highlight_git(&self, line: &str) -> Vec<Token> {
+        if line.starts_with('#') {
+            return vec![Token {
+                text: line.to_string(),
+                token_type: TokenType::Comment,
+            }];
+        }
+
+        let command = line.split_whitespace().next().unwrap_or("");
+        if matches!(self.language(), Some(Language::GitRebase)) && REBASE_COMMANDS.contains(&command) {
+            let start = line.find(command).unwrap_or(0) + command.len();
+            return vec![
+                Token {
+                    text: line[..start].to_string(),
+                    token_type: TokenType::Keyword,
+                },
+                Token {
+                    text: line[start..].to_string(),
+                    token_type: TokenType::Normal,
+                },
+            ];
+        }
+
+        vec![Token {
+            text: line.to_string(),
+            token_type: TokenType::Normal,
+        }]
+    }
+
     fn highlight_java(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "public",
-            "private",
-            "protected",
-            "static",
-            "final",
-            "abstract",
-            "class",
-            "interface",
-            "extends",
-            "implements",
-            "import",
-            "package",
-            "new",
-            "return",
-            "if",
-            "else",
-            "for",
-            "while",
-            "do",
-            "switch",
-            "case",
-            "break",
-            "continue",
-            "try",
-            "catch",
-            "finally",
-            "throw",
-            "throws",
-            "void",
-            "this",
-            "super",
-            "synchronized",
-            "volatile",
-            "transient",
-            "native",
-            "strictfp",
-            "enum",
-            "assert",
-            "instanceof",
-            "default",
-            "true",
-            "false",
-            "null",
-        ];
-
-        let types = [
-            "int",
-            "long",
-            "short",
-            "byte",
-            "char",
-            "float",
-            "double",
-            "boolean",
-            "String",
-            "Integer",
-            "Long",
-            "Short",
-            "Byte",
-            "Character",
-            "Float",
-            "Double",
-            "Boolean",
-            "Object",
-            "List",
-            "ArrayList",
-            "Map",
-            "HashMap",
-            "Set",
-            "HashSet",
-            "Collection",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, JAVA_KEYWORDS, JAVA_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_go(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "func",
-            "var",
-            "const",
-            "type",
-            "struct",
-            "interface",
-            "package",
-            "import",
-            "if",
-            "else",
-            "for",
-            "range",
-            "switch",
-            "case",
-            "break",
-            "continue",
-            "return",
-            "defer",
-            "go",
-            "select",
-            "chan",
-            "map",
-            "true",
-            "false",
-            "nil",
-            "goto",
-            "fallthrough",
-            "default",
-        ];
-
-        let types = [
-            "int",
-            "int8",
-            "int16",
-            "int32",
-            "int64",
-            "uint",
-            "uint8",
-            "uint16",
-            "uint32",
-            "uint64",
-            "float32",
-            "float64",
-            "string",
-            "bool",
-            "byte",
-            "rune",
-            "error",
-            "complex64",
-            "complex128",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, GO_KEYWORDS, GO_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_ruby(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "def",
-            "end",
-            "class",
-            "module",
-            "if",
-            "elsif",
-            "else",
-            "unless",
-            "case",
-            "when",
-            "while",
-            "until",
-            "for",
-            "break",
-            "next",
-            "redo",
-            "retry",
-            "return",
-            "yield",
-            "super",
-            "self",
-            "nil",
-            "true",
-            "false",
-            "and",
-            "or",
-            "not",
-            "begin",
-            "rescue",
-            "ensure",
-            "raise",
-            "require",
-            "include",
-            "extend",
-            "attr_reader",
-            "attr_writer",
-            "attr_accessor",
-            "alias",
-            "undef",
-        ];
-
-        let types = [
-            "Array", "Hash", "String", "Integer", "Float", "Symbol", "Proc",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "#", &[], &[])
+        self.tokenize_line(line, RUBY_KEYWORDS, RUBY_TYPES, "#", &[], &[])
     }
 
     fn highlight_php(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "function",
-            "class",
-            "public",
-            "private",
-            "protected",
-            "static",
-            "final",
-            "abstract",
-            "interface",
-            "extends",
-            "implements",
-            "new",
-            "return",
-            "if",
-            "else",
-            "elseif",
-            "for",
-            "foreach",
-            "while",
-            "do",
-            "switch",
-            "case",
-            "break",
-            "continue",
-            "try",
-            "catch",
-            "finally",
-            "throw",
-            "namespace",
-            "use",
-            "const",
-            "var",
-            "echo",
-            "print",
-            "true",
-            "false",
-            "null",
-            "require",
-            "require_once",
-            "include",
-            "include_once",
-            "as",
-        ];
-
-        let types = [
-            "int", "string", "bool", "float", "array", "object", "mixed", "void", "callable",
-            "iterable",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, PHP_KEYWORDS, PHP_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_swift(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "func",
-            "var",
-            "let",
-            "class",
-            "struct",
-            "enum",
-            "protocol",
-            "extension",
-            "if",
-            "else",
-            "guard",
-            "switch",
-            "case",
-            "for",
-            "while",
-            "repeat",
-            "break",
-            "continue",
-            "return",
-            "throw",
-            "throws",
-            "rethrows",
-            "try",
-            "catch",
-            "import",
-            "public",
-            "private",
-            "fileprivate",
-            "internal",
-            "static",
-            "final",
-            "override",
-            "mutating",
-            "nonmutating",
-            "lazy",
-            "weak",
-            "unowned",
-            "self",
-            "Self",
-            "super",
-            "init",
-            "deinit",
-            "subscript",
-            "true",
-            "false",
-            "nil",
-            "as",
-            "is",
-            "in",
-            "inout",
-            "associatedtype",
-            "typealias",
-        ];
-
-        let types = [
-            "Int",
-            "Int8",
-            "Int16",
-            "Int32",
-            "Int64",
-            "UInt",
-            "UInt8",
-            "UInt16",
-            "UInt32",
-            "UInt64",
-            "Float",
-            "Double",
-            "String",
-            "Bool",
-            "Character",
-            "Array",
-            "Dictionary",
-            "Set",
-            "Optional",
-            "Any",
-            "AnyObject",
-            "Void",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "//", &["/*"], &["*/"])
+        self.tokenize_line(line, SWIFT_KEYWORDS, SWIFT_TYPES, "//", &["/*"], &["*/"])
     }
 
     fn highlight_shell(&self, line: &str) -> Vec<Token> {
@@ -789,16 +972,7 @@ impl SyntaxHighlighter {
             }];
         }
 
-        let keywords = [
-            "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do",
-            "done", "function", "select", "time", "in", "break", "continue", "return", "exit",
-            "export", "local", "readonly", "declare", "eval", "exec", "shift", "test", "source",
-            "alias", "unalias",
-        ];
-
-        let types = ["true", "false"];
-
-        self.tokenize_line(line, &keywords, &types, "#", &[], &[])
+        self.tokenize_line(line, SHELL_KEYWORDS, SHELL_TYPES, "#", &[], &[])
     }
 
     fn highlight_html(&self, line: &str) -> Vec<Token> {
@@ -975,78 +1149,7 @@ impl SyntaxHighlighter {
     }
 
     fn highlight_sql(&self, line: &str) -> Vec<Token> {
-        let keywords = [
-            "SELECT",
-            "FROM",
-            "WHERE",
-            "INSERT",
-            "UPDATE",
-            "DELETE",
-            "CREATE",
-            "DROP",
-            "ALTER",
-            "TABLE",
-            "DATABASE",
-            "INDEX",
-            "VIEW",
-            "JOIN",
-            "INNER",
-            "LEFT",
-            "RIGHT",
-            "OUTER",
-            "ON",
-            "AS",
-            "AND",
-            "OR",
-            "NOT",
-            "IN",
-            "LIKE",
-            "BETWEEN",
-            "IS",
-            "NULL",
-            "ORDER",
-            "BY",
-            "GROUP",
-            "HAVING",
-            "LIMIT",
-            "OFFSET",
-            "UNION",
-            "DISTINCT",
-            "COUNT",
-            "SUM",
-            "AVG",
-            "MAX",
-            "MIN",
-            "PRIMARY",
-            "KEY",
-            "FOREIGN",
-            "REFERENCES",
-            "CONSTRAINT",
-            "DEFAULT",
-            "AUTO_INCREMENT",
-            "CASCADE",
-            "SET",
-            "VALUES",
-            "INTO",
-        ];
-
-        let types = [
-            "INT",
-            "INTEGER",
-            "VARCHAR",
-            "CHAR",
-            "TEXT",
-            "DATE",
-            "DATETIME",
-            "TIMESTAMP",
-            "BOOLEAN",
-            "FLOAT",
-            "DOUBLE",
-            "DECIMAL",
-            "BLOB",
-        ];
-
-        self.tokenize_line(line, &keywords, &types, "--", &["/*"], &["*/"])
+        self.tokenize_line(line, SQL_KEYWORDS, SQL_TYPES, "--", &["/*"], &["*/"])
     }
 
     fn highlight_config(&self, line: &str) -> Vec<Token> {
@@ -1383,5 +1486,10 @@ mod tests {
         let highlighter = SyntaxHighlighter::new(Some("Rust".to_string()));
         let tokens = highlighter.highlight_line("// This is a comment");
         assert_eq!(tokens[0].token_type, TokenType::Comment);
+
+        // Git drops the comment lines of a commit message
+        let highlighter = SyntaxHighlighter::new(Some("Git Commit".to_string()));
+        assert_eq!(highlighter.highlight_line("# On branch main")[0].token_type, TokenType::Comment);
+        assert_eq!(highlighter.highlight_line("Fix #12")[0].token_type, TokenType::Normal);
     }
 }
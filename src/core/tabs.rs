@@ -1,34 +1,40 @@
 use crate::tui::view::Buffer;
-use crate::tui::caret::Position;
-use crate::core::edit_history::EditHistory;
+use crate::core::selection::TextPosition;
+use crate::core::bookmarks::{Bookmark, Bookmarks};
+use crate::core::editor::Editor;
+use crate::core::git;
+use crate::core::lsp::TextEdit;
 use std::fs;
 use std::io::Error;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use serde::{Serialize, Deserialize};
 
+// Source of tab ids, which stay put while tabs are opened and closed around them
+static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Clone)]
 pub struct Tab {
-    pub buffer: Buffer,
+    // Tells tabs apart across reordering, e.g. for the pane showing this one in a split
+    pub id: u64,
+    pub editor: Editor, // Buffer, cursor, selection and undo history
     pub filename: Option<String>,  // Display name only
     pub filepath: Option<String>,  // Full path for saving
     pub filetype: Option<String>,
     pub scroll_offset: usize,
-    pub cursor_pos: Position,
     pub has_unsaved_changes: bool,
-    pub edit_history: EditHistory,
 }
 
 impl Tab {
     pub fn new(buffer: Buffer, filename: Option<String>, filepath: Option<String>, filetype: Option<String>) -> Self {
         Self {
-            buffer,
+            id: NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed),
+            editor: Editor::new(buffer),
             filename,
             filepath,
             filetype,
             scroll_offset: 0,
-            cursor_pos: Position::default(),
             has_unsaved_changes: false,
-            edit_history: EditHistory::new(500),
         }
     }
 
@@ -47,7 +53,10 @@ impl Tab {
 
         let full_path = path_buf.to_string_lossy().into_owned();
         let raw_ext = path_buf.extension().map(|ext| ext.to_string_lossy().into_owned());
-        let friendly_filetype = get_friendly_filetype(raw_ext);
+        // Files git opens in $EDITOR have no extension to go by
+        let friendly_filetype = git::message_filetype(&display_name)
+            .map(String::from)
+            .or_else(|| get_friendly_filetype(raw_ext));
 
         let content = std::fs::read_to_string(&path_buf)?;
         let buffer = Buffer::from_string(content);
@@ -62,8 +71,15 @@ struct TabInfo {
     filepath: Option<String>,
     filetype: Option<String>,
     scroll_offset: usize,
-    cursor_line: u16,
-    cursor_col: u16,
+    // Document position of the cursor. Sessions from before it was kept in document
+    // coordinates stored screen cells as `cursor_line`/`cursor_col`; those are left unread
+    // and the cursor starts at the top.
+    #[serde(default)]
+    line: usize,
+    #[serde(default)]
+    column: usize,
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -76,7 +92,8 @@ pub struct TabManager {
     pub tabs: Vec<Tab>,
     pub active_tab_index: usize,
     pub max_tabs: usize,
-    session_file: PathBuf,
+    // Where the open tabs are remembered; None when they aren't (editing for `--wait`)
+    session_file: Option<PathBuf>,
 }
 
 impl TabManager {
@@ -90,7 +107,7 @@ impl TabManager {
             tabs: vec![Tab::blank()],
             active_tab_index: 0,
             max_tabs: 10,
-            session_file,
+            session_file: Some(session_file),
         }
     }
 
@@ -117,10 +134,11 @@ impl TabManager {
                     Ok(mut t) => {
                         t.filetype = tab_info.filetype;
                         t.scroll_offset = tab_info.scroll_offset;
-                        t.cursor_pos = Position {
-                            x: tab_info.cursor_col,
-                            y: tab_info.cursor_line,
-                        };
+                        t.editor.cursor = t.editor.clamp(TextPosition {
+                            line: tab_info.line,
+                            column: tab_info.column,
+                        });
+                        t.editor.bookmarks = Bookmarks::restore(tab_info.bookmarks, t.editor.buffer.lines.len());
                         tabs.push(t);
                     }
                     Err(_) => continue, // Skip files that no longer exist on disk
@@ -132,7 +150,18 @@ impl TabManager {
             tabs,
             active_tab_index: 0,
             max_tabs: 10,
-            session_file,
+            session_file: Some(session_file),
+        })
+    }
+
+    // User ran `quick --wait somefile` as another program's editor, or `quick --diff a b` —
+    // only those files, and the session is neither restored nor saved
+    pub fn only_files(paths: &[&str]) -> Result<Self, Error> {
+        Ok(Self {
+            tabs: paths.iter().map(|path| Tab::from_file(path)).collect::<Result<_, _>>()?,
+            active_tab_index: 0,
+            max_tabs: 10,
+            session_file: None,
         })
     }
 
@@ -146,7 +175,7 @@ impl TabManager {
             tabs: vec![initial_tab],
             active_tab_index: 0,
             max_tabs: 10,
-            session_file: Self::get_session_file_path(),
+            session_file: Some(Self::get_session_file_path()),
         }
     }
 
@@ -179,10 +208,11 @@ impl TabManager {
                     Ok(mut t) => {
                         t.filetype = tab_info.filetype.clone();
                         t.scroll_offset = tab_info.scroll_offset;
-                        t.cursor_pos = Position {
-                            x: tab_info.cursor_col,
-                            y: tab_info.cursor_line,
-                        };
+                        t.editor.cursor = t.editor.clamp(TextPosition {
+                            line: tab_info.line,
+                            column: tab_info.column,
+                        });
+                        t.editor.bookmarks = Bookmarks::restore(tab_info.bookmarks, t.editor.buffer.lines.len());
                         t
                     }
                     Err(e) => {
@@ -206,18 +236,22 @@ impl TabManager {
             tabs,
             active_tab_index: active_index,
             max_tabs: 10,
-            session_file,
+            session_file: Some(session_file),
         }
     }
 
     pub fn save_session(&self) -> Result<(), Error> {
+        let Some(session_file) = &self.session_file else {
+            return Ok(());
+        };
         let tab_infos: Vec<TabInfo> = self.tabs.iter().map(|tab| TabInfo {
             filename: tab.filename.clone(),
             filepath: tab.filepath.clone(),
             filetype: tab.filetype.clone(),
             scroll_offset: tab.scroll_offset,
-            cursor_line: tab.cursor_pos.y,
-            cursor_col: tab.cursor_pos.x,
+            line: tab.editor.cursor.line,
+            column: tab.editor.cursor.column,
+            bookmarks: tab.editor.bookmarks.to_vec(),
         }).collect();
 
         let session = TabSession {
@@ -228,7 +262,7 @@ impl TabManager {
         let json = serde_json::to_string_pretty(&session)
             .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
 
-        fs::write(&self.session_file, json)?;
+        fs::write(session_file, json)?;
         Ok(())
     }
 
@@ -240,6 +274,29 @@ impl TabManager {
         &mut self.tabs[self.active_tab_index]
     }
 
+    // Where the tab with this id is now, if it is still open
+    pub fn index_of(&self, id: u64) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.id == id)
+    }
+
+    // Every bookmark in every tab, as (tab index, bookmark)
+    pub fn bookmarks(&self) -> Vec<(usize, Bookmark)> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, tab)| tab.editor.bookmarks.iter().map(move |mark| (idx, mark.clone())))
+            .collect()
+    }
+
+    // Tab index and line of the bookmark called `name`, looking in the current tab first
+    pub fn find_bookmark(&self, name: &str) -> Option<(usize, usize)> {
+        let name = name.trim();
+        let current = self.active_tab_index;
+        std::iter::once(current)
+            .chain((0..self.tabs.len()).filter(|&idx| idx != current))
+            .find_map(|idx| self.tabs[idx].editor.bookmarks.line_of(name).map(|line| (idx, line)))
+    }
+
     pub fn switch_to_tab(&mut self, tab_number: usize) -> Result<(), Error> {
         if tab_number < 1 {
             return Ok(());
@@ -266,7 +323,56 @@ impl TabManager {
         self.active_tab_index
     }
 
+    // Open a new unsaved tab holding `content` (a converted document) and switch to it
+    pub fn new_tab_with(&mut self, content: String, filetype: Option<String>) -> usize {
+        let index = self.new_tab();
+        let tab = &mut self.tabs[index];
+        *tab = Tab::new(Buffer::from_string(content), None, None, filetype);
+        tab.has_unsaved_changes = true;
+        index
+    }
+
+    // Apply edits per file path (a rename from the language server) to the tabs holding those
+    // files, opening the others in new tabs without switching to them. Returns how many files
+    // changed and how many were left alone because no tab could be opened for them.
+    pub fn apply_file_edits(&mut self, files: &[(String, Vec<TextEdit>)]) -> (usize, usize) {
+        let (mut changed, mut skipped) = (0, 0);
+        for (path, edits) in files {
+            let open = self.tabs.iter().position(|tab| tab.filepath.as_deref() == Some(path.as_str()));
+            let index = match open {
+                Some(index) => index,
+                // Opening past the limit would close a tab, maybe one with unsaved changes
+                None if self.tabs.len() < self.max_tabs => match Tab::from_file(path) {
+                    Ok(tab) => {
+                        self.tabs.push(tab);
+                        self.tabs.len() - 1
+                    }
+                    Err(_) => {
+                        skipped += 1;
+                        continue;
+                    }
+                },
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let tab = &mut self.tabs[index];
+            if tab.editor.apply_edits(edits) {
+                tab.has_unsaved_changes = true;
+                changed += 1;
+            }
+        }
+        (changed, skipped)
+    }
+
     // Open a file. If already open, switch to it. Otherwise append and switch.
+    // Whether opening `path` would close a tab with unsaved changes to make room
+    pub fn would_close_unsaved(&self, path: &str) -> bool {
+        let open = self.tabs.iter().any(|tab| tab.filepath.as_deref() == Some(path));
+        !open && self.tabs.len() >= self.max_tabs && self.tabs.last().is_some_and(|tab| tab.has_unsaved_changes)
+    }
+
     pub fn open_file_in_new_tab(&mut self, path: &str) -> Result<usize, Error> {
         for (i, tab) in self.tabs.iter().enumerate() {
             if let Some(ref filepath) = tab.filepath {
@@ -346,3 +452,37 @@ pub fn get_friendly_filetype(extension: Option<String>) -> Option<String> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_screen_cursor_is_not_restored() {
+        let old = r#"{"filename":null,"filepath":"/tmp/a","filetype":null,"scroll_offset":0,"cursor_line":6,"cursor_col":9}"#;
+        let info: TabInfo = serde_json::from_str(old).unwrap();
+        assert_eq!((info.line, info.column), (0, 0));
+    }
+
+    #[test]
+    fn test_opening_past_the_tab_limit_would_close_an_unsaved_tab() {
+        let dir = std::env::temp_dir().join(format!("quick-tabs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<String> = (0..11)
+            .map(|i| {
+                let path = dir.join(format!("{}.txt", i));
+                fs::write(&path, "text").unwrap();
+                fs::canonicalize(path).unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        let names: Vec<&str> = paths.iter().map(String::as_str).collect();
+        // No session file, so nothing is saved
+        let mut tabs = TabManager::only_files(&names[..10]).unwrap();
+        assert!(!tabs.would_close_unsaved(names[10]));
+        tabs.tabs[9].has_unsaved_changes = true;
+        assert!(tabs.would_close_unsaved(names[10]));
+        // Already open, so nothing closes
+        assert!(!tabs.would_close_unsaved(names[3]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
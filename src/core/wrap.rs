@@ -0,0 +1,167 @@
+// wrap module - maps logical lines onto visual rows for soft-wrap display
+use crate::core::folding::Folds;
+use crate::core::graphemes::wrap_ranges;
+use crate::core::selection::TextPosition;
+use crate::core::syntax::Token;
+use unicode_segmentation::UnicodeSegmentation;
+
+// One visual row of a wrapped line, as a grapheme range [start, end) of that line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisualRow {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub ends_line: bool, // last row of its logical line
+}
+
+impl VisualRow {
+    // A column on a wrap boundary belongs to the next row, except at the very end of the line
+    pub fn contains(&self, column: usize) -> bool {
+        column >= self.start && (column < self.end || (self.ends_line && column == self.end))
+    }
+
+    // Rightmost column the caret may occupy while staying on this row
+    pub fn max_column(&self) -> usize {
+        if self.ends_line {
+            self.end
+        } else {
+            self.end.saturating_sub(1).max(self.start)
+        }
+    }
+}
+
+// All visual rows of a single logical line (always at least one)
+pub fn line_rows(line_idx: usize, line: &str, width: usize) -> Vec<VisualRow> {
+    let ranges = wrap_ranges(line, width);
+    let last = ranges.len() - 1;
+    ranges
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, end))| VisualRow {
+            line: line_idx,
+            start,
+            end,
+            ends_line: i == last,
+        })
+        .collect()
+}
+
+// Visual rows starting at the top of `first_line`, up to `max_rows` of them; folded lines have none
+pub fn rows_from(lines: &[String], folds: &Folds, first_line: usize, width: usize, max_rows: usize) -> Vec<VisualRow> {
+    let mut rows = Vec::new();
+    for line_idx in folds.visible_lines(first_line, lines.len()) {
+        if rows.len() >= max_rows {
+            break;
+        }
+        rows.extend(line_rows(line_idx, &lines[line_idx], width));
+    }
+    rows.truncate(max_rows);
+    rows
+}
+
+// Index of the row showing `pos`, if it is among `rows`
+pub fn row_containing(rows: &[VisualRow], pos: TextPosition) -> Option<usize> {
+    rows.iter()
+        .position(|row| row.line == pos.line && row.contains(pos.column))
+}
+
+// Move one visual row up or down, keeping the offset into the row where possible
+pub fn move_vertically(lines: &[String], folds: &Folds, pos: TextPosition, width: usize, down: bool) -> TextPosition {
+    let Some(line) = lines.get(pos.line) else {
+        return pos;
+    };
+    let rows = line_rows(pos.line, line, width);
+    let idx = row_containing(&rows, pos).unwrap_or(rows.len() - 1);
+    let offset = pos.column.saturating_sub(rows[idx].start);
+
+    let target = if down {
+        if idx + 1 < rows.len() {
+            rows[idx + 1]
+        } else if let Some(next) = folds.next_visible(pos.line, lines.len()) {
+            line_rows(next, &lines[next], width)[0]
+        } else {
+            return pos;
+        }
+    } else if idx > 0 {
+        rows[idx - 1]
+    } else if let Some(prev) = folds.prev_visible(pos.line) {
+        let prev_rows = line_rows(prev, &lines[prev], width);
+        prev_rows[prev_rows.len() - 1]
+    } else {
+        return pos;
+    };
+
+    TextPosition {
+        line: target.line,
+        column: (target.start + offset).min(target.max_column()),
+    }
+}
+
+// Keep only the graphemes in [start, end) of a highlighted line, preserving token colours
+pub fn slice_tokens(tokens: &[Token], start: usize, end: usize) -> Vec<Token> {
+    let mut sliced = Vec::new();
+    let mut grapheme_idx = 0;
+
+    for token in tokens {
+        if grapheme_idx >= end {
+            break;
+        }
+        let mut text = String::new();
+        for grapheme in token.text.graphemes(true) {
+            if grapheme_idx >= start && grapheme_idx < end {
+                text.push_str(grapheme);
+            }
+            grapheme_idx += 1;
+        }
+        if !text.is_empty() {
+            sliced.push(Token { text, token_type: token.token_type });
+        }
+    }
+
+    sliced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rows_split_long_line() {
+        let buf = lines(&["abcdefghij", "", "xy"]);
+        let rows = rows_from(&buf, &Folds::default(), 0, 4, 10);
+        let ranges: Vec<(usize, usize, usize)> = rows.iter().map(|r| (r.line, r.start, r.end)).collect();
+        assert_eq!(ranges, vec![(0, 0, 4), (0, 4, 8), (0, 8, 10), (1, 0, 0), (2, 0, 2)]);
+        assert!(rows[2].ends_line && !rows[1].ends_line);
+    }
+
+    #[test]
+    fn test_wrap_boundary_belongs_to_next_row() {
+        let buf = lines(&["abcdefgh"]);
+        let rows = rows_from(&buf, &Folds::default(), 0, 4, 10);
+        assert_eq!(row_containing(&rows, TextPosition { line: 0, column: 4 }), Some(1));
+        assert_eq!(row_containing(&rows, TextPosition { line: 0, column: 8 }), Some(1));
+    }
+
+    #[test]
+    fn test_move_vertically_through_visual_rows() {
+        let buf = lines(&["abcdefghij", "xy"]);
+        let start = TextPosition { line: 0, column: 1 };
+
+        let down = move_vertically(&buf, &Folds::default(), start, 4, true);
+        assert_eq!(down, TextPosition { line: 0, column: 5 });
+
+        let down = move_vertically(&buf, &Folds::default(), down, 4, true);
+        assert_eq!(down, TextPosition { line: 0, column: 9 });
+
+        // Next logical line is shorter, so the column is clamped
+        let down = move_vertically(&buf, &Folds::default(), TextPosition { line: 0, column: 10 }, 4, true);
+        assert_eq!(down, TextPosition { line: 1, column: 2 });
+
+        let up = move_vertically(&buf, &Folds::default(), TextPosition { line: 1, column: 1 }, 4, false);
+        assert_eq!(up, TextPosition { line: 0, column: 9 });
+    }
+}
@@ -1,6 +1,20 @@
 // src/gui/app.rs
-use super::{editor::EditorPanel, state::EditorState, themes};
+use super::{
+    diff::{DiffOutcome, DiffPanel},
+    editor::EditorPanel,
+    preview::MarkdownPreview,
+    state::EditorState,
+    themes,
+};
 use crate::core::actions::Action;
+use crate::core::compare::{Comparison, Source};
+use crate::core::data::{self, Format};
+use crate::core::editor::LineTransform;
+use crate::core::goto::parse_target;
+use crate::core::lsp::Severity;
+use crate::core::panes::SplitDirection;
+use crate::core::selection::TextPosition;
+use crate::core::tabs::TabManager;
 use crate::core::updater::UpdateInfo;
 use egui::{Context, ViewportCommand};
 
@@ -9,21 +23,103 @@ pub struct QuickNotepadApp {
     show_shortcuts: bool,
     show_save_dialog: bool,
     save_filename: String,
+    show_goto_dialog: bool,
+    goto_input: String,
+    show_bookmarks_dialog: bool,
+    bookmark_name: String,
+    show_rename_dialog: bool,
+    rename_input: String,
+    show_filter_dialog: bool,
+    filter_input: String,
+    show_convert_dialog: bool,
+    show_diagnostics: bool,
+    show_markdown_preview: bool,
+    markdown_preview: MarkdownPreview,
+    // Two texts compared side by side, shown instead of the editor while open
+    diff: Option<DiffPanel>,
+    show_compare_dialog: bool,
+    // The git change at the cursor, from Alt+G
+    hunk_diff: Option<Vec<String>>,
     dialog_has_focus: bool,
     show_update_dialog: bool,
     update_info: Option<UpdateInfo>,
 }
 
 impl QuickNotepadApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>, file_path: Option<String>) -> Self {
-        Self {
-            state: EditorState::new(file_path),
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        file_path: Option<String>,
+        at: Option<TextPosition>,
+        compare: Option<String>,
+    ) -> Self {
+        // Lets the Markdown preview show images from local files
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+        // `quick --gui --diff a b` opens just the two files, leaving the saved session alone
+        let comparing = file_path.as_deref().zip(compare.as_deref()).map(|(left, right)| {
+            TabManager::only_files(&[left, right]).map_err(|e| format!("Cannot compare {} and {}: {}", left, right, e))
+        });
+        let comparing_files = matches!(comparing, Some(Ok(_)));
+        let mut compare_error = None;
+        let state = match comparing {
+            Some(Ok(tab_manager)) => EditorState::with_tabs(tab_manager, None),
+            Some(Err(e)) => {
+                compare_error = Some(e);
+                EditorState::new(file_path, at)
+            }
+            None => EditorState::new(file_path, at),
+        };
+        let mut app = Self {
+            state,
             show_shortcuts: false,
             show_save_dialog: false,
             save_filename: String::new(),
+            show_goto_dialog: false,
+            goto_input: String::new(),
+            show_bookmarks_dialog: false,
+            bookmark_name: String::new(),
+            show_rename_dialog: false,
+            rename_input: String::new(),
+            show_filter_dialog: false,
+            filter_input: String::new(),
+            show_convert_dialog: false,
+            show_diagnostics: false,
+            show_markdown_preview: false,
+            markdown_preview: MarkdownPreview::default(),
+            diff: None,
+            show_compare_dialog: false,
+            hunk_diff: None,
             dialog_has_focus: false,
             show_update_dialog: false,
             update_info: None,
+        };
+        if let Some(e) = compare_error {
+            app.state.set_message(e);
+        } else if comparing_files {
+            let tabs = &app.state.tab_manager.tabs;
+            let comparison = Comparison::new(Source::Tab(tabs[0].id), Source::Tab(tabs[1].id), tabs);
+            app.open_diff(comparison);
+        }
+        app
+    }
+
+    fn open_diff(&mut self, comparison: Result<Comparison, String>) {
+        match comparison {
+            Ok(comparison) => self.diff = Some(DiffPanel::new(comparison)),
+            Err(e) => self.state.set_message(e),
+        }
+    }
+
+    // This tab against the other pane's, or one picked from a list
+    fn compare_tabs(&mut self) {
+        let tabs = &self.state.tab_manager;
+        let current = tabs.current_tab().id;
+        match &self.state.split {
+            Some(split) if split.other.tab_id != current => {
+                let comparison = Comparison::new(Source::Tab(current), Source::Tab(split.other.tab_id), &tabs.tabs);
+                self.open_diff(comparison);
+            }
+            _ if tabs.tabs.len() < 2 => self.state.set_message("Only one tab open".to_string()),
+            _ => self.show_compare_dialog = true,
         }
     }
 
@@ -89,17 +185,231 @@ impl QuickNotepadApp {
                         ui.close();
                     }
 
+                    if ui.button("↪ Go to Line (Ctrl+L)").clicked() {
+                        self.handle_action(Action::GoToLine);
+                        ui.close();
+                    }
+
+                    if ui.button("⇔ Matching Bracket (Ctrl+B)").clicked() {
+                        self.handle_action(Action::JumpToBracket);
+                        ui.close();
+                    }
+
+                    if ui.button("⇔ Select to Bracket (Alt+B)").clicked() {
+                        self.handle_action(Action::SelectToBracket);
+                        ui.close();
+                    }
+
                     if ui.button("🔤 Select All (Ctrl+A)").clicked() {
                         self.handle_action(Action::SelectAll);
                         ui.close();
                     }
                 });
 
+                ui.menu_button("Lines", |ui| {
+                    if ui.button("Duplicate Line (Alt+C)").clicked() {
+                        self.handle_action(Action::DuplicateLine);
+                        ui.close();
+                    }
+
+                    if ui.button("Move Line Up (Alt+Up)").clicked() {
+                        self.handle_action(Action::MoveLineUp);
+                        ui.close();
+                    }
+
+                    if ui.button("Move Line Down (Alt+Down)").clicked() {
+                        self.handle_action(Action::MoveLineDown);
+                        ui.close();
+                    }
+
+                    if ui.button("Delete Line (Ctrl+K)").clicked() {
+                        self.handle_action(Action::DeleteLine);
+                        ui.close();
+                    }
+
+                    if ui.button("Join Lines (Alt+J)").clicked() {
+                        self.handle_action(Action::JoinLines);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Sort Lines (Alt+S)").clicked() {
+                        self.handle_action(Action::SortLines);
+                        ui.close();
+                    }
+
+                    if ui.button("Reverse Lines (Alt+R)").clicked() {
+                        self.handle_action(Action::ReverseLines);
+                        ui.close();
+                    }
+
+                    if ui.button("Unique Lines (Alt+U)").clicked() {
+                        self.handle_action(Action::UniqueLines);
+                        ui.close();
+                    }
+
+                    if ui.button("Shuffle Lines (Alt+H)").clicked() {
+                        self.handle_action(Action::ShuffleLines);
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Bookmarks", |ui| {
+                    if ui.button("★ Toggle Bookmark (Alt+M)").clicked() {
+                        self.handle_action(Action::ToggleBookmark);
+                        ui.close();
+                    }
+                    if ui.button("✏ Name Bookmark... (Alt+Shift+M)").clicked() {
+                        self.handle_action(Action::NameBookmark);
+                        ui.close();
+                    }
+                    if ui.button("⏷ Next Bookmark (Alt+N)").clicked() {
+                        self.handle_action(Action::NextBookmark);
+                        ui.close();
+                    }
+                    if ui.button("⏶ Previous Bookmark (Alt+P)").clicked() {
+                        self.handle_action(Action::PrevBookmark);
+                        ui.close();
+                    }
+                    if ui.button("☰ All Bookmarks... (Alt+K)").clicked() {
+                        self.handle_action(Action::ListBookmarks);
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Code", |ui| {
+                    if ui.button("ℹ Hover Info (Alt+I)").clicked() {
+                        self.handle_action(Action::Hover);
+                        ui.close();
+                    }
+                    if ui.button("➜ Go to Definition (F12)").clicked() {
+                        self.handle_action(Action::GoToDefinition);
+                        ui.close();
+                    }
+                    if ui.button("✏ Rename Symbol... (Ctrl+R)").clicked() {
+                        self.handle_action(Action::Rename);
+                        ui.close();
+                    }
+                    if ui.button("⚠ Diagnostics (Ctrl+E)").clicked() {
+                        self.handle_action(Action::ListDiagnostics);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("✨ Format Document (Alt+Q)").clicked() {
+                        self.handle_action(Action::FormatDocument);
+                        ui.close();
+                    }
+                    if ui.button("⚙ Filter Through Command... (Alt+X)").clicked() {
+                        self.handle_action(Action::FilterSelection);
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Data", |ui| {
+                    if ui.button("✔ Validate JSON / TOML / YAML (Alt+V)").clicked() {
+                        self.handle_action(Action::ValidateData);
+                        ui.close();
+                    }
+                    if ui.button("⬍ Pretty-print JSON (Alt+T)").clicked() {
+                        self.handle_action(Action::PrettyPrintJson);
+                        ui.close();
+                    }
+                    if ui.button("⬌ Minify JSON (Alt+Shift+T)").clicked() {
+                        self.handle_action(Action::MinifyJson);
+                        ui.close();
+                    }
+                    if ui.button("⇅ Sort JSON Keys (Alt+O)").clicked() {
+                        self.handle_action(Action::SortJsonKeys);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    for format in Format::ALL {
+                        if ui.button(format!("➜ Convert to {} (new tab)", format.name())).clicked() {
+                            self.state.convert_data(format);
+                            ui.close();
+                        }
+                    }
+                });
+
                 ui.menu_button("View", |ui| {
                     if ui.button("⌨ Shortcuts").clicked() {
                         self.show_shortcuts = !self.show_shortcuts;
                         ui.close();
                     }
+                    if ui.button("↩ Soft Wrap (Alt+Z)").clicked() {
+                        self.handle_action(Action::ToggleSoftWrap);
+                        ui.close();
+                    }
+                    let is_markdown = self.state.markdown_source().is_some();
+                    let preview_label = if self.show_markdown_preview { "👁 Hide Markdown Preview" } else { "👁 Markdown Preview" };
+                    if ui.add_enabled(is_markdown, egui::Button::new(preview_label)).clicked() {
+                        self.show_markdown_preview = !self.show_markdown_preview;
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("◫ Split Side by Side (Alt+W)").clicked() {
+                        self.handle_action(Action::SplitSideBySide);
+                        ui.close();
+                    }
+                    if ui.button("⊟ Split Stacked (Alt+Shift+W)").clicked() {
+                        self.handle_action(Action::SplitStacked);
+                        ui.close();
+                    }
+                    let is_split = self.state.split.is_some();
+                    if ui.add_enabled(is_split, egui::Button::new("⇄ Focus Other Pane (Ctrl+O)")).clicked() {
+                        self.handle_action(Action::FocusOtherPane);
+                        ui.close();
+                    }
+                    if ui.add_enabled(is_split, egui::Button::new("✕ Close Split (Ctrl+W)")).clicked() {
+                        self.handle_action(Action::CloseSplit);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("⇆ Compare with Saved (Alt+Shift+D)").clicked() {
+                        self.handle_action(Action::CompareWithSaved);
+                        ui.close();
+                    }
+                    if ui.button("⇆ Compare Tabs (Alt+Shift+C)").clicked() {
+                        self.handle_action(Action::CompareTabs);
+                        ui.close();
+                    }
+                    let in_git = self.state.git().is_some();
+                    if ui.add_enabled(in_git, egui::Button::new("± Show Git Change (Alt+G)")).clicked() {
+                        self.handle_action(Action::ShowHunkDiff);
+                        ui.close();
+                    }
+                    if ui.add_enabled(in_git, egui::Button::new("↶ Revert Git Change (Alt+Shift+G)")).clicked() {
+                        self.handle_action(Action::RevertHunk);
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("▸ Fold (Alt+F)").clicked() {
+                        self.handle_action(Action::Fold);
+                        ui.close();
+                    }
+                    if ui.button("▾ Unfold (Alt+E)").clicked() {
+                        self.handle_action(Action::Unfold);
+                        ui.close();
+                    }
+                    if ui.button("▸ Fold All (Alt+Shift+F)").clicked() {
+                        self.handle_action(Action::FoldAll);
+                        ui.close();
+                    }
+                    if ui.button("▾ Unfold All (Alt+Shift+E)").clicked() {
+                        self.handle_action(Action::UnfoldAll);
+                        ui.close();
+                    }
                 });
 
                 ui.menu_button("Help", |ui| {
@@ -132,14 +442,36 @@ impl QuickNotepadApp {
                     ""
                 };
                 ui.label(format!("{}{}", filename, dirty));
+                if let Some(git) = self.state.git() {
+                    ui.label(egui::RichText::new(format!("⎇ {}", git.branch)).color(egui::Color32::from_rgb(190, 140, 220)));
+                }
 
                 ui.separator();
 
-                ui.label(format!(
-                    "Ln {}, Col {}",
-                    self.state.cursor_pos.line + 1,
-                    self.state.cursor_pos.column + 1
-                ));
+                let cursor = self.state.editor().cursor;
+                ui.label(format!("Ln {}, Col {}", cursor.line + 1, cursor.column + 1));
+                if let Some(mark) = self.state.editor().bookmarks.at(cursor.line) {
+                    ui.label(format!("★ {}", mark.name));
+                }
+
+                let diagnostics = self.state.diagnostics();
+                let count = |severity| diagnostics.iter().filter(|d| d.severity == severity).count();
+                let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+                if errors + warnings > 0 {
+                    ui.separator();
+                    if ui.link(format!("✖ {}  ⚠ {}", errors, warnings)).clicked() {
+                        self.show_diagnostics = true;
+                    }
+                }
+                if let Some(command) = self.state.after_save_command() {
+                    ui.separator();
+                    ui.spinner();
+                    ui.label(format!("Running `{}`", command));
+                }
+                if let Some(message) = self.state.message() {
+                    ui.separator();
+                    ui.label(message);
+                }
 
                 ui.separator();
                 let version = env!("CARGO_PKG_VERSION");
@@ -207,6 +539,95 @@ impl QuickNotepadApp {
                     egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::F),
                     Action::Search,
                 ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::L),
+                    Action::GoToLine,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::B),
+                    Action::JumpToBracket,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::B),
+                    Action::SelectToBracket,
+                ),
+                // Alt+Shift before Alt, which would otherwise swallow the shifted key
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::D),
+                    Action::CompareWithSaved,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::C),
+                    Action::CompareTabs,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::G),
+                    Action::RevertHunk,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::G),
+                    Action::ShowHunkDiff,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::W),
+                    Action::SplitStacked,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::W),
+                    Action::SplitSideBySide,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::O),
+                    Action::FocusOtherPane,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::W),
+                    Action::CloseSplit,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::F),
+                    Action::FoldAll,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::E),
+                    Action::UnfoldAll,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::M),
+                    Action::NameBookmark,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT | egui::Modifiers::SHIFT, egui::Key::T),
+                    Action::MinifyJson,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::F),
+                    Action::Fold,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::E),
+                    Action::Unfold,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::M),
+                    Action::ToggleBookmark,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::N),
+                    Action::NextBookmark,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::P),
+                    Action::PrevBookmark,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::K),
+                    Action::ListBookmarks,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Z),
+                    Action::ToggleSoftWrap,
+                ),
                 (
                     egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::A),
                     Action::SelectAll,
@@ -215,9 +636,128 @@ impl QuickNotepadApp {
                     egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::U),
                     Action::CheckUpdate,
                 ),
+                (
+                    egui::KeyboardShortcut::new(
+                        egui::Modifiers::CTRL | egui::Modifiers::ALT,
+                        egui::Key::ArrowUp,
+                    ),
+                    Action::AddCursorAbove,
+                ),
+                (
+                    egui::KeyboardShortcut::new(
+                        egui::Modifiers::CTRL | egui::Modifiers::ALT,
+                        egui::Key::ArrowDown,
+                    ),
+                    Action::AddCursorBelow,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::D),
+                    Action::AddNextOccurrence,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::D),
+                    Action::SelectAllOccurrences,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::L),
+                    Action::SplitSelectionIntoLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::C),
+                    Action::DuplicateLine,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowUp),
+                    Action::MoveLineUp,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::ArrowDown),
+                    Action::MoveLineDown,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::K),
+                    Action::DeleteLine,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::J),
+                    Action::JoinLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::S),
+                    Action::SortLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::R),
+                    Action::ReverseLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::U),
+                    Action::UniqueLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::H),
+                    Action::ShuffleLines,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Space),
+                    Action::Complete,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::I),
+                    Action::Hover,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F12),
+                    Action::GoToDefinition,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::R),
+                    Action::Rename,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::E),
+                    Action::ListDiagnostics,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Q),
+                    Action::FormatDocument,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::X),
+                    Action::FilterSelection,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::V),
+                    Action::ValidateData,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::T),
+                    Action::PrettyPrintJson,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::O),
+                    Action::SortJsonKeys,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Y),
+                    Action::ConvertData,
+                ),
+                (
+                    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Escape),
+                    Action::CollapseCursors,
+                ),
             ];
 
             for (shortcut, action) in actions {
+                // egui ignores extra Shift; arrow shortcuts must leave Alt+Shift+arrows
+                // (block selection) to the editor
+                let is_arrow = matches!(
+                    shortcut.logical_key,
+                    egui::Key::ArrowUp | egui::Key::ArrowDown | egui::Key::ArrowLeft | egui::Key::ArrowRight
+                );
+                if is_arrow && i.modifiers.shift != shortcut.modifiers.shift {
+                    continue;
+                }
                 if i.consume_shortcut(&shortcut) {
                     self.handle_action(action);
                 }
@@ -261,16 +801,8 @@ impl QuickNotepadApp {
             Action::Quit => {
                 // Handle in update loop
             }
-            Action::Undo => {
-                if let Some(op) = self.state.current_edit_history().undo() {
-                    op.edit.reverse(&mut self.state.current_buffer_mut().lines);
-                }
-            }
-            Action::Redo => {
-                if let Some(op) = self.state.current_edit_history().redo() {
-                    op.edit.apply(&mut self.state.current_buffer_mut().lines);
-                }
-            }
+            Action::Undo => self.state.undo(),
+            Action::Redo => self.state.redo(),
             Action::Copy => {
                 self.state.copy_selection();
             }
@@ -284,23 +816,133 @@ impl QuickNotepadApp {
                 self.state.search_active = true;
                 self.dialog_has_focus = true;
             }
+            Action::GoToLine => {
+                self.show_goto_dialog = true;
+                self.dialog_has_focus = true;
+            }
+            Action::ToggleSoftWrap => {
+                self.state.soft_wrap = !self.state.soft_wrap;
+            }
             Action::SelectAll => {
                 self.state.select_all();
             }
-            Action::SwitchTab(num) => {
-                let _ = self.state.tab_manager.switch_to_tab(num);
+            Action::AddCursorAbove => {
+                self.state.editor_mut().add_caret_vertically(false);
             }
-            Action::CheckUpdate => {
-                self.check_for_updates_gui();
+            Action::AddCursorBelow => {
+                self.state.editor_mut().add_caret_vertically(true);
             }
-            _ => {}
-        }
-    }
-
-    fn show_save_dialog(&mut self, ctx: &Context) {
-        let mut close_dialog = false;
-
-        egui::Window::new("Save As")
+            Action::AddNextOccurrence => {
+                self.state.editor_mut().add_next_occurrence();
+            }
+            Action::SelectAllOccurrences => {
+                self.state.editor_mut().select_all_occurrences();
+            }
+            Action::SplitSelectionIntoLines => {
+                self.state.editor_mut().split_selection_into_lines();
+            }
+            Action::DuplicateLine => self.state.edit_with(|editor| editor.duplicate()),
+            Action::MoveLineUp => self.state.edit_with(|editor| editor.move_lines(false)),
+            Action::MoveLineDown => self.state.edit_with(|editor| editor.move_lines(true)),
+            Action::DeleteLine => self.state.edit_with(|editor| editor.delete_lines()),
+            Action::JoinLines => self.state.edit_with(|editor| editor.join_lines()),
+            Action::SortLines => self.state.edit_with(|editor| editor.transform_lines(LineTransform::Sort)),
+            Action::ReverseLines => self.state.edit_with(|editor| editor.transform_lines(LineTransform::Reverse)),
+            Action::UniqueLines => self.state.edit_with(|editor| editor.transform_lines(LineTransform::Unique)),
+            Action::ShuffleLines => self.state.edit_with(|editor| editor.transform_lines(LineTransform::Shuffle)),
+            Action::CollapseCursors => {
+                self.state.completion = None;
+                self.state.editor_mut().collapse_carets();
+            }
+            Action::JumpToBracket => {
+                let matcher = self.state.bracket_matcher();
+                self.state.editor_mut().jump_to_matching_bracket(&matcher);
+                self.state.ensure_cursor_visible(None);
+            }
+            Action::SelectToBracket => {
+                let matcher = self.state.bracket_matcher();
+                self.state.editor_mut().select_to_matching_bracket(&matcher);
+                self.state.ensure_cursor_visible(None);
+            }
+            Action::Fold => {
+                let matcher = self.state.bracket_matcher();
+                self.state.editor_mut().fold_at_cursor(&matcher);
+                self.state.ensure_cursor_visible(None);
+            }
+            Action::Unfold => {
+                self.state.editor_mut().unfold_at_cursor();
+            }
+            Action::FoldAll => {
+                let matcher = self.state.bracket_matcher();
+                self.state.editor_mut().fold_all(&matcher);
+                self.state.ensure_cursor_visible(None);
+            }
+            Action::UnfoldAll => {
+                self.state.editor_mut().unfold_all();
+            }
+            Action::ToggleBookmark => {
+                self.state.editor_mut().toggle_bookmark();
+            }
+            Action::NextBookmark => {
+                self.state.editor_mut().jump_to_bookmark(true);
+                self.state.ensure_cursor_visible(None);
+            }
+            Action::PrevBookmark => {
+                self.state.editor_mut().jump_to_bookmark(false);
+                self.state.ensure_cursor_visible(None);
+            }
+            // Naming and listing share one window
+            Action::NameBookmark | Action::ListBookmarks => {
+                self.show_bookmarks_dialog = true;
+                self.dialog_has_focus = true;
+            }
+            Action::Complete => self.state.complete_with_server(),
+            Action::Hover => self.state.show_hover(),
+            Action::GoToDefinition => self.state.go_to_definition(),
+            Action::Rename => {
+                self.show_rename_dialog = true;
+                self.dialog_has_focus = true;
+            }
+            Action::ListDiagnostics => {
+                self.show_diagnostics = true;
+            }
+            Action::FormatDocument => self.state.format_document(),
+            Action::ValidateData => self.state.validate_data(),
+            Action::PrettyPrintJson => self.state.rewrite_json(data::pretty_json),
+            Action::MinifyJson => self.state.rewrite_json(data::minify_json),
+            Action::SortJsonKeys => self.state.rewrite_json(data::sort_json_keys),
+            Action::ConvertData => self.show_convert_dialog = true,
+            Action::FilterSelection => {
+                self.show_filter_dialog = true;
+                self.dialog_has_focus = true;
+            }
+            Action::SwitchTab(num) => {
+                self.state.completion = None;
+                let _ = self.state.tab_manager.switch_to_tab(num);
+            }
+            Action::SplitSideBySide => self.state.split_screen(SplitDirection::Vertical),
+            Action::SplitStacked => self.state.split_screen(SplitDirection::Horizontal),
+            Action::FocusOtherPane => self.state.focus_other_pane(),
+            Action::CloseSplit => self.state.close_split(),
+            Action::CompareWithSaved => {
+                let tabs = &self.state.tab_manager;
+                let comparison = Comparison::with_saved(&tabs.tabs, tabs.active_tab_index);
+                self.open_diff(comparison);
+            }
+            Action::CompareTabs => self.compare_tabs(),
+            Action::ShowHunkDiff => self.hunk_diff = self.state.hunk_diff(),
+            Action::RevertHunk => self.state.revert_hunk(),
+            Action::CheckUpdate => {
+                self.check_for_updates_gui();
+            }
+            _ => {}
+        }
+    }
+
+    fn show_save_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+
+        egui::Window::new("Save As")
             .collapsible(false)
             .resizable(false)
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
@@ -345,6 +987,396 @@ impl QuickNotepadApp {
         }
     }
 
+    fn show_goto_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+        let mut jump = false;
+
+        egui::Window::new("Go to Line")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Line[:col] or ±N:");
+                    let response = ui.text_edit_singleline(&mut self.goto_input);
+
+                    if self.dialog_has_focus {
+                        response.request_focus();
+                    }
+
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        jump = true;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Go").clicked() {
+                        jump = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        close_dialog = true;
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if jump {
+            let cursor = self.state.editor().cursor;
+            // Unparseable input leaves the dialog open so it can be corrected
+            if let Some(target) = parse_target(&self.goto_input, cursor) {
+                self.state.go_to(target);
+                close_dialog = true;
+            }
+        }
+
+        if close_dialog {
+            self.show_goto_dialog = false;
+            self.goto_input.clear();
+            self.dialog_has_focus = false;
+        }
+    }
+
+    // Name a bookmark on the cursor line, or pick one from any tab to jump to
+    fn show_bookmarks_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+        let mut set_name = false;
+        let mut jump_to: Option<(usize, usize)> = None;
+        let marks = self.state.tab_manager.bookmarks();
+
+        egui::Window::new("Bookmarks")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Name for line {}:", self.state.editor().cursor.line + 1));
+                    let response = ui.text_edit_singleline(&mut self.bookmark_name);
+
+                    if self.dialog_has_focus {
+                        response.request_focus();
+                    }
+
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        set_name = true;
+                    }
+                    if ui.button("Set").clicked() {
+                        set_name = true;
+                    }
+                });
+
+                ui.separator();
+
+                if marks.is_empty() {
+                    ui.label("No bookmarks yet");
+                }
+                for (tab, mark) in &marks {
+                    let filename = self.state.tab_manager.tabs[*tab].filename.as_deref().unwrap_or("[No Name]");
+                    if ui.button(format!("★ {}   {}:{}", mark.name, filename, mark.line + 1)).clicked() {
+                        jump_to = Some((*tab, mark.line));
+                    }
+                }
+
+                ui.separator();
+                if ui.button("Close").clicked() {
+                    close_dialog = true;
+                }
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if set_name && !self.bookmark_name.trim().is_empty() {
+            let name = std::mem::take(&mut self.bookmark_name);
+            self.state.editor_mut().name_bookmark(&name);
+            close_dialog = true;
+        }
+
+        if let Some((tab, line)) = jump_to {
+            self.state.completion = None;
+            let _ = self.state.tab_manager.switch_to_tab(tab + 1);
+            self.state.go_to(TextPosition { line, column: 0 });
+            close_dialog = true;
+        }
+
+        if close_dialog {
+            self.show_bookmarks_dialog = false;
+            self.bookmark_name.clear();
+            self.dialog_has_focus = false;
+        }
+    }
+
+    fn show_rename_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+        let mut rename = false;
+
+        egui::Window::new("Rename Symbol")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("New name:");
+                    let response = ui.text_edit_singleline(&mut self.rename_input);
+
+                    if self.dialog_has_focus {
+                        response.request_focus();
+                    }
+
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        rename = true;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Rename").clicked() {
+                        rename = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        close_dialog = true;
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if rename && !self.rename_input.trim().is_empty() {
+            let new_name = self.rename_input.trim().to_string();
+            self.state.rename_symbol(&new_name);
+            close_dialog = true;
+        }
+
+        if close_dialog {
+            self.show_rename_dialog = false;
+            self.rename_input.clear();
+            self.dialog_has_focus = false;
+        }
+    }
+
+    fn show_filter_dialog(&mut self, ctx: &Context) {
+        let mut close_dialog = false;
+        let mut run = false;
+
+        egui::Window::new("Filter Through Command")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Command:");
+                    let response = ui.text_edit_singleline(&mut self.filter_input);
+
+                    if self.dialog_has_focus {
+                        response.request_focus();
+                    }
+
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        run = true;
+                    }
+                });
+                ui.label("Replaces the selection (or the current line) with the command's output");
+
+                ui.horizontal(|ui| {
+                    if ui.button("Run").clicked() {
+                        run = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        close_dialog = true;
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if run && !self.filter_input.trim().is_empty() {
+            let command = self.filter_input.trim().to_string();
+            self.state.filter_selection(&command);
+            close_dialog = true;
+        }
+
+        if close_dialog {
+            self.show_filter_dialog = false;
+            self.filter_input.clear();
+            self.dialog_has_focus = false;
+        }
+    }
+
+    // Pick the format to convert the document to
+    fn show_convert_dialog(&mut self, ctx: &Context) {
+        let mut convert_to = None;
+        let mut close_dialog = false;
+
+        egui::Window::new("Convert To")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Opens the converted document in a new tab");
+                ui.horizontal(|ui| {
+                    for format in Format::ALL {
+                        if ui.button(format.name()).clicked() {
+                            convert_to = Some(format);
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close_dialog = true;
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if let Some(format) = convert_to {
+            self.state.convert_data(format);
+            close_dialog = true;
+        }
+
+        if close_dialog {
+            self.show_convert_dialog = false;
+        }
+    }
+
+    // Pick the tab to compare the current one with
+    fn show_compare_dialog(&mut self, ctx: &Context) {
+        let mut compare_with = None;
+        let mut close_dialog = false;
+
+        egui::Window::new("Compare With")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let tabs = &self.state.tab_manager;
+                for (index, tab) in tabs.tabs.iter().enumerate() {
+                    if index == tabs.active_tab_index {
+                        continue;
+                    }
+                    let name = tab.filename.as_deref().unwrap_or("untitled");
+                    if ui.button(format!("{}: {}", index + 1, name)).clicked() {
+                        compare_with = Some(tab.id);
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    close_dialog = true;
+                }
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_dialog = true;
+                }
+            });
+
+        if let Some(other) = compare_with {
+            let tabs = &self.state.tab_manager;
+            let comparison = Comparison::new(Source::Tab(tabs.current_tab().id), Source::Tab(other), &tabs.tabs);
+            self.open_diff(comparison);
+            close_dialog = true;
+        }
+
+        if close_dialog {
+            self.show_compare_dialog = false;
+        }
+    }
+
+    // Every diagnostic of the current file; clicking one jumps to it
+    fn show_diagnostics_window(&mut self, ctx: &Context) {
+        let mut jump_to = None;
+
+        egui::Window::new("Diagnostics")
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let diagnostics = self.state.diagnostics();
+                if diagnostics.is_empty() {
+                    ui.label("No diagnostics");
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for diagnostic in diagnostics {
+                        let color = match diagnostic.severity {
+                            Severity::Error => egui::Color32::from_rgb(230, 90, 80),
+                            Severity::Warning => egui::Color32::from_rgb(230, 190, 70),
+                            _ => egui::Color32::from_rgb(120, 170, 220),
+                        };
+                        let text = egui::RichText::new(diagnostic.summary()).color(color).monospace();
+                        if ui.button(text).clicked() {
+                            jump_to = Some(diagnostic.start);
+                        }
+                    }
+                });
+
+                if ui.button("Close").clicked() {
+                    self.show_diagnostics = false;
+                }
+            });
+
+        if let Some(pos) = jump_to {
+            self.state.completion = None;
+            self.state.go_to(pos);
+        }
+    }
+
+    // Documentation from Alt+I, until the cursor moves
+    fn show_hover_window(&mut self, ctx: &Context) {
+        let cursor = self.state.editor().cursor;
+        if self.state.hover.as_ref().is_some_and(|(pos, _)| *pos != cursor) {
+            self.state.hover = None;
+        }
+        let Some((_, text)) = &self.state.hover else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Hover")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    ui.label(egui::RichText::new(text).monospace());
+                });
+            });
+        if !open {
+            self.state.hover = None;
+        }
+    }
+
+    // The git change from Alt+G, removed lines red and added ones green
+    fn show_hunk_diff_window(&mut self, ctx: &Context) {
+        let Some(lines) = &self.hunk_diff else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Git Change")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for line in lines {
+                        let color = match line.chars().next() {
+                            Some('-') => egui::Color32::from_rgb(230, 110, 100),
+                            Some('+') => egui::Color32::from_rgb(120, 200, 120),
+                            _ => egui::Color32::from_rgb(120, 170, 220),
+                        };
+                        ui.label(egui::RichText::new(line).monospace().color(color));
+                    }
+                });
+            });
+        if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.hunk_diff = None;
+        }
+    }
+
     fn show_shortcuts_window(&mut self, ctx: &Context) {
         use crate::core::shortcuts::Shortcuts;
 
@@ -462,22 +1494,119 @@ impl QuickNotepadApp {
 impl eframe::App for QuickNotepadApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
         themes::apply_theme(ctx);
+        // Diagnostics, post-save results and git reads arrive on their own, so keep looking
+        // while a language server, the post-save command or git runs
+        let after_save_running = self.state.poll_after_save();
+        let git_loading = self.state.sync_git();
+        if self.state.sync_lsp() || after_save_running || git_loading {
+            ctx.request_repaint_after(std::time::Duration::from_millis(200));
+        }
+        if let Some(diff) = self.diff.as_mut() {
+            if let DiffOutcome::Close = diff.handle_keys(ctx, &mut self.state.tab_manager.tabs) {
+                self.diff = None;
+            }
+        }
         self.handle_shortcuts(ctx);
         self.menu_bar(ctx);
         self.status_bar(ctx);
 
-        if !self.state.search_active && !self.show_save_dialog && !self.show_update_dialog {
+        if !self.state.search_active
+            && !self.show_save_dialog
+            && !self.show_goto_dialog
+            && !self.show_bookmarks_dialog
+            && !self.show_rename_dialog
+            && !self.show_filter_dialog
+            && !self.show_update_dialog
+        {
             self.dialog_has_focus = false;
         }
 
+        // Side by side with the editor, taking half the window to start with
+        if let Some((text, dir)) = self.state.markdown_source().filter(|_| self.show_markdown_preview) {
+            let top_line = self.state.scroll_offset.0;
+            egui::SidePanel::right("markdown_preview")
+                .resizable(true)
+                .default_width(ctx.content_rect().width() / 2.0)
+                .show(ctx, |ui| self.markdown_preview.show(ui, text, dir.as_deref(), top_line));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            EditorPanel::new(&mut self.state, !self.dialog_has_focus).show(ui);
+            if let Some(diff) = self.diff.as_mut() {
+                match diff.show(ui, &mut self.state.tab_manager.tabs) {
+                    DiffOutcome::Open => {}
+                    DiffOutcome::Close => self.diff = None,
+                    DiffOutcome::Failed(e) => {
+                        self.diff = None;
+                        self.state.set_message(e);
+                    }
+                }
+                return;
+            }
+            // A closed tab in the other pane ends the split
+            if let Some(split) = &self.state.split {
+                if self.state.tab_manager.index_of(split.other.tab_id).is_none() {
+                    self.state.close_split();
+                }
+            }
+            let Some(split) = self.state.split.clone() else {
+                EditorPanel::new(&mut self.state, !self.dialog_has_focus).show(ui);
+                return;
+            };
+            let (first, divider, second) = split_rect(ui.available_rect_before_wrap(), split.direction);
+            let (focused_rect, other_rect) = if split.focus_second { (second, first) } else { (first, second) };
+
+            let mut child = ui.new_child(egui::UiBuilder::new().max_rect(focused_rect));
+            EditorPanel::new(&mut self.state, !self.dialog_has_focus).show(&mut child);
+            ui.painter().rect_filled(divider, 0.0, ui.visuals().widgets.noninteractive.bg_stroke.color);
+
+            // The pane without focus only shows, after this frame's edits moved its carets;
+            // a click in it moves focus there
+            self.state.track_other_pane();
+            let Some(other) = self.state.split.as_ref().map(|split| split.other.clone()) else {
+                return;
+            };
+            let mut child = ui.new_child(egui::UiBuilder::new().max_rect(other_rect));
+            if EditorPanel::other_pane(&mut self.state, other).show(&mut child).is_pointer_button_down_on() {
+                self.state.focus_other_pane();
+                ctx.request_repaint();
+            }
         });
 
         if self.show_save_dialog {
             self.show_save_dialog(ctx);
         }
 
+        if self.show_goto_dialog {
+            self.show_goto_dialog(ctx);
+        }
+
+        if self.show_bookmarks_dialog {
+            self.show_bookmarks_dialog(ctx);
+        }
+
+        if self.show_rename_dialog {
+            self.show_rename_dialog(ctx);
+        }
+
+        if self.show_filter_dialog {
+            self.show_filter_dialog(ctx);
+        }
+
+        if self.show_convert_dialog {
+            self.show_convert_dialog(ctx);
+        }
+
+        if self.show_compare_dialog {
+            self.show_compare_dialog(ctx);
+        }
+
+        if self.show_diagnostics {
+            self.show_diagnostics_window(ctx);
+        }
+
+        self.show_hover_window(ctx);
+        self.show_hunk_diff_window(ctx);
+
         if self.show_shortcuts {
             self.show_shortcuts_window(ctx);
         }
@@ -487,3 +1616,20 @@ impl eframe::App for QuickNotepadApp {
         }
     }
 }
+
+// The two panes of a split editor and the line between them: (first, divider, second)
+fn split_rect(rect: egui::Rect, direction: SplitDirection) -> (egui::Rect, egui::Rect, egui::Rect) {
+    const DIVIDER: f32 = 2.0;
+    match direction {
+        SplitDirection::Vertical => {
+            let (first, rest) = rect.split_left_right_at_x(rect.center().x - DIVIDER / 2.0);
+            let (divider, second) = rest.split_left_right_at_x(rest.left() + DIVIDER);
+            (first, divider, second)
+        }
+        SplitDirection::Horizontal => {
+            let (first, rest) = rect.split_top_bottom_at_y(rect.center().y - DIVIDER / 2.0);
+            let (divider, second) = rest.split_top_bottom_at_y(rest.top() + DIVIDER);
+            (first, divider, second)
+        }
+    }
+}
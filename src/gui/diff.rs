@@ -0,0 +1,190 @@
+// src/gui/diff.rs
+// Two texts compared side by side in place of the editor: the lines of a change tinted,
+// the words that changed within them picked out, gaps where a side has no line
+use crate::core::compare::{intra_line, Comparison, Row, Side};
+use crate::core::tabs::Tab;
+use egui::{text::LayoutJob, Color32, FontFamily, FontId, Key, Pos2, Rect, Sense, Stroke, TextFormat, Ui};
+use unicode_segmentation::UnicodeSegmentation;
+
+const ROW_HEIGHT: f32 = 20.0;
+const MARGIN_WIDTH: f32 = 40.0;
+const FONT_SIZE: f32 = 14.0;
+// Rows kept above a change jumped to
+const CONTEXT_ROWS: usize = 3;
+
+const REMOVED: Color32 = Color32::from_rgb(75, 35, 35);
+const REMOVED_WORDS: Color32 = Color32::from_rgb(140, 50, 50);
+const ADDED: Color32 = Color32::from_rgb(35, 65, 35);
+const ADDED_WORDS: Color32 = Color32::from_rgb(50, 120, 50);
+const GAP: Color32 = Color32::from_rgb(45, 40, 35);
+
+pub struct DiffPanel {
+    comparison: Comparison,
+    // A row to bring into view on the next frame
+    scroll_to: Option<usize>,
+    // Why the last copy didn't happen
+    message: Option<String>,
+}
+
+// What the panel asks of the app after a frame
+pub enum DiffOutcome {
+    Open,
+    Close,
+    // A compared tab was closed
+    Failed(String),
+}
+
+impl DiffPanel {
+    // Opens at the first change
+    pub fn new(comparison: Comparison) -> Self {
+        let scroll_to = comparison.current.map(|hunk| comparison.hunk_row(hunk));
+        Self { comparison, scroll_to, message: None }
+    }
+
+    // n / p move between changes, < and > copy the current one, Esc closes. Runs before the
+    // app's shortcuts so Esc isn't taken for collapsing carets.
+    pub fn handle_keys(&mut self, ctx: &egui::Context, tabs: &mut [Tab]) -> DiffOutcome {
+        let (escape, next, previous, copy) = ctx.input_mut(|i| {
+            let escape = i.consume_key(egui::Modifiers::NONE, Key::Escape);
+            let next = i.consume_key(egui::Modifiers::NONE, Key::N);
+            let previous = i.consume_key(egui::Modifiers::NONE, Key::P);
+            let copy = i.events.iter().find_map(|event| match event {
+                egui::Event::Text(text) if text == "<" => Some(Side::Left),
+                egui::Event::Text(text) if text == ">" => Some(Side::Right),
+                _ => None,
+            });
+            (escape, next, previous, copy)
+        });
+        if escape {
+            return DiffOutcome::Close;
+        }
+        if next || previous {
+            self.step(next);
+        }
+        if let Some(side) = copy {
+            self.copy(side, tabs);
+        }
+        DiffOutcome::Open
+    }
+
+    fn step(&mut self, forward: bool) {
+        self.scroll_to = self.comparison.step(forward);
+    }
+
+    fn copy(&mut self, side: Side, tabs: &mut [Tab]) {
+        self.message = self.comparison.copy_hunk(side, tabs).err();
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, tabs: &mut [Tab]) -> DiffOutcome {
+        // Edited elsewhere meanwhile (undo from the menu, a save hook)
+        if self.comparison.is_stale(tabs) {
+            if let Err(e) = self.comparison.refresh(tabs) {
+                return DiffOutcome::Failed(e);
+            }
+        }
+
+        let mut outcome = DiffOutcome::Open;
+        ui.horizontal(|ui| {
+            let has_changes = self.comparison.current.is_some();
+            if ui.add_enabled(has_changes, egui::Button::new("▲ Previous (P)")).clicked() {
+                self.step(false);
+            }
+            if ui.add_enabled(has_changes, egui::Button::new("▼ Next (N)")).clicked() {
+                self.step(true);
+            }
+            ui.separator();
+            if ui.add_enabled(has_changes, egui::Button::new("◀ Copy to Left (<)")).clicked() {
+                self.copy(Side::Left, tabs);
+            }
+            if ui.add_enabled(has_changes, egui::Button::new("Copy to Right (>) ▶")).clicked() {
+                self.copy(Side::Right, tabs);
+            }
+            ui.separator();
+            if ui.button("✕ Close (Esc)").clicked() {
+                outcome = DiffOutcome::Close;
+            }
+            ui.separator();
+            match self.comparison.current {
+                Some(current) => ui.label(format!("Change {} of {}", current + 1, self.comparison.hunks.len())),
+                None => ui.label("No differences"),
+            };
+            if let Some(message) = &self.message {
+                ui.colored_label(Color32::from_rgb(230, 90, 80), message);
+            }
+        });
+        ui.separator();
+
+        // Titles over the two halves
+        let width = ui.available_width();
+        ui.horizontal(|ui| {
+            let (left, right) = &self.comparison.titles;
+            ui.add_sized([width / 2.0 - 4.0, ROW_HEIGHT], egui::Label::new(egui::RichText::new(left).strong()));
+            ui.add_sized([width / 2.0 - 4.0, ROW_HEIGHT], egui::Label::new(egui::RichText::new(right).strong()));
+        });
+
+        let mut scroll = egui::ScrollArea::vertical().id_salt("diff_rows").auto_shrink(false);
+        if let Some(row) = self.scroll_to.take() {
+            scroll = scroll.vertical_scroll_offset(row.saturating_sub(CONTEXT_ROWS) as f32 * ROW_HEIGHT);
+        }
+        let comparison = &self.comparison;
+        scroll.show_rows(ui, ROW_HEIGHT, comparison.rows.len(), |ui, range| {
+            for row in &comparison.rows[range] {
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), ROW_HEIGHT), Sense::hover());
+                let half = rect.width() / 2.0;
+                let left = Rect::from_min_size(rect.min, egui::vec2(half - 1.0, ROW_HEIGHT));
+                let right = Rect::from_min_size(Pos2::new(rect.left() + half + 1.0, rect.top()), egui::vec2(half - 1.0, ROW_HEIGHT));
+                paint_side(ui, left, comparison, row, Side::Left);
+                paint_side(ui, right, comparison, row, Side::Right);
+                ui.painter().vline(rect.center().x, rect.y_range(), Stroke::new(1.0, GAP));
+            }
+        });
+        outcome
+    }
+}
+
+fn paint_side(ui: &Ui, rect: Rect, comparison: &Comparison, row: &Row, side: Side) {
+    let painter = ui.painter_at(rect);
+    let (line, lines, other) = match side {
+        Side::Left => (row.left, &comparison.lines.0, row.right),
+        Side::Right => (row.right, &comparison.lines.1, row.left),
+    };
+    let (tint, words) = match side {
+        Side::Left => (REMOVED, REMOVED_WORDS),
+        Side::Right => (ADDED, ADDED_WORDS),
+    };
+    let Some(line) = line else {
+        painter.rect_filled(rect, 0.0, GAP);
+        return;
+    };
+    let current = row.hunk.is_some() && row.hunk == comparison.current;
+    painter.text(
+        Pos2::new(rect.left() + 5.0, rect.top()),
+        egui::Align2::LEFT_TOP,
+        format!("{:>3}", line + 1),
+        FontId::monospace(12.0),
+        if current { Color32::YELLOW } else { Color32::from_rgb(200, 160, 100) },
+    );
+
+    let text = &lines[line];
+    let text_rect = Rect::from_min_max(Pos2::new(rect.left() + MARGIN_WIDTH, rect.top()), rect.max);
+    let font = FontId::new(FONT_SIZE, FontFamily::Monospace);
+    let color = ui.visuals().text_color();
+    let mut job = LayoutJob::default();
+    if row.hunk.is_none() {
+        job.append(text, 0.0, TextFormat::simple(font, color));
+    } else {
+        painter.rect_filled(text_rect, 0.0, tint);
+        // A changed line paired with one on the other side marks the words that differ
+        let changed = match (other, side) {
+            (Some(other), Side::Left) => intra_line(text, &comparison.lines.1[other]).0,
+            (Some(other), Side::Right) => intra_line(&comparison.lines.0[other], text).1,
+            (None, _) => Vec::new(),
+        };
+        for (column, grapheme) in text.graphemes(true).enumerate() {
+            let background = if changed.iter().any(|range| range.contains(&column)) { words } else { Color32::TRANSPARENT };
+            job.append(grapheme, 0.0, TextFormat { background, ..TextFormat::simple(font.clone(), color) });
+        }
+    }
+    let galley = painter.layout_job(job);
+    painter.galley(Pos2::new(text_rect.left(), text_rect.top() + 2.0), galley, color);
+}
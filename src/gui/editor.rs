@@ -1,7 +1,15 @@
 // editor.rs responsible for rendering the text editor panel in GUI mode, handling input and selection logic
 use super::state::EditorState;
-use crate::core::graphemes::wrap_text_to_width;
+use crate::core::brackets::BracketMatcher;
+use crate::core::editor::{Editor, Motion};
+use crate::core::folding;
+use crate::core::git::{self, LineChange};
+use crate::core::graphemes::{grapheme_len, grapheme_slice, visual_width};
+use crate::core::lsp::Severity;
+use crate::core::panes::PaneView;
 use crate::core::selection::{Selection, TextPosition};
+use crate::core::tabs::Tab;
+use crate::core::wrap::{self, VisualRow};
 use crate::gui::syntax::{SyntaxHighlighter, TokenTypeExt};
 use egui::{
     text::{LayoutJob, TextFormat},
@@ -11,6 +19,8 @@ use unicode_width::UnicodeWidthChar;
 
 const ROW_HEIGHT: f32 = 20.0;
 const MARGIN_WIDTH: f32 = 40.0;
+// Left edge of the fold marker, just after the line number
+const FOLD_MARKER_X: f32 = 28.0;
 const FONT_SIZE: f32 = 14.0;
 
 fn monospace() -> FontId {
@@ -20,8 +30,7 @@ fn monospace() -> FontId {
 fn build_line_galley(
     line: &str,
     tokens: &[crate::core::syntax::Token],
-    sel_start: Option<usize>, // character indices into the line
-    sel_end: Option<usize>,
+    selected: &[(usize, usize)], // character index ranges into the line, one per cursor
 ) -> LayoutJob {
     let mut job = LayoutJob::default();
     job.wrap.max_width = f32::INFINITY; // never wrap inside the editor
@@ -39,10 +48,14 @@ fn build_line_galley(
 
     let byte_for_char = |c: usize| -> usize { *char_byte_offsets.get(c).unwrap_or(&line.len()) };
 
-    let sel_start_byte = sel_start.map(byte_for_char);
-    let sel_end_byte = sel_end.map(byte_for_char);
+    let selected_bytes: Vec<(usize, usize)> = selected
+        .iter()
+        .map(|&(start, end)| (byte_for_char(start), byte_for_char(end)))
+        .filter(|(start, end)| start < end)
+        .collect();
+    let is_selected = |byte: usize| selected_bytes.iter().any(|&(s, e)| byte >= s && byte < e);
 
-    // Walk tokens, splitting each token's byte range against the selection range.
+    // Walk tokens, splitting each token's byte range at the selection edges.
     let mut byte_pos = 0usize;
     for token in tokens {
         let token_len = token.text.len(); // byte length
@@ -50,37 +63,19 @@ fn build_line_galley(
 
         let syntax_color = token.token_type.color();
 
-        // Determine overlap with selection
-        let sel_s = sel_start_byte.unwrap_or(usize::MAX);
-        let sel_e = sel_end_byte.unwrap_or(0);
-
-        if sel_s >= sel_e || token_end <= sel_s || byte_pos >= sel_e {
-            // No selection overlap — emit whole token with syntax colour
-            job.append(
-                &token.text,
-                0.0,
-                TextFormat {
-                    font_id: monospace(),
-                    color: syntax_color,
-                    background: Color32::TRANSPARENT,
-                    ..Default::default()
-                },
-            );
-        } else {
-            // Partial or full overlap — split into up to 3 parts
-            let parts = [
-                (byte_pos, sel_s.min(token_end), false),
-                (sel_s.max(byte_pos), sel_e.min(token_end), true),
-                (sel_e.max(byte_pos), token_end, false),
-            ];
-            for (start, end, selected) in parts {
-                if start >= end {
-                    continue;
-                }
-                let text = &line[start..end];
-                if text.is_empty() {
-                    continue;
-                }
+        let mut cuts: Vec<usize> = selected_bytes
+            .iter()
+            .flat_map(|&(s, e)| [s, e])
+            .filter(|&cut| cut > byte_pos && cut < token_end)
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let mut start = byte_pos;
+        for end in cuts.into_iter().chain(std::iter::once(token_end)) {
+            let text = &line[start..end];
+            if !text.is_empty() {
+                let selected = is_selected(start);
                 job.append(
                     text,
                     0.0,
@@ -96,6 +91,7 @@ fn build_line_galley(
                     },
                 );
             }
+            start = end;
         }
 
         byte_pos = token_end;
@@ -134,6 +130,9 @@ fn build_line_galley(
 pub struct EditorPanel<'a> {
     state: &'a mut EditorState,
     accepts_input: bool,
+    wrap_width: Option<usize>, // columns per visual row when soft wrap is on
+    // The pane without focus in a split: its tab, carets and scroll position
+    other: Option<PaneView>,
 }
 
 impl<'a> EditorPanel<'a> {
@@ -141,19 +140,89 @@ impl<'a> EditorPanel<'a> {
         Self {
             state,
             accepts_input,
+            wrap_width: None,
+            other: None,
+        }
+    }
+
+    // The pane without focus only shows its tab; no input, search bar or popups
+    pub fn other_pane(state: &'a mut EditorState, pane: PaneView) -> Self {
+        Self {
+            state,
+            accepts_input: false,
+            wrap_width: None,
+            other: Some(pane),
+        }
+    }
+
+    // The tab this panel shows: the other pane's, or the current one
+    fn tab(&self) -> &Tab {
+        let tabs = &self.state.tab_manager;
+        match self.other.as_ref().and_then(|pane| tabs.index_of(pane.tab_id)) {
+            Some(index) => &tabs.tabs[index],
+            None => tabs.current_tab(),
+        }
+    }
+
+    fn editor(&self) -> &Editor {
+        &self.tab().editor
+    }
+
+    // (first line shown, first column shown)
+    fn scroll(&self) -> (usize, usize) {
+        match &self.other {
+            Some(pane) => (pane.scroll.0.min(self.editor().buffer.lines.len().saturating_sub(1)), pane.scroll.1),
+            None => self.state.scroll_offset,
+        }
+    }
+
+    // Soft wrap at the window width, or at the configured column if that is narrower
+    fn compute_wrap_width(&self, editor_rect: Rect) -> Option<usize> {
+        if !self.state.soft_wrap {
+            return None;
+        }
+        let text_area_px = (editor_rect.width() - MARGIN_WIDTH).max(0.0);
+        let cell_px = 8.4_f32; // same heuristic used elsewhere
+        let window_cols = ((text_area_px / cell_px) as usize).max(1);
+        Some(match self.state.wrap_column {
+            Some(column) if column > 0 => column.min(window_cols),
+            _ => window_cols,
+        })
+    }
+
+    // Rows to draw from the top of the viewport: whole lines, or wrapped pieces of them;
+    // folded lines have no rows
+    fn visible_rows(&self, max_rows: usize) -> Vec<VisualRow> {
+        let lines = &self.editor().buffer.lines;
+        let folds = &self.editor().folds;
+        let scroll_line = self.scroll().0;
+
+        match self.wrap_width {
+            Some(width) => wrap::rows_from(lines, folds, scroll_line, width, max_rows),
+            None => folds
+                .visible_lines(scroll_line, lines.len())
+                .take(max_rows)
+                .map(|line| VisualRow {
+                    line,
+                    start: 0,
+                    end: grapheme_len(&lines[line]),
+                    ends_line: true,
+                })
+                .collect(),
         }
     }
 
     pub fn show(&mut self, ui: &mut Ui) -> Response {
-        let was_search_active = self.state.search_active;
+        let was_search_active = self.state.search_active && self.other.is_none();
 
-        if self.state.search_active {
+        if was_search_active {
             self.show_search_bar(ui);
         }
 
         // Capture the full editor rect BEFORE allocating it.
         let editor_rect = ui.available_rect_before_wrap();
         let response = ui.allocate_rect(editor_rect, Sense::click_and_drag());
+        self.wrap_width = self.compute_wrap_width(editor_rect);
 
         if was_search_active && !self.state.search_active {
             ui.ctx()
@@ -165,6 +234,20 @@ impl<'a> EditorPanel<'a> {
         }
 
         self.render_content(ui, editor_rect);
+
+        // Pointing at an underlined range shows what the language server said about it
+        if let Some(pointer) = response.hover_pos().filter(|p| p.x > editor_rect.left() + MARGIN_WIDTH) {
+            let pos = self.screen_to_text(editor_rect, pointer);
+            let diagnostics = self.state.diagnostics_of(self.tab());
+            let messages: Vec<&str> = diagnostics
+                .iter()
+                .filter(|d| d.covers(pos))
+                .map(|d| d.message.as_str())
+                .collect();
+            if !messages.is_empty() {
+                return response.on_hover_text(messages.join("\n"));
+            }
+        }
         response
     }
 
@@ -234,14 +317,17 @@ impl<'a> EditorPanel<'a> {
             let visible_rows = (editor_rect.height() / ROW_HEIGHT) as usize;
             let total_lines = self.state.current_buffer().lines.len();
             let max_scroll = total_lines.saturating_sub(visible_rows);
+            // Scrolling steps over folded lines
+            let folds = &self.state.editor().folds;
+            let scroll_line = self.state.scroll_offset.0;
             // positive delta.y = content moves down = we scroll UP (show earlier lines)
-            if scroll_lines > 0.0 {
+            self.state.scroll_offset.0 = if scroll_lines > 0.0 {
                 let steps = (scroll_lines / 10.0).ceil().max(1.0) as usize * 3;
-                self.state.scroll_offset.0 = self.state.scroll_offset.0.saturating_sub(steps);
+                folds.step(scroll_line, steps, false, total_lines)
             } else {
                 let steps = (scroll_lines.abs() / 10.0).ceil().max(1.0) as usize * 3;
-                self.state.scroll_offset.0 = (self.state.scroll_offset.0 + steps).min(max_scroll);
-            }
+                folds.step(scroll_line, steps, true, total_lines).min(max_scroll)
+            };
             ui.ctx().request_repaint();
         }
 
@@ -274,28 +360,30 @@ impl<'a> EditorPanel<'a> {
             }
         }
         if let Some(text) = paste_text {
-            // Compute approximate max columns based on editor_rect width so we emulate TUI wrapping.
-            let text_area_px = (editor_rect.width() - MARGIN_WIDTH).max(0.0);
-            let cell_px = 8.4_f32; // same heuristic used elsewhere
-            let max_cols = (text_area_px / cell_px) as usize;
-
-            if let Some(sel) = self.state.selection.take().filter(|s| s.is_active()) {
-                // Delete the active selection before inserting pasted text so we don't duplicate content.
-                self.delete_selection_inline(sel);
-            }
-
+            // Pasted lines are kept intact; soft wrap only changes how they are shown
             let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+            self.state.paste(&normalized);
+        }
 
-            if max_cols > 0 {
-                let wrapped = wrap_text_to_width(&normalized, max_cols);
-                let joined = wrapped.join("\n");
-                self.state.insert_text(&joined);
+        // Completion popup: arrows pick, Tab or Enter accepts (Esc closes it with the cursors)
+        if self.state.completion.is_some() {
+            let pressed = |key| ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+            let step = if pressed(egui::Key::ArrowDown) {
+                Some(true)
+            } else if pressed(egui::Key::ArrowUp) {
+                Some(false)
             } else {
-                self.state.insert_text(&normalized);
+                None
+            };
+            if let (Some(down), Some(completion)) = (step, &mut self.state.completion) {
+                completion.select_next(down);
+            } else if pressed(egui::Key::Tab) || pressed(egui::Key::Enter) {
+                self.state.accept_completion();
             }
         }
 
-        // Text input
+        // Text input - typing a word keeps the completion popup up to date
+        let mut typed_word = None;
         ui.input(|i| {
             for event in &i.events {
                 if let egui::Event::Text(text) = event {
@@ -304,7 +392,13 @@ impl<'a> EditorPanel<'a> {
                         && !i.modifiers.command
                         && !text.chars().any(|c| c.is_control())
                     {
-                        self.state.insert_text(text);
+                        // Single keystrokes may auto-close; IME commits go in as typed
+                        let mut chars = text.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(ch), None) => self.state.type_char(ch),
+                            _ => self.state.insert_text(text),
+                        }
+                        typed_word = Some(text.chars().all(|c| c.is_alphanumeric() || c == '_'));
                     }
                 }
             }
@@ -312,6 +406,7 @@ impl<'a> EditorPanel<'a> {
 
         let has_ctrl = ui.input(|i| i.modifiers.ctrl);
         let has_shift = ui.input(|i| i.modifiers.shift);
+        let has_alt = ui.input(|i| i.modifiers.alt);
 
         if ui.input(|i| i.key_pressed(egui::Key::Enter)) && !has_ctrl {
             self.state.insert_text("\n");
@@ -322,103 +417,89 @@ impl<'a> EditorPanel<'a> {
         if ui.input(|i| i.key_pressed(egui::Key::Delete)) && !has_ctrl {
             self.state.delete_at_cursor();
         }
-        if ui.input(|i| i.key_pressed(egui::Key::Tab)) && !has_ctrl {
-            self.state.insert_text("    ");
-        }
-
-        // Arrow keys
-        if ui.input(|i| i.key_pressed(egui::Key::ArrowLeft)) && !has_ctrl {
-            if has_shift {
-                self.move_cursor_with_selection(-1, 0);
-            } else {
-                self.state.selection = None;
-                self.state.move_cursor(-1, 0);
-            }
-        }
-        if ui.input(|i| i.key_pressed(egui::Key::ArrowRight)) && !has_ctrl {
-            if has_shift {
-                self.move_cursor_with_selection(1, 0);
-            } else {
-                self.state.selection = None;
-                self.state.move_cursor(1, 0);
-            }
-        }
-        if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) && !has_ctrl {
-            if has_shift {
-                self.move_cursor_with_selection(0, -1);
-            } else {
-                self.state.selection = None;
-                self.state.move_cursor(0, -1);
-            }
-        }
-        if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) && !has_ctrl {
-            if has_shift {
-                self.move_cursor_with_selection(0, 1);
-            } else {
-                self.state.selection = None;
-                self.state.move_cursor(0, 1);
-            }
-        }
-
-        // Home / End
-        if ui.input(|i| i.key_pressed(egui::Key::Home)) && !has_ctrl {
-            if has_shift {
-                self.start_selection_if_needed();
-                self.state.cursor_pos.column = 0;
-                self.update_selection();
-            } else {
-                self.state.cursor_pos.column = 0;
-                self.state.selection = None;
-            }
+        if ui.input(|i| i.key_pressed(egui::Key::Backspace)) && has_ctrl {
+            self.state.delete_word(false);
         }
-        if ui.input(|i| i.key_pressed(egui::Key::End)) && !has_ctrl {
-            let line_len = self
-                .state
-                .current_buffer()
-                .lines
-                .get(self.state.cursor_pos.line)
-                .map(|l| l.chars().count())
-                .unwrap_or(0);
-            if has_shift {
-                self.start_selection_if_needed();
-                self.state.cursor_pos.column = line_len;
-                self.update_selection();
-            } else {
-                self.state.cursor_pos.column = line_len;
-                self.state.selection = None;
-            }
+        if ui.input(|i| i.key_pressed(egui::Key::Delete)) && has_ctrl {
+            self.state.delete_word(true);
         }
-
-        // Page Up / Down
-        if ui.input(|i| i.key_pressed(egui::Key::PageUp)) {
-            if has_shift {
-                self.start_selection_if_needed();
-                self.state.cursor_pos.line = self.state.cursor_pos.line.saturating_sub(20);
-                self.update_selection();
-            } else {
-                self.state.cursor_pos.line = self.state.cursor_pos.line.saturating_sub(20);
-                self.state.selection = None;
-            }
-        }
-        if ui.input(|i| i.key_pressed(egui::Key::PageDown)) {
-            let max_line = self.state.current_buffer().lines.len().saturating_sub(1);
+        // Tab expands snippets and walks their tab stops, Shift+Tab steps back
+        if ui.input(|i| i.key_pressed(egui::Key::Tab)) && !has_ctrl {
             if has_shift {
-                self.start_selection_if_needed();
-                self.state.cursor_pos.line = (self.state.cursor_pos.line + 20).min(max_line);
-                self.update_selection();
+                self.state.editor_mut().snippet_step(false);
             } else {
-                self.state.cursor_pos.line = (self.state.cursor_pos.line + 20).min(max_line);
-                self.state.selection = None;
+                self.state.tab();
+            }
+        }
+
+        // Other editing keys, moving the cursor or clicking close the popup
+        let closing_keys = [
+            egui::Key::Enter,
+            egui::Key::Tab,
+            egui::Key::Delete,
+            egui::Key::ArrowLeft,
+            egui::Key::ArrowRight,
+            egui::Key::ArrowUp,
+            egui::Key::ArrowDown,
+            egui::Key::Home,
+            egui::Key::End,
+            egui::Key::PageUp,
+            egui::Key::PageDown,
+        ];
+        let moved = ui.input(|i| closing_keys.into_iter().any(|key| i.key_pressed(key)));
+        match typed_word {
+            Some(true) => self.state.update_completion(false),
+            Some(false) => self.state.completion = None,
+            None if moved || response.clicked() => self.state.completion = None,
+            None if ui.input(|i| i.key_pressed(egui::Key::Backspace)) && self.state.completion.is_some() => {
+                self.state.update_completion(false)
+            }
+            None => {}
+        }
+
+        // Arrow keys, Home / End, Page Up / Down - shift extends the selection
+        let motions = [
+            (egui::Key::ArrowLeft, Motion::Left),
+            (egui::Key::ArrowRight, Motion::Right),
+            (egui::Key::ArrowUp, Motion::Up),
+            (egui::Key::ArrowDown, Motion::Down),
+            (egui::Key::Home, Motion::LineStart),
+            (egui::Key::End, Motion::LineEnd),
+            (egui::Key::PageUp, Motion::PageUp(20)),
+            (egui::Key::PageDown, Motion::PageDown(20)),
+        ];
+        // Ctrl+Left / Right jump by word
+        for (key, motion) in [
+            (egui::Key::ArrowLeft, Motion::WordLeft),
+            (egui::Key::ArrowRight, Motion::WordRight),
+        ] {
+            if ui.input(|i| i.key_pressed(key)) && has_ctrl && !has_alt {
+                self.move_cursor(motion, has_shift);
+            }
+        }
+        for (key, motion) in motions {
+            let is_page = matches!(motion, Motion::PageUp(_) | Motion::PageDown(_));
+            if ui.input(|i| i.key_pressed(key)) && (!has_ctrl || is_page) {
+                if has_alt && has_shift {
+                    // Alt+Shift: rectangular selection
+                    self.state.editor_mut().extend_block(motion);
+                } else {
+                    self.move_cursor(motion, has_shift);
+                }
             }
         }
 
-        // Mouse click
+        // Mouse click - on a fold marker it toggles the fold
         if response.clicked() {
             if let Some(pos) = response.interact_pointer_pos() {
                 let text_pos = self.screen_to_text(editor_rect, pos);
-                self.state.cursor_pos = text_pos;
-                // Clear selection on simple click
-                self.state.selection = None;
+                let on_marker = (FOLD_MARKER_X..MARGIN_WIDTH).contains(&(pos.x - editor_rect.left()));
+                if on_marker && self.toggle_fold(text_pos.line) {
+                    ui.ctx().request_repaint();
+                    return;
+                }
+                // A simple click clears the selection
+                self.state.editor_mut().move_to(text_pos, false);
                 ui.ctx().request_repaint();
             }
         }
@@ -426,12 +507,16 @@ impl<'a> EditorPanel<'a> {
         // Mouse drag
         if response.drag_started() {
             if let Some(pos) = response.interact_pointer_pos() {
-                let text_pos = self.screen_to_text(editor_rect, pos);
-                self.state.cursor_pos = text_pos;
-                self.state.selection = Some(Selection {
-                    anchor: text_pos,
-                    cursor: text_pos,
-                });
+                if has_alt {
+                    // Alt+drag selects a rectangle
+                    let block_pos = self.screen_to_block_pos(editor_rect, pos);
+                    self.state.editor_mut().select_block(block_pos, block_pos);
+                } else {
+                    let text_pos = self.screen_to_text(editor_rect, pos);
+                    let editor = self.state.editor_mut();
+                    editor.move_to(text_pos, false);
+                    editor.selection = Some(Selection::new(editor.cursor));
+                }
                 self.state.is_dragging = true;
             }
         }
@@ -449,20 +534,23 @@ impl<'a> EditorPanel<'a> {
                     .saturating_sub(visible_rows);
                 let edge = ROW_HEIGHT;
 
-                if local_y < edge && self.state.scroll_offset.0 > 0 {
-                    self.state.scroll_offset.0 = self.state.scroll_offset.0.saturating_sub(1);
+                let line_count = self.state.current_buffer().lines.len();
+                let scroll_line = self.state.scroll_offset.0;
+                if local_y < edge && scroll_line > 0 {
+                    self.state.scroll_offset.0 = self.state.editor().folds.step(scroll_line, 1, false, line_count);
                     ui.ctx().request_repaint();
-                } else if local_y > editor_rect.height() - edge
-                    && self.state.scroll_offset.0 < max_scroll
-                {
-                    self.state.scroll_offset.0 = (self.state.scroll_offset.0 + 1).min(max_scroll);
+                } else if local_y > editor_rect.height() - edge && scroll_line < max_scroll {
+                    let next = self.state.editor().folds.step(scroll_line, 1, true, line_count);
+                    self.state.scroll_offset.0 = next.min(max_scroll);
                     ui.ctx().request_repaint();
                 }
 
-                let text_pos = self.screen_to_text(editor_rect, pos);
-                self.state.cursor_pos = text_pos;
-                if let Some(ref mut sel) = self.state.selection {
-                    sel.cursor = text_pos;
+                if let Some(block) = self.state.editor().block {
+                    let block_pos = self.screen_to_block_pos(editor_rect, pos);
+                    self.state.editor_mut().select_block(block.anchor, block_pos);
+                } else {
+                    let text_pos = self.screen_to_text(editor_rect, pos);
+                    self.state.editor_mut().move_to(text_pos, true);
                 }
             }
         }
@@ -470,10 +558,8 @@ impl<'a> EditorPanel<'a> {
         if response.drag_stopped() {
             self.state.is_dragging = false;
             // Clear selection if it covers zero range (pure click with no movement)
-            if let Some(ref sel) = self.state.selection {
-                if !sel.is_active() {
-                    self.state.selection = None;
-                }
+            if self.state.editor().active_selection().is_none() {
+                self.state.editor_mut().selection = None;
             }
         }
     }
@@ -484,15 +570,34 @@ impl<'a> EditorPanel<'a> {
         let row_height = ROW_HEIGHT;
         let margin_width = MARGIN_WIDTH;
 
-        let raw_line = ((pos.y - editor_rect.top()) / row_height) as isize
-            + self.state.scroll_offset.0 as isize;
-        let line = if raw_line < 0 {
-            0usize
+        if self.wrap_width.is_some() {
+            let row_idx = ((pos.y - editor_rect.top()) / row_height).max(0.0) as usize;
+            let rows = self.visible_rows(row_idx + 1);
+            if let Some(row) = rows.get(row_idx).or(rows.last()) {
+                let line = &self.editor().buffer.lines[row.line];
+                let row_text = grapheme_slice(line, row.start, row.end);
+                let rel_x = (pos.x - editor_rect.left() - margin_width).max(0.0);
+                let column = row.start + x_to_grapheme_col(rel_x, &row_text);
+                return TextPosition {
+                    line: row.line,
+                    column: column.min(row.max_column()),
+                };
+            }
+        }
+
+        // Rows skip folded lines; above the viewport is the line before it, below is the last line
+        let row_idx = ((pos.y - editor_rect.top()) / row_height).floor();
+        let line = if row_idx < 0.0 {
+            let folds = &self.editor().folds;
+            folds.prev_visible(self.scroll().0).unwrap_or(0)
         } else {
-            (raw_line as usize).min(self.state.current_buffer().lines.len().saturating_sub(1))
+            let rows = self.visible_rows(row_idx as usize + 1);
+            rows.get(row_idx as usize)
+                .or(rows.last())
+                .map_or(0, |row| row.line)
         };
 
-        let column = if let Some(text_line) = self.state.current_buffer().lines.get(line) {
+        let column = if let Some(text_line) = self.editor().buffer.lines.get(line) {
             let rel_x = (pos.x - editor_rect.left() - margin_width).max(0.0);
             // Walk characters, accumulating width, and find which one the click lands on.
             // We don't know exact font metrics here, so we use a fixed cell width per
@@ -508,109 +613,58 @@ impl<'a> EditorPanel<'a> {
         TextPosition { line, column }
     }
 
-    // Cursor helpers
-    fn move_cursor_with_selection(&mut self, dx: isize, dy: isize) {
-        self.start_selection_if_needed();
-        self.move_cursor_raw(dx, dy);
-        self.update_selection();
-    }
-
-    fn move_cursor_raw(&mut self, dx: isize, dy: isize) {
-        if dx < 0 && self.state.cursor_pos.column > 0 {
-            self.state.cursor_pos.column -= 1;
-        } else if dx > 0 {
-            let line_len = self
-                .state
-                .current_buffer()
-                .lines
-                .get(self.state.cursor_pos.line)
-                .map(|l| l.chars().count())
-                .unwrap_or(0);
-            if self.state.cursor_pos.column < line_len {
-                self.state.cursor_pos.column += 1;
-            }
+    // Like screen_to_text, but past the end of a line the column keeps counting cells,
+    // so a block selection can reach beyond short lines
+    fn screen_to_block_pos(&self, editor_rect: Rect, pos: Pos2) -> TextPosition {
+        let text_pos = self.screen_to_text(editor_rect, pos);
+        if self.wrap_width.is_some() {
+            return text_pos;
         }
-        if dy < 0 && self.state.cursor_pos.line > 0 {
-            self.state.cursor_pos.line -= 1;
-            self.clamp_column();
-        } else if dy > 0
-            && self.state.cursor_pos.line
-                < self.state.current_buffer().lines.len().saturating_sub(1)
-        {
-            self.state.cursor_pos.line += 1;
-            self.clamp_column();
+        let line = &self.state.current_buffer().lines[text_pos.line];
+        if text_pos.column < grapheme_len(line) {
+            return text_pos;
         }
-    }
-
-    fn start_selection_if_needed(&mut self) {
-        if self.state.selection.is_none() {
-            self.state.selection = Some(Selection::new(self.state.cursor_pos));
-        }
-    }
-
-    fn update_selection(&mut self) {
-        if let Some(ref mut sel) = self.state.selection {
-            sel.update_cursor(self.state.cursor_pos);
+        let rel_x = (pos.x - editor_rect.left() - MARGIN_WIDTH).max(0.0);
+        let cells_past_end = (rel_x / 8.4 - visual_width(line) as f32).round().max(0.0) as usize;
+        TextPosition {
+            line: text_pos.line,
+            column: text_pos.column + cells_past_end,
         }
     }
 
-    fn delete_selection_inline(&mut self, selection: Selection) {
-        let (start, end) = selection.get_range();
-        let buffer = self.state.current_buffer_mut();
-
-        if start.line == end.line {
-            // Single line deletion
-            if let Some(line) = buffer.lines.get_mut(start.line) {
-                let line_chars = line.chars().count();
-                let start_col = start.column.min(line_chars);
-                let end_col = end.column.min(line_chars);
-
-                let byte_start = char_to_byte_idx(line, start_col);
-                let byte_end = char_to_byte_idx(line, end_col);
-                line.drain(byte_start..byte_end);
-            }
-        } else {
-            // Multi-line deletion: merge before/after parts
-            let before_text = if let Some(line) = buffer.lines.get(start.line) {
-                char_slice(line, 0, start.column.min(line.chars().count()))
-            } else {
-                String::new()
-            };
-
-            let after_text = if let Some(line) = buffer.lines.get(end.line) {
-                let gcount = line.chars().count();
-                char_slice(line, end.column.min(gcount), gcount)
-            } else {
-                String::new()
-            };
-
-            // Remove all lines in range
-            for _ in start.line..=end.line {
-                if start.line < buffer.lines.len() {
-                    buffer.lines.remove(start.line);
+    // Fold or unfold the region headed by `line`, as clicking its marker does
+    fn toggle_fold(&mut self, line: usize) -> bool {
+        let editor = self.state.editor_mut();
+        if editor.folds.unfold(line) {
+            return true;
+        }
+        let matcher = self.state.bracket_matcher();
+        let editor = self.state.editor_mut();
+        match folding::region_at(&editor.buffer.lines, line, &matcher) {
+            Some(fold) => {
+                editor.folds.fold(fold);
+                // Keep the cursor out of the hidden lines
+                if fold.hides(editor.cursor.line) {
+                    editor.move_to(TextPosition { line, column: 0 }, false);
                 }
+                true
             }
-
-            // Insert merged line
-            buffer
-                .lines
-                .insert(start.line, format!("{}{}", before_text, after_text));
+            None => false,
         }
-
-        // Move cursor to start of selection
-        self.state.cursor_pos = start;
-        self.state.mark_dirty();
     }
 
-    fn clamp_column(&mut self) {
-        let line_len = self
-            .state
-            .current_buffer()
-            .lines
-            .get(self.state.cursor_pos.line)
-            .map(|l| l.chars().count())
-            .unwrap_or(0);
-        self.state.cursor_pos.column = self.state.cursor_pos.column.min(line_len);
+    // Cursor helpers
+    fn move_cursor(&mut self, motion: Motion, extend: bool) {
+        match (motion, self.wrap_width) {
+            (Motion::Up | Motion::Down, Some(width)) => {
+                // Soft wrap: up/down step through visual rows
+                let down = motion == Motion::Down;
+                self.state.editor_mut().move_each(extend, |editor, pos| {
+                    wrap::move_vertically(&editor.buffer.lines, &editor.folds, pos, width, down)
+                });
+            }
+            _ => self.state.move_cursor(motion, extend),
+        }
     }
 
     // Rendering
@@ -624,93 +678,278 @@ impl<'a> EditorPanel<'a> {
             Color32::from_rgb(38, 33, 28),
         );
 
-        let scroll_line = self.state.scroll_offset.0;
         let visible_rows = (rect.height() / ROW_HEIGHT) as usize + 1;
-        let end_line = (scroll_line + visible_rows).min(self.state.current_buffer().lines.len());
+        let rows = self.visible_rows(visible_rows);
 
-        let selection_range = self
-            .state
-            .selection
-            .as_ref()
-            .filter(|s| s.is_active())
-            .map(|s| s.get_range());
+        let tab = self.tab();
+        let editor = &tab.editor;
+        let (selections, carets) = match &self.other {
+            Some(pane) => (pane.selection_ranges(editor), pane.caret_positions(editor)),
+            None => (editor.selection_ranges(), editor.caret_positions()),
+        };
 
-        let filetype = self.state.tab_manager.current_tab().filetype.clone();
+        let filetype = tab.filetype.clone();
+        let is_commit_message = filetype.as_deref() == Some(git::COMMIT_MESSAGE);
+        let matcher = BracketMatcher::new(filetype.clone());
         let highlighter = SyntaxHighlighter::new(filetype);
+        let char_width = painter.layout_no_wrap(" ".to_string(), monospace(), Color32::WHITE).size().x;
 
-        let buffer_lines: Vec<String> = self.state.current_buffer().lines.clone();
+        let buffer_lines = &editor.buffer.lines;
+        let bracket_match = matcher.matching(buffer_lines, editor.cursor).filter(|_| self.other.is_none());
+        let diagnostics = self.state.diagnostics_of(tab);
+        let git = self.state.git_of(tab.id);
 
-        for (visual_idx, line_idx) in (scroll_line..end_line).enumerate() {
-            let y_pos = rect.top() + visual_idx as f32 * ROW_HEIGHT;
+        // Highlight each logical line once, however many rows it spans
+        let mut line_tokens: Option<(usize, Vec<crate::core::syntax::Token>)> = None;
+        // Where the word being completed starts on screen, for the popup
+        let completion_start = self.state.completion.as_ref().map(|c| c.start).filter(|_| self.other.is_none());
+        let mut popup_anchor = None;
 
-            // Line number
-            painter.text(
-                Pos2::new(rect.left() + 5.0, y_pos),
-                egui::Align2::LEFT_TOP,
-                format!("{:>3}", line_idx + 1),
-                FontId::monospace(12.0),
-                Color32::from_rgb(200, 160, 100),
-            );
+        for (visual_idx, row) in rows.iter().enumerate() {
+            let y_pos = rect.top() + visual_idx as f32 * ROW_HEIGHT;
+            let line_idx = row.line;
+
+            if row.start == 0 {
+                // Bookmarked lines get a badge behind the line number
+                if editor.bookmarks.at(line_idx).is_some() {
+                    painter.rect_filled(
+                        Rect::from_min_size(Pos2::new(rect.left() + 2.0, y_pos + 1.0), egui::Vec2::new(26.0, ROW_HEIGHT - 2.0)),
+                        3.0,
+                        Color32::from_rgb(60, 110, 130),
+                    );
+                }
+                // A bar at the margin's edge on lines with diagnostics, coloured by the worst
+                let severity = diagnostics.iter().filter(|d| d.start.line == line_idx).map(|d| d.severity).min();
+                if let Some(severity) = severity {
+                    painter.rect_filled(
+                        Rect::from_min_size(Pos2::new(rect.left(), y_pos), egui::Vec2::new(3.0, ROW_HEIGHT)),
+                        0.0,
+                        severity_color(severity),
+                    );
+                }
+                // Git changes: a bar at the margin's right edge, a notch where lines were deleted
+                if let Some(change) = git.and_then(|git| git.change_at(line_idx)) {
+                    let (height, color) = match change {
+                        LineChange::Added => (ROW_HEIGHT, Color32::from_rgb(80, 160, 80)),
+                        LineChange::Modified => (ROW_HEIGHT, Color32::from_rgb(200, 160, 60)),
+                        LineChange::Deleted => (4.0, Color32::from_rgb(200, 70, 60)),
+                    };
+                    painter.rect_filled(
+                        Rect::from_min_size(Pos2::new(rect.left() + MARGIN_WIDTH - 4.0, y_pos), egui::Vec2::new(3.0, height)),
+                        0.0,
+                        color,
+                    );
+                }
+                // Line number
+                painter.text(
+                    Pos2::new(rect.left() + 5.0, y_pos),
+                    egui::Align2::LEFT_TOP,
+                    format!("{:>3}", line_idx + 1),
+                    FontId::monospace(12.0),
+                    Color32::from_rgb(200, 160, 100),
+                );
+                // ▸ on a folded region's header, ▾ where a region could be folded
+                let marker = if editor.folds.is_folded(line_idx) {
+                    Some("▸")
+                } else if folding::is_foldable(buffer_lines, line_idx, &matcher) {
+                    Some("▾")
+                } else {
+                    None
+                };
+                if let Some(marker) = marker {
+                    painter.text(
+                        Pos2::new(rect.left() + FOLD_MARKER_X, y_pos),
+                        egui::Align2::LEFT_TOP,
+                        marker,
+                        FontId::monospace(12.0),
+                        Color32::from_rgb(150, 140, 120),
+                    );
+                }
+            } else {
+                // Wrap indicator on continuation rows
+                painter.text(
+                    Pos2::new(rect.left() + 5.0, y_pos),
+                    egui::Align2::LEFT_TOP,
+                    "  ↪",
+                    FontId::monospace(12.0),
+                    Color32::from_rgb(120, 110, 100),
+                );
+            }
 
             if let Some(line) = buffer_lines.get(line_idx) {
-                let tokens = highlighter.highlight_line(line);
+                if line_tokens.as_ref().map(|(idx, _)| *idx) != Some(line_idx) {
+                    line_tokens = Some((line_idx, highlighter.highlight_line(line)));
+                }
+                let Some((_, line_tokens)) = &line_tokens else {
+                    continue;
+                };
+                let row_len = row.end - row.start;
+                let (row_text, tokens) = if row.start == 0 && row.ends_line {
+                    (line.clone(), line_tokens.clone())
+                } else {
+                    (
+                        grapheme_slice(line, row.start, row.end),
+                        wrap::slice_tokens(line_tokens, row.start, row.end),
+                    )
+                };
 
-                // Determine selection within this line (in character indices)
-                let (sel_start, sel_end) = if let Some((start, end)) = selection_range {
-                    if line_idx >= start.line && line_idx <= end.line {
-                        let s = if line_idx == start.line {
-                            Some(start.column)
-                        } else {
-                            Some(0)
-                        };
+                // Selected parts of this row (in character indices), one per cursor
+                let selected: Vec<(usize, usize)> = selections
+                    .iter()
+                    .filter(|(start, end)| line_idx >= start.line && line_idx <= end.line)
+                    .filter_map(|(start, end)| {
+                        let s = if line_idx == start.line { start.column } else { 0 };
                         let e = if line_idx == end.line {
-                            Some(end.column)
+                            end.column
                         } else {
-                            Some(line.chars().count())
+                            grapheme_len(line)
                         };
-                        (s, e)
-                    } else {
-                        (None, None)
-                    }
-                } else {
-                    (None, None)
-                };
+                        let s = s.saturating_sub(row.start).min(row_len);
+                        let e = e.saturating_sub(row.start).min(row_len);
+                        // Rows fully inside a multi-line selection also highlight past the text
+                        (s < e || (line_idx != end.line && row.ends_line)).then_some((s, e))
+                    })
+                    .collect();
 
                 // Build galley via LayoutJob — egui handles all Unicode correctly
-                let job = build_line_galley(line, &tokens, sel_start, sel_end);
+                let job = build_line_galley(&row_text, &tokens, &selected);
                 let galley = painter.layout_job(job);
                 let text_pos = Pos2::new(rect.left() + MARGIN_WIDTH, y_pos);
                 painter.galley(text_pos, galley.clone(), Color32::WHITE);
 
-                // Cursor
-                if self.state.cursor_pos.line == line_idx {
+                // Commit messages: a ruler at the column the line should end by (50 for the
+                // subject, 72 below) and a red tint past it
+                if let Some(width) = git::message_width(line_idx, line).filter(|_| is_commit_message) {
+                    let column = width.saturating_sub(row.start);
+                    let x = text_pos.x
+                        + grapheme_col_to_px(&galley, column.min(row_len))
+                        + column.saturating_sub(row_len) as f32 * char_width;
+                    if column < row_len {
+                        painter.rect_filled(
+                            Rect::from_min_max(Pos2::new(x, y_pos), Pos2::new(text_pos.x + galley.size().x, y_pos + ROW_HEIGHT)),
+                            0.0,
+                            Color32::from_rgba_unmultiplied(230, 90, 80, 60),
+                        );
+                    }
+                    // On the row the column falls in, when soft wrap splits the line
+                    if width >= row.start && (width < row.end || row.ends_line) {
+                        painter.vline(x, y_pos..=y_pos + ROW_HEIGHT, Stroke::new(1.0, Color32::from_rgb(70, 62, 52)));
+                    }
+                }
+
+                // "⋯ N lines" after the header of a folded region
+                let hidden = editor.folds.hidden_after(line_idx);
+                if row.ends_line && hidden > 0 {
+                    painter.text(
+                        Pos2::new(text_pos.x + galley.size().x + 8.0, y_pos),
+                        egui::Align2::LEFT_TOP,
+                        format!("⋯ {} lines", hidden),
+                        monospace(),
+                        Color32::from_rgb(120, 110, 100),
+                    );
+                }
+
+                // Wavy underline under diagnostics; an empty range at the end of a line still
+                // marks one character's width
+                for diagnostic in &diagnostics {
+                    let Some((start, end)) = diagnostic.columns_on(line_idx) else {
+                        continue;
+                    };
+                    let start = start.max(row.start);
+                    let end = end.min(row.end.max(start + 1));
+                    if start >= end || !row.contains(start) {
+                        continue;
+                    }
+                    let left = text_pos.x + grapheme_col_to_px(&galley, start - row.start);
+                    let right = (text_pos.x + grapheme_col_to_px(&galley, end - row.start)).max(left + 8.0);
+                    draw_squiggle(painter, left, right, y_pos + ROW_HEIGHT - 3.0, severity_color(diagnostic.severity));
+                }
+
+                // Box the bracket next to the cursor and its partner
+                for bracket in bracket_match.iter().flat_map(|&(a, b)| [a, b]) {
+                    if bracket.line != line_idx || !row.contains(bracket.column) {
+                        continue;
+                    }
+                    let col = bracket.column - row.start;
+                    let left = text_pos.x + grapheme_col_to_px(&galley, col);
+                    let right = text_pos.x + grapheme_col_to_px(&galley, col + 1);
+                    painter.rect_stroke(
+                        Rect::from_min_max(Pos2::new(left, y_pos), Pos2::new(right, y_pos + ROW_HEIGHT)),
+                        2.0,
+                        Stroke::new(1.0, Color32::from_rgb(255, 200, 80)),
+                        egui::StrokeKind::Inside,
+                    );
+                }
+
+                if let Some(start) = completion_start.filter(|s| s.line == line_idx && row.contains(s.column)) {
+                    let x = text_pos.x + grapheme_col_to_px(&galley, start.column - row.start);
+                    popup_anchor = Some(Pos2::new(x, y_pos + ROW_HEIGHT));
+                }
+
+                // Cursors - the primary one first, extra ones slightly dimmer
+                for (idx, cursor) in carets.iter().enumerate() {
+                    if cursor.line != line_idx || !row.contains(cursor.column) {
+                        continue;
+                    }
                     // Use galley cursor position for correct pixel offset
-                    let col = self.state.cursor_pos.column;
+                    let col = cursor.column - row.start;
                     let cx = rect.left() + MARGIN_WIDTH + grapheme_col_to_px(&galley, col);
+                    let color = if idx == 0 { Color32::YELLOW } else { Color32::from_rgb(200, 170, 60) };
                     painter.line_segment(
                         [Pos2::new(cx, y_pos), Pos2::new(cx, y_pos + ROW_HEIGHT)],
-                        Stroke::new(2.0, Color32::YELLOW),
+                        Stroke::new(2.0, color),
                     );
                 }
             }
         }
+
+        if let (Some(anchor), Some(completion)) = (popup_anchor, &self.state.completion) {
+            draw_completion(ui, rect, anchor, &completion.items, completion.selected);
+        }
     }
 }
 
-// Helper: get byte index for a given character index (0-based). Falls back to end-of-string.
-fn char_to_byte_idx(s: &str, char_idx: usize) -> usize {
-    s.char_indices()
-        .nth(char_idx)
-        .map(|(i, _)| i)
-        .unwrap_or(s.len())
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::from_rgb(230, 90, 80),
+        Severity::Warning => Color32::from_rgb(230, 190, 70),
+        Severity::Information => Color32::from_rgb(120, 170, 220),
+        Severity::Hint => Color32::from_rgb(150, 140, 120),
+    }
 }
 
-// Helper: slice string by character indices [start, end)
-fn char_slice(s: &str, start: usize, end: usize) -> String {
-    s.chars()
-        .skip(start)
-        .take(end.saturating_sub(start))
-        .collect()
+fn draw_squiggle(painter: &egui::Painter, left: f32, right: f32, y: f32, color: Color32) {
+    let mut points = Vec::new();
+    let mut x = left;
+    let mut up = true;
+    while x < right {
+        points.push(Pos2::new(x, if up { y - 1.5 } else { y + 1.5 }));
+        x += 2.5;
+        up = !up;
+    }
+    points.push(Pos2::new(right, y));
+    painter.add(egui::Shape::line(points, Stroke::new(1.0, color)));
+}
+
+// Completion list under the word being typed, above it when it would run off the bottom
+fn draw_completion(ui: &Ui, rect: Rect, anchor: Pos2, items: &[String], selected: usize) {
+    let painter = ui.painter();
+    let width = items.iter().map(|item| visual_width(item)).max().unwrap_or(0) as f32 * 8.4 + 16.0;
+    let height = items.len() as f32 * ROW_HEIGHT;
+    let top = if anchor.y + height > rect.bottom() { anchor.y - ROW_HEIGHT - height } else { anchor.y };
+    let left = anchor.x.min(rect.right() - width).max(rect.left() + MARGIN_WIDTH);
+    let popup = Rect::from_min_size(Pos2::new(left, top), egui::Vec2::new(width, height));
+
+    painter.rect_filled(popup, 3.0, Color32::from_rgb(50, 45, 40));
+    painter.rect_stroke(popup, 3.0, Stroke::new(1.0, Color32::from_rgb(110, 100, 90)), egui::StrokeKind::Outside);
+    for (idx, item) in items.iter().enumerate() {
+        let y = top + idx as f32 * ROW_HEIGHT;
+        if idx == selected {
+            let row = Rect::from_min_size(Pos2::new(left, y), egui::Vec2::new(width, ROW_HEIGHT));
+            painter.rect_filled(row, 3.0, Color32::from_rgb(60, 110, 130));
+        }
+        painter.text(Pos2::new(left + 8.0, y + 2.0), egui::Align2::LEFT_TOP, item, monospace(), Color32::WHITE);
+    }
 }
 
 /// Convert a pixel X offset to a grapheme (character) column index within a line.
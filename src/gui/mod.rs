@@ -1,13 +1,16 @@
 mod app;
+mod diff;
 mod editor;
+mod preview;
 mod state;
 mod syntax;
 mod themes;
 
 pub use app::QuickNotepadApp;
+use crate::core::selection::TextPosition;
 
-// Entry point for GUI mode
-pub fn run(file_path: Option<String>) {
+// Entry point for GUI mode; `compare` is a file to compare the first one with
+pub fn run(file_path: Option<String>, at: Option<TextPosition>, compare: Option<String>) {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
@@ -23,7 +26,7 @@ pub fn run(file_path: Option<String>) {
             // Setup custom fonts if needed
             setup_custom_fonts(&cc.egui_ctx);
 
-            Ok(Box::new(QuickNotepadApp::new(cc, file_path)))
+            Ok(Box::new(QuickNotepadApp::new(cc, file_path, at, compare)))
         }),
     );
 }
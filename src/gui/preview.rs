@@ -0,0 +1,253 @@
+// src/gui/preview.rs
+// Rendered Markdown shown beside the editor, scrolled along with it
+use crate::core::markdown::{self, Align, Block, BlockKind, Inline, ListMarker, Style};
+use egui::{Color32, Layout, RichText, ScrollArea, Ui};
+use egui_extras::syntax_highlighting::{self, CodeTheme};
+use egui_extras::{Column, TableBuilder};
+use std::path::{Path, PathBuf};
+
+const HEADING_SIZES: [f32; 6] = [28.0, 24.0, 20.0, 18.0, 16.0, 15.0];
+const BLOCK_SPACING: f32 = 8.0;
+const LIST_INDENT: f32 = 18.0;
+
+#[derive(Default)]
+pub struct MarkdownPreview {
+    source: String,
+    blocks: Vec<Block>,
+    // Each block's first line and its top within the preview, from the last frame
+    offsets: Vec<(usize, f32)>,
+    // The editor's top line the preview was last scrolled to
+    synced_line: Option<usize>,
+}
+
+impl MarkdownPreview {
+    // `text` is re-parsed only when it changed; `top_line` is the editor's first visible line
+    pub fn show(&mut self, ui: &mut Ui, text: String, dir: Option<&Path>, top_line: usize) {
+        if text != self.source {
+            self.blocks = markdown::parse(&text);
+            self.source = text;
+            // The blocks moved, so line them up again once they have been laid out
+            self.synced_line = None;
+        }
+
+        let mut scroll = ScrollArea::vertical().id_salt("markdown_preview").auto_shrink(false);
+        if self.synced_line != Some(top_line) && !self.offsets.is_empty() {
+            scroll = scroll.vertical_scroll_offset(self.offset_of(top_line));
+            self.synced_line = Some(top_line);
+        }
+
+        let mut offsets = Vec::with_capacity(self.blocks.len());
+        scroll.show(ui, |ui| {
+            let top = ui.min_rect().top();
+            for (index, block) in self.blocks.iter().enumerate() {
+                offsets.push((block.line, ui.cursor().top() - top));
+                ui.push_id(index, |ui| show_block(ui, block, dir));
+                ui.add_space(BLOCK_SPACING);
+            }
+        });
+        self.offsets = offsets;
+    }
+
+    // Where the preview shows `line`: between the tops of the blocks around it
+    fn offset_of(&self, line: usize) -> f32 {
+        let Some(at) = self.offsets.iter().rposition(|(start, _)| *start <= line) else {
+            return 0.0;
+        };
+        let (start, y) = self.offsets[at];
+        match self.offsets.get(at + 1) {
+            Some(&(next, next_y)) if next > start => y + (next_y - y) * (line - start) as f32 / (next - start) as f32,
+            _ => y,
+        }
+    }
+}
+
+fn show_block(ui: &mut Ui, block: &Block, dir: Option<&Path>) {
+    match &block.kind {
+        BlockKind::Heading(level, spans) => {
+            let size = HEADING_SIZES[(*level as usize).clamp(1, 6) - 1];
+            ui.horizontal_wrapped(|ui| show_inlines(ui, spans, dir, |text| text.size(size).strong()));
+            if *level <= 2 {
+                ui.separator();
+            }
+        }
+        BlockKind::Paragraph(spans) => {
+            ui.horizontal_wrapped(|ui| show_inlines(ui, spans, dir, |text| text));
+        }
+        BlockKind::ListItem { depth, marker, checked, text } => {
+            ui.horizontal_top(|ui| {
+                ui.add_space(*depth as f32 * LIST_INDENT);
+                let marker = match (checked, marker) {
+                    (Some(true), _) => "☑".to_string(),
+                    (Some(false), _) => "☐".to_string(),
+                    (None, ListMarker::Bullet) => if *depth == 0 { "•" } else { "◦" }.to_string(),
+                    (None, ListMarker::Number(n)) => format!("{}.", n),
+                };
+                ui.label(marker);
+                ui.vertical(|ui| {
+                    ui.horizontal_wrapped(|ui| show_inlines(ui, text, dir, |text| text));
+                });
+            });
+        }
+        BlockKind::Quote(spans) => {
+            egui::Frame::new()
+                .fill(ui.visuals().faint_bg_color)
+                .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    let color = ui.visuals().weak_text_color();
+                    ui.horizontal_wrapped(|ui| show_inlines(ui, spans, dir, |text| text.italics().color(color)));
+                });
+        }
+        BlockKind::Code { language, code } => {
+            egui::Frame::new()
+                .fill(ui.visuals().code_bg_color)
+                .corner_radius(4.0)
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    let theme = CodeTheme::from_style(ui.style());
+                    let mut job = syntax_highlighting::highlight(ui.ctx(), ui.style(), &theme, code, syntax_name(language));
+                    job.wrap.max_width = ui.available_width();
+                    ui.add(egui::Label::new(job).selectable(true));
+                });
+        }
+        BlockKind::Table { align, header, rows } => show_table(ui, align, header, rows, dir),
+        BlockKind::Rule => {
+            ui.separator();
+        }
+    }
+}
+
+fn show_table(ui: &mut Ui, align: &[Align], header: &[Vec<Inline>], rows: &[Vec<Vec<Inline>>], dir: Option<&Path>) {
+    let columns = header.len().max(rows.iter().map(Vec::len).max().unwrap_or(0));
+    let row_height = ui.text_style_height(&egui::TextStyle::Body) + 6.0;
+    let cell = |ui: &mut Ui, column: usize, spans: Option<&Vec<Inline>>, strong: bool| {
+        let layout = match align.get(column) {
+            Some(Align::Right) => Layout::right_to_left(egui::Align::Center),
+            Some(Align::Center) => Layout::centered_and_justified(egui::Direction::LeftToRight),
+            _ => Layout::left_to_right(egui::Align::Center),
+        };
+        ui.with_layout(layout, |ui| {
+            if let Some(spans) = spans {
+                show_inlines(ui, spans, dir, |text| if strong { text.strong() } else { text });
+            }
+        });
+    };
+
+    TableBuilder::new(ui)
+        .striped(true)
+        .vscroll(false)
+        .columns(Column::auto().at_least(40.0).resizable(true), columns)
+        .header(row_height, |mut row| {
+            for column in 0..columns {
+                row.col(|ui| cell(ui, column, header.get(column), true));
+            }
+        })
+        .body(|mut body| {
+            for cells in rows {
+                body.row(row_height, |mut row| {
+                    for column in 0..columns {
+                        row.col(|ui| cell(ui, column, cells.get(column), false));
+                    }
+                });
+            }
+        });
+}
+
+// Lay out a run of styled text; `base` adds the block's own look (heading size, quote colour)
+fn show_inlines(ui: &mut Ui, spans: &[Inline], dir: Option<&Path>, base: impl Fn(RichText) -> RichText) {
+    ui.spacing_mut().item_spacing.x = 0.0;
+    for span in spans {
+        match span {
+            Inline::Text { text, style } => {
+                let rich = base(styled(text, style));
+                match &style.link {
+                    Some(target) => {
+                        ui.hyperlink_to(rich, link_url(target, dir));
+                    }
+                    None => {
+                        ui.label(rich);
+                    }
+                }
+            }
+            Inline::Image { alt, src, link } => show_image(ui, alt, src, link.as_deref(), dir),
+        }
+    }
+}
+
+fn styled(text: &str, style: &Style) -> RichText {
+    let mut rich = RichText::new(text);
+    if style.code {
+        rich = rich.code();
+    }
+    if style.bold {
+        rich = rich.strong();
+    }
+    if style.italic {
+        rich = rich.italics();
+    }
+    if style.strike {
+        rich = rich.strikethrough();
+    }
+    rich
+}
+
+// Images load from local files only; a web image shows as its alt text, linking to it
+fn show_image(ui: &mut Ui, alt: &str, src: &str, link: Option<&str>, dir: Option<&Path>) {
+    let Some(path) = local_path(src, dir) else {
+        let label = if alt.is_empty() { src } else { alt };
+        ui.hyperlink_to(format!("🖼 {}", label), link.unwrap_or(src));
+        return;
+    };
+    if !path.is_file() {
+        ui.colored_label(Color32::from_rgb(220, 120, 120), format!("🖼 {} (not found: {})", alt, src));
+        return;
+    }
+    let image = egui::Image::new(format!("file://{}", path.display()))
+        .max_width(ui.available_width())
+        .alt_text(alt)
+        .sense(egui::Sense::click());
+    let response = ui.add(image).on_hover_text(alt);
+    if let Some(link) = link.filter(|_| response.clicked()) {
+        ui.ctx().open_url(egui::OpenUrl::new_tab(link_url(link, dir)));
+    }
+}
+
+// Relative links and images are next to the Markdown file
+fn local_path(target: &str, dir: Option<&Path>) -> Option<PathBuf> {
+    if target.contains("://") || target.starts_with("mailto:") || target.starts_with('#') {
+        return None;
+    }
+    let target = target.strip_prefix("file:").unwrap_or(target);
+    let path = Path::new(target);
+    Some(match dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    })
+}
+
+fn link_url(target: &str, dir: Option<&Path>) -> String {
+    match local_path(target, dir) {
+        Some(path) => format!("file://{}", path.display()),
+        None => target.to_string(),
+    }
+}
+
+// syntect knows languages by name ("Rust") or extension ("rs"); fences use either, lowercase
+fn syntax_name(language: &str) -> &str {
+    match language.to_lowercase().as_str() {
+        "rust" => "rs",
+        "python" => "py",
+        "javascript" | "node" => "js",
+        "typescript" => "ts",
+        "shell" | "bash" | "zsh" => "sh",
+        "c++" | "cplusplus" => "cpp",
+        "csharp" | "c#" => "cs",
+        "ruby" => "rb",
+        "markdown" => "md",
+        "golang" => "go",
+        "yml" => "yaml",
+        _ => language,
+    }
+}
@@ -1,62 +1,122 @@
 // state - adapter between core logic and GUI with Wayland-safe clipboard handling
 use crate::core::{
+    autopair::{AutoCloseSettings, AutoPairs},
+    brackets::BracketMatcher,
     buffer::Buffer,
-    edit_history::EditHistory,
-    selection::{Selection, TextPosition},
-    tabs::{get_friendly_filetype, TabManager},
+    completion::{Completer, Completion, CompletionSettings},
+    data::{Format, ParseError},
+    editor::{Editor, Motion},
+    formatter::{self, FormatSettings},
+    git::{GitFile, GitFiles},
+    graphemes::grapheme_len,
+    lsp::{Diagnostic, Location, LspManager, Reply, TextEdit},
+    panes::{PaneView, Split, SplitDirection},
+    save_hooks::{AfterSave, SaveSettings},
+    selection::TextPosition,
+    settings::Settings,
+    snippets::Snippets,
+    tabs::{get_friendly_filetype, Tab, TabManager},
 };
 
-use crate::core::graphemes::*;
+// Lines left visible above a go-to target
+const GOTO_CONTEXT_LINES: usize = 5;
+// How long a message (a language server's, a formatter's error) stays in the status bar
+const MESSAGE_TIME: std::time::Duration = std::time::Duration::from_secs(4);
 
 pub struct EditorState {
     pub tab_manager: TabManager,
-    pub selection: Option<Selection>,
-    pub cursor_pos: TextPosition,
     pub scroll_offset: (usize, usize), // (line, column)
+    // The second pane, when the editor is split; the fields above are the focused pane's
+    pub split: Option<Split>,
     pub search_query: String,
     pub search_active: bool,
     pub search_results: Vec<(usize, usize)>,
     pub search_result_idx: usize,
     pub is_dragging: bool,
+    pub soft_wrap: bool,
+    pub wrap_column: Option<usize>,
+    auto_close: AutoCloseSettings,
+    // Word completion popup, while it is open
+    pub completion: Option<Completion>,
+    completion_settings: CompletionSettings,
+    completer: Completer,
+    // Language servers for the open files
+    lsp: LspManager,
+    // Formatter command per file type
+    format: FormatSettings,
+    // Save hooks per file type, and the post-save command while it runs
+    save: SaveSettings,
+    after_save: AfterSave,
+    // How each tab's file differs from what is staged, read from git in the background
+    git: GitFiles,
+    // Last message for the status bar
+    message: Option<(String, std::time::Instant)>,
+    // Documentation of the symbol at a position, shown until the cursor leaves it
+    pub hover: Option<(TextPosition, String)>,
     clipboard_text: Option<String>,
 }
 
 impl EditorState {
-    pub fn new(file_path: Option<String>) -> Self {
+    pub fn new(file_path: Option<String>, at: Option<TextPosition>) -> Self {
+        // `file:line:col` targets only apply once the file actually opened
+        let mut target = None;
         let tab_manager = if let Some(path) = file_path {
             let mut tm = TabManager::new(Buffer::default(), None, None);
-            if let Err(e) = tm.open_file_in_new_tab(&path) {
-                eprintln!("Failed to open file: {}", e);
+            match tm.open_file_in_new_tab(&path) {
+                Ok(_) => target = at,
+                Err(e) => eprintln!("Failed to open file: {}", e),
             }
             tm
         } else {
             TabManager::new(Buffer::default(), None, None)
         };
+        Self::with_tabs(tab_manager, target)
+    }
+
+    // Over tabs opened some other way, e.g. without the saved session
+    pub fn with_tabs(tab_manager: TabManager, target: Option<TextPosition>) -> Self {
+        let settings = Settings::load();
 
-        Self {
+        let mut state = Self {
             tab_manager,
-            selection: None,
-            cursor_pos: TextPosition { line: 0, column: 0 },
             scroll_offset: (0, 0),
+            split: None,
             search_query: String::new(),
             search_active: false,
             is_dragging: false,
+            soft_wrap: settings.soft_wrap,
+            wrap_column: settings.wrap_column,
+            auto_close: settings.auto_close,
+            completion: None,
+            completion_settings: settings.completion,
+            completer: Completer::default(),
+            lsp: LspManager::new(settings.lsp),
+            format: settings.format,
+            save: settings.save,
+            after_save: AfterSave::default(),
+            git: GitFiles::default(),
+            message: None,
+            hover: None,
             clipboard_text: None,
             search_results: Vec::new(),
             search_result_idx: 0,
+        };
+        if let Some(pos) = target {
+            state.go_to(pos);
         }
+        state
     }
 
-    pub fn current_buffer(&self) -> &Buffer {
-        &self.tab_manager.current_tab().buffer
+    pub fn editor(&self) -> &Editor {
+        &self.tab_manager.current_tab().editor
     }
 
-    pub fn current_buffer_mut(&mut self) -> &mut Buffer {
-        &mut self.tab_manager.current_tab_mut().buffer
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.tab_manager.current_tab_mut().editor
     }
 
-    pub fn current_edit_history(&mut self) -> &mut EditHistory {
-        &mut self.tab_manager.current_tab_mut().edit_history
+    pub fn current_buffer(&self) -> &Buffer {
+        &self.editor().buffer
     }
 
     pub fn has_unsaved_changes(&self) -> bool {
@@ -71,190 +131,142 @@ impl EditorState {
         self.tab_manager.current_tab_mut().has_unsaved_changes = false;
     }
 
+    // Bracket matching follows the current tab's syntax rules
+    pub fn bracket_matcher(&self) -> BracketMatcher {
+        BracketMatcher::new(self.tab_manager.current_tab().filetype.clone())
+    }
+
     pub fn current_filename(&self) -> Option<&str> {
         self.tab_manager.current_tab().filename.as_deref()
     }
 
-    // Insert text at cursor position (grapheme-aware)
+    // Editing goes through the current tab's editor, which records undo history
     pub fn insert_text(&mut self, text: &str) {
-        let pos = self.cursor_pos;
-        let buffer = self.current_buffer_mut();
-
-        // Ensure line exists
-        while buffer.lines.len() <= pos.line {
-            buffer.lines.push(String::new());
-        }
-
-        // Normalize line endings already handled by caller if needed
-        // Handle multi-line insert
-        if text.contains('\n') {
-            let parts: Vec<&str> = text.split('\n').collect();
-
-            // Work with a cloned current line so split_at_grapheme lifetimes are safe
-            let current_line = buffer.lines[pos.line].clone();
-            let grapheme_count = grapheme_len(&current_line);
-            let split_at = pos.column.min(grapheme_count);
-            let (before, after) = split_at_grapheme(&current_line, split_at);
-
-            // Replace current line with before + first part
-            buffer.lines[pos.line] = format!("{}{}", before, parts[0]);
-
-            // Insert middle/last parts
-            for (i, part) in parts.iter().enumerate().skip(1) {
-                let mut line_content = part.to_string();
-                if i == parts.len() - 1 {
-                    // append the remainder of the original line
-                    line_content.push_str(after);
-                }
-
-                let insert_idx = pos.line + i;
-                // Double-check bounds safety dynamically as the vector grows
-                if insert_idx <= buffer.lines.len() {
-                    buffer.lines.insert(insert_idx, line_content);
-                } else {
-                    buffer.lines.push(line_content);
-                }
-            }
+        if self.editor_mut().insert_text(text) {
+            self.mark_dirty();
+        }
+    }
 
-            // Set cursor to end of last inserted line (grapheme count)
-            let final_line_idx = pos.line + parts.len() - 1;
-            let final_col = grapheme_len(&buffer.lines[final_line_idx]);
-            self.cursor_pos = TextPosition {
-                line: final_line_idx,
-                column: final_col,
-            };
-        } else {
-            // Single line insert - use grapheme insert
-            let line = &mut buffer.lines[pos.line];
-            let grapheme_count = grapheme_len(line);
-            let insert_at = pos.column.min(grapheme_count);
-            insert_at_grapheme(line, insert_at, text);
-            self.cursor_pos = TextPosition {
-                line: pos.line,
-                column: insert_at + grapheme_len(text),
-            };
+    // Typed characters auto-close pairs for the current file type
+    pub fn type_char(&mut self, ch: char) {
+        let pairs = self.auto_pairs();
+        if self.editor_mut().type_char(ch, &pairs) {
+            self.mark_dirty();
         }
+    }
 
-        self.mark_dirty();
+    // Open or refresh the completion popup for the word before the cursor, offering words
+    // from every open tab. Typing opens it (when enabled) once the word is long enough,
+    // `manual` (Ctrl+Space) or an open popup needs just one character.
+    pub fn update_completion(&mut self, manual: bool) {
+        let open = self.completion.is_some();
+        if !manual && !open && !self.completion_settings.auto {
+            return;
+        }
+        let min_prefix = if manual || open { 1 } else { self.completion_settings.min_prefix };
+        let active = self.tab_manager.active_tab_index;
+        let tab = self.tab_manager.current_tab();
+        let others = self.tab_manager.tabs.iter().enumerate().filter(|(idx, _)| *idx != active);
+        self.completion = self.completer.complete(
+            &tab.editor.buffer.lines,
+            tab.editor.cursor,
+            others.map(|(_, tab)| &tab.editor.buffer.lines),
+            tab.filetype.clone(),
+            min_prefix,
+        );
     }
 
-    pub fn move_cursor(&mut self, dx: isize, dy: isize) {
-        let line_count = self.current_buffer().lines.len();
+    // Ctrl+Space: buffer words now, with the language server's suggestions first once they
+    // come; these come even before a word is started, e.g. after a `.`
+    pub fn complete_with_server(&mut self) {
+        self.update_completion(true);
+        let Some(path) = self.tab_manager.current_tab().filepath.clone() else {
+            return;
+        };
+        self.sync_lsp();
+        let _ = self.lsp.completion(&path, self.editor().cursor);
+    }
 
-        let mut new_line = self.cursor_pos.line as isize + dy;
-        new_line = new_line.clamp(0, line_count.saturating_sub(1) as isize);
+    // Put the highlighted completion in place of the word before each cursor
+    pub fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let word = completion.current().to_string();
+        self.completer.accepted(&word);
+        if self.editor_mut().complete_word(&word) {
+            self.mark_dirty();
+        }
+    }
 
-        self.cursor_pos.line = new_line as usize;
+    // Tab expands the snippet trigger before the cursor, moves on to the next tab stop of an
+    // expanded snippet, or inserts four spaces
+    pub fn tab(&mut self) {
+        let tab = self.tab_manager.current_tab();
+        let snippets = Snippets::load(tab.filetype.as_deref());
+        let filename = tab.filename.clone().unwrap_or_default();
+        if self.editor_mut().expand_snippet(&snippets, &filename) {
+            self.mark_dirty();
+        } else if !self.editor_mut().snippet_step(true) {
+            self.insert_text("    ");
+        }
+    }
 
-        let line_len = grapheme_len(&self.current_buffer().lines[self.cursor_pos.line]);
-        let mut new_col = self.cursor_pos.column as isize + dx;
-        new_col = new_col.clamp(0, line_len as isize);
+    fn auto_pairs(&self) -> AutoPairs {
+        let filetype = self.tab_manager.current_tab().filetype.as_deref();
+        self.auto_close.for_filetype(filetype)
+    }
 
-        self.cursor_pos.column = new_col as usize;
+    // Clipboard lines are spread over the cursors when there is one line per cursor
+    pub fn paste(&mut self, text: &str) {
+        if self.editor_mut().paste(text) {
+            self.mark_dirty();
+        }
     }
 
-    pub fn clamp_cursor(&mut self) {
-        let line_count = self.current_buffer().lines.len();
-        self.cursor_pos.line = self.cursor_pos.line.min(line_count.saturating_sub(1));
-        let line_len = grapheme_len(&self.current_buffer().lines[self.cursor_pos.line]);
-        self.cursor_pos.column = self.cursor_pos.column.min(line_len);
+    pub fn move_cursor(&mut self, motion: Motion, extend: bool) {
+        self.editor_mut().move_cursor(motion, extend);
     }
 
     // Delete selection or character at cursor (grapheme-aware)
     pub fn delete_at_cursor(&mut self) {
-        if let Some(selection) = self.selection.take() {
-            self.delete_selection(selection);
-        } else {
-            let pos = self.cursor_pos;
-            let buffer = self.current_buffer_mut();
-            if pos.line < buffer.lines.len() {
-                let line = &mut buffer.lines[pos.line];
-                let grapheme_count = grapheme_len(line);
-                if pos.column < grapheme_count {
-                    let _ = remove_grapheme_at(line, pos.column);
-                    self.clamp_cursor();
-                    self.mark_dirty();
-                }
-            }
+        if self.editor_mut().delete_forward() {
+            self.mark_dirty();
         }
     }
 
-    // Backspace - delete character before cursor
+    // Backspace - delete selection or character before cursor
     pub fn backspace(&mut self) {
-        if let Some(selection) = self.selection.take() {
-            self.delete_selection(selection);
-        } else if self.cursor_pos.column > 0 {
-            let pos = self.cursor_pos;
-            let buffer = self.current_buffer_mut();
-            if pos.line < buffer.lines.len() {
-                let line = &mut buffer.lines[pos.line];
-                let grapheme_count = grapheme_len(line);
-                if pos.column <= grapheme_count && pos.column > 0 {
-                    let _ = remove_grapheme_at(line, pos.column - 1);
-                    self.cursor_pos.column -= 1;
-                    self.mark_dirty();
-                }
-            }
-        } else if self.cursor_pos.line > 0 {
-            let pos = self.cursor_pos;
-            let buffer = self.current_buffer_mut();
-            let current_line = buffer.lines[pos.line].clone();
-            let prev_line_len = grapheme_len(&buffer.lines[pos.line - 1]);
-            // Merge lines
-            buffer.lines[pos.line - 1].push_str(&current_line);
-            buffer.lines.remove(pos.line);
-            self.cursor_pos = TextPosition {
-                line: self.cursor_pos.line - 1,
-                column: prev_line_len,
-            };
-            self.clamp_cursor();
+        let pairs = self.auto_pairs();
+        if self.editor_mut().backspace_pair(&pairs) {
             self.mark_dirty();
         }
     }
 
-    fn delete_selection(&mut self, selection: Selection) {
-        let (start, end) = selection.get_range();
-        let buffer = self.current_buffer_mut();
+    // Ctrl+Backspace / Ctrl+Delete
+    pub fn delete_word(&mut self, forward: bool) {
+        if self.editor_mut().delete_word(forward) {
+            self.mark_dirty();
+        }
+    }
 
-        if start.line == end.line {
-            // Single line deletion (grapheme-aware)
-            if let Some(line) = buffer.lines.get_mut(start.line) {
-                let line_graphemes = grapheme_len(line);
-                let start_col = start.column.min(line_graphemes);
-                let end_col = end.column.min(line_graphemes);
-                let byte_start = grapheme_to_byte_idx(line, start_col);
-                let byte_end = grapheme_to_byte_idx(line, end_col);
-                line.drain(byte_start..byte_end);
-            }
-        } else {
-            // Multi-line deletion (grapheme-aware)
-            let before_text = if let Some(line) = buffer.lines.get(start.line) {
-                grapheme_slice(line, 0, start.column.min(grapheme_len(line)))
-            } else {
-                String::new()
-            };
-
-            let after_text = if let Some(line) = buffer.lines.get(end.line) {
-                let gcount = grapheme_len(line);
-                grapheme_slice(line, end.column.min(gcount), gcount)
-            } else {
-                String::new()
-            };
-
-            // Remove lines in range
-            for _ in start.line..=end.line {
-                if start.line < buffer.lines.len() {
-                    buffer.lines.remove(start.line);
-                }
-            }
+    // Line commands and other whole-editor edits
+    pub fn edit_with(&mut self, command: impl FnOnce(&mut Editor) -> bool) {
+        if command(self.editor_mut()) {
+            self.mark_dirty();
+        }
+    }
 
-            buffer
-                .lines
-                .insert(start.line, format!("{}{}", before_text, after_text));
+    pub fn undo(&mut self) {
+        if self.editor_mut().undo() {
+            self.mark_dirty();
         }
+    }
 
-        self.cursor_pos = start;
-        self.mark_dirty();
+    pub fn redo(&mut self) {
+        if self.editor_mut().redo() {
+            self.mark_dirty();
+        }
     }
 
     // Save current file
@@ -282,16 +294,22 @@ impl EditorState {
             .map(|name| name.to_string_lossy().into_owned())
             .unwrap_or_else(|| full_path.clone());
 
-        let buffer = self.current_buffer();
-        let last_line = buffer
-            .lines
-            .iter()
-            .rposition(|line| !line.is_empty())
-            .unwrap_or(0);
-        let content_lines: Vec<String> = buffer.lines.iter().take(last_line + 1).cloned().collect();
-        let content = content_lines.join("\n");
+        // Hooks follow the file type of the name saved to
+        let filetype = get_friendly_filetype(path_buf.extension().map(|e| e.to_string_lossy().into_owned()));
+        let hooks = self.save.for_filetype(filetype.as_deref());
+        let formatter = filetype.as_deref().and_then(|filetype| self.format.command_for(filetype)).map(String::from);
+        let dir = path_buf.parent().map(|dir| dir.to_path_buf());
+        let (_, error) = hooks.before_save(self.editor_mut(), formatter.as_deref(), dir.as_deref());
+        if let Some(e) = &error {
+            self.set_message(e.clone());
+        }
+        let content = hooks.file_text(&self.editor().content());
 
         fs::write(&full_path, content)?;
+        if let Some(command) = &hooks.after {
+            // The command's result is reported with the formatter's error, not over it
+            self.after_save.start(command, dir, error);
+        }
 
         // Update BOTH filepath (full path for saving) and filename (display name)
         self.tab_manager.current_tab_mut().filepath = Some(full_path.clone());
@@ -306,6 +324,9 @@ impl EditorState {
         self.tab_manager.current_tab_mut().filetype = friendly_filetype;
 
         self.mark_clean();
+        // Possibly a new name, in another repository
+        let tab = self.tab_manager.current_tab();
+        self.git.reload(tab.id, tab.filepath.as_deref());
 
         let _ = self.tab_manager.save_session();
 
@@ -314,14 +335,7 @@ impl EditorState {
 
     // Copy selection to clipboard using arboard
     pub fn copy_selection(&mut self) {
-        if let Some(ref selection) = self.selection {
-            if !selection.is_active() {
-                return;
-            }
-
-            let (start, end) = selection.get_range();
-            let text = self.extract_text_range(start, end);
-
+        if let Some(text) = self.editor_mut().copy_selection() {
             // Try to use arboard (works on X11 and most Wayland compositors)
             if let Ok(mut clipboard) = arboard::Clipboard::new() {
                 let _ = clipboard.set_text(&text);
@@ -340,22 +354,13 @@ impl EditorState {
     // Cut selection to clipboard
     pub fn cut_selection(&mut self) {
         self.copy_selection();
-        if let Some(selection) = self.selection.take() {
-            if selection.is_active() {
-                self.delete_selection(selection);
-            }
+        if self.editor_mut().delete_selection() {
+            self.mark_dirty();
         }
     }
 
     // Paste from clipboard using arboard
     pub fn paste_from_clipboard(&mut self) {
-        // Delete selection first if active
-        if let Some(selection) = self.selection.take() {
-            if selection.is_active() {
-                self.delete_selection(selection);
-            }
-        }
-
         // Try arboard first
         let text = if let Ok(mut clipboard) = arboard::Clipboard::new() {
             clipboard.get_text().ok()
@@ -369,74 +374,13 @@ impl EditorState {
         if let Some(mut text) = text {
             // Normalize line endings for consistent pasting
             text = text.replace("\r\n", "\n").replace('\r', "\n");
-            self.insert_text(&text);
+            self.paste(&text);
         }
     }
 
     // Select all text
     pub fn select_all(&mut self) {
-        let last_line = self
-            .current_buffer()
-            .lines
-            .iter()
-            .rposition(|line| !line.is_empty())
-            .unwrap_or(0);
-        let last_col = self
-            .current_buffer()
-            .lines
-            .get(last_line)
-            .map(|l| l.len())
-            .unwrap_or(0);
-
-        self.selection = Some(Selection {
-            anchor: TextPosition { line: 0, column: 0 },
-            cursor: TextPosition {
-                line: last_line,
-                column: last_col,
-            },
-        });
-        self.cursor_pos = TextPosition {
-            line: last_line,
-            column: last_col,
-        };
-    }
-
-    fn extract_text_range(&self, start: TextPosition, end: TextPosition) -> String {
-        let mut result = String::new();
-        let buffer = self.current_buffer();
-
-        if start.line == end.line {
-            // Single line
-            if let Some(line) = buffer.lines.get(start.line) {
-                let chars: Vec<char> = line.chars().collect();
-                let text: String = chars
-                    [start.column.min(chars.len())..end.column.min(chars.len())]
-                    .iter()
-                    .collect();
-                result.push_str(&text);
-            }
-        } else {
-            // Multi-line
-            for line_idx in start.line..=end.line {
-                if let Some(line) = buffer.lines.get(line_idx) {
-                    let chars: Vec<char> = line.chars().collect();
-
-                    if line_idx == start.line {
-                        let text: String = chars[start.column.min(chars.len())..].iter().collect();
-                        result.push_str(&text);
-                        result.push('\n');
-                    } else if line_idx == end.line {
-                        let text: String = chars[..end.column.min(chars.len())].iter().collect();
-                        result.push_str(&text);
-                    } else {
-                        result.push_str(line);
-                        result.push('\n');
-                    }
-                }
-            }
-        }
-
-        result
+        self.editor_mut().select_all();
     }
 
     // Search functionality
@@ -454,8 +398,9 @@ impl EditorState {
             let line_lower = line.to_lowercase();
             let mut start = 0;
             while let Some(pos) = line_lower[start..].find(&query) {
-                matches.push((line_idx, start + pos));
-                start += pos + 1;
+                // Columns are graphemes, like the editor's cursor
+                matches.push((line_idx, grapheme_len(&line_lower[..start + pos])));
+                start += pos + query.chars().next().map_or(1, char::len_utf8);
             }
         }
 
@@ -466,7 +411,7 @@ impl EditorState {
         }
 
         // Find the match closest to current cursor
-        let cur = self.cursor_pos;
+        let cur = self.editor().cursor;
         let idx = matches
             .iter()
             .position(|&(line, col)| line > cur.line || (line == cur.line && col >= cur.column))
@@ -501,32 +446,390 @@ impl EditorState {
 
     fn jump_to_current_match(&mut self) {
         if let Some(&(line, col)) = self.search_results.get(self.search_result_idx) {
-            let end_col = col + self.search_query.len();
-            self.cursor_pos = TextPosition { line, column: col };
-            self.selection = Some(Selection {
-                anchor: TextPosition { line, column: col },
-                cursor: TextPosition {
-                    line,
-                    column: end_col,
-                },
-            });
+            let end_col = col + grapheme_len(&self.search_query.to_lowercase());
+            self.editor_mut().select(
+                TextPosition { line, column: col },
+                TextPosition { line, column: end_col },
+            );
             // Scroll to keep match visible
             self.ensure_cursor_visible(None);
         }
     }
 
+    // Bring the language server's copy of the current file up to date and take in what the
+    // servers published and answered; true while one runs, so the app keeps repainting
+    pub fn sync_lsp(&mut self) -> bool {
+        let open: Vec<&str> = self.tab_manager.tabs.iter().filter_map(|tab| tab.filepath.as_deref()).collect();
+        self.lsp.keep_open(&open);
+        let tab = self.tab_manager.current_tab();
+        if let Err(e) = self.lsp.sync(tab.filepath.as_deref(), tab.filetype.as_deref(), &tab.editor) {
+            self.set_message(e);
+        }
+        if self.lsp.poll() {
+            self.take_lsp_replies();
+        }
+        self.lsp.is_active()
+    }
+
+    // Act on what the language servers answered. Hover text, definitions and completions
+    // only apply while the cursor is still where they were asked for.
+    fn take_lsp_replies(&mut self) {
+        for reply in self.lsp.replies() {
+            let path = self.tab_manager.current_tab().filepath.clone();
+            let cursor = self.editor().cursor;
+            let here = |asked: &str, pos: TextPosition| path.as_deref() == Some(asked) && pos == cursor;
+            match reply {
+                Ok(Reply::Hover { path, pos, text }) if here(&path, pos) => match text {
+                    Some(text) => self.hover = Some((pos, text)),
+                    None => self.set_message("Nothing to show here".to_string()),
+                },
+                Ok(Reply::Definition { path, pos, locations }) if here(&path, pos) => self.jump_to_definition(locations),
+                Ok(Reply::Completion { path, pos, words }) if here(&path, pos) && !words.is_empty() => {
+                    let buffer_words = self.completion.take();
+                    self.completion = self.completer.with_server_items(&self.current_buffer().lines, pos, buffer_words, words);
+                }
+                Ok(Reply::Rename { new_name, files }) => self.rename_everywhere(&new_name, &files),
+                Ok(_) => {}
+                Err(e) => self.set_message(e),
+            }
+        }
+    }
+
+    // Report the post-save command once it finishes; true while it runs
+    pub fn poll_after_save(&mut self) -> bool {
+        if let Some(result) = self.after_save.poll() {
+            self.set_message(result.unwrap_or_else(|e| format!("✖ {}", e)));
+            // The command may have staged or committed the file
+            let tab = self.tab_manager.current_tab();
+            self.git.reload(tab.id, tab.filepath.as_deref());
+        }
+        self.after_save.is_running()
+    }
+
+    // The post-save command still running, for the status bar
+    pub fn after_save_command(&self) -> Option<&str> {
+        self.after_save.command()
+    }
+
+    // Start reading the current file from git the first time its tab is shown, take in the
+    // reads that finished, and work out its changes for the text as it is now. True while
+    // a read is still running.
+    pub fn sync_git(&mut self) -> bool {
+        let ids: Vec<u64> = self.tab_manager.tabs.iter().map(|tab| tab.id).collect();
+        self.git.retain(&ids);
+        let tab = self.tab_manager.current_tab();
+        self.git.load(tab.id, tab.filepath.as_deref());
+        self.git.poll();
+        let editor = &tab.editor;
+        if let Some(git) = self.git.get_mut(tab.id) {
+            git.update(&editor.buffer.lines[..=editor.last_content_line()]);
+        }
+        self.git.is_loading()
+    }
+
+    // The current tab's git changes, once worked out
+    pub fn git(&self) -> Option<&GitFile> {
+        self.git_of(self.tab_manager.current_tab().id)
+    }
+
+    // What git knows of a tab's file; only the current tab's changes are kept up to date
+    pub fn git_of(&self, tab_id: u64) -> Option<&GitFile> {
+        (tab_id == self.tab_manager.current_tab().id).then(|| self.git.get(tab_id)).flatten()
+    }
+
+    // Why there are no changes to show for the current tab
+    fn no_git_message(&mut self) {
+        let message = if self.git.is_loading_tab(self.tab_manager.current_tab().id) {
+            "Still reading the file from git"
+        } else {
+            "Not in a git repository"
+        };
+        self.set_message(message.to_string());
+    }
+
+    // The change at the cursor as `git diff` shows it
+    pub fn hunk_diff(&mut self) -> Option<Vec<String>> {
+        self.sync_git();
+        let Some(git) = self.git() else {
+            self.no_git_message();
+            return None;
+        };
+        let diff = git.hunk_at(self.editor().cursor.line).map(|hunk| git.hunk_diff(hunk));
+        if diff.is_none() {
+            self.set_message("No change here".to_string());
+        }
+        diff
+    }
+
+    // Put the change at the cursor back the way it is staged
+    pub fn revert_hunk(&mut self) {
+        self.sync_git();
+        let Some(git) = self.git() else {
+            return self.no_git_message();
+        };
+        let Some(text) = git.hunk_at(self.editor().cursor.line).map(|hunk| git.reverted(hunk)) else {
+            return self.set_message("No change here".to_string());
+        };
+        if self.editor_mut().replace_all(&text) {
+            self.mark_dirty();
+        }
+    }
+
+    // The server's diagnostics and a failed JSON/TOML/YAML validation
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics_of(self.tab_manager.current_tab())
+    }
+
+    pub fn diagnostics_of(&self, tab: &Tab) -> Vec<Diagnostic> {
+        let server = self.lsp.diagnostics(tab.filepath.as_deref());
+        server.iter().chain(&tab.editor.data_error).cloned().collect()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .filter(|(_, since)| since.elapsed() < MESSAGE_TIME)
+            .map(|(message, _)| message.as_str())
+    }
+
+    pub(in crate::gui) fn set_message(&mut self, message: String) {
+        self.message = Some((message, std::time::Instant::now()));
+    }
+
+    // Path of the current file for a language server request, synced first
+    fn lsp_path(&mut self) -> Option<String> {
+        self.sync_lsp();
+        let path = self.tab_manager.current_tab().filepath.clone();
+        if path.is_none() {
+            self.set_message("Save the file to use its language server".to_string());
+        }
+        path
+    }
+
+    // Ask for the documentation of the symbol under the cursor, shown when the server answers
+    pub fn show_hover(&mut self) {
+        let Some(path) = self.lsp_path() else {
+            return;
+        };
+        if let Err(e) = self.lsp.hover(&path, self.editor().cursor) {
+            self.set_message(e);
+        }
+    }
+
+    // Ask where the symbol under the cursor is defined; the jump happens when the server answers
+    pub fn go_to_definition(&mut self) {
+        let Some(path) = self.lsp_path() else {
+            return;
+        };
+        if let Err(e) = self.lsp.definition(&path, self.editor().cursor) {
+            self.set_message(e);
+        }
+    }
+
+    // Jump to a definition, opening its file when needed
+    fn jump_to_definition(&mut self, locations: Vec<Location>) {
+        match locations.into_iter().next() {
+            // The tab that would make room has edits that aren't saved
+            Some(location) if self.tab_manager.would_close_unsaved(&location.path) => {
+                self.set_message("Too many tabs: save or close one first".to_string())
+            }
+            Some(location) => match self.tab_manager.open_file_in_new_tab(&location.path) {
+                Ok(_) => {
+                    self.completion = None;
+                    self.go_to(location.pos);
+                }
+                Err(e) => self.set_message(e.to_string()),
+            },
+            None => self.set_message("No definition found".to_string()),
+        }
+    }
+
+    // Ask the server to rename the symbol under the cursor; see rename_everywhere()
+    pub fn rename_symbol(&mut self, new_name: &str) {
+        let Some(path) = self.lsp_path() else {
+            return;
+        };
+        if let Err(e) = self.lsp.rename(&path, self.editor().cursor, new_name) {
+            self.set_message(e);
+        }
+    }
+
+    // Rename the symbol everywhere the server found it. Files get the edits in their tabs,
+    // unsaved and undoable; files not open are opened in new tabs.
+    fn rename_everywhere(&mut self, new_name: &str, files: &[(String, Vec<TextEdit>)]) {
+        if files.is_empty() {
+            return self.set_message("Nothing to rename here".to_string());
+        }
+        let (changed, skipped) = self.tab_manager.apply_file_edits(files);
+        for tab in &self.tab_manager.tabs {
+            let _ = self.lsp.sync(tab.filepath.as_deref(), tab.filetype.as_deref(), &tab.editor);
+        }
+        self.set_message(match skipped {
+            0 => format!("Renamed to '{}' in {} file(s)", new_name, changed),
+            _ => format!("Renamed to '{}' in {} file(s), {} not opened - too many tabs", new_name, changed, skipped),
+        });
+    }
+
+    // The text and directory of the current tab, when it is a Markdown file to preview
+    pub fn markdown_source(&self) -> Option<(String, Option<std::path::PathBuf>)> {
+        let tab = self.tab_manager.current_tab();
+        (tab.filetype.as_deref() == Some("Markdown")).then(|| (tab.editor.content(), self.file_dir()))
+    }
+
+    // Split the editor, or turn an existing split the other way
+    pub fn split_screen(&mut self, direction: SplitDirection) {
+        if let Some(split) = self.split.as_mut() {
+            split.direction = direction;
+            return;
+        }
+        let id = self.tab_manager.current_tab().id;
+        self.split = Some(Split::new(direction, PaneView::new(id, self.editor(), self.scroll_offset)));
+    }
+
+    pub fn close_split(&mut self) {
+        self.split = None;
+    }
+
+    // Move focus to the other pane; the one left keeps its carets and scroll position
+    pub fn focus_other_pane(&mut self) {
+        self.track_other_pane();
+        let Some(split) = self.split.take() else {
+            return self.set_message("One pane only - Alt+W splits the editor".to_string());
+        };
+        // Its tab was closed meanwhile, which ends the split
+        let Some(index) = self.tab_manager.index_of(split.other.tab_id) else {
+            return;
+        };
+        let here = PaneView::new(self.tab_manager.current_tab().id, self.editor(), self.scroll_offset);
+        self.tab_manager.active_tab_index = index;
+        self.scroll_offset = split.other.focus(self.editor_mut());
+        self.split = Some(Split { direction: split.direction, other: here, focus_second: !split.focus_second });
+        self.completion = None;
+        self.hover = None;
+    }
+
+    // When both panes show the current tab, move the other pane's carets and scroll position
+    // along with the edits made in this one; edits to a tab only this pane shows are dropped
+    pub fn track_other_pane(&mut self) {
+        let tab = self.tab_manager.current_tab_mut();
+        let edits = tab.editor.take_line_edits();
+        if let Some(split) = self.split.as_mut().filter(|split| split.other.tab_id == tab.id) {
+            for (first, old_last, new_last) in edits {
+                split.other.on_edit(first, old_last, new_last);
+            }
+        }
+    }
+
+    // Directory of the current file, where formatters look for their config
+    fn file_dir(&self) -> Option<std::path::PathBuf> {
+        let path = self.tab_manager.current_tab().filepath.as_deref()?;
+        std::path::Path::new(path).parent().map(|dir| dir.to_path_buf())
+    }
+
+    // Pipe the document through the file type's formatter and apply only what it changed
+    pub fn format_document(&mut self) {
+        let filetype = self.tab_manager.current_tab().filetype.clone().unwrap_or_default();
+        let Some(command) = self.format.command_for(&filetype).map(String::from) else {
+            let name = if filetype.is_empty() { "this file type" } else { &filetype };
+            return self.set_message(format!("No formatter for {} - add one under format.commands in settings.json", name));
+        };
+        match formatter::format_text(&command, &self.editor().content(), self.file_dir().as_deref()) {
+            Ok(text) => {
+                if self.editor_mut().replace_all(&text) {
+                    self.mark_dirty();
+                } else {
+                    self.set_message("Already formatted".to_string());
+                }
+            }
+            Err(e) => self.set_message(e),
+        }
+    }
+
+    // Replace the selection (or the cursor's line) with what a shell command prints for it
+    pub fn filter_selection(&mut self, command: &str) {
+        let (start, end) = self.editor().filter_range();
+        let input = self.editor().text_range(start, end);
+        match formatter::filter_text(command, &input, self.file_dir().as_deref()) {
+            Ok(text) => self.edit_with(|editor| editor.replace_minimal(start, end, &text)),
+            Err(e) => self.set_message(e),
+        }
+    }
+
+    fn data_format(&mut self) -> Option<Format> {
+        let format = Format::from_filetype(self.tab_manager.current_tab().filetype.as_deref());
+        if format.is_none() {
+            self.set_message("Not a JSON, TOML or YAML file".to_string());
+        }
+        format
+    }
+
+    // Mark where the document stops parsing and put the cursor there
+    fn show_data_error(&mut self, format: Format, error: ParseError) {
+        self.set_message(error.summary(format));
+        self.editor_mut().data_error = Some(error.diagnostic(format));
+        self.completion = None;
+        self.go_to(error.position());
+    }
+
+    pub fn validate_data(&mut self) {
+        let Some(format) = self.data_format() else {
+            return;
+        };
+        match format.parse(&self.editor().content()) {
+            Ok(_) => {
+                self.editor_mut().data_error = None;
+                self.set_message(format!("✔ Valid {}", format.name()));
+            }
+            Err(e) => self.show_data_error(format, e),
+        }
+    }
+
+    // Pretty-print, minify or sort the keys of a JSON document, changing only what differs
+    pub fn rewrite_json(&mut self, rewrite: fn(&str) -> Result<String, ParseError>) {
+        if self.data_format() != Some(Format::Json) {
+            return self.set_message("Only JSON files can be pretty-printed, minified or sorted".to_string());
+        }
+        match rewrite(&self.editor().content()) {
+            Ok(text) => self.edit_with(|editor| editor.replace_all(&text)),
+            Err(e) => self.show_data_error(Format::Json, e),
+        }
+    }
+
+    // Convert the document to another format in a new tab
+    pub fn convert_data(&mut self, to: Format) {
+        let Some(from) = self.data_format() else {
+            return;
+        };
+        match from.parse(&self.editor().content()) {
+            Ok(value) => match to.write(&value) {
+                Ok(text) => {
+                    self.completion = None;
+                    self.tab_manager.new_tab_with(text, Some(to.name().to_string()));
+                }
+                Err(e) => self.set_message(e),
+            },
+            Err(e) => self.show_data_error(from, e),
+        }
+    }
+
+    // Jump to a go-to target, keeping a few lines of context above it
+    pub fn go_to(&mut self, pos: TextPosition) {
+        self.editor_mut().move_to(pos, false);
+        self.scroll_offset.0 = self.editor().cursor.line.saturating_sub(GOTO_CONTEXT_LINES);
+    }
+
     pub fn clear_search(&mut self) {
         self.search_active = false;
         self.search_query.clear();
         self.search_results.clear();
         self.search_result_idx = 0;
-        self.selection = None;
+        self.editor_mut().selection = None;
     }
 
     // Ensure cursor is visible within the current viewport height (in lines).
     // visible_rows should be the number of text rows visible in the editor area.
     pub(in crate::gui) fn ensure_cursor_visible(&mut self, visible_rows: Option<usize>) {
-        let line = self.cursor_pos.line;
+        let line = self.editor().cursor.line;
         // If cursor above viewport, jump viewport up
         if line < self.scroll_offset.0 {
             self.scroll_offset.0 = line;
@@ -539,9 +842,13 @@ impl EditorState {
                 return;
             }
 
-            if line >= self.scroll_offset.0 + visible_rows {
+            // Folded lines take no rows, so count the visible lines down to the cursor
+            let folds = &self.editor().folds;
+            let line_count = self.current_buffer().lines.len();
+            let shown = folds.visible_lines(self.scroll_offset.0, line_count).take(visible_rows).any(|l| l == line);
+            if !shown {
                 // place cursor roughly in middle of viewport
-                self.scroll_offset.0 = line.saturating_sub(visible_rows / 2);
+                self.scroll_offset.0 = folds.step(line, visible_rows / 2, false, line_count);
             }
         }
     }
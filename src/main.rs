@@ -2,6 +2,8 @@ mod core;
 mod tui;
 mod gui;
 
+use core::goto::split_location;
+use core::selection::TextPosition;
 use std::env;
 
 fn main() {
@@ -11,6 +13,8 @@ fn main() {
     let want_uninstall  = args.iter().any(|a| a == "--uninstall");
     let want_shortcuts  = args.iter().any(|a| a == "--shortcuts");
     let want_gui        = args.iter().any(|a| a == "--gui");
+    let want_diff       = args.iter().any(|a| a == "--diff");
+    let want_wait       = args.iter().any(|a| a == "--wait");
 
     if want_uninstall {
         uninstall();
@@ -34,34 +38,72 @@ fn main() {
         if let Some(true) = should_self_install() {
             println!(" Quick Notepad: First-time setup detected...");
             install();
-            gui::run(None);
+            gui::run(None, None, None);
             return;
         }
     }
 
+    if want_diff {
+        let files: Vec<&String> = args.iter().skip(1).filter(|a| !a.starts_with("--")).collect();
+        let [left, right] = files[..] else {
+            eprintln!("Usage: quick --diff <file> <file> [--gui]");
+            std::process::exit(2);
+        };
+        if want_gui {
+            gui::run(Some(left.clone()), None, Some(right.clone()));
+            return;
+        }
+        let editor = match tui::TerminalEditor::compare_files(left, right) {
+            Ok(editor) => editor,
+            Err(e) => {
+                eprintln!("Error comparing {} and {}: {}", left, right, e);
+                std::process::exit(1);
+            }
+        };
+        run(editor);
+        return;
+    }
+
+    let file_arg = file_argument(&args);
+
+    // As $EDITOR: `--wait`, or a file git asks to have written (COMMIT_EDITMSG, ...)
+    let is_git_message = file_arg.as_ref().is_some_and(|(path, _)| {
+        std::path::Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| core::git::message_filetype(name).is_some())
+    });
+    if want_wait || is_git_message {
+        // Waiting is done in the terminal only; opening it there instead would fail without
+        // a tty, as when git runs the editor from a GUI client
+        if want_gui {
+            eprintln!("Usage: quick --wait <file> (--wait and commit messages edit in the terminal, not with --gui)");
+            std::process::exit(2);
+        }
+        let Some((path, at)) = file_arg else {
+            eprintln!("Usage: quick --wait <file>");
+            std::process::exit(2);
+        };
+        wait_for(&path, at);
+    }
+
     if want_gui {
-        // First non-flag argument after argv[0] is the optional file path
-        let file_path = args.iter()
-            .skip(1)
-            .find(|a| !a.starts_with("--"))
-            .cloned();
-        gui::run(file_path);
+        let (file_path, at) = match file_arg {
+            Some((path, at)) => (Some(path), at),
+            None => (None, None),
+        };
+        gui::run(file_path, at, None);
     } else {
         // TUI mode
-        let file_arg = args.iter()
-            .skip(1)
-            .find(|a| !a.starts_with("--"))
-            .map(String::as_str);
-
-        let mut editor = match file_arg {
-            Some(raw_path) => {
+        let editor = match file_arg {
+            Some((raw_path, at)) => {
                 // Resolve the path — but do NOT use canonicalize() if the file
                 // might not exist yet (e.g. `quick newfile.txt`)
-                let path_buf = std::fs::canonicalize(raw_path)
-                    .unwrap_or_else(|_| std::path::PathBuf::from(raw_path));
+                let path_buf = std::fs::canonicalize(&raw_path)
+                    .unwrap_or_else(|_| std::path::PathBuf::from(&raw_path));
                 let full_path = path_buf.to_string_lossy().into_owned();
 
-                match tui::TerminalEditor::open_file(&full_path) {
+                match tui::TerminalEditor::open_file(&full_path, at) {
                     Ok(ed) => ed,
                     Err(e) => {
                         eprintln!("Error opening file {}: {}", full_path, e);
@@ -72,8 +114,67 @@ fn main() {
             None => tui::TerminalEditor::open_fresh(),
         };
 
-        editor.run();
+        run(editor);
+    }
+}
+
+// Run the terminal editor and exit non-zero when it fails. The editor goes first, as
+// dropping it saves the session and shuts down language servers, which exit() would skip
+fn run(mut editor: tui::TerminalEditor) {
+    let result = editor.run();
+    drop(editor);
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// Edit one file in the terminal for a program waiting on it, then exit: 0 once saved, 1 when
+// the editor failed, the file was never saved or the changes were thrown away (git then
+// aborts the commit)
+fn wait_for(path: &str, at: Option<TextPosition>) -> ! {
+    let mut editor = match tui::TerminalEditor::wait_for(path, at) {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Error opening file {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let result = editor.run();
+    let done = editor.saved() && !editor.discarded_changes();
+    // Language servers are shut down when the editor goes, which exit() would skip
+    drop(editor);
+    if let Err(e) = &result {
+        eprintln!("Error: {}", e);
+    }
+    std::process::exit(if result.is_ok() && done { 0 } else { 1 });
+}
+
+// The file to open and where to put the cursor in it.
+//
+// The first non-flag argument is the file. A `+line` argument picks the line, as
+// does a `path:line[:col]` suffix when no file with that literal name exists.
+fn file_argument(args: &[String]) -> Option<(String, Option<TextPosition>)> {
+    let mut path = None;
+    let mut line = None;
+    for arg in args.iter().skip(1).filter(|a| !a.starts_with("--")) {
+        if let Some(Ok(n)) = arg.strip_prefix('+').map(str::parse::<usize>) {
+            line = Some(n);
+        } else if path.is_none() {
+            path = Some(arg.as_str());
+        }
+    }
+    let path = path?;
+
+    if let Some(line) = line {
+        let at = TextPosition { line: line.saturating_sub(1), column: 0 };
+        return Some((path.to_string(), Some(at)));
+    }
+    if std::path::Path::new(path).exists() {
+        return Some((path.to_string(), None));
     }
+    let (path, at) = split_location(path);
+    Some((path.to_string(), at))
 }
 
 // Detect elevated / root execution without external crates
@@ -315,6 +416,7 @@ fn uninstall() {
         (format!("{}/.local/share/applications/quick-notepad.desktop", home),           "Desktop entry"),
         (format!("{}/.local/share/icons/hicolor/512x512/apps/quick_notepad.png", home), "Icon"),
         (format!("{}/.quicknotepad/tabs.json", home),                                   "Session data"),
+        (format!("{}/.quicknotepad/settings.json", home),                               "Settings"),
     ];
 
     println!("\n Removing files...");
@@ -0,0 +1,171 @@
+// backend module - where rendered frames and the caret end up
+// The view only ever talks to a Backend, so rendering can run without a real terminal.
+use crate::tui::{
+    caret::{Caret, Position},
+    frame::{Cell, Frame, Style},
+    terminal::{Size, Terminal},
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+use std::io::{stdout, Error, Write};
+
+pub trait Backend {
+    fn size(&self) -> Result<Size, Error>;
+
+    // Show a finished frame and put the caret back where it was
+    fn draw(&mut self, frame: &Frame) -> Result<(), Error>;
+
+    // Move the caret to a screen cell, or hide it when the cursor is scrolled away
+    fn show_caret(&mut self, caret: &Caret) -> Result<(), Error>;
+}
+
+// Draws to stdout, only emitting the cells that changed since the previous frame
+pub struct CrosstermBackend {
+    previous: Option<Frame>,
+    caret: Option<Position>,
+    caret_visible: bool,
+}
+
+impl CrosstermBackend {
+    pub fn new() -> Self {
+        Self {
+            previous: None,
+            caret: None,
+            // Terminal::initialize hides the caret until the first sync
+            caret_visible: false,
+        }
+    }
+
+    fn set_style(style: Style) -> Result<(), Error> {
+        queue!(
+            stdout(),
+            SetAttribute(Attribute::Reset),
+            SetForegroundColor(style.fg.unwrap_or(Color::Reset)),
+            SetBackgroundColor(style.bg.unwrap_or(Color::Reset)),
+        )?;
+        if style.bold {
+            queue!(stdout(), SetAttribute(Attribute::Bold))?;
+        }
+        if style.italic {
+            queue!(stdout(), SetAttribute(Attribute::Italic))?;
+        }
+        if style.underline {
+            queue!(stdout(), SetAttribute(Attribute::Underlined))?;
+        }
+        Ok(())
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn size(&self) -> Result<Size, Error> {
+        Terminal::get_size()
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<(), Error> {
+        // A resize (or the first frame) repaints everything on a cleared screen
+        let previous = match self.previous.take() {
+            Some(previous) if previous.size() == frame.size() => previous,
+            _ => {
+                queue!(stdout(), ResetColor, Clear(ClearType::All))?;
+                Frame::new(frame.size())
+            }
+        };
+
+        // Where the terminal cursor is after the last print, to skip redundant moves
+        let mut pen: Option<(u16, u16)> = None;
+        let mut style: Option<Style> = None;
+
+        for (x, y, cell) in frame.diff(&previous) {
+            if cell.is_continuation() {
+                continue;
+            }
+            if pen != Some((x, y)) {
+                queue!(stdout(), MoveTo(x, y))?;
+            }
+            if style != Some(cell.style) {
+                Self::set_style(cell.style)?;
+                style = Some(cell.style);
+            }
+            queue!(stdout(), Print(&cell.symbol))?;
+
+            let width = if frame.cell(x + 1, y).is_some_and(Cell::is_continuation) { 2 } else { 1 };
+            pen = Some((x + width, y));
+        }
+
+        queue!(stdout(), SetAttribute(Attribute::Reset), ResetColor)?;
+        if let Some(pos) = self.caret {
+            queue!(stdout(), MoveTo(pos.x, pos.y))?;
+        }
+        self.previous = Some(frame.clone());
+        Ok(())
+    }
+
+    fn show_caret(&mut self, caret: &Caret) -> Result<(), Error> {
+        match caret.position() {
+            Some(pos) => {
+                if !self.caret_visible {
+                    queue!(stdout(), Show)?;
+                }
+                queue!(stdout(), MoveTo(pos.x, pos.y))?;
+            }
+            None if self.caret_visible => queue!(stdout(), Hide)?,
+            None => {}
+        }
+        self.caret = caret.position();
+        self.caret_visible = self.caret.is_some();
+        stdout().flush()
+    }
+}
+
+// Keeps frames in memory so tests can look at the screen
+#[cfg(test)]
+#[derive(Clone)]
+pub struct TestBackend {
+    size: Size,
+    state: std::rc::Rc<std::cell::RefCell<(Option<Frame>, Option<Position>)>>,
+}
+
+#[cfg(test)]
+impl TestBackend {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { size: Size { width, height }, state: Default::default() }
+    }
+
+    pub fn frame(&self) -> Frame {
+        self.state.borrow().0.clone().expect("nothing rendered yet")
+    }
+
+    pub fn caret(&self) -> Option<Position> {
+        self.state.borrow().1
+    }
+
+    // Every row of the last frame, for comparing against an expected screen
+    pub fn snapshot(&self) -> String {
+        let frame = self.frame();
+        (0..frame.size().height)
+            .map(|y| frame.row_text(y))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+impl Backend for TestBackend {
+    fn size(&self) -> Result<Size, Error> {
+        Ok(self.size)
+    }
+
+    fn draw(&mut self, frame: &Frame) -> Result<(), Error> {
+        self.state.borrow_mut().0 = Some(frame.clone());
+        Ok(())
+    }
+
+    fn show_caret(&mut self, caret: &Caret) -> Result<(), Error> {
+        self.state.borrow_mut().1 = caret.position();
+        Ok(())
+    }
+}
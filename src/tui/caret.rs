@@ -1,13 +1,10 @@
-// caret module responsible for caret manipulation and settings
-use crate::tui::terminal::Terminal;
-use crossterm::{
-    cursor::{ SetCursorStyle, MoveTo },
-    style::Print,
-    queue,
-};
-use std::io::{ stdout, Error, Write };
+// caret module responsible for the terminal caret's placement and its settings
+// The document cursor lives in the view's editor; the caret only mirrors it on screen.
+// The backend turns the caret's placement into terminal output.
+use crossterm::cursor::SetCursorStyle;
 
-#[derive(Copy, Clone, Debug)]
+// Screen cell (terminal coordinates), not a document position
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Position {
     pub x: u16,
     pub y: u16,
@@ -18,170 +15,38 @@ impl Position {
     pub const HEADER: u16 = 1; // Height of the header
 }
 
-impl Default for Position {
-    fn default() -> Self {
-        Self { 
-            x: Self::MARGIN, 
-            y: Self::HEADER 
-        } 
-    }
-}
-
 pub struct Caret {
     pub color: &'static str,
     pub style: SetCursorStyle,
-    position: Position,
+    // None while the cursor is scrolled out of view
+    position: Option<Position>,
 }
 
 impl Caret {
-    pub const CARET_SETTINGS: Caret = Caret { 
-        color: "yellow", 
+    pub const CARET_SETTINGS: Caret = Caret {
+        color: "yellow",
         style: SetCursorStyle::BlinkingBar,
-        position: Position { x: 4, y: 0 },
+        position: None,
     };
-    
+
     pub fn new() -> Self {
         Self {
-            color: "yellow",
-            style: SetCursorStyle::BlinkingBar,
-            position: Position::default(),
+            color: Self::CARET_SETTINGS.color,
+            style: Self::CARET_SETTINGS.style,
+            position: None,
         }
     }
-    
-    pub fn set_caret_color(color: &str) -> Result<(), Error> {
-        queue!(stdout(), Print(format!("\x1b]12;{}\x07", color)))?;
-        Ok(())
-    }
 
-    pub fn reset_caret_color() -> Result<(), Error> {
-        queue!(stdout(), Print("\x1b]112\x07"))?;
-        Ok(())
+    pub fn move_to(&mut self, pos: Position) {
+        self.position = Some(pos);
     }
 
-    pub fn move_to(&mut self, pos: Position) -> Result<(), Error> {
-        self.position = pos;
-        queue!(stdout(), MoveTo(pos.x, pos.y))?;
-        stdout().flush()?;
-        Ok(())
-    }
-    
-    pub fn get_position(&self) -> Position {
-        self.position
-    }
-    
-    pub fn next_line(&mut self) -> Result<(), Error> {
-        let size = Terminal::get_size()?;
-        
-        if self.position.y < size.height - 2 {
-            self.position.x = 4;
-            self.position.y += 1;
-            self.move_to(self.position)?;
-        }
-        Ok(())
+    // Hide the caret while the cursor is scrolled out of view
+    pub fn hide(&mut self) {
+        self.position = None;
     }
 
-    pub fn move_left(&mut self, scroll_offset: usize) -> Result<usize, Error> {
-        let size = Terminal::get_size()?;
-        let mut new_offset = scroll_offset;
-        
-        // respect MARGIN
-        if self.position.x > Position::MARGIN {
-            self.position.x -= 1;
-            self.move_to(self.position)?;
-        } else if self.position.y > Position::HEADER {
-            self.position.x = size.width - 1;
-            self.position.y -= 1;
-            self.move_to(self.position)?;
-        } else if scroll_offset > 0 {
-            self.position.x = size.width - 1;
-            new_offset -= 1;
-        }
-        Ok(new_offset)
-    }
-    
-    pub fn move_right(&mut self, scroll_offset: usize, max_lines: usize) -> Result<usize, Error> {
-        let size = Terminal::get_size()?;
-        let mut new_offset = scroll_offset;
-        
-        if self.position.x < size.width - 1 {
-            self.position.x += 1;
-            self.move_to(self.position)?;
-        } else if self.position.y < size.height - 2 {
-            self.position.x = 4;
-            self.position.y += 1;
-            self.move_to(self.position)?;
-        } else {
-            let max_scroll = max_lines.saturating_sub((size.height - 1) as usize);
-            if scroll_offset < max_scroll {
-                self.position.x = 4;
-                new_offset += 1;
-            }
-        }
-        
-        Ok(new_offset)
-    }
-
-    pub fn move_up(&mut self, scroll_offset: usize) -> Result<usize, Error> {
-        let mut new_offset = scroll_offset;
-        
-        if self.position.y == Position::HEADER && scroll_offset > 0 {
-            new_offset -= 1;
-        } else if self.position.y > Position::HEADER {
-            self.position.y -= 1;
-            self.move_to(self.position)?;
-        }
-        
-        Ok(new_offset)
-    }
-
-    pub fn move_down(&mut self, scroll_offset: usize, max_lines: usize) -> Result<usize, Error> {
-        let size = Terminal::get_size()?;
-        let mut new_offset = scroll_offset;
-        
-        if self.position.y >= size.height - 2 {
-            let max_scroll = max_lines.saturating_sub((size.height - 1) as usize);
-            if scroll_offset < max_scroll {
-                new_offset += 1;
-            }
-        } else if self.position.y < size.height - 2 {
-            self.position.y += 1;
-            self.move_to(self.position)?;
-        }
-        
-        Ok(new_offset)
-    }
-
-    pub fn move_top(&mut self) -> Result<usize, Error> {
-        self.position.y = 0;
-        self.move_to(self.position)?;
-        Ok(0)
-    }
-
-    pub fn move_bottom(&mut self) -> Result<(), Error> {
-        let size = Terminal::get_size()?;
-        self.position.y = size.height - 2;
-        self.move_to(self.position)?;
-        Ok(())
-    }
-
-    pub fn move_max_left(&mut self) -> Result<(), Error> {
-        self.position.x = Position::MARGIN;
-        self.move_to(self.position)?;
-        Ok(())
-    }
-
-    pub fn move_max_right(&mut self) -> Result<(), Error> {
-        let size = Terminal::get_size()?;
-        self.position.x = size.width - 1;
-        self.move_to(self.position)?;
-        Ok(())
-    }
-    
-    pub fn clamp_to_bounds(&mut self) -> Result<(), Error> {
-        let size = Terminal::get_size()?;
-        self.position.x = self.position.x.min(size.width - 1).max(4);
-        self.position.y = self.position.y.min(size.height - 2);
-        self.move_to(self.position)?;
-        Ok(())
+    pub fn position(&self) -> Option<Position> {
+        self.position
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,196 @@
+// frame module - an in-memory grid of terminal cells that the view renders into
+// Backends turn finished frames into terminal output (or keep them for tests).
+use crate::core::graphemes::visual_width;
+use crate::tui::terminal::Size;
+use crossterm::style::Color;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn fg(self, color: Color) -> Self {
+        Self { fg: Some(color), ..self }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    pub fn italic(self) -> Self {
+        Self { italic: true, ..self }
+    }
+
+    pub fn underline(self) -> Self {
+        Self { underline: true, ..self }
+    }
+}
+
+// One screen cell. The right half of a wide character is a cell with an empty symbol.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { symbol: " ".to_string(), style: Style::default() }
+    }
+}
+
+impl Cell {
+    pub fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Frame {
+    pub fn new(size: Size) -> Self {
+        Self {
+            width: size.width,
+            height: size.height,
+            cells: vec![Cell::default(); size.width as usize * size.height as usize],
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        Size { width: self.width, height: self.height }
+    }
+
+    pub fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get(y as usize * self.width as usize + x as usize)
+    }
+
+    fn cell_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get_mut(y as usize * self.width as usize + x as usize)
+    }
+
+    // Write text from (x, y), clipped at the right edge; returns the column after the text
+    pub fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> u16 {
+        let mut x = x;
+        for grapheme in text.graphemes(true) {
+            // Control characters would move the terminal cursor behind our back
+            if grapheme.chars().any(char::is_control) {
+                continue;
+            }
+            let width = visual_width(grapheme).max(1) as u16;
+            if x.saturating_add(width) > self.width {
+                break;
+            }
+            self.put(x, y, grapheme, style);
+            if width == 2 {
+                self.put(x + 1, y, "", style);
+            }
+            x += width;
+        }
+        x
+    }
+
+    // Paint a whole row, e.g. to give the footer its background
+    pub fn fill_row(&mut self, y: u16, style: Style) {
+        for x in 0..self.width {
+            self.put(x, y, " ", style);
+        }
+    }
+
+    // Copy `other` in with its top-left corner at (x, y), e.g. a pane drawn on its own
+    pub fn blit(&mut self, other: &Frame, x: u16, y: u16) {
+        for row in 0..other.height {
+            for column in 0..other.width {
+                if let (Some(cell), Some(source)) = (self.cell_mut(x + column, y + row), other.cell(column, row)) {
+                    *cell = source.clone();
+                }
+            }
+        }
+    }
+
+    // Restyle a cell without touching its symbol, e.g. to mark an extra caret
+    pub fn set_style(&mut self, x: u16, y: u16, style: Style) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.style = style;
+        }
+    }
+
+    fn put(&mut self, x: u16, y: u16, symbol: &str, style: Style) {
+        // Overwriting half of a wide character blanks its other half
+        if !symbol.is_empty() {
+            if self.cell(x, y).is_some_and(Cell::is_continuation) && x > 0 {
+                if let Some(left) = self.cell_mut(x - 1, y) {
+                    left.symbol = " ".to_string();
+                }
+            }
+            if self.cell(x + 1, y).is_some_and(Cell::is_continuation) {
+                if let Some(right) = self.cell_mut(x + 1, y) {
+                    right.symbol = " ".to_string();
+                }
+            }
+        }
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.symbol = symbol.to_string();
+            cell.style = style;
+        }
+    }
+
+    // Text of one row without styles or trailing blanks - handy for snapshots
+    #[cfg(test)]
+    pub fn row_text(&self, y: u16) -> String {
+        let text: String = (0..self.width)
+            .filter_map(|x| self.cell(x, y))
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    // Cells that differ from the previous frame, in drawing order
+    pub fn diff<'a>(&'a self, previous: &'a Frame) -> impl Iterator<Item = (u16, u16, &'a Cell)> + 'a {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).filter_map(move |x| {
+                let cell = self.cell(x, y)?;
+                (previous.cell(x, y) != Some(cell)).then_some((x, y, cell))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_only_changed_cells_and_wide_characters_take_two() {
+        let mut before = Frame::new(Size { width: 10, height: 2 });
+        before.print(0, 0, "hello", Style::default());
+        let mut after = before.clone();
+        assert_eq!(after.print(0, 1, "日x", Style::default()), 3);
+        after.print(1, 0, "a", Style::default().fg(Color::Yellow));
+
+        let changed: Vec<(u16, u16, &str)> =
+            after.diff(&before).map(|(x, y, c)| (x, y, c.symbol.as_str())).collect();
+        assert_eq!(changed, vec![(1, 0, "a"), (0, 1, "日"), (1, 1, ""), (2, 1, "x")]);
+        assert_eq!(after.row_text(1), "日x");
+
+        // Narrow text over the right half of a wide character clears the left half
+        after.print(1, 1, "y", Style::default());
+        assert_eq!(after.row_text(1), " yx");
+    }
+}
@@ -1,19 +1,36 @@
 // module binding tui logic, consuming shortcuts and save logic
+mod backend;
 pub mod caret;
+mod frame;
 pub mod syntax;
 mod terminal;
 pub mod view;
 
 use crate::core::{
     actions::Action,
+    brackets::BracketMatcher,
+    compare::{Comparison, Side, Source},
+    completion::Completer,
+    data::{self, Format, ParseError},
+    diff::Hunk,
+    editor::{LineTransform, Motion},
+    formatter::{self, FormatSettings},
+    lsp::{Location, LspManager, Reply, TextEdit},
+    panes::{PaneView, Split, SplitDirection},
+    save_hooks::{AfterSave, SaveSettings},
+    selection::TextPosition,
+    settings::Settings,
     shortcuts::Shortcuts,
     tabs::{get_friendly_filetype, TabManager},
     updater::Updater,
 };
 use caret::Caret;
-use crossterm::event::{read, Event, KeyCode, KeyEventKind};
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind};
 use terminal::Terminal;
-use view::View;
+use view::{DiffView, OtherTab, Popup, PromptKind, View};
+
+// How often diagnostics (and a post-save command's result) are taken in while waiting for a key
+const LSP_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
 
 pub struct TerminalEditor {
     tab_manager: TabManager,
@@ -23,69 +40,655 @@ pub struct TerminalEditor {
     view: View,
     caret: Caret,
     shortcuts: Shortcuts,
+    // Ranks completions, favouring the words accepted lately
+    completer: Completer,
+    // Language servers for the open files
+    lsp: LspManager,
+    // Formatter command per file type
+    format: FormatSettings,
+    // Save hooks per file type, and the post-save command while it runs
+    save: SaveSettings,
+    after_save: AfterSave,
     quit_program: bool,
+    discarded_changes: bool,
+    // Whether a file was written this session
+    saved: bool,
 }
 
 impl TerminalEditor {
     // User ran `quick` — restore last session or open a blank editor.
     pub fn open_fresh() -> Self {
-        let tab_manager = TabManager::restore_or_blank();
-        let view = View::new(tab_manager.current_tab().buffer.clone());
+        Self::with_tabs(TabManager::restore_or_blank())
+    }
+
+    // User ran `quick somefile.txt` — open that file as tab 1, optionally at `file:line:col`.
+    pub fn open_file(path: &str, at: Option<TextPosition>) -> Result<Self, std::io::Error> {
+        let mut tab_manager = TabManager::with_file(path)?;
+        if let Some(pos) = at {
+            tab_manager.current_tab_mut().editor.move_to(pos, false);
+        }
+        Ok(Self::with_tabs(tab_manager))
+    }
+
+    // User ran `quick --wait file`, or git ran `quick` on a commit message — that file alone,
+    // without the session; quitting ends the edit for the program waiting on it
+    pub fn wait_for(path: &str, at: Option<TextPosition>) -> Result<Self, std::io::Error> {
+        let mut tab_manager = TabManager::only_files(&[path])?;
+        if let Some(pos) = at {
+            tab_manager.current_tab_mut().editor.move_to(pos, false);
+        }
+        let mut editor = Self::with_tabs(tab_manager);
+        editor.view.show_prompt(PromptKind::SearchInfo, "Ctrl+S then Ctrl+Q when done - quitting without saving aborts".to_string());
+        Ok(editor)
+    }
+
+    fn with_tabs(tab_manager: TabManager) -> Self {
+        let mut view = View::new(tab_manager.current_tab().editor.clone());
+        let settings = Settings::load();
+        view.apply_settings(&settings);
         let mut editor = Self {
             tab_manager,
             view,
             caret: Caret::new(),
             shortcuts: Shortcuts::new(),
+            completer: Completer::default(),
+            lsp: LspManager::new(settings.lsp),
+            format: settings.format,
+            save: settings.save,
+            after_save: AfterSave::default(),
             quit_program: false,
+            discarded_changes: false,
+            saved: false,
         };
         editor.sync_view_from_tab();
         editor
     }
 
-    // User ran `quick somefile.txt` — open that file as tab 1.
-    pub fn open_file(path: &str) -> Result<Self, std::io::Error> {
-        let tab_manager = TabManager::with_file(path)?;
-        let view = View::new(tab_manager.current_tab().buffer.clone());
-        let mut editor = Self {
-            tab_manager,
-            view,
-            caret: Caret::new(),
-            shortcuts: Shortcuts::new(),
-            quit_program: false,
-        };
-        editor.sync_view_from_tab();
+    // Whether the editor was quit without saving its changes, which for `--wait` means the
+    // edit was aborted
+    pub fn discarded_changes(&self) -> bool {
+        self.discarded_changes
+    }
+
+    // Whether a file was written this session; quitting `--wait` without saving aborts too
+    pub fn saved(&self) -> bool {
+        self.saved
+    }
+
+    // User ran `quick --diff a b` — both files open in tabs, compared side by side
+    pub fn compare_files(left: &str, right: &str) -> Result<Self, std::io::Error> {
+        // A one-off comparison leaves the saved session alone
+        let mut editor = Self::with_tabs(TabManager::only_files(&[left, right])?);
+        let (left_id, right_id) = (editor.tab_manager.tabs[0].id, editor.tab_manager.tabs[1].id);
+        let comparison = Comparison::new(Source::Tab(left_id), Source::Tab(right_id), &editor.tab_manager.tabs)
+            .map_err(std::io::Error::other)?;
+        editor.view.diff = Some(DiffView::new(comparison));
         Ok(editor)
     }
 
     // Save live view/caret state INTO the current tab (call BEFORE switching).
     fn sync_tab_from_view(&mut self) {
+        self.view.track_other_pane();
         let tab = self.tab_manager.current_tab_mut();
-        tab.buffer = self.view.buffer.clone();
+        tab.editor = self.view.editor.clone();
         tab.scroll_offset = self.view.scroll_offset;
-        tab.cursor_pos = self.caret.get_position();
     }
 
     // Load current tab state OUT TO the view (call AFTER switching).
     fn sync_view_from_tab(&mut self) {
         let tab = self.tab_manager.current_tab();
-        self.view.buffer = tab.buffer.clone();
+        self.view.editor = tab.editor.clone();
         self.view.scroll_offset = tab.scroll_offset;
+        self.view.col_offset = 0;
         self.view.filename = tab.filename.clone();
         self.view.filetype = tab.filetype.clone();
-        self.view.selection = None;
+        self.view.editor.selection = None;
         self.view.search_state = None;
+        self.view.completion = None;
+        self.view.popup = None;
         self.view.clear_prompt();
         self.view.needs_redraw = true;
+        self.show_git();
+        self.sync_lsp();
+        self.refresh_split();
+    }
+
+    // Show the current tab's changes; its file is read from git in the background the
+    // first time, and the changes appear once that is done
+    fn show_git(&mut self) {
+        let ids: Vec<u64> = self.tab_manager.tabs.iter().map(|tab| tab.id).collect();
+        self.view.git_files.retain(&ids);
+        let tab = self.tab_manager.current_tab();
+        self.view.git_tab = tab.id;
+        self.view.git_files.load(tab.id, tab.filepath.as_deref());
+    }
+
+    // Read the current file from git again: after saving under a new name, or a post-save
+    // command (which may have staged or committed it)
+    fn reload_git(&mut self) {
+        let tab = self.tab_manager.current_tab();
+        self.view.git_tab = tab.id;
+        self.view.git_files.reload(tab.id, tab.filepath.as_deref());
+    }
+
+    // Keep the view's copy of the other pane's tab current; a pane whose tab was closed goes
+    fn refresh_split(&mut self) {
+        let Some(split) = &self.view.split else {
+            return;
+        };
+        match self.tab_manager.index_of(split.other.tab_id) {
+            Some(index) if index == self.tab_manager.active_tab_index => self.view.split_tab = None,
+            Some(index) => {
+                let tab = self.tab_manager.tabs[index].clone();
+                let diagnostics = self.lsp.diagnostics(tab.filepath.as_deref()).to_vec();
+                self.view.split_tab = Some(OtherTab { tab, diagnostics });
+            }
+            None => {
+                self.view.split = None;
+                self.view.split_tab = None;
+            }
+        }
+        self.view.needs_redraw = true;
+    }
+
+    // Split the screen in two panes showing this tab where it is; the new pane gets focus.
+    // Splitting a split screen again just turns it around.
+    fn split_screen(&mut self, direction: SplitDirection) -> Result<(), std::io::Error> {
+        match &mut self.view.split {
+            Some(split) => split.direction = direction,
+            None => {
+                let scroll = (self.view.scroll_offset, self.view.col_offset);
+                let here = PaneView::new(self.tab_manager.current_tab().id, &self.view.editor, scroll);
+                self.view.split = Some(Split::new(direction, here));
+                self.view.split_tab = None;
+            }
+        }
+        self.view.needs_redraw = true;
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)
+    }
+
+    // Move the focus to the other pane: its tab becomes the current one, with its carets
+    fn focus_other_pane(&mut self) -> Result<(), std::io::Error> {
+        self.view.track_other_pane();
+        let Some(split) = self.view.split.take() else {
+            self.view.show_prompt(PromptKind::Error, "One pane only - Alt+W splits the screen".to_string());
+            return Ok(());
+        };
+        let Some(index) = self.tab_manager.index_of(split.other.tab_id) else {
+            self.view.split_tab = None;
+            self.view.needs_redraw = true;
+            return Ok(());
+        };
+        self.sync_tab_from_view();
+        let scroll = (self.view.scroll_offset, self.view.col_offset);
+        let here = PaneView::new(self.tab_manager.current_tab().id, &self.view.editor, scroll);
+
+        self.tab_manager.active_tab_index = index;
+        self.sync_view_from_tab();
+        (self.view.scroll_offset, self.view.col_offset) = split.other.focus(&mut self.view.editor);
+
+        self.view.split = Some(Split { direction: split.direction, other: here, focus_second: !split.focus_second });
+        self.refresh_split();
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)
+    }
+
+    // Back to one pane, the focused one
+    fn close_split(&mut self) -> Result<(), std::io::Error> {
+        if self.view.split.take().is_none() {
+            return Ok(());
+        }
+        self.view.split_tab = None;
+        self.view.needs_redraw = true;
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)
+    }
+
+    // Alt+Shift+D: what changed since the file was saved
+    fn compare_with_saved(&mut self) -> Result<(), std::io::Error> {
+        self.sync_tab_from_view();
+        let index = self.tab_manager.active_tab_index;
+        let comparison = Comparison::with_saved(&self.tab_manager.tabs, index);
+        self.show_diff(comparison)
+    }
+
+    // Alt+Shift+C: this tab against the other pane's, or one asked for by number
+    fn compare_tabs(&mut self) -> Result<(), std::io::Error> {
+        self.sync_tab_from_view();
+        let id = self.tab_manager.current_tab().id;
+        let other = match (&self.view.split, &self.view.split_tab) {
+            (Some(split), Some(_)) => split.other.tab_id,
+            _ => {
+                let count = self.tab_manager.tabs.len();
+                let Some(input) = self.view.ask_command(format!("Compare with tab (1-{}):", count))? else {
+                    return Ok(());
+                };
+                match input.trim().parse::<usize>() {
+                    Ok(number) if (1..=count).contains(&number) => self.tab_manager.tabs[number - 1].id,
+                    _ => {
+                        self.view.show_prompt(PromptKind::Error, format!("No tab '{}'", input.trim()));
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        if other == id {
+            self.view.show_prompt(PromptKind::Error, "That is this tab".to_string());
+            return Ok(());
+        }
+        let comparison = Comparison::new(Source::Tab(id), Source::Tab(other), &self.tab_manager.tabs);
+        self.show_diff(comparison)
+    }
+
+    // The git change at the cursor, worked out for the text as it is now
+    fn hunk_at_cursor(&mut self) -> Option<Hunk> {
+        let editor = &self.view.editor;
+        let Some(git) = self.view.git_files.get_mut(self.view.git_tab) else {
+            let message = if self.view.git_files.is_loading_tab(self.view.git_tab) {
+                "Still reading the file from git"
+            } else {
+                "Not in a git repository"
+            };
+            self.view.show_prompt(PromptKind::Error, message.to_string());
+            return None;
+        };
+        git.update(&editor.buffer.lines[..=editor.last_content_line()]);
+        let hunk = git.hunk_at(editor.cursor.line).cloned();
+        if hunk.is_none() {
+            self.view.show_prompt(PromptKind::SearchInfo, "No change here".to_string());
+        }
+        hunk
+    }
+
+    // Alt+G: the change at the cursor as `git diff` shows it
+    fn show_hunk_diff(&mut self) {
+        if let Some(hunk) = self.hunk_at_cursor() {
+            let lines = self.view.git().map(|git| git.hunk_diff(&hunk)).unwrap_or_default();
+            let cursor = self.view.editor.cursor;
+            self.view.set_popup(Some(Popup { lines, selected: None, anchor: Some(cursor) }));
+        }
+    }
+
+    // Alt+Shift+G: put the change at the cursor back the way it is staged
+    fn revert_hunk(&mut self) -> Result<(), std::io::Error> {
+        if let Some(hunk) = self.hunk_at_cursor() {
+            let text = self.view.git().map(|git| git.reverted(&hunk)).unwrap_or_default();
+            let changed = self.view.line_command(&mut self.caret, |editor| editor.replace_all(&text))?;
+            self.mark_dirty_if(changed);
+        }
+        Ok(())
+    }
+
+    // Show a comparison in place of the editor. The tabs are edited directly while it is
+    // open (copying changes), so the view takes them up again once it closes.
+    fn show_diff(&mut self, comparison: Result<Comparison, String>) -> Result<(), std::io::Error> {
+        match comparison {
+            Ok(comparison) => {
+                self.view.set_popup(None);
+                self.view.set_completion(None);
+                self.view.diff = Some(DiffView::new(comparison));
+                self.view.needs_redraw = true;
+            }
+            Err(e) => self.view.show_prompt(PromptKind::Error, e),
+        }
+        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+        self.view.sync_caret(&mut self.caret)?;
+        Terminal::execute()
+    }
+
+    fn close_diff(&mut self) -> Result<(), std::io::Error> {
+        if self.view.diff.take().is_some() {
+            self.sync_view_from_tab();
+        }
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)
+    }
+
+    // Keys while the diff view is open: scrolling, moving between changes and copying them
+    fn diff_key(&mut self, event: KeyEvent) -> Result<(), std::io::Error> {
+        let page = self.view.diff_rows()?;
+        let Some(diff) = self.view.diff.as_mut() else {
+            return Ok(());
+        };
+        let mut copy = None;
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('q') => return self.close_diff(),
+            KeyCode::Down | KeyCode::Char('j') => diff.scroll_by(1, page),
+            KeyCode::Up | KeyCode::Char('k') => diff.scroll_by(-1, page),
+            KeyCode::PageDown | KeyCode::Char(' ') => diff.scroll_by(page as isize, page),
+            KeyCode::PageUp => diff.scroll_by(-(page as isize), page),
+            KeyCode::Home => diff.scroll = 0,
+            KeyCode::End => diff.scroll_by(isize::MAX, page),
+            KeyCode::Char(c @ ('n' | 'p')) => {
+                if let Some(row) = diff.comparison.step(c == 'n') {
+                    diff.show_row(row);
+                }
+            }
+            KeyCode::Char('<') => copy = Some(Side::Left),
+            KeyCode::Char('>') => copy = Some(Side::Right),
+            _ => {}
+        }
+        if let Some(side) = copy {
+            if let Err(e) = diff.comparison.copy_hunk(side, &mut self.tab_manager.tabs) {
+                self.view.show_prompt(PromptKind::Error, e);
+            }
+        }
+        self.view.needs_redraw = true;
+        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+        Terminal::execute()
+    }
+
+    // Bring the language server's copy of this file up to date and show its latest diagnostics
+    fn sync_lsp(&mut self) {
+        let open: Vec<&str> = self.tab_manager.tabs.iter().filter_map(|tab| tab.filepath.as_deref()).collect();
+        self.lsp.keep_open(&open);
+        let path = self.tab_manager.current_tab().filepath.clone();
+        if let Err(e) = self.lsp.sync(path.as_deref(), self.view.filetype.as_deref(), &self.view.editor) {
+            self.view.show_prompt(PromptKind::Error, e);
+        }
+        self.view.server_diagnostics = self.lsp.diagnostics(path.as_deref()).to_vec();
+    }
+
+    // Path of the current file for a language server request, synced first so the server
+    // sees what is on screen
+    fn lsp_path(&mut self) -> Option<String> {
+        self.sync_lsp();
+        let path = self.tab_manager.current_tab().filepath.clone();
+        if path.is_none() {
+            self.view.show_prompt(PromptKind::Error, "Save the file to use its language server".to_string());
+        }
+        path
+    }
+
+    // Diagnostics under the cursor, then the server's documentation for the symbol there
+    // once it answers
+    fn show_hover(&mut self) {
+        let cursor = self.view.editor.cursor;
+        if let Some(path) = self.tab_manager.current_tab().filepath.clone() {
+            self.sync_lsp();
+            if self.lsp.hover(&path, cursor).is_ok() {
+                // Shown now, and again with the server's text
+                if self.view.diagnostics().any(|d| d.covers(cursor)) {
+                    self.show_hover_text(None);
+                }
+                return;
+            }
+        }
+        self.show_hover_text(None);
+    }
+
+    fn show_hover_text(&mut self, text: Option<String>) {
+        let cursor = self.view.editor.cursor;
+        let mut lines: Vec<String> = self
+            .view
+            .diagnostics()
+            .filter(|d| d.covers(cursor))
+            .flat_map(|d| format!("{}: {}", d.severity.label(), d.message).lines().map(String::from).collect::<Vec<_>>())
+            .collect();
+        // Markdown code fences add nothing in a terminal
+        lines.extend(text.iter().flat_map(|text| text.lines()).filter(|l| !l.starts_with("```")).map(String::from));
+        if lines.is_empty() {
+            self.view.show_prompt(PromptKind::SearchInfo, "Nothing to show here".to_string());
+        } else {
+            self.view.set_popup(Some(Popup { lines, selected: None, anchor: Some(cursor) }));
+        }
+    }
+
+    // Ask where the symbol under the cursor is defined; the jump happens when the server answers
+    fn go_to_definition(&mut self) {
+        let Some(path) = self.lsp_path() else {
+            return;
+        };
+        if let Err(e) = self.lsp.definition(&path, self.view.editor.cursor) {
+            self.view.show_prompt(PromptKind::Error, e);
+        }
+    }
+
+    // Jump to a definition, opening its file when needed
+    fn jump_to_definition(&mut self, locations: &[Location]) -> Result<(), std::io::Error> {
+        match locations.first() {
+            // The tab that would make room has edits that aren't saved
+            Some(location) if self.tab_manager.would_close_unsaved(&location.path) => {
+                self.view.show_prompt(PromptKind::Error, "Too many tabs: save or close one first".to_string());
+            }
+            Some(location) => {
+                self.sync_tab_from_view();
+                self.tab_manager.open_file_in_new_tab(&location.path)?;
+                self.sync_view_from_tab();
+                self.view.jump_to(&mut self.caret, location.pos)?;
+            }
+            None => self.view.show_prompt(PromptKind::Error, "No definition found".to_string()),
+        }
+        Ok(())
+    }
+
+    // Ask the server to rename the symbol under the cursor; see rename_everywhere()
+    fn rename_symbol(&mut self) -> Result<(), std::io::Error> {
+        let Some(path) = self.lsp_path() else {
+            return Ok(());
+        };
+        let Some(new_name) = self.view.ask_new_name("Rename to:".to_string())? else {
+            return Ok(());
+        };
+        if let Err(e) = self.lsp.rename(&path, self.view.editor.cursor, &new_name) {
+            self.view.show_prompt(PromptKind::Error, e);
+        }
+        Ok(())
+    }
+
+    // Rename the symbol everywhere the server found it. Files get the edits in their tabs,
+    // unsaved and undoable; files not open are opened in new tabs.
+    fn rename_everywhere(&mut self, new_name: &str, files: &[(String, Vec<TextEdit>)]) -> Result<(), std::io::Error> {
+        if files.is_empty() {
+            self.view.show_prompt(PromptKind::Error, "Nothing to rename here".to_string());
+            return Ok(());
+        }
+        self.sync_tab_from_view();
+        let (changed, skipped) = self.tab_manager.apply_file_edits(files);
+        for tab in &self.tab_manager.tabs {
+            let _ = self.lsp.sync(tab.filepath.as_deref(), tab.filetype.as_deref(), &tab.editor);
+        }
+        self.sync_view_from_tab();
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)?;
+        let message = match skipped {
+            0 => format!("Renamed to '{}' in {} file(s)", new_name, changed),
+            _ => format!("Renamed to '{}' in {} file(s), {} not opened - too many tabs", new_name, changed, skipped),
+        };
+        self.view.show_prompt(PromptKind::SearchInfo, message);
+        Ok(())
+    }
+
+    // Act on what the language servers answered. Hover text, definitions and completions
+    // only apply while the cursor is still where they were asked for.
+    fn take_lsp_replies(&mut self) -> Result<(), std::io::Error> {
+        for reply in self.lsp.replies() {
+            let path = self.tab_manager.current_tab().filepath.clone();
+            let here = |asked: &str, pos: TextPosition| path.as_deref() == Some(asked) && pos == self.view.editor.cursor;
+            match reply {
+                Ok(Reply::Hover { path, pos, text }) if here(&path, pos) => self.show_hover_text(text),
+                Ok(Reply::Definition { path, pos, locations }) if here(&path, pos) => self.jump_to_definition(&locations)?,
+                Ok(Reply::Completion { path, pos, words }) if here(&path, pos) && !words.is_empty() => {
+                    let buffer_words = self.view.completion.take();
+                    let completion =
+                        self.completer.with_server_items(&self.view.editor.buffer.lines, pos, buffer_words, words);
+                    self.view.set_completion(completion);
+                }
+                Ok(Reply::Rename { new_name, files }) => self.rename_everywhere(&new_name, &files)?,
+                Ok(_) => {}
+                Err(e) => self.view.show_prompt(PromptKind::Error, e),
+            }
+        }
+        Ok(())
+    }
+
+    // The file's diagnostics in a popup list, starting at the first one from the cursor on
+    fn list_diagnostics(&mut self) {
+        self.sync_lsp();
+        let diagnostics: Vec<_> = self.view.diagnostics().collect();
+        if diagnostics.is_empty() {
+            return self.view.show_prompt(PromptKind::SearchInfo, "No diagnostics".to_string());
+        }
+        let cursor_line = self.view.editor.cursor.line;
+        let selected = diagnostics.iter().position(|d| d.start.line >= cursor_line).unwrap_or(0);
+        let lines = diagnostics.iter().map(|d| d.summary()).collect();
+        self.view.set_popup(Some(Popup { lines, selected: Some(selected), anchor: None }));
+    }
+
+    // Directory of the current file, where formatters look for their config
+    fn file_dir(&self) -> Option<std::path::PathBuf> {
+        let path = self.tab_manager.current_tab().filepath.as_deref()?;
+        std::path::Path::new(path).parent().map(|dir| dir.to_path_buf())
+    }
+
+    // Say what the editor is waiting on before running a command in the foreground, which
+    // holds the screen until it finishes or times out
+    fn show_busy(&mut self, message: String) -> Result<(), std::io::Error> {
+        self.view.show_prompt(PromptKind::SearchInfo, message);
+        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+        Terminal::execute()
+    }
+
+    // Pipe the document through the file type's formatter and apply only what it changed
+    fn format_document(&mut self) -> Result<(), std::io::Error> {
+        let filetype = self.view.filetype.clone().unwrap_or_default();
+        let Some(command) = self.format.command_for(&filetype).map(String::from) else {
+            let name = if filetype.is_empty() { "this file type" } else { &filetype };
+            let message = format!("No formatter for {} - add one under format.commands in settings.json", name);
+            self.view.show_prompt(PromptKind::Error, message);
+            return Ok(());
+        };
+        self.show_busy(format!("Formatting with `{}`...", command))?;
+        self.view.clear_prompt();
+        match formatter::format_text(&command, &self.view.editor.content(), self.file_dir().as_deref()) {
+            Ok(text) => {
+                let changed = self.view.line_command(&mut self.caret, |editor| editor.replace_all(&text))?;
+                self.mark_dirty_if(changed);
+                if !changed {
+                    self.view.show_prompt(PromptKind::SearchInfo, "Already formatted".to_string());
+                }
+            }
+            Err(e) => self.view.show_prompt(PromptKind::Error, e),
+        }
+        Ok(())
+    }
+
+    // Replace the selection (or the cursor's line) with what a shell command prints for it
+    fn filter_selection(&mut self) -> Result<(), std::io::Error> {
+        let Some(command) = self.view.ask_command("Filter through:".to_string())? else {
+            return Ok(());
+        };
+        let (start, end) = self.view.editor.filter_range();
+        let input = self.view.editor.text_range(start, end);
+        self.show_busy(format!("Running `{}`...", command))?;
+        self.view.clear_prompt();
+        match formatter::filter_text(&command, &input, self.file_dir().as_deref()) {
+            Ok(text) => {
+                let changed = self
+                    .view
+                    .line_command(&mut self.caret, |editor| editor.replace_minimal(start, end, &text))?;
+                self.mark_dirty_if(changed);
+            }
+            Err(e) => self.view.show_prompt(PromptKind::Error, e),
+        }
+        Ok(())
+    }
+
+    // The structured format of the current file, by its file type
+    fn data_format(&mut self) -> Option<Format> {
+        let format = Format::from_filetype(self.view.filetype.as_deref());
+        if format.is_none() {
+            self.view.show_prompt(PromptKind::Error, "Not a JSON, TOML or YAML file".to_string());
+        }
+        format
+    }
+
+    // Mark where the document stops parsing and put the cursor there
+    fn show_data_error(&mut self, format: Format, error: ParseError) -> Result<(), std::io::Error> {
+        self.view.editor.data_error = Some(error.diagnostic(format));
+        self.view.jump_to(&mut self.caret, error.position())?;
+        self.view.show_prompt(PromptKind::Error, error.summary(format));
+        Ok(())
+    }
+
+    fn validate_data(&mut self) -> Result<(), std::io::Error> {
+        let Some(format) = self.data_format() else {
+            return Ok(());
+        };
+        match format.parse(&self.view.editor.content()) {
+            Ok(_) => {
+                self.view.editor.data_error = None;
+                self.view.needs_redraw = true;
+                self.view.show_prompt(PromptKind::SearchInfo, format!("Valid {}", format.name()));
+            }
+            Err(e) => self.show_data_error(format, e)?,
+        }
+        Ok(())
+    }
+
+    // Pretty-print, minify or sort the keys of a JSON document, changing only what differs
+    fn rewrite_json(&mut self, rewrite: fn(&str) -> Result<String, ParseError>) -> Result<(), std::io::Error> {
+        if self.data_format() != Some(Format::Json) {
+            self.view.show_prompt(PromptKind::Error, "Only JSON files can be pretty-printed, minified or sorted".to_string());
+            return Ok(());
+        }
+        match rewrite(&self.view.editor.content()) {
+            Ok(text) => {
+                let changed = self.view.line_command(&mut self.caret, |editor| editor.replace_all(&text))?;
+                self.mark_dirty_if(changed);
+            }
+            Err(e) => self.show_data_error(Format::Json, e)?,
+        }
+        Ok(())
+    }
+
+    // Convert the document to another format in a new tab
+    fn convert_data(&mut self) -> Result<(), std::io::Error> {
+        let Some(from) = self.data_format() else {
+            return Ok(());
+        };
+        let Some(name) = self.view.ask_format("Convert to (json, toml, yaml):".to_string())? else {
+            return Ok(());
+        };
+        let Some(to) = Format::from_name(&name) else {
+            self.view.show_prompt(PromptKind::Error, format!("Unknown format '{}'", name));
+            return Ok(());
+        };
+        match from.parse(&self.view.editor.content()) {
+            Ok(value) => match to.write(&value) {
+                Ok(text) => {
+                    self.sync_tab_from_view();
+                    self.tab_manager.new_tab_with(text, Some(to.name().to_string()));
+                    self.sync_view_from_tab();
+                    self.view.set_cursor(&mut self.caret, self.view.editor.cursor)?;
+                }
+                Err(e) => self.view.show_prompt(PromptKind::Error, e),
+            },
+            Err(e) => self.show_data_error(from, e)?,
+        }
+        Ok(())
+    }
+
+    // Ctrl+Space: buffer words now, with the language server's suggestions first once they
+    // come; these come even before a word is started, e.g. after a `.`
+    fn complete_with_server(&mut self) {
+        self.update_completion(1);
+        let Some(path) = self.tab_manager.current_tab().filepath.clone() else {
+            return;
+        };
+        self.sync_lsp();
+        let _ = self.lsp.completion(&path, self.view.editor.cursor);
+    }
+
+    // Edits report whether they changed the buffer
+    fn mark_dirty_if(&mut self, changed: bool) {
+        if changed {
+            self.tab_manager.current_tab_mut().has_unsaved_changes = true;
+        }
     }
 
     // Switch to a tab by 1-based number.
     fn switch_tab(&mut self, tab_number: usize) -> Result<(), std::io::Error> {
         self.sync_tab_from_view();
         self.tab_manager.switch_to_tab(tab_number)?;
-        let cursor_pos = self.tab_manager.current_tab().cursor_pos;
         self.sync_view_from_tab();
-        self.view.render(&self.caret)?;
-        self.caret.move_to(cursor_pos)?;
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)?;
         Terminal::execute()?;
         Ok(())
     }
@@ -95,19 +698,88 @@ impl TerminalEditor {
         self.sync_tab_from_view();
         self.tab_manager.new_tab();
         self.sync_view_from_tab();
-        self.caret.move_to(caret::Position::default())?;
-        self.view.render(&self.caret)?;
+        self.view.set_cursor(&mut self.caret, self.view.editor.cursor)?;
         Terminal::execute()?;
         Ok(())
     }
 
+    // Show the bookmarks of every tab in the footer and jump to the one named, switching tabs
+    // when it lives elsewhere
+    fn list_bookmarks(&mut self) -> Result<(), std::io::Error> {
+        self.sync_tab_from_view();
+        let marks = self.tab_manager.bookmarks();
+        if marks.is_empty() {
+            self.view.show_prompt(
+                crate::tui::view::PromptKind::Error,
+                "No bookmarks - Alt+M sets one".to_string(),
+            );
+            self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+            return Terminal::execute();
+        }
+
+        let listing: Vec<String> = marks
+            .iter()
+            .map(|(tab, mark)| {
+                let filename = self.tab_manager.tabs[*tab].filename.as_deref().unwrap_or("[No Name]");
+                format!("{} {}:{}", mark.name, filename, mark.line + 1)
+            })
+            .collect();
+        let Some(name) = self.view.ask_bookmark(format!("{} | go to:", listing.join(", ")))? else {
+            return Ok(());
+        };
+
+        match self.tab_manager.find_bookmark(&name) {
+            Some((tab, line)) => {
+                if tab != self.tab_manager.active_tab_index {
+                    self.switch_tab(tab + 1)?;
+                }
+                self.view.jump_to(&mut self.caret, TextPosition { line, column: 0 })?;
+            }
+            None => self.view.show_prompt(
+                crate::tui::view::PromptKind::Error,
+                format!("No bookmark '{}'", name),
+            ),
+        }
+        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+        Terminal::execute()
+    }
+
+    // Open or refresh the completion popup for the word before the cursor once it has
+    // `min_prefix` characters, offering words from every open tab
+    fn update_completion(&mut self, min_prefix: usize) {
+        let active = self.tab_manager.active_tab_index;
+        let others = self.tab_manager.tabs.iter().enumerate().filter(|(idx, _)| *idx != active);
+        let completion = self.completer.complete(
+            &self.view.editor.buffer.lines,
+            self.view.editor.cursor,
+            others.map(|(_, tab)| &tab.editor.buffer.lines),
+            self.view.filetype.clone(),
+            min_prefix,
+        );
+        self.view.set_completion(completion);
+    }
+
+    // Put the highlighted completion in place of the word before each cursor
+    fn accept_completion(&mut self) -> Result<(), std::io::Error> {
+        let Some(completion) = self.view.completion.take() else {
+            return Ok(());
+        };
+        let word = completion.current().to_string();
+        self.completer.accepted(&word);
+        self.view.needs_redraw = true;
+        let changed = self.view.line_command(&mut self.caret, |editor| editor.complete_word(&word))?;
+        self.mark_dirty_if(changed);
+        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+        Terminal::execute()
+    }
+
     fn check_and_install_update(&mut self) -> Result<(), std::io::Error> {
         self.view.show_prompt(
             crate::tui::view::PromptKind::SearchInfo,
             "Checking for updates...".to_string(),
         );
         self.view.needs_redraw = true;
-        self.view.render_if_needed(&self.caret, false)?;
+        self.view.render_if_needed(false)?;
         Terminal::execute()?;
 
         let updater = Updater::new();
@@ -119,11 +791,11 @@ impl TerminalEditor {
                     crate::tui::view::PromptKind::Error,
                     format!("Failed to check for updates: {}", e),
                 );
-                self.view.render_if_needed(&self.caret, false)?;
+                self.view.render_if_needed(false)?;
                 Terminal::execute()?;
                 std::thread::sleep(std::time::Duration::from_secs(3));
                 self.view.clear_prompt();
-                self.view.render_if_needed(&self.caret, false)?;
+                self.view.render_if_needed(false)?;
                 Terminal::execute()?;
                 return Ok(());
             }
@@ -137,11 +809,11 @@ impl TerminalEditor {
                     update_info.current_version
                 ),
             );
-            self.view.render_if_needed(&self.caret, false)?;
+            self.view.render_if_needed(false)?;
             Terminal::execute()?;
             std::thread::sleep(std::time::Duration::from_secs(2));
             self.view.clear_prompt();
-            self.view.render_if_needed(&self.caret, false)?;
+            self.view.render_if_needed(false)?;
             Terminal::execute()?;
             return Ok(());
         }
@@ -153,7 +825,7 @@ impl TerminalEditor {
         self.view
             .show_prompt(crate::tui::view::PromptKind::SearchInfo, message);
         self.view.needs_redraw = true;
-        self.view.render_if_needed(&self.caret, false)?;
+        self.view.render_if_needed(false)?;
         Terminal::execute()?;
 
         loop {
@@ -164,7 +836,7 @@ impl TerminalEditor {
                             crate::tui::view::PromptKind::SearchInfo,
                             "Downloading update...".to_string(),
                         );
-                        self.view.render_if_needed(&self.caret, false)?;
+                        self.view.render_if_needed(false)?;
                         Terminal::execute()?;
 
                         match updater.perform_update() {
@@ -174,7 +846,7 @@ impl TerminalEditor {
                                     "Update successful! Restart to use the new version."
                                         .to_string(),
                                 );
-                                self.view.render_if_needed(&self.caret, false)?;
+                                self.view.render_if_needed(false)?;
                                 Terminal::execute()?;
                                 std::thread::sleep(std::time::Duration::from_secs(3));
                                 self.quit_program = true;
@@ -184,7 +856,7 @@ impl TerminalEditor {
                                     crate::tui::view::PromptKind::Error,
                                     format!("Update failed: {}", e),
                                 );
-                                self.view.render_if_needed(&self.caret, false)?;
+                                self.view.render_if_needed(false)?;
                                 Terminal::execute()?;
                                 std::thread::sleep(std::time::Duration::from_secs(3));
                             }
@@ -193,7 +865,7 @@ impl TerminalEditor {
                     }
                     KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
                         self.view.clear_prompt();
-                        self.view.render_if_needed(&self.caret, false)?;
+                        self.view.render_if_needed(false)?;
                         Terminal::execute()?;
                         break;
                     }
@@ -204,46 +876,67 @@ impl TerminalEditor {
         }
 
         self.view.clear_prompt();
-        self.view.render_if_needed(&self.caret, false)?;
+        self.view.render_if_needed(false)?;
         Terminal::execute()?;
         Ok(())
     }
 
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), std::io::Error> {
         if let Err(error) = Terminal::initialize(&mut self.view, &mut self.caret) {
-            eprintln!("Terminal Initialisation Failed: {:?}", error);
+            let _ = Terminal::terminate();
+            return Err(error);
         }
 
-        let cursor_pos = self.tab_manager.current_tab().cursor_pos;
-        self.caret.move_to(cursor_pos).ok();
-        self.view.render(&self.caret).ok();
+        // Restored cursors may point past the end of a file that changed on disk, and a
+        // `file:line` target from the command line may be off screen
+        self.view.jump_to(&mut self.caret, self.view.editor.cursor).ok();
 
-        match self.main_loop() {
-            Ok(_) => {}
-            Err(e) => {
-                self.view
-                    .show_prompt(crate::tui::view::PromptKind::Error, format!("Error: {}", e));
-                let _ = self.view.render_if_needed(
-                    &self.caret,
-                    self.tab_manager.current_tab().has_unsaved_changes,
-                );
-                let _ = Terminal::execute();
-            }
-        }
-
-        if let Err(error) = Terminal::terminate() {
-            eprintln!("Terminal Termination Failed: {:?}", error);
-        }
+        let result = self.main_loop();
+        // Leave the terminal usable even when the loop failed, then report why
+        let terminated = Terminal::terminate();
+        result.and(terminated)
     }
 
     fn main_loop(&mut self) -> Result<(), std::io::Error> {
         loop {
+            // While language servers, a post-save command or git reads run, wait for input in
+            // short steps and show the diagnostics they publish, the command's result or the
+            // file's changes meanwhile
+            while (self.lsp.is_active() || self.after_save.is_running() || self.view.git_files.is_loading())
+                && !crossterm::event::poll(LSP_POLL_INTERVAL)?
+            {
+                if self.view.git_files.poll() {
+                    self.view.needs_redraw = true;
+                    self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                    self.view.sync_caret(&mut self.caret)?;
+                    Terminal::execute()?;
+                }
+                if let Some(result) = self.after_save.poll() {
+                    match result {
+                        Ok(message) => self.view.show_prompt(PromptKind::SearchInfo, message),
+                        Err(message) => self.view.show_prompt(PromptKind::Error, message),
+                    }
+                    self.reload_git();
+                    self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                    self.view.sync_caret(&mut self.caret)?;
+                    Terminal::execute()?;
+                }
+                if self.lsp.poll() {
+                    let path = self.tab_manager.current_tab().filepath.clone();
+                    self.view.server_diagnostics = self.lsp.diagnostics(path.as_deref()).to_vec();
+                    self.take_lsp_replies()?;
+                    self.view.needs_redraw = true;
+                    self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                    self.view.sync_caret(&mut self.caret)?;
+                    Terminal::execute()?;
+                }
+            }
+
             // Auto-clear timed prompts
             if let Some(since) = self.view.prompt_since {
                 if since.elapsed() >= std::time::Duration::from_secs(2) {
                     self.view.clear_prompt();
                     let _ = self.view.render_if_needed(
-                        &self.caret,
                         self.tab_manager.current_tab().has_unsaved_changes,
                     );
                     let _ = Terminal::execute();
@@ -251,269 +944,467 @@ impl TerminalEditor {
             }
 
             match read()? {
-                Event::Key(event) => {
-                    if event.kind == KeyEventKind::Press {
-                        // Search navigation intercept
-                        if self.view.is_search_active() {
-                            match event.code {
-                                KeyCode::Down => {
-                                    self.view.next_search_match(&mut self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                KeyCode::Up => {
-                                    self.view.prev_search_match(&mut self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                KeyCode::Esc => {
-                                    self.view.clear_search();
-                                    self.view.render(&self.caret)?;
-                                    Terminal::execute()?;
-                                    continue;
-                                }
-                                _ => {
-                                    self.view.clear_search();
+                Event::Key(event) if event.kind == KeyEventKind::Press => {
+                    // The diff view takes all keys; quitting closes it first
+                    if self.view.diff.is_some() {
+                        if !matches!(self.shortcuts.resolve(&event), Some(Action::Quit)) {
+                            self.diff_key(event)?;
+                            continue;
+                        }
+                        self.close_diff()?;
+                    }
+
+                    // Popup intercept: the diagnostics list takes arrows, Enter and Esc; hover
+                    // text closes on any key, which then does its usual job (Esc only closes)
+                    if let Some(popup) = &mut self.view.popup {
+                        match (popup.selected, event.code) {
+                            (Some(selected), KeyCode::Down | KeyCode::Up) if event.modifiers.is_empty() => {
+                                let count = popup.lines.len();
+                                let step = if event.code == KeyCode::Down { 1 } else { count - 1 };
+                                popup.selected = Some((selected + step) % count);
+                                self.view.needs_redraw = true;
+                                self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            (Some(selected), KeyCode::Enter) => {
+                                let target = self.view.diagnostics().nth(selected).map(|d| d.start);
+                                self.view.set_popup(None);
+                                if let Some(pos) = target {
+                                    self.view.jump_to(&mut self.caret, pos)?;
                                 }
+                                self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                                Terminal::execute()?;
+                                continue;
                             }
+                            (_, KeyCode::Esc) => {
+                                self.view.set_popup(None);
+                                self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            _ => self.view.set_popup(None),
                         }
+                    }
 
-                        if let Some(action) = self.shortcuts.resolve(&event) {
-                            match action {
-                                Action::SwitchTab(tab_num) => self.switch_tab(tab_num)?,
-
-                                Action::Undo => {
-                                    if let Some(operation) =
-                                        self.tab_manager.current_tab_mut().edit_history.undo()
-                                    {
-                                        operation.edit.reverse(&mut self.view.buffer.lines);
-                                        self.view.scroll_offset = operation.scroll_before;
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(
-                                            &self.caret,
-                                            self.tab_manager.current_tab().has_unsaved_changes,
-                                        )?;
-                                        self.caret.move_to(operation.cursor_before)?;
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
+                    // Completion popup intercept: arrows pick, Tab or Enter accepts, Esc closes
+                    if self.view.completion.is_some() && event.modifiers.is_empty() {
+                        match event.code {
+                            KeyCode::Down | KeyCode::Up => {
+                                if let Some(completion) = &mut self.view.completion {
+                                    completion.select_next(event.code == KeyCode::Down);
                                 }
+                                self.view.needs_redraw = true;
+                                self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            KeyCode::Tab | KeyCode::Enter => {
+                                self.accept_completion()?;
+                                continue;
+                            }
+                            KeyCode::Esc => {
+                                self.view.set_completion(None);
+                                self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
 
-                                Action::Redo => {
-                                    if let Some(operation) =
-                                        self.tab_manager.current_tab_mut().edit_history.redo()
-                                    {
-                                        operation.edit.apply(&mut self.view.buffer.lines);
-                                        self.view.scroll_offset = operation.scroll_after;
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(
-                                            &self.caret,
-                                            self.tab_manager.current_tab().has_unsaved_changes,
-                                        )?;
-                                        self.caret.move_to(operation.cursor_after)?;
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
+                    // Search navigation intercept
+                    if self.view.is_search_active() {
+                        match event.code {
+                            KeyCode::Down => {
+                                self.view.next_search_match(&mut self.caret)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            KeyCode::Up => {
+                                self.view.prev_search_match(&mut self.caret)?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            KeyCode::Esc => {
+                                self.view.clear_search();
+                                self.view.render()?;
+                                Terminal::execute()?;
+                                continue;
+                            }
+                            _ => {
+                                self.view.clear_search();
+                            }
+                        }
+                    }
+
+                    if let Some(action) = self.shortcuts.resolve(&event) {
+                        // Typing a word keeps the completion popup up to date, other keys close it
+                        let completing = self.view.completion.is_some();
+                        let typed_word_char = matches!(action, Action::Print)
+                            && matches!(event.code, KeyCode::Char(c) if c.is_alphanumeric() || c == '_');
+                        let keeps_completion = matches!(action, Action::Backspace | Action::Complete);
+                        let refreshes_completion = matches!(action, Action::Backspace);
+
+                        match action {
+                            Action::SwitchTab(tab_num) => self.switch_tab(tab_num)?,
+
+                            Action::Undo => {
+                                let changed = self.view.undo(&mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::Redo => {
+                                let changed = self.view.redo(&mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::Save => self.save_file()?,
+                            Action::CheckUpdate => self.check_and_install_update()?,
+                            Action::New => self.new_tab()?,
+                            Action::Search => self.view.search(&mut self.caret)?,
+                            Action::GoToLine => self.view.go_to_line(&mut self.caret)?,
+                            Action::ToggleSoftWrap => self.view.toggle_soft_wrap(&mut self.caret)?,
+
+                            Action::Copy => {
+                                if let Err(e) = self.view.copy_selection() {
+                                    self.view.show_prompt(
+                                        crate::tui::view::PromptKind::Error,
+                                        e.to_string(),
+                                    );
+                                } else {
+                                    self.view.show_prompt(
+                                        crate::tui::view::PromptKind::SearchInfo,
+                                        "Copied!".into(),
+                                    );
                                 }
+                            }
 
-                                Action::Save => self.save_file()?,
-                                Action::CheckUpdate => self.check_and_install_update()?,
-                                Action::New => self.new_tab()?,
-                                Action::Search => self.view.search(&mut self.caret)?,
-
-                                Action::Copy => {
-                                    if let Err(e) = self.view.copy_selection() {
-                                        self.view.show_prompt(
-                                            crate::tui::view::PromptKind::Error,
-                                            e.to_string(),
-                                        );
-                                    } else {
-                                        self.view.show_prompt(
-                                            crate::tui::view::PromptKind::SearchInfo,
-                                            "Copied!".into(),
-                                        );
-                                    }
+                            Action::Cut => match self.view.cut_selection(&mut self.caret) {
+                                Ok(true) => {
+                                    self.tab_manager.current_tab_mut().has_unsaved_changes = true;
                                 }
+                                Err(e) => self.view.show_prompt(
+                                    crate::tui::view::PromptKind::Error,
+                                    e.to_string(),
+                                ),
+                                _ => {}
+                            },
 
-                                Action::Cut => match self.view.cut_selection(&mut self.caret) {
-                                    Ok(Some(op)) => {
-                                        let tab = self.tab_manager.current_tab_mut();
-                                        tab.edit_history.push(op);
-                                        tab.has_unsaved_changes = true;
+                            Action::Paste => {
+                                match self.view.paste_from_clipboard(&mut self.caret) {
+                                    Ok(true) => {
+                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
+                                            true;
                                     }
                                     Err(e) => self.view.show_prompt(
                                         crate::tui::view::PromptKind::Error,
                                         e.to_string(),
                                     ),
                                     _ => {}
-                                },
-
-                                Action::Paste => {
-                                    match self.view.paste_from_clipboard(&mut self.caret) {
-                                        Ok(Some(op)) => {
-                                            let tab = self.tab_manager.current_tab_mut();
-                                            tab.edit_history.push(op);
-                                            tab.has_unsaved_changes = true;
-                                        }
-                                        Err(e) => self.view.show_prompt(
-                                            crate::tui::view::PromptKind::Error,
-                                            e.to_string(),
-                                        ),
-                                        _ => {}
-                                    }
                                 }
+                            }
 
-                                Action::Left => self.view.move_left(&mut self.caret)?,
-                                Action::Right => self.view.move_right(&mut self.caret)?,
-                                Action::Up => self.view.move_up(&mut self.caret)?,
-                                Action::Down => self.view.move_down(&mut self.caret)?,
-                                Action::Top => self.view.move_top(&mut self.caret)?,
-                                Action::Bottom => self.view.move_bottom(&mut self.caret)?,
-                                Action::MaxLeft => self.view.move_max_left(&mut self.caret)?,
-                                Action::MaxRight => self.view.move_max_right(&mut self.caret)?,
-
-                                Action::SelectLeft => {
-                                    self.view.move_with_selection("left", &mut self.caret)?
-                                }
-                                Action::SelectRight => {
-                                    self.view.move_with_selection("right", &mut self.caret)?
-                                }
-                                Action::SelectUp => {
-                                    self.view.move_with_selection("up", &mut self.caret)?
-                                }
-                                Action::SelectDown => {
-                                    self.view.move_with_selection("down", &mut self.caret)?
-                                }
-                                Action::SelectTop => {
-                                    self.view.move_with_selection("top", &mut self.caret)?
-                                }
-                                Action::SelectBottom => {
-                                    self.view.move_with_selection("bottom", &mut self.caret)?
-                                }
-                                Action::SelectMaxLeft => {
-                                    self.view.move_with_selection("max_left", &mut self.caret)?
-                                }
-                                Action::SelectMaxRight => self
-                                    .view
-                                    .move_with_selection("max_right", &mut self.caret)?,
-                                Action::SelectAll => self.view.select_all(&mut self.caret)?,
+                            Action::Left => self.view.move_left(&mut self.caret)?,
+                            Action::Right => self.view.move_right(&mut self.caret)?,
+                            Action::Up => self.view.move_up(&mut self.caret)?,
+                            Action::Down => self.view.move_down(&mut self.caret)?,
+                            Action::Top => self.view.move_top(&mut self.caret)?,
+                            Action::Bottom => self.view.move_bottom(&mut self.caret)?,
+                            Action::MaxLeft => self.view.move_max_left(&mut self.caret)?,
+                            Action::MaxRight => self.view.move_max_right(&mut self.caret)?,
+                            Action::WordLeft => {
+                                self.view.move_without_selection("word_left", &mut self.caret)?
+                            }
+                            Action::WordRight => {
+                                self.view.move_without_selection("word_right", &mut self.caret)?
+                            }
+                            Action::SelectWordLeft => {
+                                self.view.move_with_selection("word_left", &mut self.caret)?
+                            }
+                            Action::SelectWordRight => {
+                                self.view.move_with_selection("word_right", &mut self.caret)?
+                            }
 
-                                Action::NextLine => {
-                                    if let Some(op) = self.view.insert_newline(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
+                            Action::SelectLeft => {
+                                self.view.move_with_selection("left", &mut self.caret)?
+                            }
+                            Action::SelectRight => {
+                                self.view.move_with_selection("right", &mut self.caret)?
+                            }
+                            Action::SelectUp => {
+                                self.view.move_with_selection("up", &mut self.caret)?
+                            }
+                            Action::SelectDown => {
+                                self.view.move_with_selection("down", &mut self.caret)?
+                            }
+                            Action::SelectTop => {
+                                self.view.move_with_selection("top", &mut self.caret)?
+                            }
+                            Action::SelectBottom => {
+                                self.view.move_with_selection("bottom", &mut self.caret)?
+                            }
+                            Action::SelectMaxLeft => {
+                                self.view.move_with_selection("max_left", &mut self.caret)?
+                            }
+                            Action::SelectMaxRight => self
+                                .view
+                                .move_with_selection("max_right", &mut self.caret)?,
+                            Action::SelectAll => self.view.select_all(&mut self.caret)?,
 
-                                Action::Backspace => {
-                                    if let Some(op) = self.view.backspace(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
+                            Action::AddCursorAbove => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.add_caret_vertically(false))?,
+                            Action::AddCursorBelow => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.add_caret_vertically(true))?,
+                            Action::AddNextOccurrence => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.add_next_occurrence())?,
+                            Action::SelectAllOccurrences => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.select_all_occurrences())?,
+                            Action::SplitSelectionIntoLines => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.split_selection_into_lines())?,
+                            Action::BlockSelectLeft => self.view.extend_block(Motion::Left, &mut self.caret)?,
+                            Action::BlockSelectRight => self.view.extend_block(Motion::Right, &mut self.caret)?,
+                            Action::BlockSelectUp => self.view.extend_block(Motion::Up, &mut self.caret)?,
+                            Action::BlockSelectDown => self.view.extend_block(Motion::Down, &mut self.caret)?,
+                            Action::CollapseCursors => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.collapse_carets())?,
+                            Action::JumpToBracket => {
+                                let matcher = BracketMatcher::new(self.view.filetype.clone());
+                                self.view.cursor_command(&mut self.caret, |editor| {
+                                    editor.jump_to_matching_bracket(&matcher)
+                                })?
+                            }
+                            Action::SelectToBracket => {
+                                let matcher = BracketMatcher::new(self.view.filetype.clone());
+                                self.view.cursor_command(&mut self.caret, |editor| {
+                                    editor.select_to_matching_bracket(&matcher)
+                                })?
+                            }
+                            Action::Fold => {
+                                let matcher = BracketMatcher::new(self.view.filetype.clone());
+                                self.view.cursor_command(&mut self.caret, |editor| editor.fold_at_cursor(&matcher))?
+                            }
+                            Action::Unfold => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.unfold_at_cursor())?,
+                            Action::FoldAll => {
+                                let matcher = BracketMatcher::new(self.view.filetype.clone());
+                                self.view.cursor_command(&mut self.caret, |editor| editor.fold_all(&matcher))?
+                            }
+                            Action::UnfoldAll => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.unfold_all())?,
+                            Action::ToggleBookmark => self.view.cursor_command(&mut self.caret, |editor| {
+                                editor.toggle_bookmark();
+                                true
+                            })?,
+                            Action::NameBookmark => {
+                                if let Some(name) = self.view.ask_bookmark("Bookmark name:".to_string())? {
+                                    self.view.cursor_command(&mut self.caret, |editor| {
+                                        editor.name_bookmark(&name);
+                                        true
+                                    })?;
                                 }
+                            }
+                            Action::NextBookmark => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.jump_to_bookmark(true))?,
+                            Action::PrevBookmark => self
+                                .view
+                                .cursor_command(&mut self.caret, |editor| editor.jump_to_bookmark(false))?,
+                            Action::ListBookmarks => self.list_bookmarks()?,
+                            Action::Complete => self.complete_with_server(),
+                            Action::Hover => self.show_hover(),
+                            Action::GoToDefinition => self.go_to_definition(),
+                            Action::Rename => self.rename_symbol()?,
+                            Action::ListDiagnostics => self.list_diagnostics(),
+                            Action::FormatDocument => self.format_document()?,
+                            Action::FilterSelection => self.filter_selection()?,
+                            Action::ValidateData => self.validate_data()?,
+                            Action::PrettyPrintJson => self.rewrite_json(data::pretty_json)?,
+                            Action::MinifyJson => self.rewrite_json(data::minify_json)?,
+                            Action::SortJsonKeys => self.rewrite_json(data::sort_json_keys)?,
+                            Action::ConvertData => self.convert_data()?,
+                            Action::SplitSideBySide => self.split_screen(SplitDirection::Vertical)?,
+                            Action::SplitStacked => self.split_screen(SplitDirection::Horizontal)?,
+                            Action::FocusOtherPane => self.focus_other_pane()?,
+                            Action::CloseSplit => self.close_split()?,
+                            Action::CompareWithSaved => self.compare_with_saved()?,
+                            Action::CompareTabs => self.compare_tabs()?,
+                            Action::ShowHunkDiff => self.show_hunk_diff(),
+                            Action::RevertHunk => self.revert_hunk()?,
 
-                                Action::Delete => {
-                                    if let Some(op) = self.view.delete_char(&mut self.caret)? {
-                                        self.tab_manager.current_tab_mut().edit_history.push(op);
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                }
+                            Action::NextLine => {
+                                let changed = self.view.insert_newline(&mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
 
-                                Action::ToggleCtrlShortcuts => {
-                                    self.view.toggle_ctrl_shortcuts();
-                                    self.view.render(&self.caret)?;
-                                }
+                            Action::Backspace => {
+                                let changed = self.view.backspace(&mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::Delete => {
+                                let changed = self.view.delete_char(&mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::DuplicateLine => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.duplicate())?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::MoveLineUp => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.move_lines(false))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::MoveLineDown => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.move_lines(true))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::DeleteLine => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.delete_lines())?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::JoinLines => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.join_lines())?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::SortLines => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.transform_lines(LineTransform::Sort))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::ReverseLines => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.transform_lines(LineTransform::Reverse))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::UniqueLines => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.transform_lines(LineTransform::Unique))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::ShuffleLines => {
+                                let changed = self.view.line_command(&mut self.caret, |editor| editor.transform_lines(LineTransform::Shuffle))?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::DeleteWordLeft => {
+                                let changed = self.view.delete_word(false, &mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
+
+                            Action::DeleteWordRight => {
+                                let changed = self.view.delete_word(true, &mut self.caret)?;
+                                self.mark_dirty_if(changed);
+                            }
 
-                                Action::Quit => {
-                                    if self.tab_manager.current_tab().has_unsaved_changes {
-                                        self.view.show_prompt(
-                                            crate::tui::view::PromptKind::Error,
-                                            "Unsaved changes. Quit? (y/n)".to_string(),
-                                        );
-                                        self.view.needs_redraw = true;
-                                        self.view.render_if_needed(&self.caret, true)?;
-                                        Terminal::execute()?;
-
-                                        loop {
-                                            match read()? {
-                                                Event::Key(ev)
-                                                    if ev.kind == KeyEventKind::Press =>
-                                                {
-                                                    match ev.code {
-                                                        KeyCode::Char('y') | KeyCode::Char('Y') => {
-                                                            self.quit_program = true;
-                                                            break;
-                                                        }
-                                                        KeyCode::Char('n')
-                                                        | KeyCode::Char('N')
-                                                        | KeyCode::Esc => {
-                                                            self.view.clear_prompt();
-                                                            self.view.render_if_needed(
-                                                                &self.caret,
-                                                                true,
-                                                            )?;
-                                                            Terminal::execute()?;
-                                                            break;
-                                                        }
-                                                        _ => {}
+                            Action::ToggleCtrlShortcuts => {
+                                self.view.toggle_ctrl_shortcuts();
+                                self.view.render()?;
+                            }
+
+                            Action::Quit => {
+                                if self.tab_manager.current_tab().has_unsaved_changes {
+                                    self.view.show_prompt(
+                                        crate::tui::view::PromptKind::Error,
+                                        "Unsaved changes. Quit? (y/n)".to_string(),
+                                    );
+                                    self.view.needs_redraw = true;
+                                    self.view.render_if_needed(true)?;
+                                    Terminal::execute()?;
+
+                                    loop {
+                                        match read()? {
+                                            Event::Key(ev)
+                                                if ev.kind == KeyEventKind::Press =>
+                                            {
+                                                match ev.code {
+                                                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                                        self.quit_program = true;
+                                                        self.discarded_changes = true;
+                                                        break;
                                                     }
+                                                    KeyCode::Char('n')
+                                                    | KeyCode::Char('N')
+                                                    | KeyCode::Esc => {
+                                                        self.view.clear_prompt();
+                                                        self.view.render_if_needed(true)?;
+                                                        Terminal::execute()?;
+                                                        break;
+                                                    }
+                                                    _ => {}
                                                 }
-                                                _ => {}
                                             }
+                                            _ => {}
                                         }
-                                    } else {
-                                        self.quit_program = true;
                                     }
+                                } else {
+                                    self.quit_program = true;
                                 }
+                            }
 
-                                Action::Print => match event.code {
-                                    KeyCode::Tab => {
-                                        for _ in 0..4 {
-                                            if let Some(op) =
-                                                self.view.type_character(' ', &mut self.caret)?
-                                            {
-                                                self.tab_manager
-                                                    .current_tab_mut()
-                                                    .edit_history
-                                                    .push(op);
-                                            }
-                                        }
-                                        self.tab_manager.current_tab_mut().has_unsaved_changes =
-                                            true;
-                                    }
-                                    KeyCode::Char(character) => {
-                                        if let Some(op) =
-                                            self.view.type_character(character, &mut self.caret)?
-                                        {
-                                            self.tab_manager
-                                                .current_tab_mut()
-                                                .edit_history
-                                                .push(op);
-                                            self.tab_manager
-                                                .current_tab_mut()
-                                                .has_unsaved_changes = true;
-                                        }
-                                    }
-                                    _ => {}
-                                },
-
+                            Action::Print => match event.code {
+                                KeyCode::Tab => {
+                                    let changed = self.view.tab(&mut self.caret)?;
+                                    self.mark_dirty_if(changed);
+                                }
+                                KeyCode::BackTab => self
+                                    .view
+                                    .cursor_command(&mut self.caret, |editor| editor.snippet_step(false))?,
+                                KeyCode::Char(character) => {
+                                    let changed = self.view.type_character(character, &mut self.caret)?;
+                                    self.mark_dirty_if(changed);
+                                }
                                 _ => {}
-                            }
+                            },
+
+                            _ => {}
+                        }
 
-                            self.view.render_if_needed(
-                                &self.caret,
-                                self.tab_manager.current_tab().has_unsaved_changes,
-                            )?;
-                            Terminal::execute()?;
+                        if typed_word_char && (completing || self.view.completion_settings.auto) {
+                            let min_prefix = if completing { 1 } else { self.view.completion_settings.min_prefix };
+                            self.update_completion(min_prefix);
+                        } else if completing && refreshes_completion {
+                            self.update_completion(1);
+                        } else if !typed_word_char && !keeps_completion {
+                            self.view.set_completion(None);
                         }
+                        self.sync_lsp();
+
+                        self.view.render_if_needed(
+                            self.tab_manager.current_tab().has_unsaved_changes,
+                        )?;
+                        Terminal::execute()?;
                     }
                 }
 
                 Event::Mouse(mouse_event) => {
                     use crossterm::event::MouseEventKind;
+                    // The diff view only scrolls
+                    let page = self.view.diff_rows()?;
+                    if let Some(diff) = self.view.diff.as_mut() {
+                        match mouse_event.kind {
+                            MouseEventKind::ScrollUp => diff.scroll_by(-3, page),
+                            MouseEventKind::ScrollDown => diff.scroll_by(3, page),
+                            _ => continue,
+                        }
+                        self.view.needs_redraw = true;
+                        self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
+                        Terminal::execute()?;
+                        continue;
+                    }
                     match mouse_event.kind {
                         // Handle mouse wheel scroll events
                         MouseEventKind::ScrollUp => {
@@ -526,6 +1417,10 @@ impl TerminalEditor {
                             if let Some(action) = self.shortcuts.resolve_mouse(&mouse_event) {
                                 match action {
                                     Action::MouseDown(x, y) => {
+                                        // A click in the other pane focuses it first
+                                        if self.view.in_other_pane(x, y)? {
+                                            self.focus_other_pane()?;
+                                        }
                                         self.view.handle_mouse_down(x, y, &mut self.caret)?
                                     }
                                     Action::MouseDrag(x, y) => {
@@ -557,42 +1452,71 @@ impl TerminalEditor {
             }
 
             if self.quit_program {
+                // The session saved on exit reads cursors and bookmarks from the tabs
+                self.sync_tab_from_view();
                 break;
             }
         }
         Ok(())
     }
 
+    // Run the file type's pre-save hooks on the document and return the text to write,
+    // with the post-save command to start once it is written and the formatter's error
+    fn prepare_save(&mut self) -> Result<(String, Option<String>, Option<String>), std::io::Error> {
+        let filetype = self.view.filetype.clone();
+        let hooks = self.save.for_filetype(filetype.as_deref());
+        let formatter = filetype.as_deref().and_then(|filetype| self.format.command_for(filetype)).map(String::from);
+        let dir = self.file_dir();
+        let busy = hooks.format && formatter.is_some();
+        if let Some(command) = formatter.as_deref().filter(|_| busy) {
+            self.show_busy(format!("Formatting with `{}`...", command))?;
+        }
+        let mut error = None;
+        self.view.line_command(&mut self.caret, |editor| {
+            let (changed, formatter_error) = hooks.before_save(editor, formatter.as_deref(), dir.as_deref());
+            error = formatter_error;
+            changed
+        })?;
+        if busy {
+            self.view.clear_prompt();
+        }
+        Ok((hooks.file_text(&self.view.editor.content()), hooks.after, error))
+    }
+
+    // Once the file is written: start the post-save command and show the formatter's
+    // error, which stays on screen with the command's progress and then its result
+    fn report_save(&mut self, command: Option<String>, error: Option<String>) {
+        let status = match command {
+            Some(command) => {
+                self.after_save.start(&command, self.file_dir(), error);
+                self.after_save.status()
+            }
+            None => error.map(Err),
+        };
+        match status {
+            Some(Ok(message)) => self.view.show_prompt(PromptKind::SearchInfo, message),
+            Some(Err(message)) => self.view.show_prompt(PromptKind::Error, message),
+            None => {}
+        }
+    }
+
     fn save_file(&mut self) -> Result<(), std::io::Error> {
         use std::fs;
 
         let filepath_opt = self.tab_manager.current_tab().filepath.clone();
 
         if let Some(filepath) = filepath_opt {
-            let last_line = self
-                .view
-                .buffer
-                .lines
-                .iter()
-                .rposition(|line| !line.is_empty())
-                .unwrap_or(0);
-            let content = self
-                .view
-                .buffer
-                .lines
-                .iter()
-                .take(last_line + 1)
-                .cloned()
-                .collect::<Vec<_>>()
-                .join("\n");
+            let (content, after, error) = self.prepare_save()?;
 
             match fs::write(&filepath, content) {
                 Ok(_) => {
+                    self.report_save(after, error);
+                    self.saved = true;
                     self.tab_manager.current_tab_mut().has_unsaved_changes = false;
                     self.sync_tab_from_view();
                     let _ = self.tab_manager.save_session();
                     self.view.needs_redraw = true;
-                    self.view.render_if_needed(&self.caret, false)?;
+                    self.view.render_if_needed(false)?;
                     Terminal::execute()?;
                 }
                 Err(e) => return Err(e),
@@ -604,10 +1528,7 @@ impl TerminalEditor {
                 "Save as: ".to_string(),
             );
             self.view.needs_redraw = true;
-            self.view.render_if_needed(
-                &self.caret,
-                self.tab_manager.current_tab().has_unsaved_changes,
-            )?;
+            self.view.render_if_needed(self.tab_manager.current_tab().has_unsaved_changes)?;
             Terminal::execute()?;
 
             loop {
@@ -653,32 +1574,20 @@ impl TerminalEditor {
                                         friendly_filetype,
                                     );
 
-                                    let last_line = self
-                                        .view
-                                        .buffer
-                                        .lines
-                                        .iter()
-                                        .rposition(|line| !line.is_empty())
-                                        .unwrap_or(0);
-                                    let content = self
-                                        .view
-                                        .buffer
-                                        .lines
-                                        .iter()
-                                        .take(last_line + 1)
-                                        .cloned()
-                                        .collect::<Vec<_>>()
-                                        .join("\n");
+                                    let (content, after, error) = self.prepare_save()?;
 
                                     match fs::write(&full_path, content) {
                                         Ok(_) => {
+                                            self.report_save(after, error);
+                                            self.saved = true;
                                             self.tab_manager
                                                 .current_tab_mut()
                                                 .has_unsaved_changes = false;
+                                            self.reload_git();
                                             self.sync_tab_from_view();
                                             let _ = self.tab_manager.save_session();
                                             self.view.needs_redraw = true;
-                                            self.view.render_if_needed(&self.caret, false)?;
+                                            self.view.render_if_needed(false)?;
                                             Terminal::execute()?;
                                         }
                                         Err(e) => {
@@ -686,7 +1595,7 @@ impl TerminalEditor {
                                                 crate::tui::view::PromptKind::Error,
                                                 format!("Failed to save: {}", e),
                                             );
-                                            self.view.render_if_needed(&self.caret, true)?;
+                                            self.view.render_if_needed(true)?;
                                             Terminal::execute()?;
                                             return Err(e);
                                         }
@@ -697,7 +1606,6 @@ impl TerminalEditor {
                             KeyCode::Esc => {
                                 self.view.clear_prompt();
                                 self.view.render_if_needed(
-                                    &self.caret,
                                     self.tab_manager.current_tab().has_unsaved_changes,
                                 )?;
                                 Terminal::execute()?;
@@ -706,7 +1614,6 @@ impl TerminalEditor {
                             _ => {}
                         }
                         self.view.render_if_needed(
-                            &self.caret,
                             self.tab_manager.current_tab().has_unsaved_changes,
                         )?;
                         Terminal::execute()?;
@@ -1,25 +1,35 @@
 // terminal module responsible for terminal manipulation and information
 use crate::tui::{
     view::View,
-    caret::{ Position, Caret },
+    caret::Caret,
 };
 use crossterm::{
     event::{EnableMouseCapture, DisableMouseCapture},
     cursor::{ DisableBlinking, EnableBlinking, Hide, Show },
     queue,
+    style::Print,
     terminal::{ 
-        Clear, ClearType, DisableLineWrap, disable_raw_mode, 
+        DisableLineWrap, disable_raw_mode, 
         enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen 
     }
 };
 use std::io::{ stdout, Error, Write };
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Size {
     pub height: u16,
     pub width: u16,
 }
 
+// A block of screen cells, e.g. one pane of a split screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
 pub struct Terminal;
 
 impl Terminal {
@@ -27,21 +37,21 @@ impl Terminal {
     pub fn initialize(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
         enable_raw_mode()?;
         queue!(stdout(), EnterAlternateScreen, DisableLineWrap, Hide, EnableMouseCapture )?;
-        Self::clear_screen()?;
         
         queue!(stdout(), Caret::CARET_SETTINGS.style)?;
-        Caret::set_caret_color(Caret::CARET_SETTINGS.color)?;
+        Self::set_caret_color(Caret::CARET_SETTINGS.color)?;
     
-        view.render(caret)?;
-        queue!(stdout(), Show, EnableBlinking)?;
-        caret.move_to(Position { x: Position::MARGIN, y: Position::HEADER })?;
+        // The first frame clears the screen; syncing the caret shows it again
+        view.render()?;
+        queue!(stdout(), EnableBlinking)?;
+        view.sync_caret(caret)?;
         
         Self::execute()?;
         Ok(())
     }
 
     pub fn terminate() -> Result<(), Error> {
-        Caret::reset_caret_color()?;
+        Self::reset_caret_color()?;
         queue!(stdout(), DisableBlinking, Show, LeaveAlternateScreen, DisableMouseCapture)?;
         disable_raw_mode()?;
         Self::execute()?;
@@ -49,13 +59,14 @@ impl Terminal {
         Ok(())
     }
 
-    pub fn clear_screen() -> Result<(), Error> {
-        queue!(stdout(), Clear(ClearType::All))?;
+    // OSC 12 sets the caret colour on terminals that support it
+    fn set_caret_color(color: &str) -> Result<(), Error> {
+        queue!(stdout(), Print(format!("\x1b]12;{}\x07", color)))?;
         Ok(())
     }
-    
-    pub fn clear_rest_of_line() -> Result<(), Error> {
-        queue!(stdout(), Clear(ClearType::UntilNewLine))?;
+
+    fn reset_caret_color() -> Result<(), Error> {
+        queue!(stdout(), Print("\x1b]112\x07"))?;
         Ok(())
     }
 
@@ -0,0 +1,115 @@
+// diff module - two texts compared side by side in place of the editor: the lines of a
+// change tinted, the words that changed within them picked out, gaps where a side has none
+use super::helpers::{halves, pane_rect};
+use crate::core::compare::{intra_line, Comparison, Row, Side};
+use crate::core::panes::SplitDirection;
+use crate::tui::{
+    caret::Position,
+    frame::{Frame, Style},
+    terminal::Size,
+};
+use crossterm::style::Color;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+const TITLE: Style = Style { fg: Some(Color::Black), bg: Some(Color::Grey), bold: true, italic: false, underline: false };
+const REMOVED: Style = Style { fg: Some(Color::White), bg: Some(Color::DarkRed), bold: false, italic: false, underline: false };
+const REMOVED_WORDS: Style = Style { fg: Some(Color::White), bg: Some(Color::Red), bold: true, italic: false, underline: false };
+const ADDED: Style = Style { fg: Some(Color::White), bg: Some(Color::DarkGreen), bold: false, italic: false, underline: false };
+const ADDED_WORDS: Style = Style { fg: Some(Color::Black), bg: Some(Color::Green), bold: true, italic: false, underline: false };
+// Rows kept above a change jumped to
+const CONTEXT_ROWS: usize = 3;
+
+pub struct DiffView {
+    pub comparison: Comparison,
+    // First row shown
+    pub scroll: usize,
+}
+
+impl DiffView {
+    // Opens at the first change
+    pub fn new(comparison: Comparison) -> Self {
+        let mut diff = Self { comparison, scroll: 0 };
+        if let Some(hunk) = diff.comparison.current {
+            diff.show_row(diff.comparison.hunk_row(hunk));
+        }
+        diff
+    }
+
+    pub fn show_row(&mut self, row: usize) {
+        self.scroll = row.saturating_sub(CONTEXT_ROWS);
+    }
+
+    pub fn scroll_by(&mut self, rows: isize, visible_rows: usize) {
+        let last = self.comparison.rows.len().saturating_sub(visible_rows);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(last);
+    }
+}
+
+// Rows of the comparison below the title line
+pub fn visible_rows(size: Size) -> usize {
+    pane_rect(size, None).height.saturating_sub(1) as usize
+}
+
+pub fn draw(frame: &mut Frame, diff: &DiffView) {
+    let (left, divider, right) = halves(pane_rect(frame.size(), None), SplitDirection::Vertical);
+    let comparison = &diff.comparison;
+    for (rect, side) in [(left, Side::Left), (right, Side::Right)] {
+        let mut body = Frame::new(Size { width: rect.width, height: rect.height });
+        let title = match side {
+            Side::Left => &comparison.titles.0,
+            Side::Right => &comparison.titles.1,
+        };
+        body.fill_row(0, TITLE);
+        body.print(1, 0, title, TITLE);
+        let rows = comparison.rows.iter().skip(diff.scroll).take(rect.height.saturating_sub(1) as usize);
+        for (y, row) in rows.enumerate() {
+            draw_row(&mut body, y as u16 + 1, comparison, row, side);
+        }
+        frame.blit(&body, rect.x, rect.y);
+    }
+    for y in divider.y..divider.y + divider.height {
+        frame.print(divider.x, y, "│", Style::default().fg(Color::DarkGrey));
+    }
+}
+
+fn draw_row(frame: &mut Frame, y: u16, comparison: &Comparison, row: &Row, side: Side) {
+    let (line, lines, other) = match side {
+        Side::Left => (row.left, &comparison.lines.0, row.right),
+        Side::Right => (row.right, &comparison.lines.1, row.left),
+    };
+    let (tint, words) = match side {
+        Side::Left => (REMOVED, REMOVED_WORDS),
+        Side::Right => (ADDED, ADDED_WORDS),
+    };
+    let Some(line) = line else {
+        // Nothing on this side: a hatched gap
+        let gap = "╱".repeat(frame.size().width.saturating_sub(Position::MARGIN) as usize);
+        frame.print(Position::MARGIN, y, &gap, Style::default().fg(Color::DarkGrey));
+        return;
+    };
+    let current = row.hunk.is_some() && row.hunk == comparison.current;
+    let number = Style::default().fg(Color::Yellow);
+    let x = frame.print(0, y, &format!("{:>3}", line + 1), if current { number.bold() } else { number });
+    frame.print(x, y, if current { "▌" } else { " " }, Style::default().fg(Color::Yellow));
+
+    let text = &lines[line];
+    if row.hunk.is_none() {
+        frame.print(Position::MARGIN, y, text, Style::default());
+        return;
+    }
+    // A changed line paired with one on the other side marks the words that differ
+    let changed: Vec<Range<usize>> = match (other, side) {
+        (Some(other), Side::Left) => intra_line(text, &comparison.lines.1[other]).0,
+        (Some(other), Side::Right) => intra_line(&comparison.lines.0[other], text).1,
+        (None, _) => Vec::new(),
+    };
+    for x in Position::MARGIN..frame.size().width {
+        frame.print(x, y, " ", tint);
+    }
+    let mut x = Position::MARGIN;
+    for (column, grapheme) in text.graphemes(true).enumerate() {
+        let style = if changed.iter().any(|range| range.contains(&column)) { words } else { tint };
+        x = frame.print(x, y, grapheme, style);
+    }
+}
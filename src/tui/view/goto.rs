@@ -0,0 +1,71 @@
+// goto module - footer prompt for jumping to a line, line:column or relative offset
+use super::View;
+use crate::core::goto::parse_target;
+use crate::core::selection::TextPosition;
+use crate::tui::{caret::Caret, terminal::Terminal};
+use crossterm::event::{Event, KeyCode, KeyEventKind, read};
+use std::io::Error;
+
+pub fn go_to_line(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
+    view.show_prompt(
+        super::PromptKind::GoTo,
+        "Go to line[:col] or ±N:".to_string(),
+    );
+    view.needs_redraw = true;
+    view.render_if_needed(false)?;
+    Terminal::execute()?;
+
+    let mut input = String::new();
+
+    loop {
+        match read()? {
+            Event::Key(event) if event.kind == KeyEventKind::Press => match event.code {
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    view.append_prompt_char(c);
+                    view.render_if_needed(false)?;
+                    Terminal::execute()?;
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                    view.backspace_prompt();
+                    view.render_if_needed(false)?;
+                    Terminal::execute()?;
+                }
+                KeyCode::Enter => {
+                    view.clear_prompt();
+                    match parse_target(&input, view.editor.cursor) {
+                        Some(target) => jump_to(view, caret, target)?,
+                        None if input.trim().is_empty() => {}
+                        None => view.show_prompt(
+                            super::PromptKind::Error,
+                            format!("Not a line number: '{}'", input.trim()),
+                        ),
+                    }
+                    break;
+                }
+                KeyCode::Esc => {
+                    view.clear_prompt();
+                    break;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    view.render_if_needed(false)?;
+    Terminal::execute()
+}
+
+// Move to `target`, centering its line when it was off screen
+pub(super) fn jump_to(view: &mut View, caret: &mut Caret, target: TextPosition) -> Result<(), Error> {
+    let target = view.clamp_position(target);
+    let visible_rows = super::helpers::visible_rows(view)?;
+    if target.line < view.scroll_offset || target.line >= view.scroll_offset + visible_rows {
+        view.scroll_offset = target.line.saturating_sub(visible_rows / 2);
+    }
+    view.editor.move_to(target, false);
+    view.needs_redraw = true;
+    view.set_cursor(caret, target)
+}
@@ -1,17 +1,35 @@
 // view mod module with corrected EditOperation returns
 mod clipboard;
+mod diff;
+mod goto;
 mod keyboard;
 mod mouse;
+mod prompt;
 mod render;
 mod search;
 mod selection;
 
 pub use crate::core::buffer::Buffer;
+use crate::core::graphemes::{grapheme_len, grapheme_slice, visual_width};
 use crate::core::{
-    edit_history::EditOperation,
-    selection::{Selection, TextPosition},
+    autopair::{AutoCloseSettings, AutoPairs},
+    brackets::BracketMatcher,
+    completion::{Completion, CompletionSettings},
+    editor::{Editor, Motion},
+    git::{GitFile, GitFiles},
+    lsp::Diagnostic,
+    panes::{Split, SplitDirection},
+    selection::TextPosition,
+    tabs::Tab,
+    wrap,
 };
-use crate::tui::{caret::Caret, terminal::Terminal};
+use crate::tui::{
+    backend::{Backend, CrosstermBackend},
+    caret::Caret,
+    frame::Frame,
+    terminal::{Rect, Size},
+};
+pub use diff::DiffView;
 pub use search::SearchState;
 use std::io::Error;
 
@@ -21,6 +39,11 @@ pub(crate) enum PromptKind {
     Error,
     Search,
     SearchInfo,
+    GoTo,
+    Bookmark,
+    Rename,
+    Command,
+    Format,
 }
 
 // Prompt state shown in the footer when active.
@@ -30,11 +53,41 @@ pub(in crate::tui) struct Prompt {
     pub input: String,
 }
 
+// A box of text over the editor: documentation under the cursor, or a list (the
+// diagnostics) with one entry highlighted
+pub struct Popup {
+    pub lines: Vec<String>,
+    pub selected: Option<usize>,
+    // Shown below this position, or across the top of the text area when None
+    pub anchor: Option<TextPosition>,
+}
+
+// The tab shown by the pane without focus when it isn't the focused pane's, as it was when
+// that pane lost focus, with the language server's findings for it
+pub struct OtherTab {
+    pub tab: Tab,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
 pub struct View {
-    pub buffer: Buffer,
-    pub selection: Option<Selection>,
+    // Buffer, document-space cursor, selection and undo history; the terminal caret is
+    // derived from editor.cursor on every move
+    pub editor: Editor,
     pub is_dragging: bool,
     pub scroll_offset: usize,
+    // Horizontal scroll: index of the first grapheme shown after the margin
+    pub col_offset: usize,
+    // Soft wrap: long lines continue on the next screen row (col_offset stays 0)
+    pub soft_wrap: bool,
+    pub wrap_column: Option<usize>,
+    // Which brackets and quotes close themselves, per file type
+    pub auto_close: AutoCloseSettings,
+    // When the completion popup opens by itself, and the popup while it is open
+    pub completion_settings: CompletionSettings,
+    pub completion: Option<Completion>,
+    // Language server findings for this file, underlined and marked in the margin
+    pub server_diagnostics: Vec<Diagnostic>,
+    pub popup: Option<Popup>,
     pub filename: Option<String>,
     pub filetype: Option<String>,
     pub prompt_since: Option<std::time::Instant>,
@@ -42,25 +95,53 @@ pub struct View {
     pub needs_redraw: bool,
     pub search_state: Option<SearchState>,
     pub(in crate::tui) prompt: Option<Prompt>,
+    // A split screen: the pane without focus, and its tab when it shows another one
+    pub split: Option<Split>,
+    pub split_tab: Option<OtherTab>,
+    // Two texts compared side by side, shown instead of the editor while open
+    pub diff: Option<DiffView>,
+    // How each tab's file differs from what is staged, when it's in a git working tree, and
+    // the tab shown
+    pub git_files: GitFiles,
+    pub git_tab: u64,
+    // Where frames are drawn - the terminal, or memory in tests
+    backend: Box<dyn Backend>,
     #[allow(dead_code)]
     // clipboard for wayland must be here even tho rust warns its unused - its not!
     clipboard: Option<arboard::Clipboard>,
 }
 
 impl View {
-    pub fn new(buffer: Buffer) -> Self {
+    pub fn new(editor: Editor) -> Self {
+        Self::with_backend(editor, Box::new(CrosstermBackend::new()))
+    }
+
+    pub fn with_backend(editor: Editor, backend: Box<dyn Backend>) -> Self {
         Self {
-            buffer,
+            editor,
             scroll_offset: 0,
+            col_offset: 0,
+            soft_wrap: false,
+            wrap_column: None,
+            auto_close: AutoCloseSettings::default(),
+            completion_settings: CompletionSettings::default(),
+            completion: None,
+            server_diagnostics: Vec::new(),
+            popup: None,
             filename: None,
             filetype: None,
             prompt_since: None,
             show_shortcuts: false,
-            selection: None,
             is_dragging: false,
             prompt: None,
             needs_redraw: true,
             search_state: None,
+            split: None,
+            split_tab: None,
+            diff: None,
+            git_files: GitFiles::default(),
+            git_tab: 0,
+            backend,
             clipboard: try_clipboard(),
         }
     }
@@ -116,47 +197,168 @@ impl View {
             .map(|p| (&p.kind, p.message.as_str(), p.input.as_str()))
     }
 
+    // The current tab's git changes, once read
+    pub fn git(&self) -> Option<&GitFile> {
+        self.git_files.get(self.git_tab)
+    }
+
+    pub fn apply_settings(&mut self, settings: &crate::core::settings::Settings) {
+        self.soft_wrap = settings.soft_wrap;
+        self.wrap_column = settings.wrap_column;
+        self.auto_close = settings.auto_close.clone();
+        self.completion_settings = settings.completion.clone();
+    }
+
+    // Columns available per visual row when soft wrap is on, None when lines scroll horizontally
+    pub fn wrap_width(&self) -> Option<usize> {
+        self.wrap_width_in(self.pane().ok()?)
+    }
+
+    // The same for a pane with these cells
+    pub fn wrap_width_in(&self, pane: Rect) -> Option<usize> {
+        if !self.soft_wrap {
+            return None;
+        }
+        let text_width = helpers::text_width_in(pane);
+        Some(match self.wrap_column {
+            Some(column) if column > 0 => column.min(text_width),
+            _ => text_width,
+        })
+    }
+
+    pub fn toggle_soft_wrap(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        self.soft_wrap = !self.soft_wrap;
+        self.col_offset = 0;
+        self.needs_redraw = true;
+        self.set_cursor(caret, self.editor.cursor)
+    }
+
     pub fn toggle_ctrl_shortcuts(&mut self) {
         self.show_shortcuts = !self.show_shortcuts;
         self.needs_redraw = true;
     }
 
-    // Rendering
-    pub fn render(&self, caret: &Caret) -> Result<(), Error> {
-        render::render_view(self, caret, false)
+    // Rendering - build a frame of the whole screen and hand it to the backend,
+    // which only repaints the cells that changed
+    pub fn render(&mut self) -> Result<(), Error> {
+        self.draw_frame(false)
     }
 
     // render only if needed and clear the flag
-    pub fn render_if_needed(&mut self, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
+    pub fn render_if_needed(&mut self, is_dirty: bool) -> Result<(), Error> {
         if self.needs_redraw {
-            render::render_view(self, caret, is_dirty)?;
+            self.draw_frame(is_dirty)?;
             self.needs_redraw = false;
         }
         Ok(())
     }
 
-    // Clipboard operations - Return Option<EditOperation>
-    pub fn copy_selection(&self) -> Result<(), Error> {
+    fn draw_frame(&mut self, is_dirty: bool) -> Result<(), Error> {
+        let mut frame = Frame::new(self.size()?);
+        if let Some(git) = self.git_files.get_mut(self.git_tab) {
+            git.update(&self.editor.buffer.lines[..=self.editor.last_content_line()]);
+        }
+        self.track_other_pane();
+        render::render_frame(&mut frame, self, is_dirty);
+        self.backend.draw(&frame)
+    }
+
+    pub fn size(&self) -> Result<Size, Error> {
+        self.backend.size()
+    }
+
+    // Screen cells of the focused pane: everything between header and footer, or part of it
+    pub fn pane(&self) -> Result<Rect, Error> {
+        Ok(helpers::pane_rect(self.size()?, self.split.as_ref()))
+    }
+
+    // Whether a screen cell is in the pane without focus
+    pub fn in_other_pane(&self, x: u16, y: u16) -> Result<bool, Error> {
+        let Some(split) = &self.split else {
+            return Ok(false);
+        };
+        let pane = helpers::other_pane_rect(self.size()?, split);
+        Ok((pane.x..pane.x + pane.width).contains(&x) && (pane.y..pane.y + pane.height).contains(&y))
+    }
+
+    // When both panes show this document, move the other pane's carets and scroll position
+    // along with the edits made here; edits to a document only this pane shows are dropped
+    pub fn track_other_pane(&mut self) {
+        let edits = self.editor.take_line_edits();
+        if let Some(split) = self.split.as_mut().filter(|_| self.split_tab.is_none()) {
+            for (first, old_last, new_last) in edits {
+                split.other.on_edit(first, old_last, new_last);
+            }
+        }
+    }
+
+    // Clipboard operations - edits return true when the buffer changed
+    pub fn copy_selection(&mut self) -> Result<(), Error> {
         clipboard::copy_selection(self)
     }
 
-    pub fn cut_selection(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
-        let result = clipboard::cut_selection(self, caret)?;
-        if result.is_some() {
+    pub fn cut_selection(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        clipboard::cut_selection(self, caret)
+    }
+
+    pub fn paste_from_clipboard(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        clipboard::paste_from_clipboard(self, caret)
+    }
+
+    // Jump to a line, line:column or relative offset typed in the footer
+    pub fn go_to_line(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        goto::go_to_line(self, caret)
+    }
+
+    // Jump straight to a position, e.g. one given on the command line
+    pub fn jump_to(&mut self, caret: &mut Caret, pos: TextPosition) -> Result<(), Error> {
+        goto::jump_to(self, caret, pos)
+    }
+
+    // Ask for a bookmark name in the footer
+    pub fn ask_bookmark(&mut self, message: String) -> Result<Option<String>, Error> {
+        prompt::ask(self, PromptKind::Bookmark, message)
+    }
+
+    // Ask for the new name of the symbol being renamed
+    pub fn ask_new_name(&mut self, message: String) -> Result<Option<String>, Error> {
+        prompt::ask(self, PromptKind::Rename, message)
+    }
+
+    // Ask for a shell command to filter the selection through
+    pub fn ask_command(&mut self, message: String) -> Result<Option<String>, Error> {
+        prompt::ask(self, PromptKind::Command, message)
+    }
+
+    // Ask which format to convert the document to
+    pub fn ask_format(&mut self, message: String) -> Result<Option<String>, Error> {
+        prompt::ask(self, PromptKind::Format, message)
+    }
+
+    // The server's diagnostics and a failed JSON/TOML/YAML validation, as shown
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.server_diagnostics.iter().chain(&self.editor.data_error)
+    }
+
+    // Rows of the comparison on screen, for paging through it
+    pub fn diff_rows(&self) -> Result<usize, Error> {
+        Ok(diff::visible_rows(self.size()?))
+    }
+
+    // Show or hide the hover / diagnostics popup
+    pub fn set_popup(&mut self, popup: Option<Popup>) {
+        if popup.is_some() || self.popup.is_some() {
             self.needs_redraw = true;
         }
-        Ok(result)
+        self.popup = popup;
     }
 
-    pub fn paste_from_clipboard(
-        &mut self,
-        caret: &mut Caret,
-    ) -> Result<Option<EditOperation>, Error> {
-        let result = clipboard::paste_from_clipboard(self, caret)?;
-        if result.is_some() {
+    // Show or hide the completion popup
+    pub fn set_completion(&mut self, completion: Option<Completion>) {
+        if completion.is_some() || self.completion.is_some() {
             self.needs_redraw = true;
         }
-        Ok(result)
+        self.completion = completion;
     }
 
     // Search in text
@@ -237,47 +439,51 @@ impl View {
         Ok(())
     }
 
-    // Keyboard operations - Return Option<EditOperation>
-    pub fn type_character(
-        &mut self,
-        character: char,
-        caret: &mut Caret,
-    ) -> Result<Option<EditOperation>, Error> {
-        let result = keyboard::type_character(self, character, caret)?;
-        if result.is_some() {
-            self.needs_redraw = true;
-        }
-        Ok(result)
+    // Keyboard operations - return true when the buffer changed
+    pub fn type_character(&mut self, character: char, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::type_char(self, character, caret)
     }
 
-    pub fn insert_newline(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
-        let result = keyboard::insert_newline(self, caret)?;
-        if result.is_some() {
-            self.needs_redraw = true;
-        }
-        Ok(result)
+    pub fn tab(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::tab(self, caret)
     }
 
-    pub fn delete_char(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
-        let result = keyboard::delete_char(self, caret)?;
-        if result.is_some() {
-            self.needs_redraw = true;
-        }
-        Ok(result)
+    pub fn insert_newline(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::insert_text(self, "\n", caret)
     }
 
-    pub fn backspace(&mut self, caret: &mut Caret) -> Result<Option<EditOperation>, Error> {
-        let result = keyboard::backspace(self, caret)?;
-        if result.is_some() {
-            self.needs_redraw = true;
-        }
-        Ok(result)
+    pub fn delete_char(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::delete_char(self, caret)
+    }
+
+    pub fn backspace(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::backspace(self, caret)
+    }
+
+    pub fn delete_word(&mut self, forward: bool, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::delete_word(self, forward, caret)
+    }
+
+    pub fn line_command(
+        &mut self,
+        caret: &mut Caret,
+        command: impl FnOnce(&mut Editor) -> bool,
+    ) -> Result<bool, Error> {
+        keyboard::edit_with(self, caret, command)
+    }
+
+    pub fn undo(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::undo(self, caret)
+    }
+
+    pub fn redo(&mut self, caret: &mut Caret) -> Result<bool, Error> {
+        keyboard::redo(self, caret)
     }
 
     // Movement operations - only mark dirty if scroll changes or selection changes
     pub fn move_up(&mut self, caret: &mut Caret) -> Result<(), Error> {
         let old_offset = self.scroll_offset;
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("up", caret)?;
         if self.scroll_offset != old_offset || had_selection {
             self.needs_redraw = true;
@@ -287,7 +493,7 @@ impl View {
 
     pub fn move_down(&mut self, caret: &mut Caret) -> Result<(), Error> {
         let old_offset = self.scroll_offset;
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("down", caret)?;
         if self.scroll_offset != old_offset || had_selection {
             self.needs_redraw = true;
@@ -297,7 +503,7 @@ impl View {
 
     pub fn move_left(&mut self, caret: &mut Caret) -> Result<(), Error> {
         let old_offset = self.scroll_offset;
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("left", caret)?;
         if self.scroll_offset != old_offset || had_selection {
             self.needs_redraw = true;
@@ -307,7 +513,7 @@ impl View {
 
     pub fn move_right(&mut self, caret: &mut Caret) -> Result<(), Error> {
         let old_offset = self.scroll_offset;
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("right", caret)?;
         if self.scroll_offset != old_offset || had_selection {
             self.needs_redraw = true;
@@ -328,7 +534,7 @@ impl View {
     }
 
     pub fn move_max_left(&mut self, caret: &mut Caret) -> Result<(), Error> {
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("max_left", caret)?;
         if had_selection {
             self.needs_redraw = true;
@@ -337,7 +543,7 @@ impl View {
     }
 
     pub fn move_max_right(&mut self, caret: &mut Caret) -> Result<(), Error> {
-        let had_selection = self.selection.is_some();
+        let had_selection = self.editor.selection.is_some();
         self.move_without_selection("max_right", caret)?;
         if had_selection {
             self.needs_redraw = true;
@@ -366,39 +572,129 @@ impl View {
         Ok(())
     }
 
-    pub fn handle_resize(&mut self, caret: &mut Caret, is_dirty: bool) -> Result<(), Error> {
-        caret.clamp_to_bounds()?;
+    // Grow the rectangular selection (Alt+Shift+arrows)
+    pub fn extend_block(&mut self, motion: Motion, caret: &mut Caret) -> Result<(), Error> {
+        self.editor.extend_block(motion);
         self.needs_redraw = true;
-        self.render_if_needed(caret, is_dirty)?;
-        caret.move_to(caret.get_position())?;
-        Ok(())
+        self.set_cursor(caret, self.editor.cursor)
     }
 
-    // Helper for clamping cursor to line length
-    pub(in crate::tui::view) fn clamp_cursor_to_line(
-        &self,
+    // Multi-cursor commands on the editor; the primary cursor is the one kept on screen
+    pub fn cursor_command(
+        &mut self,
         caret: &mut Caret,
+        command: impl FnOnce(&mut Editor) -> bool,
     ) -> Result<(), Error> {
-        use crate::tui::caret::Position;
+        if command(&mut self.editor) {
+            self.needs_redraw = true;
+            self.set_cursor(caret, self.editor.cursor)?;
+        }
+        Ok(())
+    }
 
-        let pos = caret.get_position();
-        let buffer_line_idx =
-            (pos.y.saturating_sub(Position::HEADER)) as usize + self.scroll_offset;
+    pub fn handle_resize(&mut self, caret: &mut Caret, is_dirty: bool) -> Result<(), Error> {
+        self.needs_redraw = true;
+        self.scroll_to_cursor()?;
+        self.render_if_needed(is_dirty)?;
+        self.sync_caret(caret)
+    }
 
-        if let Some(line) = self.buffer.lines.get(buffer_line_idx) {
-            let line_end = Position::MARGIN + line.len() as u16;
-            let size = Terminal::get_size()?;
-            let max_x = line_end.min(size.width - 1);
+    // Move the document cursor, scroll it into view and place the terminal caret on it
+    pub fn set_cursor(&mut self, caret: &mut Caret, pos: TextPosition) -> Result<(), Error> {
+        self.editor.cursor = self.clamp_position(pos);
+        if self.editor.folds.reveal(self.editor.cursor.line) {
+            self.needs_redraw = true;
+        }
+        self.scroll_to_cursor()?;
+        if self.needs_redraw {
+            self.render()?;
+        }
+        self.sync_caret(caret)
+    }
 
-            if pos.x > max_x {
-                caret.move_to(Position { x: max_x, y: pos.y })?;
-            } else {
-                caret.move_to(pos)?;
-            }
+    // Put the terminal caret on the cell showing the cursor, or hide it when scrolled away
+    pub fn sync_caret(&mut self, caret: &mut Caret) -> Result<(), Error> {
+        match helpers::text_to_screen_pos(self, self.editor.cursor)? {
+            Some(pos) if self.diff.is_none() => caret.move_to(pos),
+            _ => caret.hide(),
+        }
+        self.backend.show_caret(caret)
+    }
+
+    // Auto-close pairs for the file type being edited
+    pub(in crate::tui::view) fn auto_pairs(&self) -> AutoPairs {
+        self.auto_close.for_filetype(self.filetype.as_deref())
+    }
+
+    // The bracket next to the cursor and its partner, highlighted while the cursor is there
+    pub(in crate::tui::view) fn bracket_match(&self) -> Option<(TextPosition, TextPosition)> {
+        BracketMatcher::new(self.filetype.clone()).matching(&self.editor.buffer.lines, self.editor.cursor)
+    }
+
+    // Keep a position inside the buffer
+    pub(in crate::tui::view) fn clamp_position(&self, pos: TextPosition) -> TextPosition {
+        self.editor.clamp(pos)
+    }
+
+    // Adjust col_offset so that `pos` falls inside the visible text area
+    pub(in crate::tui::view) fn scroll_to_column(&mut self, pos: TextPosition) -> Result<(), Error> {
+        let text_width = helpers::text_width(self)?;
+        let old_offset = self.col_offset;
+        let column = pos.column;
+        let line = self.editor.buffer.lines.get(pos.line).map(String::as_str).unwrap_or("");
+
+        if column < self.col_offset {
+            self.col_offset = column;
         } else {
-            caret.move_to(pos)?;
+            // Scroll right until everything up to and including the cell at `column` fits
+            let cell_width = |c: usize| {
+                let g = grapheme_slice(line, c, c + 1);
+                visual_width(&g).max(1)
+            };
+            let target = visual_width(&grapheme_slice(line, 0, column)) + cell_width(column);
+            let mut hidden = visual_width(&grapheme_slice(line, 0, self.col_offset));
+            while target - hidden > text_width && self.col_offset < column {
+                hidden += cell_width(self.col_offset);
+                self.col_offset += 1;
+            }
+        }
+
+        if self.col_offset != old_offset {
+            self.needs_redraw = true;
+        }
+        Ok(())
+    }
+
+    // Scroll vertically (whole lines) and horizontally so the cursor is on screen
+    pub(in crate::tui::view) fn scroll_to_cursor(&mut self) -> Result<(), Error> {
+        let pos = self.editor.cursor;
+        let visible_rows = helpers::visible_rows(self)?.max(1);
+        let old_scroll = self.scroll_offset;
+        let (lines, folds) = (&self.editor.buffer.lines, &self.editor.folds);
+
+        if pos.line < self.scroll_offset {
+            self.scroll_offset = pos.line;
+        } else if let Some(width) = self.wrap_width() {
+            // Drop whole lines off the top until the target row fits on screen
+            while self.scroll_offset < pos.line {
+                let rows = wrap::rows_from(lines, folds, self.scroll_offset, width, visible_rows);
+                if wrap::row_containing(&rows, pos).is_some() {
+                    break;
+                }
+                self.scroll_offset = folds.next_visible(self.scroll_offset, lines.len()).unwrap_or(pos.line);
+            }
+        } else if !folds.visible_lines(self.scroll_offset, lines.len()).take(visible_rows).any(|l| l == pos.line) {
+            // Folded lines take no rows, so count visible lines back from the cursor
+            self.scroll_offset = folds.step(pos.line, visible_rows - 1, false, lines.len());
+        }
+
+        if self.wrap_width().is_none() {
+            self.scroll_to_column(pos)?;
         }
 
+        if self.scroll_offset != old_scroll {
+            self.needs_redraw = true;
+        }
         Ok(())
     }
 }
@@ -406,17 +702,30 @@ impl View {
 impl Default for View {
     fn default() -> Self {
         Self {
-            buffer: Buffer::default(),
+            editor: Editor::new(Buffer::default()),
             scroll_offset: 0,
+            col_offset: 0,
+            soft_wrap: false,
+            wrap_column: None,
+            auto_close: AutoCloseSettings::default(),
+            completion_settings: CompletionSettings::default(),
+            completion: None,
+            server_diagnostics: Vec::new(),
+            popup: None,
             filename: None,
             filetype: None,
             prompt_since: None,
             show_shortcuts: false,
-            selection: None,
             is_dragging: false,
             prompt: None,
             needs_redraw: true,
             search_state: None,
+            split: None,
+            split_tab: None,
+            diff: None,
+            git_files: GitFiles::default(),
+            git_tab: 0,
+            backend: Box::new(CrosstermBackend::new()),
             clipboard: try_clipboard(),
         }
     }
@@ -426,62 +735,168 @@ impl Default for View {
 pub mod helpers {
     use super::*;
     use crate::tui::caret::Position;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    // The focused pane's cells: the rows between header and footer, or one half of them
+    // when split (left or top first), with a divider line between the halves
+    pub fn pane_rect(size: Size, split: Option<&Split>) -> Rect {
+        let full = Rect {
+            x: 0,
+            y: Position::HEADER,
+            width: size.width,
+            height: size.height.saturating_sub(Position::HEADER + 1),
+        };
+        let Some(split) = split else {
+            return full;
+        };
+        let (first, _, second) = halves(full, split.direction);
+        if split.focus_second { second } else { first }
+    }
+
+    // The first pane, the divider and the second pane
+    pub fn halves(full: Rect, direction: SplitDirection) -> (Rect, Rect, Rect) {
+        match direction {
+            SplitDirection::Vertical => {
+                let first = full.width.saturating_sub(1) / 2;
+                let second = full.width.saturating_sub(first + 1);
+                (
+                    Rect { width: first, ..full },
+                    Rect { x: full.x + first, width: 1, ..full },
+                    Rect { x: full.x + first + 1, width: second, ..full },
+                )
+            }
+            SplitDirection::Horizontal => {
+                let first = full.height.saturating_sub(1) / 2;
+                let second = full.height.saturating_sub(first + 1);
+                (
+                    Rect { height: first, ..full },
+                    Rect { y: full.y + first, height: 1, ..full },
+                    Rect { y: full.y + first + 1, height: second, ..full },
+                )
+            }
+        }
+    }
+
+    // Number of screen rows available for text (between header and footer)
+    pub fn visible_rows(view: &View) -> Result<usize, Error> {
+        Ok(view.pane()?.height as usize)
+    }
+
+    // The pane without focus
+    pub fn other_pane_rect(size: Size, split: &Split) -> Rect {
+        pane_rect(size, Some(&Split { focus_second: !split.focus_second, ..split.clone() }))
+    }
+
+    // Screen columns available for text, keeping the last one free for the caret after a full row
+    pub fn text_width(view: &View) -> Result<usize, Error> {
+        Ok(text_width_in(view.pane()?))
+    }
+
+    pub fn text_width_in(pane: Rect) -> usize {
+        (pane.width.saturating_sub(Position::MARGIN + 1) as usize).max(1)
+    }
+
+    // Grapheme column (counted from `start`) under display column `x` of a line
+    fn column_at_x(line: &str, start: usize, x: usize) -> usize {
+        let mut width = 0;
+        for (idx, g) in line.graphemes(true).enumerate().skip(start) {
+            let w = visual_width(g);
+            // Clicking the right half of a wide character still lands before it
+            if width + w > x {
+                return idx;
+            }
+            width += w;
+        }
+        grapheme_len(line)
+    }
 
     pub fn screen_to_text_pos(
         view: &View,
         screen_x: u16,
         screen_y: u16,
     ) -> Result<TextPosition, Error> {
-        let size = Terminal::get_size()?;
-
-        // Clamp to valid screen area (don't include footer)
-        let y = screen_y.min(size.height.saturating_sub(2));
-
-        // Adjust for margin
-        let x = screen_x.saturating_sub(Position::MARGIN);
-
-        // Convert screen Y to buffer line index (accounting for header and scroll)
-        let line_idx = if y >= Position::HEADER {
-            (y - Position::HEADER) as usize + view.scroll_offset
-        } else {
-            0
-        };
+        let pane = view.pane()?;
+
+        // Clamp to the pane (the footer and the other pane aren't text)
+        let y = screen_y.min(pane.y + pane.height.saturating_sub(1));
+        let row = y.saturating_sub(pane.y) as usize;
+        let x = screen_x.saturating_sub(pane.x + Position::MARGIN) as usize;
+
+        let (lines, folds) = (&view.editor.buffer.lines, &view.editor.folds);
+        if let Some(width) = view.wrap_width() {
+            let rows = wrap::rows_from(lines, folds, view.scroll_offset, width, row + 1);
+            if let Some(r) = rows.get(row) {
+                let column = column_at_x(&view.editor.buffer.lines[r.line], r.start, x);
+                return Ok(TextPosition {
+                    line: r.line,
+                    column: column.min(r.max_column()),
+                });
+            }
+            // Below the last row: end of the buffer
+            return Ok(view.clamp_position(TextPosition {
+                line: usize::MAX,
+                column: usize::MAX,
+            }));
+        }
 
-        // Clamp to actual line length
-        let max_col = if let Some(line) = view.buffer.lines.get(line_idx) {
-            line.len()
-        } else {
-            0
+        // Below the last row: start of the last visible line
+        let Some(line_idx) = folds.visible_lines(view.scroll_offset, lines.len()).nth(row) else {
+            let line = folds.prev_visible(lines.len()).unwrap_or(0);
+            return Ok(view.clamp_position(TextPosition { line, column: 0 }));
         };
+        let column = column_at_x(&lines[line_idx], view.col_offset, x);
 
-        Ok(TextPosition {
+        Ok(view.clamp_position(TextPosition {
             line: line_idx,
-            column: (x as usize).min(max_col),
-        })
-    }
+            column,
+        }))
+    }
+
+    // Screen cell showing a document position, None when it is scrolled out of view
+    pub fn text_to_screen_pos(view: &View, pos: TextPosition) -> Result<Option<Position>, Error> {
+        let scroll = (view.scroll_offset, view.col_offset);
+        Ok(screen_pos(&view.editor, scroll, view.wrap_width(), view.pane()?, pos))
+    }
+
+    // The same in any pane: its document, scroll position, soft wrap width and cells
+    pub fn screen_pos(
+        editor: &Editor,
+        (scroll_offset, col_offset): (usize, usize),
+        wrap_width: Option<usize>,
+        pane: Rect,
+        pos: TextPosition,
+    ) -> Option<Position> {
+        let (lines, folds) = (&editor.buffer.lines, &editor.folds);
+        if pos.line < scroll_offset || folds.is_hidden(pos.line) {
+            return None;
+        }
+        let visible_rows = pane.height as usize;
+        let line = lines.get(pos.line).map(String::as_str).unwrap_or("");
 
-    pub fn text_to_screen_pos(view: &View, pos: TextPosition) -> (u16, u16) {
-        // Convert buffer line index to screen Y (accounting for header and scroll)
-        let screen_y = if pos.line >= view.scroll_offset {
-            Position::HEADER + (pos.line - view.scroll_offset) as u16
+        let (row, first_col) = if let Some(width) = wrap_width {
+            let rows = wrap::rows_from(lines, folds, scroll_offset, width, visible_rows);
+            let idx = wrap::row_containing(&rows, pos)?;
+            (idx, rows[idx].start)
         } else {
-            Position::HEADER
+            if pos.column < col_offset {
+                return None;
+            }
+            let on_screen = folds.visible_lines(scroll_offset, lines.len());
+            (on_screen.take(visible_rows).position(|l| l == pos.line)?, col_offset)
         };
 
-        let screen_x = (pos.column as u16) + Position::MARGIN;
-        (screen_x, screen_y)
-    }
-
-    pub fn get_current_text_pos(view: &View, caret: &Caret) -> TextPosition {
-        let pos = caret.get_position();
-        let buffer_line_idx =
-            (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-        let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
-
-        TextPosition {
-            line: buffer_line_idx,
-            column: char_pos,
+        // Wide characters take two cells, so measure the text between the row start and the cursor
+        let x = visual_width(&grapheme_slice(line, first_col, pos.column));
+        let max_x = (pane.x + pane.width).saturating_sub(1) as usize;
+        let x = (pane.x + Position::MARGIN) as usize + x;
+        if x > max_x {
+            return None;
         }
+
+        Some(Position {
+            x: x as u16,
+            y: pane.y + row as u16,
+        })
     }
 }
 
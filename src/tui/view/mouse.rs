@@ -1,11 +1,11 @@
 // mouse module responsible for handling mouse events
 use super::{
-    helpers::{screen_to_text_pos, text_to_screen_pos},
+    helpers::{screen_to_text_pos, visible_rows},
     View,
 };
 use crate::core::graphemes::*;
 use crate::core::selection::{Selection, TextPosition};
-use crate::tui::caret::{Caret, Position};
+use crate::tui::caret::Caret;
 use crate::tui::terminal::Terminal;
 use std::io::Error;
 
@@ -18,13 +18,12 @@ pub fn handle_down(
     let pos = screen_to_text_pos(view, screen_x, screen_y)?;
 
     // Start new selection
-    view.selection = Some(Selection::new(pos));
+    view.editor.selection = Some(Selection::new(pos));
     view.is_dragging = true;
 
-    let (sx, sy) = text_to_screen_pos(view, pos);
-    caret.move_to(Position { x: sx, y: sy })?;
+    view.set_cursor(caret, pos)?;
 
-    view.render(caret)?;
+    view.render()?;
     Ok(())
 }
 
@@ -40,14 +39,13 @@ pub fn handle_drag(
 
     let pos = screen_to_text_pos(view, screen_x, screen_y)?;
 
-    if let Some(ref mut selection) = view.selection {
+    if let Some(ref mut selection) = view.editor.selection {
         selection.update_cursor(pos);
     }
 
-    let (sx, sy) = text_to_screen_pos(view, pos);
-    caret.move_to(Position { x: sx, y: sy })?;
+    view.set_cursor(caret, pos)?;
 
-    view.render(caret)?;
+    view.render()?;
     Ok(())
 }
 
@@ -55,18 +53,18 @@ pub fn handle_up(
     view: &mut View,
     _screen_x: u16,
     _screen_y: u16,
-    caret: &mut Caret,
+    _caret: &mut Caret,
 ) -> Result<(), Error> {
     view.is_dragging = false;
 
     // If selection is empty (just a click), clear it
-    if let Some(ref selection) = view.selection {
+    if let Some(ref selection) = view.editor.selection {
         if !selection.is_active() {
-            view.selection = None;
+            view.editor.selection = None;
         }
     }
 
-    view.render(caret)?;
+    view.render()?;
     Ok(())
 }
 
@@ -78,8 +76,8 @@ pub fn handle_double_click(
 ) -> Result<(), Error> {
     let pos = screen_to_text_pos(view, screen_x, screen_y)?;
 
-    if let Some(line) = view.buffer.lines.get(pos.line) {
-        let (start, end) = find_word_boundaries(line, pos.column);
+    if let Some(line) = view.editor.buffer.lines.get(pos.line) {
+        let (start, end) = word_boundaries(line, pos.column);
 
         let start_pos = TextPosition {
             line: pos.line,
@@ -90,16 +88,15 @@ pub fn handle_double_click(
             column: end,
         };
 
-        view.selection = Some(Selection {
+        view.editor.selection = Some(Selection {
             anchor: start_pos,
             cursor: end_pos,
         });
 
-        let (sx, sy) = text_to_screen_pos(view, end_pos);
-        caret.move_to(Position { x: sx, y: sy })?;
+        view.set_cursor(caret, end_pos)?;
     }
 
-    view.render(caret)?;
+    view.render()?;
     Ok(())
 }
 
@@ -111,99 +108,55 @@ pub fn handle_triple_click(
 ) -> Result<(), Error> {
     let pos = screen_to_text_pos(view, screen_x, screen_y)?;
 
-    if let Some(line) = view.buffer.lines.get(pos.line) {
+    if let Some(line) = view.editor.buffer.lines.get(pos.line) {
         let start_pos = TextPosition {
             line: pos.line,
             column: 0,
         };
         let end_pos = TextPosition {
             line: pos.line,
-            column: line.len(),
+            column: grapheme_len(line),
         };
 
-        view.selection = Some(Selection {
+        view.editor.selection = Some(Selection {
             anchor: start_pos,
             cursor: end_pos,
         });
 
-        let (sx, sy) = text_to_screen_pos(view, end_pos);
-        caret.move_to(Position { x: sx, y: sy })?;
+        view.set_cursor(caret, end_pos)?;
     }
 
-    view.render(caret)?;
+    view.render()?;
     Ok(())
 }
 
-fn find_word_boundaries(line: &str, col: usize) -> (usize, usize) {
-    let grapheme_count = grapheme_len(line);
-
-    if col >= grapheme_count || grapheme_count == 0 {
-        return (col, col);
-    }
-
-    let is_word_char = |g: &str| g.chars().all(|c| c.is_alphanumeric() || c == '_');
-
-    let current = grapheme_at(line, col).unwrap_or("");
-    if !is_word_char(current) {
-        return (col, col + 1);
-    }
-
-    let mut start = col;
-    while start > 0 {
-        if let Some(g) = grapheme_at(line, start - 1) {
-            if is_word_char(g) {
-                start -= 1;
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-
-    let mut end = col;
-    while end < grapheme_count {
-        if let Some(g) = grapheme_at(line, end) {
-            if is_word_char(g) {
-                end += 1;
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-
-    (start, end)
-}
-
 // Scroll the view UP
 pub fn scroll_up(view: &mut View, caret: &mut Caret, lines: usize) -> Result<(), Error> {
     if view.scroll_offset == 0 {
         return Ok(());
     }
-    view.scroll_offset = view.scroll_offset.saturating_sub(lines);
+    let line_count = view.editor.buffer.lines.len();
+    view.scroll_offset = view.editor.folds.step(view.scroll_offset, lines, false, line_count);
     view.needs_redraw = true;
-    view.render_if_needed(caret, false)?;
-    // Keep caret clamped to the visible area
-    caret.clamp_to_bounds()?;
+    view.render_if_needed(false)?;
+    // The cursor stays put; the caret disappears if it scrolled out of view
+    view.sync_caret(caret)?;
     Terminal::execute()?;
     Ok(())
 }
 
 // Scroll the view DOWN
 pub fn scroll_down(view: &mut View, caret: &mut Caret, lines: usize) -> Result<(), Error> {
-    let size = Terminal::get_size()?;
-    let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
-    let max_scroll = view.buffer.lines.len().saturating_sub(visible_rows);
+    let max_scroll = view.editor.buffer.lines.len().saturating_sub(visible_rows(view)?);
 
     if view.scroll_offset >= max_scroll {
         return Ok(());
     }
-    view.scroll_offset = (view.scroll_offset + lines).min(max_scroll);
+    let line_count = view.editor.buffer.lines.len();
+    view.scroll_offset = view.editor.folds.step(view.scroll_offset, lines, true, line_count).min(max_scroll);
     view.needs_redraw = true;
-    view.render_if_needed(caret, false)?;
-    caret.clamp_to_bounds()?;
+    view.render_if_needed(false)?;
+    view.sync_caret(caret)?;
     Terminal::execute()?;
     Ok(())
 }
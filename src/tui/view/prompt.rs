@@ -0,0 +1,44 @@
+// prompt module - a line typed in the footer: a bookmark's name, a symbol's new name, a
+// shell command, a format to convert to
+use super::View;
+use crate::tui::terminal::Terminal;
+use crossterm::event::{Event, KeyCode, KeyEventKind, read};
+use std::io::Error;
+
+// Read what is typed after `message`; None when cancelled or left empty
+pub fn ask(view: &mut View, kind: super::PromptKind, message: String) -> Result<Option<String>, Error> {
+    view.show_prompt(kind, message);
+    view.render_if_needed(false)?;
+    Terminal::execute()?;
+
+    let mut input = String::new();
+
+    let answer = loop {
+        match read()? {
+            Event::Key(event) if event.kind == KeyEventKind::Press => match event.code {
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    view.append_prompt_char(c);
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                    view.backspace_prompt();
+                }
+                KeyCode::Enter => {
+                    let name = input.trim();
+                    break (!name.is_empty()).then(|| name.to_string());
+                }
+                KeyCode::Esc => break None,
+                _ => continue,
+            },
+            _ => continue,
+        }
+        view.render_if_needed(false)?;
+        Terminal::execute()?;
+    };
+
+    view.clear_prompt();
+    view.render_if_needed(false)?;
+    Terminal::execute()?;
+    Ok(answer)
+}
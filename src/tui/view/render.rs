@@ -1,178 +1,496 @@
 // render module responsible for all the render logic
+// Everything is drawn into an in-memory Frame; the view's backend puts it on screen.
 use super::View;
 use unicode_segmentation::UnicodeSegmentation; 
+use crate::core::brackets::BracketMatcher;
+use crate::core::editor::Editor;
+use crate::core::folding;
 use crate::core::graphemes::*;
+use crate::core::git::{self, GitFile, LineChange};
+use crate::core::lsp::{Diagnostic, Severity};
+use crate::core::panes::SplitDirection;
 use crate::core::selection::TextPosition;
+use crate::core::syntax::Token;
+use crate::core::wrap;
 use crate::tui::{
-    caret::{Caret, Position},
-    terminal::Terminal,
+    caret::Position,
+    frame::{Frame, Style},
     syntax::{SyntaxHighlighter, TokenTypeExt},
+    terminal::{Rect, Size},
 };
-use crossterm::{
-    cursor::MoveTo,
-    queue,
-    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
-};
-use std::io::{Error, stdout};
+use crossterm::style::Color;
+
+const SELECTION: Style = Style { fg: Some(Color::White), bg: Some(Color::DarkBlue), bold: false, italic: false, underline: false };
+const SECONDARY_CARET: Style = Style { fg: Some(Color::Black), bg: Some(Color::DarkYellow), bold: false, italic: false, underline: false };
+const BOOKMARK: Style = Style { fg: Some(Color::Black), bg: Some(Color::Cyan), bold: true, italic: false, underline: false };
+const BRACKET_MATCH: Style = Style { fg: Some(Color::Yellow), bg: Some(Color::DarkGrey), bold: true, italic: false, underline: false };
+const POPUP: Style = Style { fg: Some(Color::White), bg: Some(Color::DarkGrey), bold: false, italic: false, underline: false };
+const POPUP_SELECTED: Style = Style { fg: Some(Color::Black), bg: Some(Color::Cyan), bold: false, italic: false, underline: false };
+const FOOTER: Style = Style { fg: Some(Color::White), bg: Some(Color::Black), bold: false, italic: false, underline: false };
+
+pub fn render_frame(frame: &mut Frame, view: &View, is_dirty: bool) {
+    draw_header(frame);
+    if let Some(diff) = &view.diff {
+        super::diff::draw(frame, diff);
+        draw_footer(frame, view, is_dirty);
+        return;
+    }
+    // The pane without focus goes first, so the focused one's popups can lie over it
+    if let Some(split) = &view.split {
+        draw_divider(frame, split.direction);
+        if let Some(other) = Pane::other(view, frame.size()) {
+            draw_pane(frame, &other);
+        }
+    }
+    draw_pane(frame, &Pane::focused(view, frame.size()));
+    draw_completion(frame, view);
+    draw_popup(frame, view);
+    draw_footer(frame, view, is_dirty);
+}
 
-pub fn render_view(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
-    let current_pos = caret.get_position();
-    let size = Terminal::get_size()?;
+// One pane as it is drawn, borrowed from the view: its document with what is marked on it,
+// and its own carets and scroll position
+struct Pane<'a> {
+    editor: &'a Editor,
+    filetype: Option<&'a str>,
+    server_diagnostics: &'a [Diagnostic],
+    git: Option<&'a GitFile>,
+    // (first line shown, first column shown)
+    scroll: (usize, usize),
+    rect: Rect,
+    wrap_width: Option<usize>,
+    selections: Vec<(TextPosition, TextPosition)>,
+    // Primary first; the terminal's caret shows the focused pane's
+    carets: Vec<TextPosition>,
+    focused: bool,
+}
 
-    draw_header()?;
+impl<'a> Pane<'a> {
+    fn focused(view: &'a View, size: Size) -> Self {
+        let rect = super::helpers::pane_rect(size, view.split.as_ref());
+        Self {
+            editor: &view.editor,
+            filetype: view.filetype.as_deref(),
+            server_diagnostics: &view.server_diagnostics,
+            git: view.git(),
+            scroll: (view.scroll_offset, view.col_offset),
+            rect,
+            wrap_width: view.wrap_width_in(rect),
+            selections: view.editor.selection_ranges(),
+            carets: view.editor.caret_positions(),
+            focused: true,
+        }
+    }
 
-    let visible_rows = (size.height.saturating_sub(Position::HEADER + 1)) as usize;
+    // The pane without focus: the focused pane's document, or the other tab it shows
+    fn other(view: &'a View, size: Size) -> Option<Self> {
+        let split = view.split.as_ref()?;
+        let rect = super::helpers::other_pane_rect(size, split);
+        let (editor, filetype, server_diagnostics, git) = match &view.split_tab {
+            // Another tab's file has changes of its own, which aren't worked out
+            Some(other) => (&other.tab.editor, other.tab.filetype.as_deref(), other.diagnostics.as_slice(), None),
+            None => (&view.editor, view.filetype.as_deref(), view.server_diagnostics.as_slice(), view.git()),
+        };
+        let scroll = (split.other.scroll.0.min(editor.buffer.lines.len().saturating_sub(1)), split.other.scroll.1);
+        Some(Self {
+            editor,
+            filetype,
+            server_diagnostics,
+            git,
+            scroll,
+            rect,
+            wrap_width: view.wrap_width_in(rect),
+            selections: split.other.selection_ranges(editor),
+            carets: split.other.caret_positions(editor),
+            focused: false,
+        })
+    }
 
-    let last_non_empty_line = view
-        .buffer
-        .lines
-        .iter()
-        .rposition(|line| !line.is_empty())
-        .unwrap_or(0);
+    // The language server's findings and a failed JSON/TOML/YAML validation
+    fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.server_diagnostics.iter().chain(&self.editor.data_error)
+    }
 
-    let selection_range = view
-        .selection
-        .as_ref()
-        .filter(|s| s.is_active())
-        .map(|s| s.get_range());
+    // Screen cell showing a document position, None when it is scrolled out of view
+    fn screen_pos(&self, pos: TextPosition) -> Option<Position> {
+        super::helpers::screen_pos(self.editor, self.scroll, self.wrap_width, self.rect, pos)
+    }
+}
 
-    // Create syntax highlighter based on current file type
-    let highlighter = SyntaxHighlighter::new(view.filetype.clone());
+// A pane's text rows and margin, drawn on their own so nothing spills into the other pane,
+// then what is marked over them. The pane without focus shows its cursor as a marked cell.
+fn draw_pane(frame: &mut Frame, pane: &Pane) {
+    let rect = pane.rect;
+    let mut body = Frame::new(Size { width: rect.width, height: rect.height });
+    let visible_rows = rect.height as usize;
 
-    for row in 0..visible_rows {
-        let buffer_line_idx = row + view.scroll_offset;
-        let terminal_row = row as u16 + Position::HEADER;
+    let last_non_empty_line = pane.editor.last_content_line();
 
-        queue!(stdout(), MoveTo(0, terminal_row))?;
-        Terminal::clear_rest_of_line()?;
+    // Create syntax highlighter based on current file type
+    let highlighter = SyntaxHighlighter::new(pane.filetype.map(String::from));
+    let matcher = BracketMatcher::new(pane.filetype.map(String::from));
 
-        if buffer_line_idx <= last_non_empty_line {
-            draw_margin_line(terminal_row, buffer_line_idx)?;
+    if let Some(wrap_width) = pane.wrap_width {
+        render_wrapped_rows(&mut body, pane, &highlighter, &matcher, visible_rows, wrap_width);
+    } else {
+        // Folded lines take no rows
+        let lines = &pane.editor.buffer.lines;
+        let on_screen = pane.editor.folds.visible_lines(pane.scroll.0, lines.len());
+        for (row, buffer_line_idx) in on_screen.take(visible_rows).enumerate() {
+            let terminal_row = row as u16;
+
+            if buffer_line_idx <= last_non_empty_line {
+                let marker = fold_marker(pane, &matcher, buffer_line_idx);
+                draw_margin_line(&mut body, pane, terminal_row, buffer_line_idx, marker);
+            }
+
+            if let Some(line) = lines.get(buffer_line_idx) {
+                let max_width = (rect.width.saturating_sub(Position::MARGIN)) as usize;
+                
+                // Highlight the whole line so strings/comments keep their colour,
+                // then keep only the horizontally visible part
+                let tokens = highlighter.highlight_line(line);
+                let visible_tokens = clip_tokens(&tokens, pane.scroll.1, max_width);
+
+                render_line_with_selection_and_syntax(
+                    &mut body,
+                    terminal_row,
+                    &visible_tokens, 
+                    buffer_line_idx, 
+                    pane.scroll.1,
+                    &pane.selections,
+                );
+
+                let text_end = visible_tokens.iter().map(|t| visual_width(&t.text)).sum::<usize>();
+                draw_fold_hint(&mut body, pane, terminal_row, Position::MARGIN + text_end as u16, buffer_line_idx);
+            }
         }
+    }
+    frame.blit(&body, rect.x, rect.y);
 
-        if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
-            let max_width = (size.width.saturating_sub(Position::MARGIN)) as usize;
-            
-            // Truncate by visual width, not grapheme count
-            let mut truncated = String::new();
-            let mut current_width = 0;
-            
-            for grapheme in line.graphemes(true) {
-                let g_width = visual_width(grapheme);
-                if current_width + g_width > max_width {
-                    break;
+    draw_diagnostics(frame, pane);
+    if pane.filetype == Some(git::COMMIT_MESSAGE) {
+        draw_message_ruler(frame, pane);
+    }
+    if pane.focused {
+        draw_bracket_match(frame, pane, &matcher);
+    }
+    draw_secondary_carets(frame, pane);
+}
+
+// The line between the two panes of a split screen
+fn draw_divider(frame: &mut Frame, direction: SplitDirection) {
+    let full = super::helpers::pane_rect(frame.size(), None);
+    let (_, divider, _) = super::helpers::halves(full, direction);
+    let style = Style::default().fg(Color::DarkGrey);
+    for y in divider.y..divider.y + divider.height {
+        let line = match direction {
+            SplitDirection::Vertical => "│".to_string(),
+            SplitDirection::Horizontal => "─".repeat(divider.width as usize),
+        };
+        frame.print(divider.x, y, &line, style);
+    }
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+        Severity::Information => Color::Blue,
+        Severity::Hint => Color::DarkGrey,
+    }
+}
+
+// Underline what the language server reported, in the colour of its severity. A range at
+// the end of a line (a missing semicolon) marks the cell after the last character.
+fn draw_diagnostics(frame: &mut Frame, pane: &Pane) {
+    let lines = &pane.editor.buffer.lines;
+    for diagnostic in pane.diagnostics() {
+        let last = diagnostic.end.line.min(pane.editor.last_content_line());
+        for line in diagnostic.start.line..=last {
+            let Some((start, end)) = diagnostic.columns_on(line) else {
+                continue;
+            };
+            let len = lines.get(line).map_or(0, |l| grapheme_len(l));
+            let start = start.min(len);
+            let end = end.min(len).max(start + 1);
+            for column in start..end {
+                let Some(pos) = pane.screen_pos(TextPosition { line, column }) else {
+                    continue;
+                };
+                if let Some(cell) = frame.cell(pos.x, pos.y) {
+                    let style = cell.style.underline().fg(severity_color(diagnostic.severity));
+                    frame.set_style(pos.x, pos.y, style);
                 }
-                truncated.push_str(grapheme);
-                current_width += g_width;
             }
+        }
+    }
+}
+
+// Commit messages: a ruler at the column each line should end by (50 for the subject, 72
+// below), and red text past it
+fn draw_message_ruler(frame: &mut Frame, pane: &Pane) {
+    let lines = &pane.editor.buffer.lines;
+    let on_screen = pane.editor.folds.visible_lines(pane.scroll.0, lines.len());
+    for line in on_screen.take(pane.rect.height as usize) {
+        let Some(width) = git::message_width(line, &lines[line]) else {
+            continue;
+        };
+        let len = grapheme_len(&lines[line]);
+        for column in width..len {
+            if let Some(pos) = pane.screen_pos(TextPosition { line, column }) {
+                let style = frame.cell(pos.x, pos.y).map_or_else(Style::default, |cell| cell.style);
+                frame.set_style(pos.x, pos.y, style.fg(Color::Red));
+            }
+        }
+        if len < width {
+            let Some(end) = pane.screen_pos(TextPosition { line, column: len }) else {
+                continue;
+            };
+            let x = end.x as usize + (width - len);
+            if x < (pane.rect.x + pane.rect.width) as usize {
+                frame.set_style(x as u16, end.y, Style { bg: Some(Color::DarkGrey), ..Style::default() });
+            }
+        }
+    }
+}
 
-            render_line_with_selection_and_syntax(
-                &truncated, 
-                buffer_line_idx, 
-                selection_range, 
-                &highlighter
-            )?;
+// Mark the bracket next to the cursor and its partner
+fn draw_bracket_match(frame: &mut Frame, pane: &Pane, matcher: &BracketMatcher) {
+    if let Some((here, partner)) = matcher.matching(&pane.editor.buffer.lines, pane.editor.cursor) {
+        for pos in [here, partner] {
+            if let Some(pos) = pane.screen_pos(pos) {
+                frame.set_style(pos.x, pos.y, BRACKET_MATCH);
+            }
         }
     }
+}
 
-    draw_footer(view, caret, is_dirty)?;
+// The terminal has one real caret (the focused pane's primary cursor); extra carets are
+// highlighted cells
+fn draw_secondary_carets(frame: &mut Frame, pane: &Pane) {
+    for &caret in pane.carets.iter().skip(usize::from(pane.focused)) {
+        if let Some(pos) = pane.screen_pos(caret) {
+            frame.set_style(pos.x, pos.y, SECONDARY_CARET);
+        }
+    }
+}
 
-    queue!(stdout(), MoveTo(current_pos.x, current_pos.y))?;
-    Ok(())
+// Completion popup under the word being typed, or above it when there is no room below
+fn draw_completion(frame: &mut Frame, view: &View) {
+    let Some(completion) = &view.completion else {
+        return;
+    };
+    let Ok(Some(anchor)) = super::helpers::text_to_screen_pos(view, completion.start) else {
+        return;
+    };
+    let size = frame.size();
+    let footer_row = size.height.saturating_sub(1);
+    let rows = (completion.items.len() as u16).min(footer_row.saturating_sub(Position::HEADER + 1));
+    let top = if anchor.y + 1 + rows <= footer_row {
+        anchor.y + 1
+    } else {
+        anchor.y.saturating_sub(rows).max(Position::HEADER)
+    };
+    let width = completion.items.iter().map(|item| visual_width(item)).max().unwrap_or(0) + 2;
+    let x = anchor.x.saturating_sub(1).min(size.width.saturating_sub(width as u16));
+
+    for (idx, item) in completion.items.iter().take(rows as usize).enumerate() {
+        let style = if idx == completion.selected { POPUP_SELECTED } else { POPUP };
+        let padding = width - 1 - visual_width(item);
+        frame.print(x, top + idx as u16, &format!(" {item}{}", " ".repeat(padding)), style);
+    }
 }
 
-fn draw_header() -> Result<(), Error> {
-    let size = Terminal::get_size()?;
-    queue!(
-        stdout(),
-        MoveTo(0, 0),
-        SetForegroundColor(Color::Yellow),
-        MoveTo(size.width / 2, 0),
-        Print(" Quick Notepad ".to_string()),
-        ResetColor
-    )?;
-    Terminal::clear_rest_of_line()?;
-    Ok(())
+// Hover text under the cursor (above it when there is no room below), or the diagnostics
+// list across the top of the text area, scrolled to keep the highlighted entry in view
+fn draw_popup(frame: &mut Frame, view: &View) {
+    let Some(popup) = &view.popup else {
+        return;
+    };
+    let size = frame.size();
+    let footer_row = size.height.saturating_sub(1);
+    let room = footer_row.saturating_sub(Position::HEADER);
+    let anchor = popup.anchor.and_then(|pos| super::helpers::text_to_screen_pos(view, pos).ok().flatten());
+    let (x, top, rows) = match anchor {
+        Some(anchor) => {
+            let rows = (popup.lines.len() as u16).min(room.saturating_sub(1));
+            let top = if anchor.y + 1 + rows <= footer_row {
+                anchor.y + 1
+            } else {
+                anchor.y.saturating_sub(rows).max(Position::HEADER)
+            };
+            (anchor.x.saturating_sub(1), top, rows)
+        }
+        None => {
+            let pane = super::helpers::pane_rect(size, view.split.as_ref());
+            (pane.x + Position::MARGIN, pane.y, (popup.lines.len() as u16).min(pane.height))
+        }
+    };
+    let width = popup.lines.iter().map(|line| visual_width(line)).max().unwrap_or(0) + 2;
+    let x = x.min(size.width.saturating_sub(width as u16));
+    let first = popup.selected.map_or(0, |selected| (selected + 1).saturating_sub(rows as usize));
+
+    for (row, (idx, line)) in popup.lines.iter().enumerate().skip(first).take(rows as usize).enumerate() {
+        let style = if popup.selected == Some(idx) { POPUP_SELECTED } else { POPUP };
+        let padding = (width - 1).saturating_sub(visual_width(line));
+        frame.print(x, top + row as u16, &format!(" {line}{}", " ".repeat(padding)), style);
+    }
 }
 
-fn draw_margin_line(row: u16, buffer_line_idx: usize) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        MoveTo(0, row),
-        SetForegroundColor(Color::Yellow),
-        Print(format!("{:>3} ", buffer_line_idx + 1)),
-        ResetColor
-    )?;
-    Ok(())
+fn draw_header(frame: &mut Frame) {
+    let width = frame.size().width;
+    frame.print(width / 2, 0, " Quick Notepad ", Style::default().fg(Color::Yellow));
 }
 
-pub fn draw_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
-    let size = Terminal::get_size()?;
-    let footer_row = size.height - 1;
+// Soft wrap: each logical line may span several screen rows
+fn render_wrapped_rows(
+    frame: &mut Frame,
+    pane: &Pane,
+    highlighter: &SyntaxHighlighter,
+    matcher: &BracketMatcher,
+    visible_rows: usize,
+    wrap_width: usize,
+) {
+    let last_non_empty_line = pane.editor.last_content_line();
+    let rows = wrap::rows_from(
+        &pane.editor.buffer.lines,
+        &pane.editor.folds,
+        pane.scroll.0,
+        wrap_width,
+        visible_rows,
+    );
+
+    // Highlight each logical line once, however many rows it spans
+    let mut line_tokens: Option<(usize, Vec<Token>)> = None;
+
+    for (row, visual_row) in rows.iter().enumerate().take(visible_rows) {
+        let terminal_row = row as u16;
+
+        if visual_row.start > 0 {
+            draw_wrap_indicator(frame, terminal_row);
+        } else if visual_row.line <= last_non_empty_line {
+            let marker = fold_marker(pane, matcher, visual_row.line);
+            draw_margin_line(frame, pane, terminal_row, visual_row.line, marker);
+        }
+
+        if line_tokens.as_ref().map(|(idx, _)| *idx) != Some(visual_row.line) {
+            let line = &pane.editor.buffer.lines[visual_row.line];
+            line_tokens = Some((visual_row.line, highlighter.highlight_line(line)));
+        }
+        if let Some((_, tokens)) = &line_tokens {
+            let visible_tokens = clip_tokens(tokens, visual_row.start, wrap_width);
+            render_line_with_selection_and_syntax(
+                frame,
+                terminal_row,
+                &visible_tokens,
+                visual_row.line,
+                visual_row.start,
+                &pane.selections,
+            );
+            if visual_row.ends_line {
+                let text_end = visible_tokens.iter().map(|t| visual_width(&t.text)).sum::<usize>();
+                draw_fold_hint(frame, pane, terminal_row, Position::MARGIN + text_end as u16, visual_row.line);
+            }
+        }
+    }
+}
 
-    // Clear the footer line and set background
-    queue!(
-        stdout(),
-        MoveTo(0, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
-    Terminal::clear_rest_of_line()?;
+// Continuation rows of a wrapped line get an arrow instead of a line number
+fn draw_wrap_indicator(frame: &mut Frame, row: u16) {
+    frame.print(0, row, "  ↪ ", Style::default().fg(Color::DarkGrey));
+}
+
+// Line number (highlighted on lines with diagnostics, by the worst one, or bookmarks), then a
+// fold marker in the margin's last column
+fn draw_margin_line(frame: &mut Frame, pane: &Pane, row: u16, buffer_line_idx: usize, marker: char) {
+    let severity = pane
+        .diagnostics()
+        .filter(|d| d.start.line == buffer_line_idx)
+        .map(|d| d.severity)
+        .min();
+    let style = match (severity, pane.editor.bookmarks.at(buffer_line_idx)) {
+        (Some(severity), _) => Style { fg: Some(Color::Black), bg: Some(severity_color(severity)), ..BOOKMARK },
+        (None, Some(_)) => BOOKMARK,
+        (None, None) => Style::default().fg(Color::Yellow),
+    };
+    let x = frame.print(0, row, &format!("{:>3}", buffer_line_idx + 1), style);
+    // Git changes tint the fold marker's cell: added, modified, or deleted just above
+    let change = pane.git.and_then(|git| git.change_at(buffer_line_idx)).map(|change| match change {
+        LineChange::Added => Color::DarkGreen,
+        LineChange::Modified => Color::DarkYellow,
+        LineChange::Deleted => Color::DarkRed,
+    });
+    frame.print(x, row, &marker.to_string(), Style { bg: change, ..Style::default().fg(Color::DarkGrey) });
+}
+
+// "⋯ N lines" after the header of a folded region
+fn draw_fold_hint(frame: &mut Frame, pane: &Pane, row: u16, x: u16, line: usize) {
+    let hidden = pane.editor.folds.hidden_after(line);
+    if hidden > 0 {
+        frame.print(x, row, &format!(" ⋯ {} lines", hidden), Style::default().fg(Color::DarkGrey));
+    }
+}
+
+// ▸ on a folded region's header, ▾ where a region could be folded
+fn fold_marker(pane: &Pane, matcher: &BracketMatcher, line: usize) -> char {
+    if pane.editor.folds.is_folded(line) {
+        '▸'
+    } else if folding::is_foldable(&pane.editor.buffer.lines, line, matcher) {
+        '▾'
+    } else {
+        ' '
+    }
+}
 
-    // Move back to start of footer
-    queue!(stdout(), MoveTo(0, footer_row))?;
+fn draw_footer(frame: &mut Frame, view: &View, is_dirty: bool) {
+    let footer_row = frame.size().height.saturating_sub(1);
+
+    // Footer line has a black background
+    frame.fill_row(footer_row, FOOTER);
 
     // If shortcuts are toggled, show them. If a prompt is active, render the prompt/footer.
     if view.show_shortcuts {
-        draw_shortcuts_footer()?;
+        draw_shortcuts_footer(frame, footer_row);
     } else if view.prompt.is_some() {
-        draw_prompt_footer(view, caret)?;
+        draw_prompt_footer(frame, footer_row, view);
+    } else if let Some(diff) = &view.diff {
+        draw_diff_footer(frame, footer_row, diff);
     } else {
-        draw_info_footer(view, caret, is_dirty)?;
+        draw_info_footer(frame, footer_row, view, is_dirty);
     }
-
-    queue!(stdout(), ResetColor)?;
-    Ok(())
 }
 
-fn draw_info_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Error> {
-    let current_pos = caret.get_position();
-
-    let size = Terminal::get_size()?;
-    let footer_row = size.height - 1;
+fn draw_info_footer(frame: &mut Frame, footer_row: u16, view: &View, is_dirty: bool) {
+    let width = frame.size().width;
 
     // Left side: Filename or [No Name] and modified tag
-    queue!(stdout(), MoveTo(1, footer_row))?;
     let filename_display = view.filename.as_deref().unwrap_or("[No Name]");
     let modified_tag = if is_dirty { "*" } else { "" };
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(if is_dirty { Color::Red } else { Color::Yellow }),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {}{} ", filename_display, modified_tag)),
-        SetAttribute(Attribute::Reset),
-    )?;
+    let x = frame.print(
+        1,
+        footer_row,
+        &format!(" {}{} ", filename_display, modified_tag),
+        FOOTER.bold().fg(if is_dirty { Color::Red } else { Color::Yellow }),
+    );
     
     // filetype or [unknown file type]
     let filetype_display = view.filetype.as_deref().unwrap_or("[unknown file type]");
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::Yellow),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {} ", filetype_display)),
-        SetAttribute(Attribute::Reset),
-    )?;
+    let x = frame.print(
+        x,
+        footer_row,
+        &format!(" {} ", filetype_display),
+        FOOTER.bold().fg(Color::Yellow),
+    );
+    let x = match view.git() {
+        Some(git) => frame.print(x, footer_row, &format!(" ⎇ {} ", git.branch), FOOTER.fg(Color::Magenta)),
+        None => x,
+    };
 
     // Calculate stats - find last non-empty line for accurate count
-    let total_lines = view
-        .buffer
-        .lines
-        .iter()
-        .rposition(|line| !line.is_empty())
-        .map(|idx| idx + 1)
-        .unwrap_or(1);
+    let total_lines = view.editor.last_content_line() + 1;
     let total_chars: usize = view
+        .editor
         .buffer
         .lines
         .iter()
@@ -180,66 +498,74 @@ fn draw_info_footer(view: &View, caret: &Caret, is_dirty: bool) -> Result<(), Er
         .map(|line| line.len())
         .sum();
 
-    // Current position (adjust for margin)
-    let line_num = current_pos.x.saturating_sub(Position::HEADER) + 1 + view.scroll_offset as u16;
-    let col_num = current_pos.y.saturating_sub(Position::MARGIN - 1);
+    // Current position in the document
+    let line_num = view.editor.cursor.line + 1;
+    let col_num = view.editor.cursor.column + 1;
 
     // Middle-left: Stats
-    let stats = format!(" Ln {}, Col {} ", line_num, col_num);
-    queue!(stdout(), SetForegroundColor(Color::White), Print(stats),)?;
+    let stats = match view.editor.secondary.len() {
+        0 => format!(" Ln {}, Col {} ", line_num, col_num),
+        extra => format!(" Ln {}, Col {} ({} cursors) ", line_num, col_num, extra + 1),
+    };
+    let x = frame.print(x, footer_row, &stats, FOOTER);
+    if let Some(mark) = view.editor.bookmarks.at(view.editor.cursor.line) {
+        frame.print(x, footer_row, &format!("★{} ", mark.name), FOOTER.fg(Color::Cyan));
+    }
 
     // Middle: Lines and Characters count
     let counts = format!("Lines: {} | Chars: {} ", total_lines, total_chars);
     let counts_width = counts.len() as u16;
-    let middle_pos = (size.width / 2).saturating_sub(counts_width / 2);
-    queue!(
-        stdout(),
-        MoveTo(middle_pos, footer_row),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::White),
-        Print(counts),
-    )?;
+    let middle_pos = (width / 2).saturating_sub(counts_width / 2);
+    frame.print(middle_pos, footer_row, &counts, FOOTER);
     
     let credits = "© Filip Domanski";
     let credits_width = credits.len() as u16;
     let credits_pos = middle_pos + credits_width;
-    queue!(
-        stdout(),
-        MoveTo(credits_pos, footer_row),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(Color::Yellow),
-        SetAttribute(Attribute::Bold),
-        Print(format!(" {} ", credits)),
-        SetAttribute(Attribute::Reset),
-    )?;
+    frame.print(
+        credits_pos,
+        footer_row,
+        &format!(" {} ", credits),
+        FOOTER.bold().fg(Color::Yellow),
+    );
 
     // Right side: Tab hint - show "Ctrl+1-9 for tabs" or actual tab info if we have tab_manager
     let hint = " Ctrl+1-9 for tabs | Ctrl+g for shortcuts ";
     let hint_width = hint.len() as u16;
-    let hint_pos = size.width.saturating_sub(hint_width + 1);
-    queue!(
-        stdout(),
-        MoveTo(hint_pos, footer_row),
-        SetForegroundColor(Color::DarkYellow),
-        SetAttribute(Attribute::Italic),
-        Print(hint),
-        SetAttribute(Attribute::Reset),
-    )?;
-
-    Ok(())
+    let hint_pos = width.saturating_sub(hint_width + 1);
+    frame.print(hint_pos, footer_row, hint, FOOTER.italic().fg(Color::DarkYellow));
+
+    // Language server errors and warnings, just before the hint
+    let count = |severity| view.diagnostics().filter(|d| d.severity == severity).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    if errors + warnings > 0 {
+        let errors = format!(" ✖ {} ", errors);
+        let warnings = format!("⚠ {} ", warnings);
+        let x = hint_pos.saturating_sub(visual_width(&errors) as u16 + visual_width(&warnings) as u16);
+        let x = frame.print(x, footer_row, &errors, FOOTER.bold().fg(Color::Red));
+        frame.print(x, footer_row, &warnings, FOOTER.bold().fg(Color::Yellow));
+    }
 }
 
-fn draw_shortcuts_footer() -> Result<(), Error> {
-    use crate::core::shortcuts::Shortcuts;
+// The keys of the diff view, and which change is the current one
+fn draw_diff_footer(frame: &mut Frame, footer_row: u16, diff: &super::DiffView) {
+    let comparison = &diff.comparison;
+    let mut x = 1;
+    for (key, desc) in [("n/p", "next/previous change"), ("</>", "copy change left/right"), ("Esc", "close")] {
+        x = frame.print(x, footer_row, &format!(" {}", key), FOOTER.bold().fg(Color::DarkYellow));
+        x = frame.print(x, footer_row, &format!(" {} │", desc), FOOTER);
+    }
+    let status = match comparison.current {
+        Some(current) => format!(" Change {} of {} ", current + 1, comparison.hunks.len()),
+        None => " No differences ".to_string(),
+    };
+    let width = frame.size().width;
+    frame.print(width.saturating_sub(visual_width(&status) as u16 + 1), footer_row, &status, FOOTER.bold().fg(Color::Yellow));
+}
 
-    let size = Terminal::get_size()?;
-    let footer_row = size.height - 1;
+fn draw_shortcuts_footer(frame: &mut Frame, footer_row: u16) {
+    use crate::core::shortcuts::Shortcuts;
 
-    queue!(
-        stdout(),
-        MoveTo(1, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
+    let width = frame.size().width;
 
     // Get shortcuts from Shortcuts module
     let shortcuts = Shortcuts::get_ctrl_shortcuts();
@@ -248,266 +574,369 @@ fn draw_shortcuts_footer() -> Result<(), Error> {
     for (i, (key, desc)) in shortcuts.iter().enumerate() {
         // Check if we have space
         let entry_width = key.len() + desc.len() + 4;
-        if current_x + entry_width as u16 > size.width - 2 {
+        if current_x + entry_width as u16 > width.saturating_sub(2) {
             break;
         }
 
         // Draw key in bold yellow
-        queue!(
-            stdout(),
-            MoveTo(current_x, footer_row),
-            SetForegroundColor(Color::DarkYellow),
-            SetAttribute(Attribute::Bold),
-            Print(key),
-            SetAttribute(Attribute::Reset),
-        )?;
-        current_x += key.len() as u16;
+        current_x = frame.print(current_x, footer_row, key, FOOTER.bold().fg(Color::DarkYellow));
 
         // Draw description
-        queue!(
-            stdout(),
-            MoveTo(current_x, footer_row),
-            SetForegroundColor(Color::White),
-            Print(format!(" {} ", desc)),
-        )?;
+        frame.print(current_x, footer_row, &format!(" {} ", desc), FOOTER);
         current_x += desc.len() as u16 + 1;
 
         // Add separator except for last item
         if i < shortcuts.len() - 1 {
-            queue!(stdout(), SetForegroundColor(Color::DarkGrey), Print("│ "),)?;
+            frame.print(current_x, footer_row, "│ ", FOOTER.fg(Color::DarkGrey));
             current_x += 2;
         }
     }
-
-    Ok(())
 }
 
 // Render a prompt-style footer
-fn draw_prompt_footer(view: &View, _caret: &Caret) -> Result<(), Error> {
-    let size = Terminal::get_size()?;
-    let footer_row = size.height - 1;
-
-    // Clear and set background for the prompt area
-    queue!(
-        stdout(),
-        MoveTo(0, footer_row),
-        SetBackgroundColor(Color::Black),
-    )?;
-    Terminal::clear_rest_of_line()?;
-
-    // Move to content start
-    queue!(stdout(), MoveTo(1, footer_row))?;
+fn draw_prompt_footer(frame: &mut Frame, footer_row: u16, view: &View) {
+    let width = frame.size().width;
 
     if let Some(prompt) = &view.prompt {
         // Use local paths for enum to avoid needing extra imports
         match &prompt.kind {
             super::PromptKind::SaveAs => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::DarkYellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!("{}{}", prompt.message, prompt.input)),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+                frame.print(
+                    1,
+                    footer_row,
+                    &format!("{}{}", prompt.message, prompt.input),
+                    FOOTER.bold().fg(Color::DarkYellow),
+                );
+                draw_esc_hint(frame, width, footer_row);
             }
-            super::PromptKind::Search => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::DarkYellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!(" {} ", prompt.message)),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::White),
-                    Print(&prompt.input),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+            super::PromptKind::Search
+            | super::PromptKind::GoTo
+            | super::PromptKind::Bookmark
+            | super::PromptKind::Rename
+            | super::PromptKind::Command => {
+                let x = frame.print(
+                    1,
+                    footer_row,
+                    &format!(" {} ", prompt.message),
+                    FOOTER.bold().fg(Color::DarkYellow),
+                );
+                frame.print(x, footer_row, &prompt.input, FOOTER);
+                draw_esc_hint(frame, width, footer_row);
+            }
+            // A choice rather than free text: the formats to convert to
+            super::PromptKind::Format => {
+                let x = frame.print(1, footer_row, &format!(" {} ", prompt.message), FOOTER.bold().fg(Color::Cyan));
+                frame.print(x, footer_row, &prompt.input, FOOTER.bold());
+                draw_esc_hint(frame, width, footer_row);
             }
             super::PromptKind::SearchInfo => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::Green),
-                    SetAttribute(Attribute::Bold),
-                    Print(" 🔍 "),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::White),
-                    Print(&prompt.message),
-                )?;
-                draw_esc_hint(size.width, footer_row)?;
+                let x = frame.print(1, footer_row, " 🔍 ", FOOTER.bold().fg(Color::Green));
+                frame.print(x, footer_row, &prompt.message, FOOTER);
+                draw_esc_hint(frame, width, footer_row);
             }
             super::PromptKind::Error => {
-                queue!(
-                    stdout(),
-                    SetForegroundColor(Color::Red),
-                    SetAttribute(Attribute::Bold),
-                    Print(format!(" {} ", prompt.message)),
-                    SetAttribute(Attribute::Reset),
-                )?;
+                frame.print(
+                    1,
+                    footer_row,
+                    &format!(" {} ", prompt.message),
+                    FOOTER.bold().fg(Color::Red),
+                );
             }
         }
     }
-
-    Ok(())
 }
 
-fn render_line_with_selection_and_syntax(
-    line: &str, 
-    line_idx: usize, 
-    selection_range: Option<(TextPosition, TextPosition)>,
-    highlighter: &SyntaxHighlighter,
-) -> Result<(), Error> {
-    // Get syntax tokens for this line
-    let tokens = highlighter.highlight_line(line);
-    
-    if let Some((start, end)) = selection_range {
-        let in_selection = line_idx >= start.line && line_idx <= end.line;
-
-        if !in_selection {
-            // No selection on this line - just render with syntax highlighting
-            render_tokens(&tokens)?;
-            return Ok(());
+// Cut tokens down to the graphemes in [first_col, first_col + max_width) visual columns
+fn clip_tokens(tokens: &[Token], first_col: usize, max_width: usize) -> Vec<Token> {
+    let mut visible = Vec::new();
+    let mut grapheme_idx = 0;
+    let mut current_width = 0;
+
+    'tokens: for token in tokens {
+        let mut text = String::new();
+        for grapheme in token.text.graphemes(true) {
+            if grapheme_idx >= first_col {
+                // Truncate by visual width, not grapheme count
+                let g_width = visual_width(grapheme);
+                if current_width + g_width > max_width {
+                    if !text.is_empty() {
+                        visible.push(Token { text, token_type: token.token_type });
+                    }
+                    break 'tokens;
+                }
+                text.push_str(grapheme);
+                current_width += g_width;
+            }
+            grapheme_idx += 1;
+        }
+        if !text.is_empty() {
+            visible.push(Token { text, token_type: token.token_type });
         }
+    }
 
-        // Line has selection - need to handle both selection highlighting and syntax
-        let chars: Vec<char> = line.chars().collect();
-        let sel_start = if line_idx == start.line {
-            start.column
-        } else {
-            0
-        };
-        let sel_end = if line_idx == end.line {
-            end.column
-        } else {
-            chars.len()
-        };
+    visible
+}
 
-        render_tokens_with_selection(&tokens, sel_start, sel_end)?;
+fn render_line_with_selection_and_syntax(
+    frame: &mut Frame,
+    row: u16,
+    tokens: &[Token], 
+    line_idx: usize, 
+    col_offset: usize,
+    selections: &[(TextPosition, TextPosition)],
+) {
+    // Selected parts of this line (one per cursor). Tokens start at col_offset,
+    // so shift the selections into visible coordinates.
+    let ranges: Vec<(usize, usize)> = selections
+        .iter()
+        .filter(|(start, end)| line_idx >= start.line && line_idx <= end.line)
+        .map(|(start, end)| {
+            let sel_start = if line_idx == start.line {
+                start.column.saturating_sub(col_offset)
+            } else {
+                0
+            };
+            let sel_end = if line_idx == end.line {
+                end.column.saturating_sub(col_offset)
+            } else {
+                usize::MAX
+            };
+            (sel_start, sel_end)
+        })
+        .collect();
+
+    if ranges.is_empty() {
+        // No selection on this line - just render with syntax highlighting
+        render_tokens(frame, row, tokens);
     } else {
-        // No selection at all - just render with syntax highlighting
-        render_tokens(&tokens)?;
+        render_tokens_with_selection(frame, row, tokens, &ranges);
     }
-
-    Ok(())
 }
 
 // Helper: Render tokens with syntax highlighting (no selection)
-fn render_tokens(tokens: &[crate::core::syntax::Token]) -> Result<(), Error> {
+fn render_tokens(frame: &mut Frame, row: u16, tokens: &[Token]) {
+    let mut x = Position::MARGIN;
     for token in tokens {
-        print_text_colored(&token.text, token.token_type.color())?;
+        x = frame.print(x, row, &token.text, Style::default().fg(token.token_type.color()));
     }
-    Ok(())
 }
 
 // Helper: Render tokens with both syntax highlighting and selection
 fn render_tokens_with_selection(
-    tokens: &[crate::core::syntax::Token],
-    sel_start: usize,
-    sel_end: usize,
-) -> Result<(), Error> {
+    frame: &mut Frame,
+    row: u16,
+    tokens: &[Token],
+    ranges: &[(usize, usize)],
+) {
+    let mut x = Position::MARGIN;
     let mut char_pos = 0;
     
     for token in tokens {
-        let token_len = token.text.chars().count();
+        let token_len = grapheme_len(&token.text);
         let token_end = char_pos + token_len;
+        let syntax = Style::default().fg(token.token_type.color());
         
         // Check if this token overlaps with selection
-        if token_end <= sel_start || char_pos >= sel_end {
+        if ranges.iter().all(|&(sel_start, sel_end)| token_end <= sel_start || char_pos >= sel_end) {
             // Token is completely outside selection
-            print_text_colored(&token.text, token.token_type.color())?;
-        } else if char_pos >= sel_start && token_end <= sel_end {
+            x = frame.print(x, row, &token.text, syntax);
+        } else if ranges.iter().any(|&(sel_start, sel_end)| char_pos >= sel_start && token_end <= sel_end) {
             // Token is completely inside selection
-            print_text_selected(&token.text)?;
+            x = frame.print(x, row, &token.text, SELECTION);
         } else {
-            // Token is partially selected - need to split it
-            render_token_partial_selection(token, char_pos, sel_start, sel_end)?;
+            // Token is partially selected - split by grapheme so selection columns
+            // line up with cursor columns
+            for (i, grapheme) in token.text.graphemes(true).enumerate() {
+                let abs_pos = char_pos + i;
+                let selected = ranges.iter().any(|&(sel_start, sel_end)| abs_pos >= sel_start && abs_pos < sel_end);
+                let style = if selected { SELECTION } else { syntax };
+                x = frame.print(x, row, grapheme, style);
+            }
         }
         
         char_pos = token_end;
     }
-    
-    Ok(())
 }
 
-// Helper: Render a token that's partially selected
-fn render_token_partial_selection(
-    token: &crate::core::syntax::Token,
-    char_pos: usize,
-    sel_start: usize,
-    sel_end: usize,
-) -> Result<(), Error> {
-    let token_chars: Vec<char> = token.text.chars().collect();
-    
-    for (i, ch) in token_chars.iter().enumerate() {
-        let abs_pos = char_pos + i;
-        if abs_pos >= sel_start && abs_pos < sel_end {
-            // Character is selected
-            print_char_selected(*ch)?;
-        } else {
-            // Character is not selected - use syntax color
-            print_char_colored(*ch, token.token_type.color())?;
-        }
-    }
-    
-    Ok(())
+// Helper function to draw the Esc hint on the right side of the footer
+fn draw_esc_hint(frame: &mut Frame, screen_width: u16, footer_row: u16) {
+    let hint = " Press Esc to cancel ";
+    let hint_width = hint.len() as u16;
+    let hint_pos = screen_width.saturating_sub(hint_width + 1);
+    frame.print(hint_pos, footer_row, hint, FOOTER.italic().fg(Color::DarkYellow));
 }
 
-// Helper: Print text with a specific color
-fn print_text_colored(text: &str, color: Color) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetForegroundColor(color),
-        Print(text),
-        ResetColor
-    )?;
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{buffer::Buffer, editor::Editor};
+    use crate::tui::{backend::TestBackend, caret::Caret};
+
+    fn render(text: &str, width: u16, height: u16) -> (View, TestBackend, Caret) {
+        let backend = TestBackend::new(width, height);
+        let mut view = View::with_backend(
+            Editor::new(Buffer::from_string(text.to_string())),
+            Box::new(backend.clone()),
+        );
+        let mut caret = Caret::new();
+        view.render().unwrap();
+        view.sync_caret(&mut caret).unwrap();
+        (view, backend, caret)
+    }
 
-// Helper: Print text with selection highlighting
-fn print_text_selected(text: &str) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::DarkBlue),
-        SetForegroundColor(Color::White),
-        Print(text),
-        ResetColor
-    )?;
-    Ok(())
-}
+    // Text rows between header and footer
+    fn text_rows(backend: &TestBackend) -> String {
+        let snapshot = backend.snapshot();
+        let rows: Vec<&str> = snapshot.lines().collect();
+        rows[1..rows.len() - 1].join("\n")
+    }
 
-// Helper: Print a single character with a specific color
-fn print_char_colored(ch: char, color: Color) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetForegroundColor(color),
-        Print(ch),
-        ResetColor
-    )?;
-    Ok(())
-}
+    // '^' under every selected cell of a row
+    fn selected_cells(backend: &TestBackend, y: u16) -> String {
+        let frame = backend.frame();
+        let marks: String = (0..frame.size().width)
+            .map(|x| if frame.cell(x, y).unwrap().style == SELECTION { '^' } else { ' ' })
+            .collect();
+        marks.trim_end().to_string()
+    }
 
-// Helper: Print a single character with selection highlighting
-fn print_char_selected(ch: char) -> Result<(), Error> {
-    queue!(
-        stdout(),
-        SetBackgroundColor(Color::DarkBlue),
-        SetForegroundColor(Color::White),
-        Print(ch),
-        ResetColor
-    )?;
-    Ok(())
-}
+    #[test]
+    fn test_selection_snapshot() {
+        let (mut view, backend, mut caret) = render("fn main() {\n    let x = 1;\n}", 30, 6);
+        view.editor.select(TextPosition { line: 0, column: 3 }, TextPosition { line: 1, column: 8 });
+        view.needs_redraw = true;
+        view.set_cursor(&mut caret, view.editor.cursor).unwrap();
+
+        // The opening line carries a fold marker in the margin
+        assert_eq!(text_rows(&backend), "  1▾fn main() {\n  2     let x = 1;\n  3 }\n");
+        assert_eq!(selected_cells(&backend, 1), "       ^^^^^^^^");
+        assert_eq!(selected_cells(&backend, 2), "    ^^^^^^^^");
+        assert_eq!(backend.caret(), Some(Position { x: 12, y: 2 }));
+    }
 
-// Helper function to draw the Esc hint on the right side of the footer
-fn draw_esc_hint(screen_width: u16, footer_row: u16) -> Result<(), Error> {
-    let hint = " Press Esc to cancel ";
-    let hint_width = hint.len() as u16;
-    let hint_pos = screen_width.saturating_sub(hint_width + 1);
-    queue!(
-        stdout(),
-        MoveTo(hint_pos, footer_row),
-        SetForegroundColor(Color::DarkYellow),
-        SetAttribute(Attribute::Italic),
-        Print(hint),
-        SetAttribute(Attribute::Reset),
-    )?;
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn test_search_highlights_current_match_and_reports_it() {
+        let (mut view, backend, mut caret) = render("one two\ntwo three two", 60, 6);
+        super::super::search::perform_search(&mut view, &mut caret, "TWO").unwrap();
+
+        assert_eq!(selected_cells(&backend, 1), "        ^^^");
+        assert_eq!(selected_cells(&backend, 2), "");
+        let footer = backend.snapshot().lines().last().unwrap().to_string();
+        assert_eq!(footer, "  🔍 Match 1 of 3 | ↑/↓ to navigate    Press Esc to cancel");
+
+        super::super::search::next_search_match(&mut view, &mut caret).unwrap();
+        assert_eq!(selected_cells(&backend, 1), "");
+        assert_eq!(selected_cells(&backend, 2), "    ^^^");
+    }
+
+    #[test]
+    fn test_diagnostics_are_underlined_marked_and_counted() {
+        use crate::core::lsp::Diagnostic;
+        let (mut view, backend, _) = render("let x = 1\nlet y = x;", 60, 6);
+        view.server_diagnostics = vec![Diagnostic {
+            start: TextPosition { line: 0, column: 9 },
+            end: TextPosition { line: 0, column: 9 },
+            severity: Severity::Error,
+            message: "expected `;`".to_string(),
+        }];
+        view.render().unwrap();
+
+        let frame = backend.frame();
+        let underlined: Vec<u16> = (0..60).filter(|&x| frame.cell(x, 1).unwrap().style.underline).collect();
+        // The empty range at the end of the line marks the cell after the text
+        assert_eq!(underlined, vec![13]);
+        assert_eq!(frame.cell(2, 1).unwrap().style.bg, Some(Color::Red));
+        assert_eq!(frame.cell(2, 2).unwrap().style.bg, None);
+        assert!(backend.snapshot().lines().last().unwrap().contains("✖ 1"));
+    }
+
+    #[test]
+    fn test_split_panes_show_the_buffer_at_their_own_positions() {
+        use crate::core::panes::{PaneView, Split, SplitDirection};
+        let (mut view, backend, mut caret) = render("one\ntwo\nthree", 24, 5);
+        let mut other = PaneView::new(0, &view.editor, (1, 0));
+        other.cursor = TextPosition { line: 2, column: 1 };
+        view.split = Some(Split::new(SplitDirection::Vertical, other));
+        view.needs_redraw = true;
+        view.set_cursor(&mut caret, TextPosition { line: 0, column: 2 }).unwrap();
+
+        // The left pane starts at line 2 and marks its cursor; the caret is in the right one
+        assert_eq!(text_rows(&backend), "  2 two    │  1 one\n  3 three  │  2 two\n           │  3 three");
+        assert_eq!(backend.frame().cell(5, 2).unwrap().style, SECONDARY_CARET);
+        assert_eq!(backend.caret(), Some(Position { x: 18, y: 1 }));
+
+        // Enter in the right pane moves the left one's view and cursor down with their lines
+        view.editor.insert_text("\n");
+        view.render().unwrap();
+        assert_eq!(text_rows(&backend), "  3 two    │  1 on\n  4 three  │  2 e\n           │  3 two");
+        assert_eq!(backend.frame().cell(5, 2).unwrap().style, SECONDARY_CARET);
+
+        // Another tab in the left pane; drawing it leaves the focused document's carets alone
+        let tab = crate::core::tabs::Tab::new(Buffer::from_string("other".to_string()), None, None, None);
+        view.split_tab = Some(super::super::OtherTab { tab, diagnostics: Vec::new() });
+        view.split.as_mut().unwrap().other.scroll = (0, 0);
+        view.render().unwrap();
+        assert!(text_rows(&backend).starts_with("  1 other  │  1 on\n"));
+        assert_eq!(view.editor.cursor, TextPosition { line: 1, column: 0 });
+    }
+
+    #[test]
+    fn test_diff_view_lines_up_both_sides() {
+        use crate::core::compare::{Comparison, Source};
+        let (mut view, backend, _) = render("", 30, 6);
+        let text = |text: &str| Source::Text { title: text.replace('\n', ","), text: text.to_string() };
+        let comparison = Comparison::new(text("a\nb\nc"), text("a\nc\nd"), &[]).unwrap();
+        view.diff = Some(super::super::DiffView::new(comparison));
+        view.render().unwrap();
+
+        // Titles, then the rows with a gap where a side has no line
+        assert_eq!(
+            text_rows(&backend),
+            " a,b,c        │ a,c,d\n  1 a         │  1 a\n  2▌b         │    ╱╱╱╱╱╱╱╱╱╱╱\n  3 c         │  2 c"
+        );
+        assert_eq!(backend.frame().cell(4, 3).unwrap().style.bg, Some(Color::DarkRed));
+    }
+
+    #[test]
+    fn test_git_changes_in_the_margin_and_branch_in_the_footer() {
+        use crate::core::git::GitFile;
+        let (mut view, backend, _) = render("one\nTWO\nnew\nfour", 100, 7);
+        view.git_files.insert(view.git_tab, Some(GitFile::new("main".to_string(), Some("one\ntwo\nthree\nfour\nfive\n"))));
+        view.render().unwrap();
+
+        let frame = backend.frame();
+        let tints: Vec<_> = (1..6).map(|y| frame.cell(3, y).unwrap().style.bg).collect();
+        assert_eq!(tints, vec![None, Some(Color::DarkYellow), Some(Color::DarkYellow), Some(Color::DarkRed), None]);
+        assert!(backend.snapshot().lines().last().unwrap().contains("⎇ main"));
+    }
+
+    #[test]
+    fn test_commit_message_ruler() {
+        let subject = "s".repeat(52);
+        let (mut view, backend, _) = render(&format!("{}\n\nbody\n# comment", subject), 100, 7);
+        view.filetype = Some(crate::core::git::COMMIT_MESSAGE.to_string());
+        view.render().unwrap();
+
+        // The subject turns red past column 50; the body has its ruler at 72, comments none
+        let frame = backend.frame();
+        let margin = Position::MARGIN;
+        assert_ne!(frame.cell(margin + 49, 1).unwrap().style.fg, Some(Color::Red));
+        assert_eq!(frame.cell(margin + 50, 1).unwrap().style.fg, Some(Color::Red));
+        assert_eq!(frame.cell(margin + 72, 3).unwrap().style.bg, Some(Color::DarkGrey));
+        assert_eq!(frame.cell(margin + 72, 4).unwrap().style.bg, None);
+    }
+
+    #[test]
+    fn test_wide_and_combining_characters_snapshot() {
+        let (mut view, backend, mut caret) = render("日本語 e\u{301}x\ncafé", 20, 5);
+        view.set_cursor(&mut caret, TextPosition { line: 0, column: 5 }).unwrap();
+
+        assert_eq!(text_rows(&backend), "  1 日本語 e\u{301}x\n  2 café\n");
+        let frame = backend.frame();
+        assert_eq!(frame.cell(4, 1).unwrap().symbol, "日");
+        assert!(frame.cell(5, 1).unwrap().is_continuation());
+        assert_eq!(frame.cell(11, 1).unwrap().symbol, "e\u{301}");
+        // Three wide characters, a space and one combined grapheme before the caret
+        assert_eq!(backend.caret(), Some(Position { x: 12, y: 1 }));
+    }
+}
@@ -1,14 +1,12 @@
 // search module for text search functionality
 use super::View;
-use crate::tui::{
-    caret::{Caret, Position},
-    terminal::Terminal,
-};
+use crate::tui::{caret::Caret, terminal::Terminal};
+use crate::core::graphemes::grapheme_len;
 use crate::core::selection::{Selection, TextPosition};
 use crossterm::event::{Event, KeyCode, KeyEventKind, read};
 use std::io::Error;
 
-// Stores all search match locations
+// Stores all search match locations (column and length in graphemes)
 #[derive(Clone, Debug)]
 pub struct SearchMatch {
     pub line: usize,
@@ -59,7 +57,7 @@ pub fn search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
         "Search:".to_string(),
     );
     view.needs_redraw = true;
-    view.render_if_needed(caret, false)?;
+    view.render_if_needed(false)?;
     Terminal::execute()?;
 
     let mut search_query = String::new();
@@ -72,13 +70,13 @@ pub fn search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
                     KeyCode::Char(c) => {
                         search_query.push(c);
                         view.append_prompt_char(c);
-                        view.render_if_needed(caret, false)?;
+                        view.render_if_needed(false)?;
                         Terminal::execute()?;
                     }
                     KeyCode::Backspace => {
                         search_query.pop();
                         view.backspace_prompt();
-                        view.render_if_needed(caret, false)?;
+                        view.render_if_needed(false)?;
                         Terminal::execute()?;
                     }
                     KeyCode::Enter => {
@@ -90,7 +88,7 @@ pub fn search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
                     }
                     KeyCode::Esc => {
                         view.clear_prompt();
-                        view.render_if_needed(caret, false)?;
+                        view.render_if_needed(false)?;
                         Terminal::execute()?;
                         break;
                     }
@@ -104,13 +102,13 @@ pub fn search(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
     Ok(())
 }
 
-fn perform_search(view: &mut View, caret: &mut Caret, query: &str) -> Result<(), Error> {
+pub(super) fn perform_search(view: &mut View, caret: &mut Caret, query: &str) -> Result<(), Error> {
     if query.is_empty() {
         return Ok(());
     }
 
     // Find all occurrences
-    let matches = find_all_occurrences(&view.buffer.lines, query);
+    let matches = find_all_occurrences(&view.editor.buffer.lines, query);
 
     if matches.is_empty() {
         // No match found - show error in prompt
@@ -118,22 +116,19 @@ fn perform_search(view: &mut View, caret: &mut Caret, query: &str) -> Result<(),
             super::PromptKind::Error,
             format!("No matches found for '{}'", query),
         );
-        view.render_if_needed(caret, false)?;
+        view.render_if_needed(false)?;
         Terminal::execute()?;
         
         std::thread::sleep(std::time::Duration::from_secs(2));
         view.clear_prompt();
-        view.render_if_needed(caret, false)?;
+        view.render_if_needed(false)?;
         Terminal::execute()?;
         return Ok(());
     }
 
     // Find the match closest to current cursor position
-    let current_pos = caret.get_position();
-    let current_line = (current_pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-    let current_col = (current_pos.x as usize).saturating_sub(Position::MARGIN as usize);
-    
-    let closest_idx = find_closest_match(&matches, current_line, current_col);
+    let current_pos = view.editor.cursor;
+    let closest_idx = find_closest_match(&matches, current_pos.line, current_pos.column);
 
     // Store search state in view
     let search_state = SearchState::new(query.to_string(), matches);
@@ -155,12 +150,14 @@ fn find_all_occurrences(lines: &[String], query: &str) -> Vec<SearchMatch> {
         let mut start = 0;
 
         while let Some(pos) = line_lower[start..].find(&query_lower) {
+            let byte_idx = start + pos;
             matches.push(SearchMatch {
                 line: line_idx,
-                column: start + pos,
-                length: query.len(),
+                column: grapheme_len(&line_lower[..byte_idx]),
+                length: grapheme_len(&query_lower),
             });
-            start += pos + 1; // Move past this match to find next
+            // Move past the first character of this match to find the next one
+            start = byte_idx + query_lower.chars().next().map_or(1, char::len_utf8);
         }
     }
 
@@ -197,8 +194,7 @@ fn find_closest_match(matches: &[SearchMatch], line: usize, col: usize) -> usize
 fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
     if let Some(search_state) = &view.search_state {
         if let Some(m) = search_state.current_match() {
-            let size = Terminal::get_size()?;
-            let visible_rows = size.height.saturating_sub(Position::HEADER + 1) as usize;
+            let visible_rows = super::helpers::visible_rows(view)?;
 
             // Adjust scroll to show the match
             if m.line < view.scroll_offset {
@@ -217,7 +213,7 @@ fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
                 column: m.column + m.length,
             };
 
-            view.selection = Some(Selection {
+            view.editor.selection = Some(Selection {
                 anchor: start_pos,
                 cursor: end_pos,
             });
@@ -230,12 +226,12 @@ fn move_to_current_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
                 format!("Match {} of {} | ↑/↓ to navigate", current, total),
             );
 
-            // Move caret to end of match
-            let (screen_x, screen_y) = super::helpers::text_to_screen_pos(view, end_pos);
-            
+            // Move caret to end of match, keeping the start visible when scrolling horizontally
+            if view.wrap_width().is_none() {
+                view.scroll_to_column(start_pos)?;
+            }
             view.needs_redraw = true;
-            view.render(caret)?;
-            caret.move_to(Position { x: screen_x, y: screen_y })?;
+            view.set_cursor(caret, end_pos)?;
             Terminal::execute()?;
         }
     }
@@ -261,7 +257,19 @@ pub fn prev_search_match(view: &mut View, caret: &mut Caret) -> Result<(), Error
 
 pub fn clear_search(view: &mut View) {
     view.search_state = None;
-    view.selection = None;
+    view.editor.selection = None;
     view.clear_prompt();
     view.needs_redraw = true;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_columns_are_graphemes_not_bytes() {
+        let lines = vec!["héllo wörld wörld".to_string()];
+        let matches = find_all_occurrences(&lines, "WÖR");
+        let found: Vec<(usize, usize)> = matches.iter().map(|m| (m.column, m.length)).collect();
+        assert_eq!(found, vec![(6, 3), (12, 3)]);
+    }
+}
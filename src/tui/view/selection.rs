@@ -1,181 +1,60 @@
 // selection module responsible for handling selection logic shared between mouse and keyboard
-use super::{View, helpers};
-use crate::tui::{
-    terminal::Terminal,
-    caret::{Caret, Position},
-};
-use crate::core::selection::{Selection, TextPosition};
-use crate::core::graphemes::*;
+use super::View;
+use crate::tui::caret::Caret;
+use crate::core::editor::Motion;
+use crate::core::wrap;
 use std::io::Error;
 
 pub fn move_with_selection(view: &mut View, direction: &str, caret: &mut Caret) -> Result<(), Error> {
-    let current_pos = helpers::get_current_text_pos(view, caret);
-    
-    // Initialize selection if it doesn't exist
-    if view.selection.is_none() {
-        view.selection = Some(Selection::new(current_pos));
-    }
-    
-    // Perform the movement
-    perform_movement(view, direction, caret)?;
-    
-    // Update selection cursor to new position
-    let new_pos = helpers::get_current_text_pos(view, caret);
-    if let Some(ref mut selection) = view.selection {
-        selection.update_cursor(new_pos);
-    }
-    
-    view.render(caret)?;
+    perform_movement(view, direction, true, caret)?;
+    view.render()?;
     Ok(())
 }
 
 pub fn move_without_selection(view: &mut View, direction: &str, caret: &mut Caret) -> Result<(), Error> {
-    // Clear selection
-    view.selection = None;
-    
-    // Perform movement
-    perform_movement(view, direction, caret)?;
-    
-    view.render(caret)?;
+    perform_movement(view, direction, false, caret)?;
+    view.render()?;
     Ok(())
 }
 
+fn perform_movement(view: &mut View, direction: &str, extend: bool, caret: &mut Caret) -> Result<(), Error> {
+    let motion = match direction {
+        "left" => Motion::Left,
+        "right" => Motion::Right,
+        "up" => Motion::Up,
+        "down" => Motion::Down,
+        "top" => Motion::BufferStart,
+        "bottom" => Motion::BufferEnd,
+        "max_left" => Motion::LineStart,
+        "max_right" => Motion::LineEnd,
+        "word_left" => Motion::WordLeft,
+        "word_right" => Motion::WordRight,
+        _ => return Ok(()),
+    };
 
-fn perform_movement(view: &mut View, direction: &str, caret: &mut Caret) -> Result<(), Error> {
-    match direction {
-        "left" => {
-            let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-            
-            if pos.x <= Position::MARGIN && buffer_line_idx > 0 {
-                let prev_line_len = view.buffer.lines.get(buffer_line_idx - 1)
-                    .map(|l| grapheme_len(l))
-                    .unwrap_or(0);
-                
-                if pos.y > Position::HEADER {
-                    caret.move_to(Position { 
-                        x: Position::MARGIN + prev_line_len as u16, 
-                        y: pos.y - 1 
-                    })?;
-                } else if view.scroll_offset > 0 {
-                    view.scroll_offset -= 1;
-                    view.render(caret)?;
-                    caret.move_to(Position { 
-                        x: Position::MARGIN + prev_line_len as u16, 
-                        y: Position::HEADER 
-                    })?;
-                }
-            } else {
-                let new_offset = caret.move_left(view.scroll_offset)?;
-                view.scroll_offset = new_offset;
-            }
-        },
-        "right" => {
-            let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-            
-            if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
-                let char_pos = (pos.x as usize).saturating_sub(Position::MARGIN as usize);
-                let line_grapheme_len = grapheme_len(line);
-                let line_end = Position::MARGIN + line_grapheme_len as u16;
-                let size = Terminal::get_size()?;
-                
-                if pos.x < line_end && pos.x < size.width - 1 {
-                    let new_offset = caret.move_right(view.scroll_offset, view.buffer.lines.len())?;
-                    view.scroll_offset = new_offset;
-                    return Ok(());
-                }
-                
-                if char_pos >= line_grapheme_len && buffer_line_idx + 1 < view.buffer.lines.len() {
-                    if pos.y < size.height - 2 {
-                        caret.move_to(Position { x: Position::MARGIN, y: pos.y + 1 })?;
-                    } else {
-                        view.scroll_offset += 1;
-                        view.render(caret)?;
-                        caret.move_to(Position { x: Position::MARGIN, y: pos.y })?;
-                    }
-                }
-            }
-        },
-        "up" => {
-            let new_offset = caret.move_up(view.scroll_offset)?;
-            view.scroll_offset = new_offset;
-            view.render(caret)?;
-            view.clamp_cursor_to_line(caret)?;
-        },
-        "down" => {
-            let new_offset = caret.move_down(view.scroll_offset, view.buffer.lines.len())?;
-            view.scroll_offset = new_offset;
-            view.render(caret)?;
-            view.clamp_cursor_to_line(caret)?;
-        },
-        "top" => {
-            let new_offset = caret.move_top()?;
-            view.scroll_offset = new_offset;
-            view.render(caret)?;
-            caret.move_to(Position { x: Position::MARGIN, y: Position::HEADER })?;
-        },
-        "bottom" => {
-            let size = Terminal::get_size()?;
-            let visible_rows = (size.height.saturating_sub(Position::HEADER + 1)) as usize;
-            
-            let last_line = view.buffer.lines.iter()
-                .rposition(|line| !line.is_empty())
-                .unwrap_or(0);
-            
-            if last_line >= visible_rows {
-                view.scroll_offset = last_line - visible_rows + 1;
-            } else {
-                view.scroll_offset = 0;
-            }
-            
-            view.render(caret)?;
-            caret.move_bottom()?;
-            view.clamp_cursor_to_line(caret)?;
-        },
-        "max_left" => {
-            caret.move_max_left()?;
-        },
-        "max_right" => {
-            let pos = caret.get_position();
-            let buffer_line_idx = (pos.y.saturating_sub(Position::HEADER)) as usize + view.scroll_offset;
-            
-            if let Some(line) = view.buffer.lines.get(buffer_line_idx) {
-                let size = Terminal::get_size()?;
-                let line_visual_width = visual_width(line);
-                let line_end = Position::MARGIN + line_visual_width as u16;
-                let max_x = line_end.min(size.width - 1);
-                caret.move_to(Position { x: max_x, y: pos.y })?;
-            } else {
-                caret.move_max_right()?;
-            }
-        },
-        _ => {}
+    let had_bracket_match = view.bracket_match().is_some();
+
+    // Every caret moves; the primary one is kept on screen
+    match (motion, view.wrap_width()) {
+        (Motion::Up | Motion::Down, Some(width)) => {
+            // Soft wrap: step through visual rows, not logical lines
+            let down = motion == Motion::Down;
+            view.editor.move_each(extend, |editor, pos| {
+                wrap::move_vertically(&editor.buffer.lines, &editor.folds, pos, width, down)
+            });
+        },
+        _ => view.editor.move_cursor(motion, extend),
     }
-    Ok(())
+    // Extra carets and bracket highlights are drawn cells, not the terminal caret
+    if !view.editor.secondary.is_empty() || had_bracket_match || view.bracket_match().is_some() {
+        view.needs_redraw = true;
+    }
+    view.set_cursor(caret, view.editor.cursor)
 }
 
 pub fn select_all(view: &mut View, caret: &mut Caret) -> Result<(), Error> {
-    // Find last non-empty line
-    let last_line_idx = view.buffer.lines.iter()
-        .rposition(|line| !line.is_empty())
-        .unwrap_or(0);
-    
-    let last_line_len = view.buffer.lines.get(last_line_idx)
-        .map(|l| grapheme_len(l))
-        .unwrap_or(0);
-    
-    let start = TextPosition { line: 0, column: 0 };
-    let end = TextPosition { line: last_line_idx, column: last_line_len };
-    
-    view.selection = Some(Selection {
-        anchor: start,
-        cursor: end,
-    });
-    
-    let (screen_x, screen_y) = super::helpers::text_to_screen_pos(view, end);
-    caret.move_to(crate::tui::caret::Position { x: screen_x, y: screen_y })?;
-    
-    view.render(caret)?;
+    view.editor.select_all();
+    view.set_cursor(caret, view.editor.cursor)?;
+    view.render()?;
     Ok(())
-}
\ No newline at end of file
+}